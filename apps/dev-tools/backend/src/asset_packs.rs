@@ -6,7 +6,7 @@ use axum::{
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{fs, path::Path as FsPath};
 use uuid::Uuid;
 
 use crate::AppState;
@@ -50,6 +50,54 @@ pub struct AssetPackManifest {
     pub textures: Vec<PackTexturePointer>,
     #[serde(default)]
     pub sprites: Vec<PackSpritePointer>,
+    /// Adjacency rules for block palette packs, used by the WFC layout generator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_rules: Option<BlockPaletteRules>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockPaletteRules {
+    pub tiles: Vec<BlockPaletteTile>,
+    /// Tile types allowed to border each other in any direction. Pairs not listed, including a
+    /// tile next to itself, are forbidden.
+    pub adjacency: Vec<BlockAdjacencyRule>,
+    /// Walkable tile pinned under the spawn and the paths to every door.
+    pub path_tile: String,
+    #[serde(default)]
+    pub river_tile: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockPaletteTile {
+    #[serde(rename = "type")]
+    pub tile_type: String,
+    #[serde(default = "default_rule_weight")]
+    pub weight: f32,
+    pub walkable: bool,
+    #[serde(default = "default_rule_weight")]
+    pub move_cost: f32,
+    #[serde(default)]
+    pub texture_url: Option<String>,
+    #[serde(default)]
+    pub blocks_light: Option<bool>,
+    #[serde(default)]
+    pub light_level: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockAdjacencyRule {
+    pub a: String,
+    pub b: String,
+    /// Relative preference for this pairing when a neighbour is already placed.
+    #[serde(default = "default_rule_weight")]
+    pub weight: f32,
+}
+
+fn default_rule_weight() -> f32 {
+    1.0
 }
 
 #[derive(Deserialize)]
@@ -68,6 +116,19 @@ pub struct UpdatePackRequest {
     pub grouping: Option<AssetPackGrouping>,
     pub textures: Option<Vec<PackTexturePointer>>,
     pub sprites: Option<Vec<PackSpritePointer>>,
+    pub block_rules: Option<BlockPaletteRules>,
+}
+
+/// Adjacency rules of a block palette pack, if it has any.
+pub fn load_block_rules(
+    packs_dir: &FsPath,
+    pack_id: &str,
+) -> Result<Option<BlockPaletteRules>, String> {
+    let content = fs::read_to_string(packs_dir.join(format!("{}.json", pack_id)))
+        .map_err(|error| format!("Failed to read block palette pack: {error}"))?;
+    let manifest = serde_json::from_str::<AssetPackManifest>(&content)
+        .map_err(|error| format!("Failed to parse block palette pack: {error}"))?;
+    Ok(manifest.block_rules)
 }
 
 // Routes
@@ -139,6 +200,7 @@ async fn create_pack(
         grouping: payload.grouping,
         textures: vec![],
         sprites: vec![],
+        block_rules: None,
     };
 
     let path = state.packs_dir.join(format!("{}.json", pack_id));
@@ -200,6 +262,9 @@ async fn update_pack(
     if let Some(sprites) = payload.sprites {
         manifest.sprites = sprites;
     }
+    if let Some(block_rules) = payload.block_rules {
+        manifest.block_rules = Some(block_rules);
+    }
 
     if let Err(e) = fs::write(&path, serde_json::to_string_pretty(&manifest).unwrap()) {
         return (
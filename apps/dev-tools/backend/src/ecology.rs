@@ -573,7 +573,8 @@ pub async fn get_ecology_data(
         .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err))?;
     if let Ok(hierarchy) = load_hierarchy(&state.planets_dir, &world_id) {
         let report = load_biome_report(&state.planets_dir, &world_id);
-        sync_biomes_with_hierarchy(&hierarchy, &mut bundle, report.as_ref());
+        let palette = load_biome_palette(&state.planets_dir, &world_id);
+        sync_biomes_with_hierarchy(&hierarchy, &mut bundle, report.as_ref(), &palette);
     }
 
     Ok(Json(bundle))
@@ -590,7 +591,8 @@ pub async fn save_ecology_data(
     normalize_bundle_for_save(&existing, &mut bundle);
     if let Ok(hierarchy) = load_hierarchy(&state.planets_dir, &world_id) {
         let report = load_biome_report(&state.planets_dir, &world_id);
-        sync_biomes_with_hierarchy(&hierarchy, &mut bundle, report.as_ref());
+        let palette = load_biome_palette(&state.planets_dir, &world_id);
+        sync_biomes_with_hierarchy(&hierarchy, &mut bundle, report.as_ref(), &palette);
     }
     validate_bundle_references(&bundle).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
     save_ecology_bundle(&state.planets_dir, &world_id, &bundle)
@@ -612,7 +614,8 @@ pub async fn sync_biomes_handler(
     })?;
 
     let report = load_biome_report(&state.planets_dir, &world_id);
-    sync_biomes_with_hierarchy(&hierarchy, &mut bundle, report.as_ref());
+    let palette = load_biome_palette(&state.planets_dir, &world_id);
+    sync_biomes_with_hierarchy(&hierarchy, &mut bundle, report.as_ref(), &palette);
 
     save_ecology_bundle(&state.planets_dir, &world_id, &bundle)
         .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err))?;
@@ -970,7 +973,8 @@ async fn generate_biome_description_impl(
     let mut bundle = load_ecology_bundle(planets_dir, world_id)?;
 
     let report = load_biome_report(planets_dir, world_id);
-    sync_biomes_with_hierarchy(&hierarchy, &mut bundle, report.as_ref());
+    let palette = load_biome_palette(planets_dir, world_id);
+    sync_biomes_with_hierarchy(&hierarchy, &mut bundle, report.as_ref(), &palette);
 
     let biome = bundle
         .biomes
@@ -1018,7 +1022,8 @@ async fn generate_flora_batch_impl(
     let hierarchy = load_hierarchy(planets_dir, world_id).ok();
     if let Some(hierarchy) = hierarchy.as_ref() {
         let report = load_biome_report(planets_dir, world_id);
-        sync_biomes_with_hierarchy(hierarchy, &mut bundle, report.as_ref());
+        let palette = load_biome_palette(planets_dir, world_id);
+        sync_biomes_with_hierarchy(hierarchy, &mut bundle, report.as_ref(), &palette);
     }
 
     let biome_ids = resolve_requested_biome_ids(&bundle, &request.biome_ids);
@@ -1065,7 +1070,8 @@ async fn generate_fauna_batch_impl(
     let hierarchy = load_hierarchy(planets_dir, world_id).ok();
     if let Some(hierarchy) = hierarchy.as_ref() {
         let report = load_biome_report(planets_dir, world_id);
-        sync_biomes_with_hierarchy(hierarchy, &mut bundle, report.as_ref());
+        let palette = load_biome_palette(planets_dir, world_id);
+        sync_biomes_with_hierarchy(hierarchy, &mut bundle, report.as_ref(), &palette);
     }
 
     let biome_ids = resolve_requested_biome_ids(&bundle, &request.biome_ids);
@@ -2427,13 +2433,13 @@ fn sync_biomes_with_hierarchy(
     hierarchy: &HierarchyData,
     bundle: &mut EcologyBundle,
     report: Option<&worldgen_core::BiomeReport>,
+    palette: &[worldgen_core::BiomePaletteEntry],
 ) {
     let mut province_ids_by_biome: HashMap<String, Vec<u32>> = HashMap::new();
     for province in &hierarchy.provinces {
-        let biome_id = province
-            .biome_primary_id
-            .clone()
-            .or_else(|| biome_id_from_index(&bundle.archetypes, province.biome_primary).ok());
+        let biome_id = province.biome_primary_id.clone().or_else(|| {
+            biome_id_from_index(palette, &bundle.archetypes, province.biome_primary).ok()
+        });
         if let Some(biome_id) = biome_id {
             province_ids_by_biome
                 .entry(biome_id)
@@ -2587,6 +2593,35 @@ pub(crate) fn empty_bundle(world_id: &str) -> EcologyBundle {
     }
 }
 
+pub(crate) fn province_biome_ids(bundle: &EcologyBundle, province_id: u32) -> Vec<String> {
+    bundle
+        .biomes
+        .iter()
+        .filter(|biome| biome.province_ids.contains(&province_id))
+        .map(|biome| biome.id.clone())
+        .collect()
+}
+
+pub(crate) fn flora_for_province(bundle: &EcologyBundle, province_id: u32) -> Vec<FloraEntry> {
+    let biome_ids = province_biome_ids(bundle, province_id);
+    bundle
+        .flora
+        .iter()
+        .filter(|entry| entry.biome_ids.iter().any(|id| biome_ids.contains(id)))
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn fauna_for_province(bundle: &EcologyBundle, province_id: u32) -> Vec<FaunaEntry> {
+    let biome_ids = province_biome_ids(bundle, province_id);
+    bundle
+        .fauna
+        .iter()
+        .filter(|entry| entry.biome_ids.iter().any(|id| biome_ids.contains(id)))
+        .cloned()
+        .collect()
+}
+
 fn read_optional_json<T: for<'de> Deserialize<'de>>(path: PathBuf) -> Result<Vec<T>, String> {
     if !path.exists() {
         return Ok(Vec::new());
@@ -2622,6 +2657,19 @@ fn load_hierarchy(planets_dir: &FsPath, world_id: &str) -> Result<HierarchyData,
     })
 }
 
+fn load_biome_palette(
+    planets_dir: &FsPath,
+    world_id: &str,
+) -> Vec<worldgen_core::BiomePaletteEntry> {
+    read_typed_json::<Vec<worldgen_core::BiomePaletteEntry>>(
+        planets_dir
+            .join(world_id)
+            .join("worldgen")
+            .join(worldgen_core::biome::BIOME_PALETTE_FILE),
+    )
+    .unwrap_or_default()
+}
+
 fn load_biome_report(planets_dir: &FsPath, world_id: &str) -> Option<worldgen_core::BiomeReport> {
     read_typed_json::<worldgen_core::BiomeReport>(
         planets_dir
@@ -2632,10 +2680,16 @@ fn load_biome_report(planets_dir: &FsPath, world_id: &str) -> Option<worldgen_co
     .ok()
 }
 
+/// The palette written next to `biome.png` records which archetype each index meant when the
+/// map was classified; the current registry is only a fallback for planets without one.
 fn biome_id_from_index(
+    palette: &[worldgen_core::BiomePaletteEntry],
     registry: &worldgen_core::BiomeRegistry,
-    biome_index: u8,
+    biome_index: u16,
 ) -> Result<String, String> {
+    if let Some(entry) = palette.iter().find(|entry| entry.index == biome_index) {
+        return Ok(entry.biome_id.clone());
+    }
     registry
         .archetypes
         .get(biome_index as usize)
@@ -3586,7 +3640,7 @@ mod tests {
             province_summaries: Vec::new(),
         };
 
-        sync_biomes_with_hierarchy(&hierarchy, &mut bundle, Some(&report));
+        sync_biomes_with_hierarchy(&hierarchy, &mut bundle, Some(&report), &[]);
 
         assert_eq!(bundle.biomes.len(), 2);
         assert_eq!(bundle.biomes[0].id, desert_id);
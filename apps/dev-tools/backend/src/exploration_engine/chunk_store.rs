@@ -0,0 +1,280 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use super::types::{ExplorationChunk, ExplorationObject, ExplorationTile};
+
+const CHUNK_MAGIC: &[u8; 4] = b"EXCK";
+/// Version 2 added the chunk level to the header; version 1 chunks are ground level.
+const CHUNK_FORMAT_VERSION: u16 = 2;
+/// Largest cell count a decoded chunk may claim, far above the chunk size the manifest writes,
+/// so a corrupt header cannot request an unbounded allocation.
+const MAX_CHUNK_CELLS: usize = 256 * 256;
+
+/// Chunk ids stay `chunk-{row}-{col}` on the ground floor so single-level clients are unaffected.
+pub fn chunk_id(level: i32, chunk_row: u32, chunk_col: u32) -> String {
+    if level == 0 {
+        format!("chunk-{chunk_row}-{chunk_col}")
+    } else {
+        format!("chunk-l{level}-{chunk_row}-{chunk_col}")
+    }
+}
+
+/// Encodes a chunk as `EXCK` + format version, the chunk header, a JSON palette of distinct
+/// tiles, run-length `(palette index, run)` pairs in row-major order and a JSON object section.
+pub fn encode_chunk(chunk: &ExplorationChunk) -> Result<Vec<u8>, String> {
+    let mut palette = Vec::<&ExplorationTile>::new();
+    let mut palette_keys = HashMap::<String, u16>::new();
+    let mut runs = Vec::<(u16, u16)>::new();
+    for tile in &chunk.tiles {
+        let key = serde_json::to_string(tile)
+            .map_err(|error| format!("Failed to serialize exploration tile: {error}"))?;
+        let index = match palette_keys.get(&key) {
+            Some(index) => *index,
+            None => {
+                let index = u16::try_from(palette.len())
+                    .map_err(|_| "Exploration chunk has too many distinct tiles".to_string())?;
+                palette.push(tile);
+                palette_keys.insert(key, index);
+                index
+            }
+        };
+        match runs.last_mut() {
+            Some((last, run)) if *last == index && *run < u16::MAX => *run += 1,
+            _ => runs.push((index, 1)),
+        }
+    }
+
+    let palette_json = serde_json::to_vec(&palette)
+        .map_err(|error| format!("Failed to serialize exploration tile palette: {error}"))?;
+    let objects_json = serde_json::to_vec(&chunk.objects)
+        .map_err(|error| format!("Failed to serialize exploration chunk objects: {error}"))?;
+
+    let mut bytes =
+        Vec::with_capacity(32 + palette_json.len() + runs.len() * 4 + objects_json.len());
+    bytes.extend_from_slice(CHUNK_MAGIC);
+    bytes.extend_from_slice(&CHUNK_FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&chunk.level.to_le_bytes());
+    for value in [
+        chunk.chunk_row,
+        chunk.chunk_col,
+        chunk.origin_row,
+        chunk.origin_col,
+        chunk.width,
+        chunk.height,
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(&(palette_json.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&palette_json);
+    bytes.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+    for (index, run) in runs {
+        bytes.extend_from_slice(&index.to_le_bytes());
+        bytes.extend_from_slice(&run.to_le_bytes());
+    }
+    bytes.extend_from_slice(&(objects_json.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&objects_json);
+    Ok(bytes)
+}
+
+pub fn decode_chunk(bytes: &[u8]) -> Result<ExplorationChunk, String> {
+    let mut reader = ChunkReader { bytes, offset: 0 };
+    if reader.take(4)? != CHUNK_MAGIC {
+        return Err("Failed to parse exploration chunk: bad magic".to_string());
+    }
+    let version = u16::from_le_bytes(reader.array()?);
+    let level = match version {
+        1 => 0,
+        CHUNK_FORMAT_VERSION => i32::from_le_bytes(reader.array()?),
+        _ => {
+            return Err(format!(
+                "Failed to parse exploration chunk: unsupported format version {version}"
+            ))
+        }
+    };
+    let chunk_row = reader.u32()?;
+    let chunk_col = reader.u32()?;
+    let origin_row = reader.u32()?;
+    let origin_col = reader.u32()?;
+    let width = reader.u32()?;
+    let height = reader.u32()?;
+
+    let cell_count = (width as usize)
+        .checked_mul(height as usize)
+        .filter(|count| *count <= MAX_CHUNK_CELLS)
+        .ok_or_else(|| {
+            format!(
+                "Failed to parse exploration chunk: {width}x{height} exceeds the chunk size limit"
+            )
+        })?;
+
+    let palette_len = reader.u32()? as usize;
+    let palette = serde_json::from_slice::<Vec<ExplorationTile>>(reader.take(palette_len)?)
+        .map_err(|error| format!("Failed to parse exploration tile palette: {error}"))?;
+    let run_count = reader.u32()? as usize;
+    let mut tiles = Vec::with_capacity(cell_count);
+    for _ in 0..run_count {
+        let index = u16::from_le_bytes(reader.array()?) as usize;
+        let run = u16::from_le_bytes(reader.array()?) as usize;
+        let tile = palette.get(index).ok_or_else(|| {
+            format!("Failed to parse exploration chunk: palette index {index} out of range")
+        })?;
+        if tiles.len() + run > cell_count {
+            return Err(format!(
+                "Failed to parse exploration chunk: runs exceed the {cell_count} tiles of the chunk"
+            ));
+        }
+        tiles.extend(std::iter::repeat_n(tile, run).cloned());
+    }
+    if tiles.len() != cell_count {
+        return Err(format!(
+            "Failed to parse exploration chunk: expected {cell_count} tiles, found {}",
+            tiles.len()
+        ));
+    }
+
+    let objects_len = reader.u32()? as usize;
+    let objects = serde_json::from_slice::<Vec<ExplorationObject>>(reader.take(objects_len)?)
+        .map_err(|error| format!("Failed to parse exploration chunk objects: {error}"))?;
+
+    Ok(ExplorationChunk {
+        id: chunk_id(level, chunk_row, chunk_col),
+        level,
+        chunk_row,
+        chunk_col,
+        origin_row,
+        origin_col,
+        width,
+        height,
+        tiles,
+        objects,
+    })
+}
+
+/// Content hash of an encoded chunk, used as its ETag.
+pub fn chunk_hash(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+struct ChunkReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ChunkReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "Failed to parse exploration chunk: truncated data".to_string())?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut value = [0; N];
+        value.copy_from_slice(self.take(N)?);
+        Ok(value)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(kind: &str, walkable: bool) -> ExplorationTile {
+        ExplorationTile {
+            r#type: kind.to_string(),
+            walkable,
+            move_cost: if walkable { 1.0 } else { 0.0 },
+            texture_url: None,
+            is_spawn_zone: None,
+            interior_id: None,
+            light_level: Some(0.8),
+            blocks_light: Some(!walkable),
+            door_id: None,
+            level_link: None,
+        }
+    }
+
+    #[test]
+    fn chunks_round_trip_and_compress_runs() {
+        let tiles = (0..256)
+            .map(|index| {
+                if index % 16 == 0 {
+                    tile("wall", false)
+                } else {
+                    tile("grass", true)
+                }
+            })
+            .collect::<Vec<_>>();
+        let chunk = ExplorationChunk {
+            id: "chunk-l-1-1-2".to_string(),
+            level: -1,
+            chunk_row: 1,
+            chunk_col: 2,
+            origin_row: 16,
+            origin_col: 32,
+            width: 16,
+            height: 16,
+            tiles,
+            objects: Vec::new(),
+        };
+
+        let bytes = encode_chunk(&chunk).unwrap();
+        let json = serde_json::to_vec(&chunk).unwrap();
+        assert!(bytes.len() * 10 < json.len());
+        assert_eq!(
+            chunk_hash(&bytes),
+            chunk_hash(&encode_chunk(&chunk).unwrap())
+        );
+
+        let decoded = decode_chunk(&bytes).unwrap();
+        assert_eq!(serde_json::to_vec(&decoded).unwrap(), json);
+        assert!(decode_chunk(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn corrupt_headers_and_runs_are_rejected() {
+        let chunk = ExplorationChunk {
+            id: "chunk-0-0".to_string(),
+            level: 0,
+            chunk_row: 0,
+            chunk_col: 0,
+            origin_row: 0,
+            origin_col: 0,
+            width: 2,
+            height: 2,
+            tiles: vec![tile("grass", true); 4],
+            objects: Vec::new(),
+        };
+        let bytes = encode_chunk(&chunk).unwrap();
+        // Magic, version and level come first, then six u32 header fields ending in width and
+        // height, the palette and the run count.
+        let width_at = 4 + 2 + 4 + 4 * 4;
+        let palette_len =
+            u32::from_le_bytes(bytes[width_at + 8..width_at + 12].try_into().unwrap()) as usize;
+        let first_run_at = width_at + 12 + palette_len + 4;
+
+        let mut oversized = bytes.clone();
+        oversized[width_at..width_at + 8].copy_from_slice(&[0xff; 8]);
+        assert!(decode_chunk(&oversized)
+            .unwrap_err()
+            .contains("exceeds the chunk size limit"));
+
+        let mut long_run = bytes.clone();
+        long_run[first_run_at + 2..first_run_at + 4].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(decode_chunk(&long_run)
+            .unwrap_err()
+            .contains("runs exceed the 4 tiles"));
+
+        assert_eq!(decode_chunk(&bytes).unwrap().tiles.len(), 4);
+    }
+}
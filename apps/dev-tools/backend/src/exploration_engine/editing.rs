@@ -0,0 +1,926 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    path::Path as FsPath,
+};
+
+use super::manifest::{
+    edit_history_path, load_location_map, location_lock, manifest_path, write_location_edit,
+};
+use super::types::{
+    ExplorationManifestDescriptor, ExplorationMap, ExplorationObject, ExplorationSpawnPoint,
+    ExplorationTile,
+};
+use crate::{jobs::now_ms, AppState};
+
+/// Edits kept per location; older ones can no longer be undone.
+const MAX_EDIT_HISTORY: usize = 100;
+
+/// One change to a chunked location. Cells are `[row, col]`; `level` defaults to the ground.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ExplorationEditOp {
+    PaintTiles {
+        #[serde(default)]
+        level: i32,
+        cells: Vec<[u32; 2]>,
+        tile: ExplorationTile,
+    },
+    /// Sets each listed cell to its own tile; undo entries restore tiles this way.
+    ReplaceTiles {
+        #[serde(default)]
+        level: i32,
+        tiles: Vec<ExplorationTileEdit>,
+    },
+    AddObject {
+        object: ExplorationObject,
+    },
+    #[serde(rename_all = "camelCase")]
+    MoveObject {
+        #[serde(default)]
+        level: i32,
+        object_id: String,
+        x: u32,
+        y: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    DeleteObject {
+        #[serde(default)]
+        level: i32,
+        object_id: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetInterior {
+        #[serde(default)]
+        level: i32,
+        cells: Vec<[u32; 2]>,
+        interior_id: Option<String>,
+    },
+    /// Turns the cell into a walkable door, or with no id just detaches its door.
+    #[serde(rename_all = "camelCase")]
+    SetDoor {
+        #[serde(default)]
+        level: i32,
+        row: u32,
+        col: u32,
+        door_id: Option<String>,
+    },
+    SetSpawn {
+        row: u32,
+        col: u32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationTileEdit {
+    pub row: u32,
+    pub col: u32,
+    pub tile: ExplorationTile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationEditEntry {
+    /// Revision the edit produced.
+    pub revision: u64,
+    pub applied_at: u64,
+    pub operations: Vec<ExplorationEditOp>,
+    /// Operations that restore the map as it was before the edit.
+    pub inverse: Vec<ExplorationEditOp>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationEditHistory {
+    /// Revision the newest edit or undo left the map at. When the stored map has moved on, e.g.
+    /// after a regeneration, the entries no longer apply and are dropped.
+    pub revision: u64,
+    pub entries: Vec<ExplorationEditEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationEditRequest {
+    /// Revision the client edited against; a stale one is rejected with `409 Conflict`.
+    #[serde(default)]
+    pub base_revision: Option<u64>,
+    pub operations: Vec<ExplorationEditOp>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationEditResponse {
+    pub descriptor: ExplorationManifestDescriptor,
+    pub changed_chunk_ids: Vec<String>,
+    pub undo_depth: usize,
+}
+
+pub async fn get_exploration_edits(
+    State(state): State<AppState>,
+    Path((world_id, location_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    if !manifest_path(&state.planets_dir, &world_id, &location_id).exists() {
+        return (
+            StatusCode::NOT_FOUND,
+            "Exploration manifest not found".to_string(),
+        )
+            .into_response();
+    }
+    match load_edit_history(&state.planets_dir, &world_id, &location_id) {
+        Ok(history) => (StatusCode::OK, Json(history)).into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error).into_response(),
+    }
+}
+
+/// Applies a patch to a chunked location as one new revision.
+pub async fn apply_exploration_edit(
+    State(state): State<AppState>,
+    Path((world_id, location_id)): Path<(String, String)>,
+    Json(request): Json<ExplorationEditRequest>,
+) -> impl IntoResponse {
+    match edit_location(&state.planets_dir, &world_id, &location_id, request) {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Reverts the newest edit, as a new revision.
+pub async fn undo_exploration_edit(
+    State(state): State<AppState>,
+    Path((world_id, location_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match undo_location_edit(&state.planets_dir, &world_id, &location_id) {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+fn edit_location(
+    planets_dir: &FsPath,
+    world_id: &str,
+    location_id: &str,
+    request: ExplorationEditRequest,
+) -> Result<ExplorationEditResponse, (StatusCode, String)> {
+    let lock = location_lock(world_id, location_id);
+    let _guard = lock.lock().map_err(lock_error)?;
+    let mut history = load_edit_history(planets_dir, world_id, location_id)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error))?;
+    let CommittedEdit {
+        descriptor,
+        changed_chunk_ids,
+        inverse,
+    } = commit_operations(
+        planets_dir,
+        world_id,
+        location_id,
+        request.base_revision,
+        &request.operations,
+    )?;
+
+    if history.revision + 1 != descriptor.revision {
+        history.entries.clear();
+    }
+    history.revision = descriptor.revision;
+    history.entries.push(ExplorationEditEntry {
+        revision: descriptor.revision,
+        applied_at: now_ms(),
+        operations: request.operations,
+        inverse,
+    });
+    let overflow = history.entries.len().saturating_sub(MAX_EDIT_HISTORY);
+    history.entries.drain(..overflow);
+    save_edit_history(planets_dir, world_id, location_id, &history)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error))?;
+
+    Ok(ExplorationEditResponse {
+        descriptor,
+        changed_chunk_ids,
+        undo_depth: history.entries.len(),
+    })
+}
+
+fn undo_location_edit(
+    planets_dir: &FsPath,
+    world_id: &str,
+    location_id: &str,
+) -> Result<ExplorationEditResponse, (StatusCode, String)> {
+    let lock = location_lock(world_id, location_id);
+    let _guard = lock.lock().map_err(lock_error)?;
+    let mut history = load_edit_history(planets_dir, world_id, location_id)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error))?;
+    let Some(entry) = history.entries.last() else {
+        return Err((StatusCode::CONFLICT, "Nothing to undo".to_string()));
+    };
+    let CommittedEdit {
+        descriptor,
+        changed_chunk_ids,
+        ..
+    } = commit_operations(
+        planets_dir,
+        world_id,
+        location_id,
+        Some(history.revision),
+        &entry.inverse,
+    )
+    .map_err(|(status, error)| match status {
+        StatusCode::CONFLICT => (
+            StatusCode::CONFLICT,
+            "Exploration map changed since the last edit; nothing to undo".to_string(),
+        ),
+        _ => (status, error),
+    })?;
+
+    history.entries.pop();
+    history.revision = descriptor.revision;
+    save_edit_history(planets_dir, world_id, location_id, &history)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error))?;
+
+    Ok(ExplorationEditResponse {
+        descriptor,
+        changed_chunk_ids,
+        undo_depth: history.entries.len(),
+    })
+}
+
+struct CommittedEdit {
+    descriptor: ExplorationManifestDescriptor,
+    changed_chunk_ids: Vec<String>,
+    inverse: Vec<ExplorationEditOp>,
+}
+
+/// Loads the location, applies and validates the operations, then stores the next revision.
+fn commit_operations(
+    planets_dir: &FsPath,
+    world_id: &str,
+    location_id: &str,
+    base_revision: Option<u64>,
+    operations: &[ExplorationEditOp],
+) -> Result<CommittedEdit, (StatusCode, String)> {
+    if !manifest_path(planets_dir, world_id, location_id).exists() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Exploration manifest not found".to_string(),
+        ));
+    }
+    if operations.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Exploration edit has no operations".to_string(),
+        ));
+    }
+    let (mut storage, mut map) = load_location_map(planets_dir, world_id, location_id)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error))?;
+    if let Some(base_revision) = base_revision {
+        if base_revision != storage.descriptor.revision {
+            return Err((
+                StatusCode::CONFLICT,
+                format!(
+                    "Exploration map is at revision {}, not {base_revision}",
+                    storage.descriptor.revision
+                ),
+            ));
+        }
+    }
+
+    let inverse = apply_operations(&mut map, &mut storage.descriptor.spawn, operations)
+        .map_err(|error| (StatusCode::BAD_REQUEST, error))?;
+    validate_map(&map, &storage.descriptor.spawn)
+        .map_err(|error| (StatusCode::UNPROCESSABLE_ENTITY, error))?;
+    let (descriptor, changed_chunk_ids) = write_location_edit(planets_dir, &storage, &map)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error))?;
+    Ok(CommittedEdit {
+        descriptor,
+        changed_chunk_ids,
+        inverse,
+    })
+}
+
+/// Applies operations in order and returns the operations that revert them.
+pub fn apply_operations(
+    map: &mut ExplorationMap,
+    spawn: &mut ExplorationSpawnPoint,
+    operations: &[ExplorationEditOp],
+) -> Result<Vec<ExplorationEditOp>, String> {
+    let mut inverse = Vec::with_capacity(operations.len());
+    for operation in operations {
+        inverse.push(apply_operation(map, spawn, operation)?);
+    }
+    inverse.reverse();
+    Ok(inverse)
+}
+
+fn apply_operation(
+    map: &mut ExplorationMap,
+    spawn: &mut ExplorationSpawnPoint,
+    operation: &ExplorationEditOp,
+) -> Result<ExplorationEditOp, String> {
+    match operation {
+        ExplorationEditOp::PaintTiles { level, cells, tile } => {
+            let edits = cells
+                .iter()
+                .map(|[row, col]| ExplorationTileEdit {
+                    row: *row,
+                    col: *col,
+                    tile: tile.clone(),
+                })
+                .collect::<Vec<_>>();
+            replace_tiles(map, *level, &edits)
+        }
+        ExplorationEditOp::ReplaceTiles { level, tiles } => replace_tiles(map, *level, tiles),
+        ExplorationEditOp::SetInterior {
+            level,
+            cells,
+            interior_id,
+        } => {
+            let mut edits = Vec::with_capacity(cells.len());
+            for [row, col] in cells {
+                let mut tile = tile_at(map, *level, *row, *col)?.clone();
+                tile.interior_id = interior_id.clone();
+                edits.push(ExplorationTileEdit {
+                    row: *row,
+                    col: *col,
+                    tile,
+                });
+            }
+            replace_tiles(map, *level, &edits)
+        }
+        ExplorationEditOp::SetDoor {
+            level,
+            row,
+            col,
+            door_id,
+        } => {
+            let mut tile = tile_at(map, *level, *row, *col)?.clone();
+            if door_id.is_some() {
+                tile.r#type = "door".to_string();
+                tile.walkable = true;
+                tile.move_cost = 1.0;
+            }
+            tile.door_id = door_id.clone();
+            replace_tiles(
+                map,
+                *level,
+                &[ExplorationTileEdit {
+                    row: *row,
+                    col: *col,
+                    tile,
+                }],
+            )
+        }
+        ExplorationEditOp::AddObject { object } => {
+            if all_objects(map).any(|existing| existing.id == object.id) {
+                return Err(format!("Exploration object {} already exists", object.id));
+            }
+            level_objects_mut(map, object.level)?.push(object.clone());
+            Ok(ExplorationEditOp::DeleteObject {
+                level: object.level,
+                object_id: object.id.clone(),
+            })
+        }
+        ExplorationEditOp::MoveObject {
+            level,
+            object_id,
+            x,
+            y,
+        } => {
+            let object = level_objects_mut(map, *level)?
+                .iter_mut()
+                .find(|object| &object.id == object_id)
+                .ok_or_else(|| format!("Exploration object {object_id} not found"))?;
+            let inverse = ExplorationEditOp::MoveObject {
+                level: *level,
+                object_id: object_id.clone(),
+                x: object.x,
+                y: object.y,
+            };
+            object.x = *x;
+            object.y = *y;
+            Ok(inverse)
+        }
+        ExplorationEditOp::DeleteObject { level, object_id } => {
+            let objects = level_objects_mut(map, *level)?;
+            let index = objects
+                .iter()
+                .position(|object| &object.id == object_id)
+                .ok_or_else(|| format!("Exploration object {object_id} not found"))?;
+            let mut object = objects.remove(index);
+            object.level = *level;
+            Ok(ExplorationEditOp::AddObject { object })
+        }
+        ExplorationEditOp::SetSpawn { row, col } => {
+            tile_at(map, 0, *row, *col)?;
+            let inverse = ExplorationEditOp::SetSpawn {
+                row: spawn.row,
+                col: spawn.col,
+            };
+            *spawn = ExplorationSpawnPoint {
+                row: *row,
+                col: *col,
+            };
+            Ok(inverse)
+        }
+    }
+}
+
+/// Writes the edits and returns a `ReplaceTiles` restoring what each cell held before.
+fn replace_tiles(
+    map: &mut ExplorationMap,
+    level: i32,
+    edits: &[ExplorationTileEdit],
+) -> Result<ExplorationEditOp, String> {
+    let width = map.width;
+    let height = map.height;
+    let tiles = level_tiles_mut(map, level)?;
+    let mut seen = HashSet::new();
+    let mut previous = Vec::with_capacity(edits.len());
+    for edit in edits {
+        if edit.row >= height || edit.col >= width {
+            return Err(format!(
+                "Exploration cell {},{} is outside the map",
+                edit.row, edit.col
+            ));
+        }
+        let index = (edit.row * width + edit.col) as usize;
+        if seen.insert(index) {
+            previous.push(ExplorationTileEdit {
+                row: edit.row,
+                col: edit.col,
+                tile: tiles[index].clone(),
+            });
+        }
+        tiles[index] = edit.tile.clone();
+    }
+    Ok(ExplorationEditOp::ReplaceTiles {
+        level,
+        tiles: previous,
+    })
+}
+
+fn tile_at(
+    map: &ExplorationMap,
+    level: i32,
+    row: u32,
+    col: u32,
+) -> Result<&ExplorationTile, String> {
+    if row >= map.height || col >= map.width {
+        return Err(format!("Exploration cell {row},{col} is outside the map"));
+    }
+    level_tiles(map, level)
+        .map(|tiles| &tiles[(row * map.width + col) as usize])
+        .ok_or_else(|| format!("Exploration level {level} not found"))
+}
+
+fn level_tiles(map: &ExplorationMap, level: i32) -> Option<&[ExplorationTile]> {
+    if level == 0 {
+        return Some(&map.tiles);
+    }
+    map.levels
+        .iter()
+        .find(|entry| entry.level == level)
+        .map(|entry| entry.tiles.as_slice())
+}
+
+fn level_tiles_mut(map: &mut ExplorationMap, level: i32) -> Result<&mut [ExplorationTile], String> {
+    if level == 0 {
+        return Ok(&mut map.tiles);
+    }
+    map.levels
+        .iter_mut()
+        .find(|entry| entry.level == level)
+        .map(|entry| entry.tiles.as_mut_slice())
+        .ok_or_else(|| format!("Exploration level {level} not found"))
+}
+
+fn level_objects_mut(
+    map: &mut ExplorationMap,
+    level: i32,
+) -> Result<&mut Vec<ExplorationObject>, String> {
+    if level == 0 {
+        return Ok(&mut map.objects);
+    }
+    map.levels
+        .iter_mut()
+        .find(|entry| entry.level == level)
+        .map(|entry| &mut entry.objects)
+        .ok_or_else(|| format!("Exploration level {level} not found"))
+}
+
+fn all_objects(map: &ExplorationMap) -> impl Iterator<Item = &ExplorationObject> {
+    map.objects
+        .iter()
+        .chain(map.levels.iter().flat_map(|level| level.objects.iter()))
+}
+
+/// Checks the invariants the simulation relies on: a walkable, unobstructed spawn, walkable
+/// doors and stairs leading to walkable tiles, every door and stairs reachable from the spawn,
+/// and in-bounds objects with unique ids.
+pub fn validate_map(map: &ExplorationMap, spawn: &ExplorationSpawnPoint) -> Result<(), String> {
+    let spawn_tile = tile_at(map, 0, spawn.row, spawn.col)?;
+    if !spawn_tile.walkable {
+        return Err(format!(
+            "Spawn point {},{} is not walkable",
+            spawn.row, spawn.col
+        ));
+    }
+    if let Some(object) = map
+        .objects
+        .iter()
+        .find(|object| !object.passable && object_covers(object, spawn.row, spawn.col))
+    {
+        return Err(format!("Spawn point is blocked by object {}", object.id));
+    }
+
+    let levels = std::iter::once((0, map.tiles.as_slice())).chain(
+        map.levels
+            .iter()
+            .map(|entry| (entry.level, entry.tiles.as_slice())),
+    );
+    for (level, tiles) in levels {
+        for (index, tile) in tiles.iter().enumerate() {
+            let row = index as u32 / map.width.max(1);
+            let col = index as u32 % map.width.max(1);
+            if tile.walkable && tile.move_cost <= 0.0 {
+                return Err(format!(
+                    "Walkable tile {row},{col} on level {level} needs a positive move cost"
+                ));
+            }
+            if tile.door_id.is_some() && !tile.walkable {
+                return Err(format!(
+                    "Door tile {row},{col} on level {level} is not walkable"
+                ));
+            }
+            if let Some(link) = &tile.level_link {
+                if !tile.walkable {
+                    return Err(format!(
+                        "{} tile {row},{col} on level {level} is not walkable",
+                        link.kind
+                    ));
+                }
+                let target = tile_at(map, link.level, link.row, link.col)
+                    .map_err(|error| format!("{} at {row},{col}: {error}", link.kind))?;
+                if !target.walkable {
+                    return Err(format!(
+                        "{} at {row},{col} on level {level} leads to a blocked tile",
+                        link.kind
+                    ));
+                }
+            }
+        }
+    }
+
+    let reached = reachable_cells(map, spawn);
+    let levels = std::iter::once((0, map.tiles.as_slice())).chain(
+        map.levels
+            .iter()
+            .map(|entry| (entry.level, entry.tiles.as_slice())),
+    );
+    for (level, tiles) in levels {
+        for (index, tile) in tiles.iter().enumerate() {
+            let row = index as u32 / map.width.max(1);
+            let col = index as u32 % map.width.max(1);
+            let kind = match (&tile.door_id, &tile.level_link) {
+                (_, Some(link)) => link.kind.as_str(),
+                (Some(_), None) => "Door",
+                (None, None) => continue,
+            };
+            if !reached.contains(&(level, row, col)) {
+                return Err(format!(
+                    "{kind} tile {row},{col} on level {level} cannot be reached from the spawn"
+                ));
+            }
+        }
+    }
+
+    let mut ids = HashSet::new();
+    for object in all_objects(map) {
+        if !ids.insert(object.id.as_str()) {
+            return Err(format!("Exploration object id {} is duplicated", object.id));
+        }
+        if object.x + object.width.max(1) > map.width
+            || object.y + object.height.max(1) > map.height
+        {
+            return Err(format!(
+                "Exploration object {} extends outside the map",
+                object.id
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Cells `(level, row, col)` a pawn can walk to from the spawn, counting doors as open and
+/// taking stairs and ladders. Diagonal steps need both orthogonal neighbours open, so a
+/// four-way fill reaches the same cells as the pathfinder.
+fn reachable_cells(
+    map: &ExplorationMap,
+    spawn: &ExplorationSpawnPoint,
+) -> HashSet<(i32, u32, u32)> {
+    let mut blocked = HashSet::new();
+    let objects = map.objects.iter().map(|object| (0, object)).chain(
+        map.levels
+            .iter()
+            .flat_map(|entry| entry.objects.iter().map(|object| (entry.level, object))),
+    );
+    for (level, object) in objects.filter(|(_, object)| !object.passable) {
+        for row in object.y..object.y + object.height.max(1) {
+            for col in object.x..object.x + object.width.max(1) {
+                blocked.insert((level, row, col));
+            }
+        }
+    }
+    let open = |level: i32, row: u32, col: u32| {
+        tile_at(map, level, row, col).is_ok_and(|tile| tile.walkable)
+            && !blocked.contains(&(level, row, col))
+    };
+    let mut reached = HashSet::new();
+    if !open(0, spawn.row, spawn.col) {
+        return reached;
+    }
+    reached.insert((0, spawn.row, spawn.col));
+    let mut queue = VecDeque::from([(0, spawn.row, spawn.col)]);
+    while let Some((level, row, col)) = queue.pop_front() {
+        let mut next = Vec::with_capacity(5);
+        for (dr, dc) in [(1_i64, 0_i64), (-1, 0), (0, 1), (0, -1)] {
+            let (next_row, next_col) = (row as i64 + dr, col as i64 + dc);
+            if next_row >= 0
+                && next_col >= 0
+                && next_row < map.height as i64
+                && next_col < map.width as i64
+            {
+                next.push((level, next_row as u32, next_col as u32));
+            }
+        }
+        if let Some(link) = tile_at(map, level, row, col)
+            .ok()
+            .and_then(|tile| tile.level_link.as_ref())
+        {
+            next.push((link.level, link.row, link.col));
+        }
+        for cell in next {
+            if !reached.contains(&cell) && open(cell.0, cell.1, cell.2) {
+                reached.insert(cell);
+                queue.push_back(cell);
+            }
+        }
+    }
+    reached
+}
+
+fn object_covers(object: &ExplorationObject, row: u32, col: u32) -> bool {
+    row >= object.y
+        && row < object.y + object.height.max(1)
+        && col >= object.x
+        && col < object.x + object.width.max(1)
+}
+
+fn load_edit_history(
+    planets_dir: &FsPath,
+    world_id: &str,
+    location_id: &str,
+) -> Result<ExplorationEditHistory, String> {
+    let path = edit_history_path(planets_dir, world_id, location_id);
+    if !path.exists() {
+        return Ok(ExplorationEditHistory::default());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read exploration edit history: {error}"))?;
+    serde_json::from_str(&content)
+        .map_err(|error| format!("Failed to parse exploration edit history: {error}"))
+}
+
+fn save_edit_history(
+    planets_dir: &FsPath,
+    world_id: &str,
+    location_id: &str,
+    history: &ExplorationEditHistory,
+) -> Result<(), String> {
+    let path = edit_history_path(planets_dir, world_id, location_id);
+    let content = serde_json::to_string(history)
+        .map_err(|error| format!("Failed to serialize exploration edit history: {error}"))?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, content)
+        .map_err(|error| format!("Failed to write exploration edit history: {error}"))?;
+    fs::rename(&temp_path, &path)
+        .map_err(|error| format!("Failed to replace exploration edit history: {error}"))
+}
+
+fn lock_error<T>(_error: std::sync::PoisonError<T>) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Exploration location lock poisoned".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exploration_engine::manifest::load_manifest_descriptor;
+    use crate::exploration_engine::types::{ExplorationLevelLink, ExplorationMapLevel};
+    use crate::exploration_jobs::{ensure_test_exploration_location, TEST_EXPLORATION_LOCATION_ID};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Five by three floor split by a wall down column 2, with a door behind the wall and a
+    /// cellar whose door is reached by stairs.
+    fn split_map() -> ExplorationMap {
+        let floor = ExplorationTile {
+            r#type: "floor".to_string(),
+            walkable: true,
+            move_cost: 1.0,
+            texture_url: None,
+            is_spawn_zone: None,
+            interior_id: None,
+            light_level: None,
+            blocks_light: None,
+            door_id: None,
+            level_link: None,
+        };
+        let wall = ExplorationTile {
+            r#type: "wall".to_string(),
+            walkable: false,
+            move_cost: 0.0,
+            ..floor.clone()
+        };
+        let door = ExplorationTile {
+            door_id: Some("door-east".to_string()),
+            ..floor.clone()
+        };
+        let mut tiles = (0..15)
+            .map(|index| {
+                if index % 5 == 2 {
+                    wall.clone()
+                } else {
+                    floor.clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        tiles[9] = door.clone();
+        tiles[6].level_link = Some(ExplorationLevelLink {
+            level: -1,
+            row: 1,
+            col: 1,
+            kind: "stairs".to_string(),
+        });
+        let mut cellar = vec![floor; 15];
+        cellar[8] = ExplorationTile {
+            door_id: Some("door-cellar".to_string()),
+            ..door
+        };
+        ExplorationMap {
+            id: "split".to_string(),
+            width: 5,
+            height: 3,
+            tiles,
+            pawns: Vec::new(),
+            objects: Vec::new(),
+            name: None,
+            fog_of_war: None,
+            ambient_light: None,
+            version: None,
+            render_mode: None,
+            metadata: None,
+            levels: vec![ExplorationMapLevel {
+                level: -1,
+                name: None,
+                tiles: cellar,
+                objects: Vec::new(),
+            }],
+            triggers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn doors_and_stairs_must_be_reachable_from_the_spawn() {
+        let spawn = ExplorationSpawnPoint { row: 1, col: 0 };
+        let mut map = split_map();
+        let error = validate_map(&map, &spawn).unwrap_err();
+        assert!(error.contains("Door tile 1,4 on level 0"), "{error}");
+
+        map.tiles[7] = map.tiles[8].clone();
+        validate_map(&map, &spawn).unwrap();
+
+        map.levels[0].tiles[7].walkable = false;
+        map.levels[0].tiles[7].move_cost = 0.0;
+        map.levels[0].tiles[2].walkable = false;
+        map.levels[0].tiles[2].move_cost = 0.0;
+        map.levels[0].tiles[12].walkable = false;
+        map.levels[0].tiles[12].move_cost = 0.0;
+        let error = validate_map(&map, &spawn).unwrap_err();
+        assert!(error.contains("Door tile 1,3 on level -1"), "{error}");
+    }
+
+    #[test]
+    fn edits_rewrite_touched_chunks_and_undo_restores_them() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("ashtrail-exploration-edit-test-{nanos}"));
+        let world_id = "world-edit";
+        let location_id = TEST_EXPLORATION_LOCATION_ID;
+        ensure_test_exploration_location(&dir, world_id).unwrap();
+        let (storage, original) = load_location_map(&dir, world_id, location_id).unwrap();
+        let spawn = storage.descriptor.spawn.clone();
+        let rock = ExplorationTile {
+            r#type: "rock".to_string(),
+            walkable: false,
+            move_cost: 0.0,
+            ..original.tiles[0].clone()
+        };
+
+        let blocked = edit_location(
+            &dir,
+            world_id,
+            location_id,
+            ExplorationEditRequest {
+                base_revision: None,
+                operations: vec![ExplorationEditOp::PaintTiles {
+                    level: 0,
+                    cells: vec![[spawn.row, spawn.col]],
+                    tile: rock.clone(),
+                }],
+            },
+        );
+        assert_eq!(blocked.err().unwrap().0, StatusCode::UNPROCESSABLE_ENTITY);
+
+        let stale = edit_location(
+            &dir,
+            world_id,
+            location_id,
+            ExplorationEditRequest {
+                base_revision: Some(storage.descriptor.revision + 5),
+                operations: vec![ExplorationEditOp::SetSpawn {
+                    row: spawn.row,
+                    col: spawn.col,
+                }],
+            },
+        );
+        assert_eq!(stale.err().unwrap().0, StatusCode::CONFLICT);
+
+        let edited = edit_location(
+            &dir,
+            world_id,
+            location_id,
+            ExplorationEditRequest {
+                base_revision: Some(storage.descriptor.revision),
+                operations: vec![
+                    ExplorationEditOp::PaintTiles {
+                        level: 0,
+                        cells: vec![[1, 1], [1, 2], [1, 1]],
+                        tile: rock,
+                    },
+                    ExplorationEditOp::AddObject {
+                        object: ExplorationObject {
+                            id: "editor-crate".to_string(),
+                            x: 2,
+                            y: 1,
+                            ..original.objects[0].clone()
+                        },
+                    },
+                ],
+            },
+        )
+        .unwrap();
+        assert_eq!(edited.descriptor.revision, storage.descriptor.revision + 1);
+        assert_eq!(edited.changed_chunk_ids, vec!["chunk-0-0".to_string()]);
+        assert_eq!(edited.undo_depth, 1);
+        let (_, map) = load_location_map(&dir, world_id, location_id).unwrap();
+        assert_eq!(map.tiles[(map.width + 2) as usize].r#type, "rock");
+        assert!(map.objects.iter().any(|object| object.id == "editor-crate"));
+
+        let undone = undo_location_edit(&dir, world_id, location_id).unwrap();
+        assert_eq!(undone.changed_chunk_ids, vec!["chunk-0-0".to_string()]);
+        assert_eq!(undone.undo_depth, 0);
+        let (_, restored) = load_location_map(&dir, world_id, location_id).unwrap();
+        assert_eq!(
+            serde_json::to_value(&restored.tiles).unwrap(),
+            serde_json::to_value(&original.tiles).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&restored.objects).unwrap(),
+            serde_json::to_value(&original.objects).unwrap()
+        );
+        assert_eq!(
+            load_manifest_descriptor(&dir, world_id, location_id)
+                .unwrap()
+                .revision,
+            storage.descriptor.revision + 2
+        );
+        assert_eq!(
+            undo_location_edit(&dir, world_id, location_id)
+                .err()
+                .unwrap()
+                .0,
+            StatusCode::CONFLICT
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
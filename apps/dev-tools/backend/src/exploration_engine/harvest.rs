@@ -0,0 +1,225 @@
+use rand::Rng;
+
+use crate::ecology::{FloraCategory, FloraEdibility, FloraEntry};
+
+use super::types::{ExplorationItemStack, ExplorationPawnCondition, ExplorationPawnStats};
+
+/// Wall-clock length of one in-game day for flora regrowth timers.
+pub const EXPLORATION_DAY_MS: u64 = 20 * 60 * 1000;
+const CONDITION_DURATION_SECONDS: u64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct HarvestOutcome {
+    pub success: bool,
+    pub items: Vec<ExplorationItemStack>,
+    pub conditions: Vec<ExplorationPawnCondition>,
+}
+
+pub fn natural_object_type_for_flora(entry: &FloraEntry) -> &'static str {
+    match entry.category {
+        FloraCategory::Tree => "tree",
+        FloraCategory::Shrub => "shrub",
+        FloraCategory::Grass => "grass",
+        FloraCategory::Crop => "crop",
+        FloraCategory::Fungus => "fungus",
+        FloraCategory::Aquatic => "reeds",
+        FloraCategory::AlienOther => "alien-plant",
+    }
+}
+
+pub fn flora_blocks_movement(entry: &FloraEntry) -> bool {
+    matches!(entry.category, FloraCategory::Tree | FloraCategory::Shrub)
+}
+
+/// Picks a flora entry for a natural object, favouring common species over rare ones.
+pub fn pick_flora<'a, R: Rng>(candidates: &'a [FloraEntry], rng: &mut R) -> Option<&'a FloraEntry> {
+    let total = candidates.iter().map(flora_spawn_weight).sum::<u32>();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.random_range(0..total);
+    for entry in candidates {
+        let weight = flora_spawn_weight(entry);
+        if roll < weight {
+            return Some(entry);
+        }
+        roll -= weight;
+    }
+    candidates.last()
+}
+
+fn flora_spawn_weight(entry: &FloraEntry) -> u32 {
+    (110 - entry.resource_profile.rarity.clamp(0, 100)) as u32
+}
+
+pub fn harvest_skill(entry: &FloraEntry, stats: &ExplorationPawnStats) -> i32 {
+    let handling = match entry.category {
+        FloraCategory::Tree | FloraCategory::Shrub => stats.strength.max(stats.agility),
+        _ => stats.agility,
+    };
+    handling * 3 + stats.wisdom * 2
+}
+
+/// Rolls a single harvest attempt. Yield scales with how far the actor's skill clears the
+/// species' difficulty; every hazard rolls independently against the actor's endurance.
+pub fn resolve_harvest<R: Rng>(
+    entry: &FloraEntry,
+    stats: &ExplorationPawnStats,
+    tick: u64,
+    tick_rate_hz: u64,
+    rng: &mut R,
+) -> HarvestOutcome {
+    let resource = &entry.resource_profile;
+    let margin = harvest_skill(entry, stats) - resource.harvest_difficulty;
+    let success_chance = (0.55 + margin as f64 / 100.0).clamp(0.1, 0.95);
+    let success = rng.random_bool(success_chance);
+
+    let mut items = Vec::new();
+    if success {
+        let base_quantity = 1.0 + resource.yield_per_harvest.max(0) as f64 / 20.0;
+        let quality = (0.6 + margin.max(0) as f64 / 100.0 + rng.random_range(0.0..0.4)).min(1.5);
+        let quantity = (base_quantity * quality).round().max(1.0) as u32;
+        items.push(ExplorationItemStack {
+            item_id: format!("flora-{}", entry.id),
+            name: entry.name.clone(),
+            category: harvest_item_category(entry).to_string(),
+            quantity,
+        });
+    }
+
+    let hazard = &entry.hazard_profile;
+    let expires_at_tick = tick + CONDITION_DURATION_SECONDS * tick_rate_hz.max(1);
+    let mut conditions = Vec::new();
+    for (id, label, value) in [
+        ("poisoned", "Poisoned", hazard.toxicity),
+        ("scratched", "Scratched", hazard.thorniness),
+        ("irritated", "Irritated", hazard.irritation),
+    ] {
+        let exposure = (value - stats.endurance * 2).clamp(0, 100);
+        if exposure > 0 && rng.random_range(0..100) < exposure {
+            conditions.push(ExplorationPawnCondition {
+                id: id.to_string(),
+                label: label.to_string(),
+                severity: (value / 25).clamp(1, 4),
+                expires_at_tick,
+            });
+        }
+    }
+
+    HarvestOutcome {
+        success,
+        items,
+        conditions,
+    }
+}
+
+pub fn regrowth_deadline_ms(entry: &FloraEntry, now_ms: u64) -> u64 {
+    now_ms + entry.resource_profile.regrowth_days.max(1) as u64 * EXPLORATION_DAY_MS
+}
+
+/// Adds harvested items to an inventory, stacking onto items it already holds.
+pub fn stack_items(inventory: &mut Vec<ExplorationItemStack>, items: &[ExplorationItemStack]) {
+    for item in items {
+        match inventory
+            .iter_mut()
+            .find(|stack| stack.item_id == item.item_id)
+        {
+            Some(stack) => stack.quantity += item.quantity,
+            None => inventory.push(item.clone()),
+        }
+    }
+}
+
+fn harvest_item_category(entry: &FloraEntry) -> &'static str {
+    let edible = !matches!(entry.edibility, FloraEdibility::None);
+    if (edible && entry.resource_profile.nutrition_value >= 30)
+        || entry.resource_profile.medicinal_value >= 50
+    {
+        "consumable"
+    } else {
+        "resource"
+    }
+}
+
+/// Movement multiplier applied while a pawn suffers from harvest conditions.
+pub fn condition_speed_factor(conditions: &[ExplorationPawnCondition]) -> f32 {
+    conditions
+        .iter()
+        .filter(|condition| condition.id == "poisoned")
+        .map(|condition| 1.0 - 0.1 * condition.severity as f32)
+        .fold(1.0, f32::min)
+        .max(0.4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecology::FloraResourceProfile;
+    use crate::exploration_engine::sim::tests::sample_flora;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn skilled() -> ExplorationPawnStats {
+        ExplorationPawnStats {
+            strength: 10,
+            agility: 10,
+            endurance: 10,
+            wisdom: 10,
+            ..ExplorationPawnStats::default()
+        }
+    }
+
+    #[test]
+    fn yield_grows_with_the_species_yield() {
+        let mut meagre = sample_flora();
+        meagre.resource_profile = FloraResourceProfile {
+            yield_per_harvest: 0,
+            ..meagre.resource_profile
+        };
+        let bountiful = sample_flora();
+        let total = |entry: &FloraEntry| {
+            let mut rng = StdRng::seed_from_u64(7);
+            (0..50)
+                .map(|_| resolve_harvest(entry, &skilled(), 0, 10, &mut rng))
+                .filter(|outcome| outcome.success)
+                .map(|outcome| outcome.items[0].quantity)
+                .sum::<u32>()
+        };
+        assert!(total(&bountiful) > total(&meagre));
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let outcome = (0..50)
+            .map(|_| resolve_harvest(&bountiful, &skilled(), 0, 10, &mut rng))
+            .find(|outcome| outcome.success)
+            .unwrap();
+        assert_eq!(outcome.items[0].item_id, "flora-flora-berry");
+        assert_eq!(outcome.items[0].category, "consumable");
+    }
+
+    #[test]
+    fn harvested_items_stack_in_the_inventory() {
+        let berries = |quantity| ExplorationItemStack {
+            item_id: "flora-berry".to_string(),
+            name: "Ash Berry".to_string(),
+            category: "consumable".to_string(),
+            quantity,
+        };
+        let mut inventory = Vec::new();
+        stack_items(&mut inventory, &[berries(2)]);
+        stack_items(&mut inventory, &[berries(3)]);
+        assert_eq!(inventory, vec![berries(5)]);
+    }
+
+    #[test]
+    fn regrowth_takes_whole_days() {
+        let mut entry = sample_flora();
+        assert_eq!(
+            regrowth_deadline_ms(&entry, 1_000),
+            1_000 + 2 * EXPLORATION_DAY_MS
+        );
+        entry.resource_profile.regrowth_days = 0;
+        assert_eq!(
+            regrowth_deadline_ms(&entry, 1_000),
+            1_000 + EXPLORATION_DAY_MS
+        );
+    }
+}
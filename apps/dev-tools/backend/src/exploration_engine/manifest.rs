@@ -1,16 +1,38 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
+    collections::BTreeMap,
     fs,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
+use super::chunk_store::{chunk_hash, chunk_id, decode_chunk, encode_chunk};
 use super::types::{
-    ExplorationChunk, ExplorationManifestDescriptor, ExplorationMap, ExplorationObject,
-    ExplorationPawn, ExplorationSpawnPoint,
+    ExplorationChunk, ExplorationLevel, ExplorationManifestDescriptor, ExplorationMap,
+    ExplorationMapLevel, ExplorationObject, ExplorationPawn, ExplorationSpawnPoint,
+    ExplorationTile, ExplorationTrigger,
 };
 
 pub const EXPLORATION_CHUNK_SIZE: u32 = 16;
+/// Version 4 stores binary chunks in one directory per manifest revision; version 3 kept
+/// pretty-printed JSON chunks directly under `chunks/`.
+pub const EXPLORATION_MANIFEST_VERSION: u32 = 4;
+const CHUNK_JSON_EXPORT_ENV: &str = "EXPLORATION_CHUNK_JSON_EXPORT";
+
+/// Revision lock per `(world_id, location_id)`.
+type LocationLocks = BTreeMap<(String, String), Arc<Mutex<()>>>;
+
+/// One lock per `(world_id, location_id)`, held by every writer of a new revision so edits and
+/// regenerations never stage the same one.
+static LOCATION_LOCKS: Mutex<LocationLocks> = Mutex::new(BTreeMap::new());
+
+/// Open [`ChunkSource`] count per `(world_id, location_id, revision)`.
+type RevisionLeases = BTreeMap<(String, String, u64), usize>;
+
+/// Revisions that live sessions still read chunks from; revision cleanup keeps their directories
+/// until the last source reading them is dropped.
+static REVISION_LEASES: Mutex<RevisionLeases> = Mutex::new(BTreeMap::new());
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +41,31 @@ pub struct ExplorationStorageManifest {
     pub descriptor: ExplorationManifestDescriptor,
     #[serde(default)]
     pub pawns: Vec<ExplorationPawn>,
+    /// Content hash of each stored chunk, keyed `"{chunk_row}_{chunk_col}"` on the ground level
+    /// and `"l{level}_{chunk_row}_{chunk_col}"` on other levels.
+    #[serde(default)]
+    pub chunk_hashes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub triggers: Vec<ExplorationTrigger>,
+}
+
+impl ExplorationStorageManifest {
+    pub fn chunk_hash(&self, level: i32, chunk_row: u32, chunk_col: u32) -> Option<&str> {
+        self.chunk_hashes
+            .get(&chunk_key(level, chunk_row, chunk_col))
+            .map(String::as_str)
+    }
+
+    /// Levels with stored chunks, ground first.
+    pub fn level_ids(&self) -> Vec<i32> {
+        let mut levels = vec![0];
+        for level in &self.descriptor.levels {
+            if !levels.contains(&level.level) {
+                levels.push(level.level);
+            }
+        }
+        levels
+    }
 }
 
 pub fn manifest_path(planets_dir: &Path, world_id: &str, location_id: &str) -> PathBuf {
@@ -29,6 +76,14 @@ pub fn manifest_path(planets_dir: &Path, world_id: &str, location_id: &str) -> P
         .join("manifest.json")
 }
 
+pub fn session_state_path(planets_dir: &Path, world_id: &str, location_id: &str) -> PathBuf {
+    location_dir(planets_dir, world_id, location_id).join("state.json")
+}
+
+pub fn edit_history_path(planets_dir: &Path, world_id: &str, location_id: &str) -> PathBuf {
+    location_dir(planets_dir, world_id, location_id).join("edits.json")
+}
+
 fn location_dir(planets_dir: &Path, world_id: &str, location_id: &str) -> PathBuf {
     planets_dir
         .join(world_id)
@@ -40,7 +95,38 @@ fn chunks_dir(planets_dir: &Path, world_id: &str, location_id: &str) -> PathBuf
     location_dir(planets_dir, world_id, location_id).join("chunks")
 }
 
+fn revision_dir(planets_dir: &Path, world_id: &str, location_id: &str, revision: u64) -> PathBuf {
+    chunks_dir(planets_dir, world_id, location_id).join(format!("r{revision}"))
+}
+
+fn chunk_key(level: i32, chunk_row: u32, chunk_col: u32) -> String {
+    if level == 0 {
+        format!("{chunk_row}_{chunk_col}")
+    } else {
+        format!("l{level}_{chunk_row}_{chunk_col}")
+    }
+}
+
 fn chunk_path(
+    planets_dir: &Path,
+    storage: &ExplorationStorageManifest,
+    level: i32,
+    chunk_row: u32,
+    chunk_col: u32,
+) -> PathBuf {
+    revision_dir(
+        planets_dir,
+        &storage.descriptor.world_id,
+        &storage.descriptor.location_id,
+        storage.descriptor.revision,
+    )
+    .join(format!(
+        "chunk_{}.bin",
+        chunk_key(level, chunk_row, chunk_col)
+    ))
+}
+
+fn legacy_chunk_path(
     planets_dir: &Path,
     world_id: &str,
     location_id: &str,
@@ -51,69 +137,337 @@ fn chunk_path(
         .join(format!("chunk_{chunk_row}_{chunk_col}.json"))
 }
 
+fn chunk_json_export_enabled() -> bool {
+    std::env::var(CHUNK_JSON_EXPORT_ENV)
+        .ok()
+        .map(|value| matches!(value.trim(), "1" | "true" | "TRUE" | "yes" | "YES"))
+        .unwrap_or(false)
+}
+
+/// Keeps one revision's chunk directory on disk while held.
+#[derive(Debug)]
+struct RevisionLease {
+    world_id: String,
+    location_id: String,
+    revision: u64,
+}
+
+impl RevisionLease {
+    fn acquire(world_id: &str, location_id: &str, revision: u64) -> Self {
+        let lease = Self {
+            world_id: world_id.to_string(),
+            location_id: location_id.to_string(),
+            revision,
+        };
+        *REVISION_LEASES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(lease.key())
+            .or_default() += 1;
+        lease
+    }
+
+    fn key(&self) -> (String, String, u64) {
+        (
+            self.world_id.clone(),
+            self.location_id.clone(),
+            self.revision,
+        )
+    }
+}
+
+impl Drop for RevisionLease {
+    fn drop(&mut self) {
+        let mut leases = REVISION_LEASES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let key = self.key();
+        if let Some(count) = leases.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                leases.remove(&key);
+            }
+        }
+    }
+}
+
+fn leased_revisions(world_id: &str, location_id: &str) -> Vec<u64> {
+    REVISION_LEASES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .keys()
+        .filter(|(world, location, _)| world == world_id && location == location_id)
+        .map(|(_, _, revision)| *revision)
+        .collect()
+}
+
+/// Lock serializing revision writes to one location.
+pub fn location_lock(world_id: &str, location_id: &str) -> Arc<Mutex<()>> {
+    let mut locks = LOCATION_LOCKS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    locks
+        .entry((world_id.to_string(), location_id.to_string()))
+        .or_default()
+        .clone()
+}
+
+/// Replaces the location with a freshly chunked map, under the location's lock.
 pub fn write_chunked_location(
     planets_dir: &Path,
     world_id: &str,
     location_id: &str,
     map: &ExplorationMap,
 ) -> Result<ExplorationManifestDescriptor, String> {
-    let location_dir = location_dir(planets_dir, world_id, location_id);
-    let chunks_dir = chunks_dir(planets_dir, world_id, location_id);
-    fs::create_dir_all(&chunks_dir)
+    let lock = location_lock(world_id, location_id);
+    let _guard = lock
+        .lock()
+        .map_err(|_| "Exploration location lock poisoned".to_string())?;
+    store_chunked_location(planets_dir, world_id, location_id, map)
+}
+
+fn store_chunked_location(
+    planets_dir: &Path,
+    world_id: &str,
+    location_id: &str,
+    map: &ExplorationMap,
+) -> Result<ExplorationManifestDescriptor, String> {
+    let revision = load_storage_manifest(planets_dir, world_id, location_id)
+        .map(|storage| storage.descriptor.revision + 1)
+        .unwrap_or(1);
+    let (storage, chunks) = chunk_map(world_id, location_id, map, revision);
+    write_chunk_store(planets_dir, storage, &chunks, None).map(|storage| storage.descriptor)
+}
+
+/// Stores an edited map as the next revision of `storage`, keeping its descriptor apart from the
+/// revision. Unchanged chunks are linked from the current revision rather than re-encoded into
+/// new files; returns the new descriptor and the ids of the chunks whose content changed.
+pub fn write_location_edit(
+    planets_dir: &Path,
+    storage: &ExplorationStorageManifest,
+    map: &ExplorationMap,
+) -> Result<(ExplorationManifestDescriptor, Vec<String>), String> {
+    let revision = storage.descriptor.revision + 1;
+    let (_, chunks) = chunk_map(
+        &storage.descriptor.world_id,
+        &storage.descriptor.location_id,
+        map,
+        revision,
+    );
+    let mut next = storage.clone();
+    next.descriptor.revision = revision;
+    next.pawns = map.pawns.clone();
+    next.triggers = map.triggers.clone();
+    let written = write_chunk_store(planets_dir, next, &chunks, Some(storage))?;
+    let changed = chunks
+        .iter()
+        .filter(|chunk| {
+            written.chunk_hash(chunk.level, chunk.chunk_row, chunk.chunk_col)
+                != storage.chunk_hash(chunk.level, chunk.chunk_row, chunk.chunk_col)
+        })
+        .map(|chunk| chunk.id.clone())
+        .collect();
+    Ok((written.descriptor, changed))
+}
+
+/// Writes every chunk into a fresh revision directory, then swaps the manifest in with a rename.
+/// Readers follow the manifest, so an interrupted write leaves the previous revision intact.
+/// Chunks identical to one in `previous` are hard-linked from its revision directory. The revision
+/// being replaced is kept until the following write; older ones are pruned.
+fn write_chunk_store(
+    planets_dir: &Path,
+    mut storage: ExplorationStorageManifest,
+    chunks: &[ExplorationChunk],
+    previous: Option<&ExplorationStorageManifest>,
+) -> Result<ExplorationStorageManifest, String> {
+    let world_id = storage.descriptor.world_id.clone();
+    let location_id = storage.descriptor.location_id.clone();
+    let staging_dir = revision_dir(
+        planets_dir,
+        &world_id,
+        &location_id,
+        storage.descriptor.revision,
+    );
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir).map_err(|error| {
+            format!("Failed to clear interrupted exploration chunk write: {error}")
+        })?;
+    }
+    fs::create_dir_all(&staging_dir)
         .map_err(|error| format!("Failed to create exploration chunk directory: {error}"))?;
 
-    if let Ok(entries) = fs::read_dir(&chunks_dir) {
-        for entry in entries.flatten() {
-            let _ = fs::remove_file(entry.path());
+    let export_json = chunk_json_export_enabled();
+    storage.chunk_hashes.clear();
+    for chunk in chunks {
+        let bytes = encode_chunk(chunk)?;
+        let hash = chunk_hash(&bytes);
+        let path = chunk_path(
+            planets_dir,
+            &storage,
+            chunk.level,
+            chunk.chunk_row,
+            chunk.chunk_col,
+        );
+        let linked = previous
+            .filter(|previous| {
+                previous.chunk_hash(chunk.level, chunk.chunk_row, chunk.chunk_col)
+                    == Some(hash.as_str())
+            })
+            .is_some_and(|previous| {
+                let source = chunk_path(
+                    planets_dir,
+                    previous,
+                    chunk.level,
+                    chunk.chunk_row,
+                    chunk.chunk_col,
+                );
+                fs::hard_link(&source, &path).is_ok()
+            });
+        if !linked {
+            fs::write(&path, bytes)
+                .map_err(|error| format!("Failed to write exploration chunk: {error}"))?;
+        }
+        storage.chunk_hashes.insert(
+            chunk_key(chunk.level, chunk.chunk_row, chunk.chunk_col),
+            hash,
+        );
+        if export_json {
+            let chunk_json = serde_json::to_string_pretty(chunk)
+                .map_err(|error| format!("Failed to serialize exploration chunk: {error}"))?;
+            fs::write(
+                staging_dir.join(format!(
+                    "chunk_{}.json",
+                    chunk_key(chunk.level, chunk.chunk_row, chunk.chunk_col)
+                )),
+                chunk_json,
+            )
+            .map_err(|error| format!("Failed to export exploration chunk JSON: {error}"))?;
         }
     }
 
-    let (storage, chunks) = chunk_map(world_id, location_id, map);
     let manifest_json = serde_json::to_string_pretty(&storage)
         .map_err(|error| format!("Failed to serialize exploration manifest: {error}"))?;
-    fs::write(location_dir.join("manifest.json"), manifest_json)
+    let manifest_path = manifest_path(planets_dir, &world_id, &location_id);
+    let temp_path = manifest_path.with_extension("json.tmp");
+    fs::write(&temp_path, manifest_json)
         .map_err(|error| format!("Failed to write exploration manifest: {error}"))?;
+    fs::rename(&temp_path, &manifest_path)
+        .map_err(|error| format!("Failed to replace exploration manifest: {error}"))?;
 
-    for chunk in chunks {
-        let chunk_json = serde_json::to_string_pretty(&chunk)
-            .map_err(|error| format!("Failed to serialize exploration chunk: {error}"))?;
-        fs::write(
-            chunk_path(
-                planets_dir,
-                world_id,
-                location_id,
-                chunk.chunk_row,
-                chunk.chunk_col,
-            ),
-            chunk_json,
-        )
-        .map_err(|error| format!("Failed to write exploration chunk: {error}"))?;
+    // The revision just replaced stays on disk until the next write, so a reader that loaded
+    // the old manifest can still finish, and revisions open in a session stay until it closes.
+    let retained_dirs = storage
+        .descriptor
+        .revision
+        .checked_sub(1)
+        .into_iter()
+        .chain(leased_revisions(&world_id, &location_id))
+        .map(|revision| revision_dir(planets_dir, &world_id, &location_id, revision))
+        .collect::<Vec<_>>();
+    if let Ok(entries) = fs::read_dir(chunks_dir(planets_dir, &world_id, &location_id)) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path == staging_dir || retained_dirs.contains(&path) {
+                continue;
+            }
+            let _ = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+        }
     }
 
-    Ok(storage.descriptor)
+    Ok(storage)
 }
 
+/// Migrates a legacy location to the current chunk store under the location's lock, then loads
+/// its storage manifest.
 pub fn ensure_chunked_location(
     planets_dir: &Path,
     world_id: &str,
     location_id: &str,
 ) -> Result<ExplorationStorageManifest, String> {
-    let manifest_path = manifest_path(planets_dir, world_id, location_id);
-    let content = fs::read_to_string(&manifest_path)
+    if pending_migration(planets_dir, world_id, location_id)?.is_some() {
+        let lock = location_lock(world_id, location_id);
+        let _guard = lock
+            .lock()
+            .map_err(|_| "Exploration location lock poisoned".to_string())?;
+        migrate_location(planets_dir, world_id, location_id)?;
+    }
+    load_storage_manifest(planets_dir, world_id, location_id)
+}
+
+enum PendingMigration {
+    /// A pre-chunking manifest holding the whole map.
+    Rechunk(Value),
+    /// A version 3 location with JSON chunks.
+    JsonChunks,
+}
+
+fn pending_migration(
+    planets_dir: &Path,
+    world_id: &str,
+    location_id: &str,
+) -> Result<Option<PendingMigration>, String> {
+    let content = fs::read_to_string(manifest_path(planets_dir, world_id, location_id))
         .map_err(|error| format!("Failed to read exploration manifest: {error}"))?;
     let value = serde_json::from_str::<Value>(&content)
         .map_err(|error| format!("Failed to parse exploration manifest: {error}"))?;
 
     let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
     let has_chunks = chunks_dir(planets_dir, world_id, location_id).exists();
+    Ok(if version < 3 || !has_chunks {
+        Some(PendingMigration::Rechunk(value))
+    } else if version < EXPLORATION_MANIFEST_VERSION as u64 {
+        Some(PendingMigration::JsonChunks)
+    } else {
+        None
+    })
+}
 
-    if version < 3 || !has_chunks {
-        let map = serde_json::from_value::<ExplorationMap>(value)
-            .map_err(|error| format!("Failed to migrate exploration manifest: {error}"))?;
-        write_chunked_location(planets_dir, world_id, location_id, &map)?;
+/// Runs whatever migration the location still needs. The caller holds the location lock, and
+/// the manifest is re-read here so a migration finished while waiting for it is not repeated.
+fn migrate_location(planets_dir: &Path, world_id: &str, location_id: &str) -> Result<(), String> {
+    match pending_migration(planets_dir, world_id, location_id)? {
+        Some(PendingMigration::Rechunk(value)) => {
+            let map = serde_json::from_value::<ExplorationMap>(value)
+                .map_err(|error| format!("Failed to migrate exploration manifest: {error}"))?;
+            store_chunked_location(planets_dir, world_id, location_id, &map).map(|_| ())
+        }
+        Some(PendingMigration::JsonChunks) => {
+            migrate_json_chunks(planets_dir, world_id, location_id)
+        }
+        None => Ok(()),
     }
+}
 
-    load_storage_manifest(planets_dir, world_id, location_id)
+/// Re-encodes a version 3 location, whose chunks are JSON files, into the binary store.
+fn migrate_json_chunks(
+    planets_dir: &Path,
+    world_id: &str,
+    location_id: &str,
+) -> Result<(), String> {
+    let mut storage = load_storage_manifest(planets_dir, world_id, location_id)?;
+    let chunk_size = storage.descriptor.chunk_size.max(1);
+    let mut chunks = Vec::new();
+    for chunk_row in 0..storage.descriptor.height.div_ceil(chunk_size) {
+        for chunk_col in 0..storage.descriptor.width.div_ceil(chunk_size) {
+            let path = legacy_chunk_path(planets_dir, world_id, location_id, chunk_row, chunk_col);
+            if !path.exists() {
+                continue;
+            }
+            let content = fs::read_to_string(&path)
+                .map_err(|error| format!("Failed to read exploration chunk: {error}"))?;
+            chunks.push(
+                serde_json::from_str::<ExplorationChunk>(&content)
+                    .map_err(|error| format!("Failed to parse exploration chunk: {error}"))?,
+            );
+        }
+    }
+    storage.descriptor.version = EXPLORATION_MANIFEST_VERSION;
+    storage.descriptor.revision += 1;
+    write_chunk_store(planets_dir, storage, &chunks, None).map(|_| ())
 }
 
 pub fn load_storage_manifest(
@@ -135,29 +489,43 @@ pub fn load_manifest_descriptor(
     Ok(ensure_chunked_location(planets_dir, world_id, location_id)?.descriptor)
 }
 
-pub fn load_chunk(
+/// Encoded chunk bytes and their content hash, for serving the chunk with an ETag.
+pub fn load_chunk_bytes(
     planets_dir: &Path,
     world_id: &str,
     location_id: &str,
+    level: i32,
     chunk_row: u32,
     chunk_col: u32,
-) -> Result<Option<ExplorationChunk>, String> {
-    ensure_chunked_location(planets_dir, world_id, location_id)?;
-    let path = chunk_path(planets_dir, world_id, location_id, chunk_row, chunk_col);
+) -> Result<Option<(Vec<u8>, String)>, String> {
+    let storage = ensure_chunked_location(planets_dir, world_id, location_id)?;
+    read_stored_chunk(planets_dir, &storage, level, chunk_row, chunk_col)
+}
+
+fn read_stored_chunk(
+    planets_dir: &Path,
+    storage: &ExplorationStorageManifest,
+    level: i32,
+    chunk_row: u32,
+    chunk_col: u32,
+) -> Result<Option<(Vec<u8>, String)>, String> {
+    let Some(hash) = storage.chunk_hash(level, chunk_row, chunk_col) else {
+        return Ok(None);
+    };
+    let path = chunk_path(planets_dir, storage, level, chunk_row, chunk_col);
     if !path.exists() {
         return Ok(None);
     }
-    let content = fs::read_to_string(&path)
-        .map_err(|error| format!("Failed to read exploration chunk: {error}"))?;
-    let chunk = serde_json::from_str::<ExplorationChunk>(&content)
-        .map_err(|error| format!("Failed to parse exploration chunk: {error}"))?;
-    Ok(Some(chunk))
+    let bytes =
+        fs::read(&path).map_err(|error| format!("Failed to read exploration chunk: {error}"))?;
+    Ok(Some((bytes, hash.to_string())))
 }
 
 pub fn load_chunks_in_radius(
     planets_dir: &Path,
     world_id: &str,
     location_id: &str,
+    level: i32,
     center_row: u32,
     center_col: u32,
     radius: u32,
@@ -177,16 +545,73 @@ pub fn load_chunks_in_radius(
     let mut chunks = Vec::new();
     for chunk_row in min_row..=max_row {
         for chunk_col in min_col..=max_col {
-            if let Some(chunk) =
-                load_chunk(planets_dir, world_id, location_id, chunk_row, chunk_col)?
+            if let Some((bytes, _)) =
+                read_stored_chunk(planets_dir, &storage, level, chunk_row, chunk_col)?
             {
-                chunks.push(chunk);
+                chunks.push(decode_chunk(&bytes)?);
             }
         }
     }
     Ok(chunks)
 }
 
+/// Reads single chunks of one manifest revision on demand, so a session decodes only the chunks
+/// it visits. The revision stays on disk while any source reading it is alive, however many
+/// edits land in the meantime.
+#[derive(Debug, Clone)]
+pub struct ChunkSource {
+    planets_dir: PathBuf,
+    storage: ExplorationStorageManifest,
+    _lease: Arc<RevisionLease>,
+}
+
+impl ChunkSource {
+    pub fn open(planets_dir: &Path, world_id: &str, location_id: &str) -> Result<Self, String> {
+        ensure_chunked_location(planets_dir, world_id, location_id)?;
+        // Writers hold the lock, so the revision read here cannot be pruned before it is leased.
+        let lock = location_lock(world_id, location_id);
+        let _guard = lock
+            .lock()
+            .map_err(|_| "Exploration location lock poisoned".to_string())?;
+        let storage = load_storage_manifest(planets_dir, world_id, location_id)?;
+        let lease = RevisionLease::acquire(world_id, location_id, storage.descriptor.revision);
+        Ok(Self {
+            planets_dir: planets_dir.to_path_buf(),
+            storage,
+            _lease: Arc::new(lease),
+        })
+    }
+
+    pub fn level_ids(&self) -> Vec<i32> {
+        self.storage.level_ids()
+    }
+
+    /// Whether the revision stores a chunk at this key; absent chunks are never read.
+    pub fn has_chunk(&self, level: i32, chunk_row: u32, chunk_col: u32) -> bool {
+        self.storage
+            .chunk_hash(level, chunk_row, chunk_col)
+            .is_some()
+    }
+
+    pub fn load_chunk(
+        &self,
+        level: i32,
+        chunk_row: u32,
+        chunk_col: u32,
+    ) -> Result<Option<ExplorationChunk>, String> {
+        read_stored_chunk(
+            &self.planets_dir,
+            &self.storage,
+            level,
+            chunk_row,
+            chunk_col,
+        )?
+        .map(|(bytes, _)| decode_chunk(&bytes))
+        .transpose()
+    }
+}
+
+/// Every stored chunk of every level.
 pub fn load_all_chunks(
     planets_dir: &Path,
     world_id: &str,
@@ -197,24 +622,124 @@ pub fn load_all_chunks(
     let max_chunk_row = storage.descriptor.height.saturating_sub(1) / chunk_size;
     let max_chunk_col = storage.descriptor.width.saturating_sub(1) / chunk_size;
     let mut chunks = Vec::new();
-    for chunk_row in 0..=max_chunk_row {
-        for chunk_col in 0..=max_chunk_col {
-            if let Some(chunk) =
-                load_chunk(planets_dir, world_id, location_id, chunk_row, chunk_col)?
-            {
-                chunks.push(chunk);
+    for level in storage.level_ids() {
+        for chunk_row in 0..=max_chunk_row {
+            for chunk_col in 0..=max_chunk_col {
+                if let Some((bytes, _)) =
+                    read_stored_chunk(planets_dir, &storage, level, chunk_row, chunk_col)?
+                {
+                    chunks.push(decode_chunk(&bytes)?);
+                }
             }
         }
     }
     Ok(chunks)
 }
 
+/// Reassembles the full map of a chunked location from its stored chunks, for editing. The
+/// caller holds the location lock.
+pub fn load_location_map(
+    planets_dir: &Path,
+    world_id: &str,
+    location_id: &str,
+) -> Result<(ExplorationStorageManifest, ExplorationMap), String> {
+    migrate_location(planets_dir, world_id, location_id)?;
+    let storage = load_storage_manifest(planets_dir, world_id, location_id)?;
+    let width = storage.descriptor.width;
+    let height = storage.descriptor.height;
+    let mut levels = BTreeMap::<i32, (Vec<Option<ExplorationTile>>, Vec<ExplorationObject>)>::new();
+    for chunk in load_all_chunks(planets_dir, world_id, location_id)? {
+        let (tiles, objects) = levels
+            .entry(chunk.level)
+            .or_insert_with(|| (vec![None; (width * height) as usize], Vec::new()));
+        for (offset, tile) in chunk.tiles.into_iter().enumerate() {
+            let row = chunk.origin_row + offset as u32 / chunk.width.max(1);
+            let col = chunk.origin_col + offset as u32 % chunk.width.max(1);
+            if let Some(slot) = tiles.get_mut((row * width + col) as usize) {
+                *slot = Some(tile);
+            }
+        }
+        objects.extend(chunk.objects);
+    }
+
+    let mut ground = None;
+    let mut extra_levels = Vec::new();
+    for (level, (tiles, objects)) in levels {
+        let tiles = tiles
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("Failed to load exploration level {level}: missing chunks"))?;
+        if level == 0 {
+            ground = Some((tiles, objects));
+        } else {
+            extra_levels.push(ExplorationMapLevel {
+                level,
+                name: storage
+                    .descriptor
+                    .levels
+                    .iter()
+                    .find(|entry| entry.level == level)
+                    .map(|entry| entry.name.clone()),
+                tiles,
+                objects,
+            });
+        }
+    }
+    let (tiles, objects) =
+        ground.ok_or_else(|| "Failed to load exploration map: no ground level".to_string())?;
+    // Storage lists levels ground first, then in generation order.
+    extra_levels.sort_by_key(|entry| {
+        storage
+            .descriptor
+            .levels
+            .iter()
+            .position(|level| level.level == entry.level)
+    });
+
+    let map = ExplorationMap {
+        id: storage.descriptor.id.clone(),
+        width,
+        height,
+        tiles,
+        pawns: storage.pawns.clone(),
+        objects,
+        name: Some(storage.descriptor.name.clone()),
+        fog_of_war: None,
+        ambient_light: Some(storage.descriptor.ambient_light),
+        version: Some(storage.descriptor.version),
+        render_mode: Some(storage.descriptor.render_mode.clone()),
+        metadata: storage.descriptor.metadata.clone(),
+        levels: extra_levels,
+        triggers: storage.triggers.clone(),
+    };
+    Ok((storage, map))
+}
+
 fn chunk_map(
     world_id: &str,
     location_id: &str,
     map: &ExplorationMap,
+    revision: u64,
 ) -> (ExplorationStorageManifest, Vec<ExplorationChunk>) {
     let spawn = find_spawn(map);
+    let levels = if map.levels.is_empty() {
+        Vec::new()
+    } else {
+        std::iter::once(ExplorationLevel {
+            level: 0,
+            name: "Ground".to_string(),
+        })
+        .chain(map.levels.iter().map(|level| {
+            ExplorationLevel {
+                level: level.level,
+                name: level
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("Level {}", level.level)),
+            }
+        }))
+        .collect()
+    };
     let descriptor = ExplorationManifestDescriptor {
         id: map.id.clone(),
         world_id: world_id.to_string(),
@@ -223,12 +748,36 @@ fn chunk_map(
         width: map.width,
         height: map.height,
         chunk_size: EXPLORATION_CHUNK_SIZE,
-        version: 3,
+        version: EXPLORATION_MANIFEST_VERSION,
+        revision,
         render_mode: "isometric".to_string(),
         ambient_light: map.ambient_light.unwrap_or(0.76),
         spawn,
         metadata: map.metadata.clone(),
+        levels,
     };
+    let mut chunks = chunk_level(map, 0, &map.tiles, &map.objects);
+    for level in &map.levels {
+        chunks.extend(chunk_level(map, level.level, &level.tiles, &level.objects));
+    }
+
+    (
+        ExplorationStorageManifest {
+            descriptor,
+            pawns: map.pawns.clone(),
+            chunk_hashes: BTreeMap::new(),
+            triggers: map.triggers.clone(),
+        },
+        chunks,
+    )
+}
+
+fn chunk_level(
+    map: &ExplorationMap,
+    level: i32,
+    level_tiles: &[ExplorationTile],
+    level_objects: &[ExplorationObject],
+) -> Vec<ExplorationChunk> {
     let mut chunks = Vec::new();
     let chunk_cols = map.width.div_ceil(EXPLORATION_CHUNK_SIZE);
     let chunk_rows = map.height.div_ceil(EXPLORATION_CHUNK_SIZE);
@@ -245,23 +794,26 @@ fn chunk_map(
                     let world_row = origin_row + row;
                     let world_col = origin_col + col;
                     let index = (world_row * map.width + world_col) as usize;
-                    if let Some(tile) = map.tiles.get(index) {
+                    if let Some(tile) = level_tiles.get(index) {
                         tiles.push(tile.clone());
                     }
                 }
             }
 
-            let objects = map
-                .objects
+            let objects = level_objects
                 .iter()
                 .filter(|object| {
                     object_belongs_to_chunk(object, origin_row, origin_col, width, height)
                 })
-                .cloned()
+                .map(|object| ExplorationObject {
+                    level,
+                    ..object.clone()
+                })
                 .collect::<Vec<_>>();
 
             chunks.push(ExplorationChunk {
-                id: format!("chunk-{chunk_row}-{chunk_col}"),
+                id: chunk_id(level, chunk_row, chunk_col),
+                level,
                 chunk_row,
                 chunk_col,
                 origin_row,
@@ -273,14 +825,7 @@ fn chunk_map(
             });
         }
     }
-
-    (
-        ExplorationStorageManifest {
-            descriptor,
-            pawns: map.pawns.clone(),
-        },
-        chunks,
-    )
+    chunks
 }
 
 fn object_belongs_to_chunk(
@@ -332,9 +877,26 @@ fn find_spawn(map: &ExplorationMap) -> ExplorationSpawnPoint {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::exploration_engine::types::{ExplorationObject, ExplorationTile};
+    use crate::exploration_engine::state::{
+        load_session_state, new_save_state, save_session_state,
+    };
+    use crate::exploration_engine::types::{
+        ExplorationLevelLink, ExplorationMapLevel, ExplorationMovementMode,
+    };
     use std::time::{SystemTime, UNIX_EPOCH};
 
+    fn load_chunk(
+        planets_dir: &Path,
+        world_id: &str,
+        location_id: &str,
+        chunk_row: u32,
+        chunk_col: u32,
+    ) -> Result<Option<ExplorationChunk>, String> {
+        load_chunk_bytes(planets_dir, world_id, location_id, 0, chunk_row, chunk_col)?
+            .map(|(bytes, _)| decode_chunk(&bytes))
+            .transpose()
+    }
+
     fn unique_temp_dir() -> PathBuf {
         let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -376,6 +938,7 @@ mod tests {
                     } else {
                         None
                     },
+                    level_link: None,
                 });
             }
         }
@@ -389,6 +952,7 @@ mod tests {
                 name: "NPC".to_string(),
                 x: 6.0,
                 y: 6.0,
+                level: 0,
                 tile_row: 6,
                 tile_col: 6,
                 target_x: None,
@@ -412,6 +976,13 @@ mod tests {
                 current_anchor_id: Some("6:6".to_string()),
                 current_intent: Some("idle".to_string()),
                 next_decision_at_tick: None,
+                stats: None,
+                conditions: Vec::new(),
+                wildlife: None,
+                movement_mode: ExplorationMovementMode::Walk,
+                armor_noise: 0.0,
+                awareness: None,
+                inventory: Vec::new(),
             }],
             objects: vec![
                 ExplorationObject {
@@ -432,6 +1003,8 @@ mod tests {
                     roof_group_id: Some("roof-a".to_string()),
                     height_tiles: Some(2),
                     blocks_light: Some(false),
+                    flora_id: None,
+                    level: 0,
                 },
                 ExplorationObject {
                     id: "roof-object".to_string(),
@@ -451,6 +1024,8 @@ mod tests {
                     roof_group_id: Some("roof-a".to_string()),
                     height_tiles: Some(2),
                     blocks_light: Some(true),
+                    flora_id: None,
+                    level: 0,
                 },
             ],
             name: Some("Test".to_string()),
@@ -459,6 +1034,8 @@ mod tests {
             version: Some(2),
             render_mode: Some("isometric".to_string()),
             metadata: Some(serde_json::json!({ "source": "test" })),
+            levels: Vec::new(),
+            triggers: Vec::new(),
         }
     }
 
@@ -471,11 +1048,11 @@ mod tests {
 
         let descriptor =
             write_chunked_location(&dir, world_id, location_id, &sample_map()).unwrap();
-        assert_eq!(descriptor.version, 3);
+        assert_eq!(descriptor.version, EXPLORATION_MANIFEST_VERSION);
         assert_eq!(descriptor.chunk_size, EXPLORATION_CHUNK_SIZE);
 
         let storage = ensure_chunked_location(&dir, world_id, location_id).unwrap();
-        assert_eq!(storage.descriptor.version, 3);
+        assert_eq!(storage.descriptor.version, EXPLORATION_MANIFEST_VERSION);
         assert_eq!(storage.pawns.len(), 1);
 
         let chunk = load_chunk(&dir, world_id, location_id, 0, 0)
@@ -497,7 +1074,7 @@ mod tests {
         fs::write(location_dir.join("manifest.json"), content).unwrap();
 
         let storage = ensure_chunked_location(&dir, world_id, location_id).unwrap();
-        assert_eq!(storage.descriptor.version, 3);
+        assert_eq!(storage.descriptor.version, EXPLORATION_MANIFEST_VERSION);
         assert!(chunks_dir(&dir, world_id, location_id).exists());
         assert!(load_chunk(&dir, world_id, location_id, 0, 0)
             .unwrap()
@@ -505,4 +1082,234 @@ mod tests {
 
         let _ = fs::remove_dir_all(dir);
     }
+
+    #[test]
+    fn concurrent_readers_migrate_a_legacy_location_once() {
+        let dir = unique_temp_dir();
+        let world_id = "world-g";
+        let location_id = "loc-g";
+        fs::create_dir_all(location_dir(&dir, world_id, location_id)).unwrap();
+        let content = serde_json::to_string_pretty(&sample_map()).unwrap();
+        fs::write(manifest_path(&dir, world_id, location_id), content).unwrap();
+
+        let revisions = std::thread::scope(|scope| {
+            let readers = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        ensure_chunked_location(&dir, world_id, location_id)
+                            .unwrap()
+                            .descriptor
+                            .revision
+                    })
+                })
+                .collect::<Vec<_>>();
+            readers
+                .into_iter()
+                .map(|reader| reader.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(revisions, vec![1; 8]);
+        assert_eq!(
+            fs::read_dir(chunks_dir(&dir, world_id, location_id))
+                .unwrap()
+                .count(),
+            1
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn migrates_v3_json_chunks_to_binary_store() {
+        let dir = unique_temp_dir();
+        let world_id = "world-d";
+        let location_id = "loc-d";
+        let (mut storage, chunks) = chunk_map(world_id, location_id, &sample_map(), 1);
+        storage.descriptor.version = 3;
+        fs::create_dir_all(chunks_dir(&dir, world_id, location_id)).unwrap();
+        fs::write(
+            manifest_path(&dir, world_id, location_id),
+            serde_json::to_string_pretty(&storage).unwrap(),
+        )
+        .unwrap();
+        for chunk in &chunks {
+            fs::write(
+                legacy_chunk_path(
+                    &dir,
+                    world_id,
+                    location_id,
+                    chunk.chunk_row,
+                    chunk.chunk_col,
+                ),
+                serde_json::to_string_pretty(chunk).unwrap(),
+            )
+            .unwrap();
+        }
+
+        let migrated = ensure_chunked_location(&dir, world_id, location_id).unwrap();
+        assert_eq!(migrated.descriptor.version, EXPLORATION_MANIFEST_VERSION);
+        assert_eq!(migrated.descriptor.revision, 2);
+        assert_eq!(migrated.chunk_hashes.len(), chunks.len());
+        assert!(!legacy_chunk_path(&dir, world_id, location_id, 0, 0).exists());
+        let loaded = load_all_chunks(&dir, world_id, location_id).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&chunks).unwrap()
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn interrupted_write_keeps_previous_revision_readable() {
+        let dir = unique_temp_dir();
+        let world_id = "world-e";
+        let location_id = "loc-e";
+        fs::create_dir_all(location_dir(&dir, world_id, location_id)).unwrap();
+        let descriptor =
+            write_chunked_location(&dir, world_id, location_id, &sample_map()).unwrap();
+
+        // A crash before the manifest swap leaves a partial directory for the next revision.
+        let partial = revision_dir(&dir, world_id, location_id, descriptor.revision + 1);
+        fs::create_dir_all(&partial).unwrap();
+        fs::write(partial.join("chunk_0_0.bin"), b"partial").unwrap();
+
+        let (bytes, hash) = load_chunk_bytes(&dir, world_id, location_id, 0, 0, 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(chunk_hash(&bytes), hash);
+        assert_eq!(
+            load_chunks_in_radius(&dir, world_id, location_id, 0, 0, 0, 0)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let rewritten = write_chunked_location(&dir, world_id, location_id, &sample_map()).unwrap();
+        assert_eq!(rewritten.revision, descriptor.revision + 1);
+        assert!(load_chunk(&dir, world_id, location_id, 0, 0)
+            .unwrap()
+            .is_some());
+        assert!(revision_dir(&dir, world_id, location_id, descriptor.revision).exists());
+
+        let pruned = write_chunked_location(&dir, world_id, location_id, &sample_map()).unwrap();
+        assert_eq!(pruned.revision, rewritten.revision + 1);
+        assert!(revision_dir(&dir, world_id, location_id, rewritten.revision).exists());
+        assert!(!revision_dir(&dir, world_id, location_id, descriptor.revision).exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn open_chunk_source_survives_later_edits() {
+        let dir = unique_temp_dir();
+        let world_id = "world-h";
+        let location_id = "loc-h";
+        fs::create_dir_all(location_dir(&dir, world_id, location_id)).unwrap();
+        let opened = write_chunked_location(&dir, world_id, location_id, &sample_map()).unwrap();
+        let source = ChunkSource::open(&dir, world_id, location_id).unwrap();
+
+        for step in 0..2 {
+            let (storage, mut map) = load_location_map(&dir, world_id, location_id).unwrap();
+            map.tiles[step].r#type = "rubble".to_string();
+            write_location_edit(&dir, &storage, &map).unwrap();
+        }
+        assert_eq!(
+            load_storage_manifest(&dir, world_id, location_id)
+                .unwrap()
+                .descriptor
+                .revision,
+            opened.revision + 2
+        );
+        let chunk = source.load_chunk(0, 0, 0).unwrap().unwrap();
+        assert_eq!(chunk.tiles[0].r#type, sample_map().tiles[0].r#type);
+        let reopened = source.clone();
+        drop(source);
+        assert!(reopened.load_chunk(0, 0, 0).unwrap().is_some());
+
+        drop(reopened);
+        write_chunked_location(&dir, world_id, location_id, &sample_map()).unwrap();
+        assert!(!revision_dir(&dir, world_id, location_id, opened.revision).exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn chunks_each_level_separately() {
+        let dir = unique_temp_dir();
+        let world_id = "world-f";
+        let location_id = "loc-f";
+        fs::create_dir_all(location_dir(&dir, world_id, location_id)).unwrap();
+        let mut map = sample_map();
+        let mut basement = map.tiles.clone();
+        basement[9 * 18 + 9].level_link = Some(ExplorationLevelLink {
+            level: 0,
+            row: 9,
+            col: 9,
+            kind: "stairs".to_string(),
+        });
+        map.levels.push(ExplorationMapLevel {
+            level: -1,
+            name: Some("Cellar".to_string()),
+            tiles: basement,
+            objects: vec![map.objects[1].clone()],
+        });
+
+        let descriptor = write_chunked_location(&dir, world_id, location_id, &map).unwrap();
+        assert_eq!(
+            descriptor
+                .levels
+                .iter()
+                .map(|level| (level.level, level.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(0, "Ground"), (-1, "Cellar")]
+        );
+
+        let cellar = load_chunks_in_radius(&dir, world_id, location_id, -1, 9, 9, 0).unwrap();
+        assert_eq!(cellar.len(), 1);
+        assert_eq!(cellar[0].id, "chunk-l-1-0-0");
+        assert_eq!(cellar[0].objects[0].level, -1);
+        assert!(cellar[0].tiles[9 * 16 + 9].level_link.is_some());
+        assert!(load_chunk(&dir, world_id, location_id, 0, 0)
+            .unwrap()
+            .unwrap()
+            .tiles[9 * 16 + 9]
+            .level_link
+            .is_none());
+        assert_eq!(
+            load_all_chunks(&dir, world_id, location_id).unwrap().len(),
+            8
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn regenerating_location_invalidates_saved_session_state() {
+        let dir = unique_temp_dir();
+        let world_id = "world-c";
+        let location_id = "loc-c";
+        fs::create_dir_all(dir.join(world_id).join("exploration").join(location_id)).unwrap();
+
+        let descriptor =
+            write_chunked_location(&dir, world_id, location_id, &sample_map()).unwrap();
+        let mut saved = new_save_state(&descriptor, 1_000, 42);
+        saved.visibility.opened_door_ids.push("door-a".to_string());
+        save_session_state(&dir, world_id, location_id, &saved).unwrap();
+
+        let restored = load_session_state(&dir, &descriptor).unwrap().unwrap();
+        assert_eq!(restored.tick, 42);
+        assert_eq!(
+            restored.visibility.opened_door_ids,
+            vec!["door-a".to_string()]
+        );
+
+        let regenerated =
+            write_chunked_location(&dir, world_id, location_id, &sample_map()).unwrap();
+        assert!(regenerated.revision > descriptor.revision);
+        assert!(load_session_state(&dir, &regenerated).unwrap().is_none());
+        assert!(!session_state_path(&dir, world_id, location_id).exists());
+
+        let _ = fs::remove_dir_all(dir);
+    }
 }
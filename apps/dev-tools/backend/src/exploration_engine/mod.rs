@@ -1,4 +1,14 @@
+pub mod chunk_store;
+pub mod editing;
+pub mod harvest;
 pub mod manifest;
+pub mod party;
+pub mod pathfinding;
+pub mod protocol;
 pub mod sim;
 pub mod session;
+pub mod state;
+pub mod stealth;
+pub mod triggers;
 pub mod types;
+pub mod wildlife;
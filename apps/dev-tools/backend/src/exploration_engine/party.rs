@@ -0,0 +1,78 @@
+use super::types::ExplorationFormation;
+
+const FORMATION_SPACING: f32 = 1.0;
+const LOOSE_SPACING: f32 = 2.0;
+
+/// Slot offsets for `count` followers as `(back, right)` distances in tiles, measured in the
+/// leader's frame. Followers fill slots in order, so the closest slots come first.
+pub fn formation_offsets(formation: ExplorationFormation, count: usize) -> Vec<(f32, f32)> {
+    (1..=count)
+        .map(|index| {
+            let rank = index.div_ceil(2) as f32;
+            let side = if index % 2 == 1 { 1.0 } else { -1.0 };
+            match formation {
+                ExplorationFormation::Column => (index as f32 * FORMATION_SPACING, 0.0),
+                ExplorationFormation::Line => (0.0, side * rank * FORMATION_SPACING),
+                ExplorationFormation::Wedge => {
+                    (rank * FORMATION_SPACING, side * rank * FORMATION_SPACING)
+                }
+                ExplorationFormation::Loose => (rank * LOOSE_SPACING, side * LOOSE_SPACING * 0.75),
+            }
+        })
+        .collect()
+}
+
+/// Unit `(row, col)` heading from one tile to another, defaulting to south when they coincide.
+pub fn heading_between(from: (i32, i32), to: (i32, i32)) -> (f32, f32) {
+    let row = (to.0 - from.0) as f32;
+    let col = (to.1 - from.1) as f32;
+    let length = (row * row + col * col).sqrt();
+    if length < 0.001 {
+        return (1.0, 0.0);
+    }
+    (row / length, col / length)
+}
+
+pub fn heading_from_facing(facing: Option<&str>) -> (f32, f32) {
+    match facing {
+        Some("north") => (-1.0, 0.0),
+        Some("east") => (0.0, 1.0),
+        Some("west") => (0.0, -1.0),
+        _ => (1.0, 0.0),
+    }
+}
+
+/// Tile for a slot offset around `anchor`, with the leader facing along `heading`.
+pub fn slot_cell(anchor: (i32, i32), heading: (f32, f32), offset: (f32, f32)) -> (i32, i32) {
+    let (back, right) = offset;
+    // Right of a heading in row/col space, e.g. facing south puts the right hand to the west.
+    let right_axis = (heading.1, -heading.0);
+    let row = anchor.0 as f32 - heading.0 * back + right_axis.0 * right;
+    let col = anchor.1 as f32 - heading.1 * back + right_axis.1 * right;
+    (row.round() as i32, col.round() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_rotate_with_heading() {
+        let offsets = formation_offsets(ExplorationFormation::Column, 2);
+        assert_eq!(slot_cell((10, 10), (1.0, 0.0), offsets[0]), (9, 10));
+        assert_eq!(slot_cell((10, 10), (0.0, 1.0), offsets[1]), (10, 8));
+
+        let line = formation_offsets(ExplorationFormation::Line, 2);
+        let right = slot_cell((10, 10), (0.0, 1.0), line[0]);
+        let left = slot_cell((10, 10), (0.0, 1.0), line[1]);
+        assert_eq!(right, (11, 10));
+        assert_eq!(left, (9, 10));
+
+        let wedge = formation_offsets(ExplorationFormation::Wedge, 3);
+        let cells = wedge
+            .iter()
+            .map(|offset| slot_cell((10, 10), (-1.0, 0.0), *offset))
+            .collect::<Vec<_>>();
+        assert_eq!(cells, vec![(11, 11), (11, 9), (12, 12)]);
+    }
+}
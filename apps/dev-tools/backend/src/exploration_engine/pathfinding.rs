@@ -0,0 +1,1000 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Mutex;
+
+const SQRT_2: f32 = std::f32::consts::SQRT_2;
+const NO_PARENT: u32 = u32::MAX;
+/// Routes spanning at least this many chunks are planned over the portal graph first.
+const HIERARCHY_MIN_CHUNK_DISTANCE: u32 = 2;
+/// Border openings wider than this get a portal at each end instead of one in the middle.
+const MAX_SINGLE_PORTAL_RUN: usize = 6;
+const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, 0),
+    (1, 0),
+    (0, -1),
+    (0, 1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+];
+
+/// Walkability and move costs for a whole location, stored row-major. A cost of `0.0` marks a
+/// blocked cell. Besides the flat grid it keeps a chunk-level portal graph so long routes only
+/// search the chunks they cross.
+pub struct NavGrid {
+    width: u32,
+    height: u32,
+    chunk_size: u32,
+    chunk_rows: u32,
+    chunk_cols: u32,
+    costs: Vec<f32>,
+    uniform: Vec<bool>,
+    border_links: HashMap<(u32, u32), Vec<(u32, u32)>>,
+    intra_edges: Vec<Vec<(u32, u32, f32)>>,
+    scratch: Mutex<SearchScratch>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    min_row: i32,
+    min_col: i32,
+    max_row: i32,
+    max_col: i32,
+}
+
+impl Bounds {
+    fn contains(&self, row: i32, col: i32) -> bool {
+        row >= self.min_row && row <= self.max_row && col >= self.min_col && col <= self.max_col
+    }
+}
+
+struct Query<'a> {
+    grid: &'a NavGrid,
+    bounds: Bounds,
+    occupied: Option<&'a HashSet<u32>>,
+    ignore: Option<u32>,
+}
+
+impl Query<'_> {
+    fn walkable(&self, row: i32, col: i32) -> bool {
+        if !self.bounds.contains(row, col) {
+            return false;
+        }
+        let Some(index) = self.grid.index_of(row, col) else {
+            return false;
+        };
+        if self.grid.costs[index as usize] <= 0.0 {
+            return false;
+        }
+        match self.occupied {
+            Some(occupied) => Some(index) == self.ignore || !occupied.contains(&index),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OpenEntry {
+    f: f32,
+    g: f32,
+    index: u32,
+}
+
+impl PartialEq for OpenEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenEntry {}
+
+impl PartialOrd for OpenEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenEntry {
+    // Reversed on `f` so the std max-heap pops the cheapest entry; ties prefer deeper nodes.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .f
+            .total_cmp(&self.f)
+            .then_with(|| self.g.total_cmp(&other.g))
+    }
+}
+
+/// Per-cell search state reused across queries. A generation stamp marks which entries belong
+/// to the current search so nothing has to be cleared between calls.
+struct SearchScratch {
+    generation: u32,
+    seen: Vec<u32>,
+    closed: Vec<u32>,
+    g: Vec<f32>,
+    parent: Vec<u32>,
+    open: BinaryHeap<OpenEntry>,
+}
+
+impl SearchScratch {
+    fn new(cells: usize) -> Self {
+        Self {
+            generation: 0,
+            seen: vec![0; cells],
+            closed: vec![0; cells],
+            g: vec![f32::INFINITY; cells],
+            parent: vec![NO_PARENT; cells],
+            open: BinaryHeap::new(),
+        }
+    }
+
+    fn begin(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            self.seen.fill(0);
+            self.closed.fill(0);
+            self.generation = 1;
+        }
+        self.open.clear();
+    }
+
+    fn g(&self, index: u32) -> f32 {
+        if self.seen[index as usize] == self.generation {
+            self.g[index as usize]
+        } else {
+            f32::INFINITY
+        }
+    }
+
+    fn parent(&self, index: u32) -> u32 {
+        self.parent[index as usize]
+    }
+
+    fn relax(&mut self, index: u32, g: f32, parent: u32, h: f32) -> bool {
+        if g >= self.g(index) {
+            return false;
+        }
+        self.seen[index as usize] = self.generation;
+        self.g[index as usize] = g;
+        self.parent[index as usize] = parent;
+        self.open.push(OpenEntry { f: g + h, g, index });
+        true
+    }
+
+    fn pop(&mut self) -> Option<OpenEntry> {
+        while let Some(entry) = self.open.pop() {
+            if self.closed[entry.index as usize] == self.generation {
+                continue;
+            }
+            self.closed[entry.index as usize] = self.generation;
+            return Some(entry);
+        }
+        None
+    }
+}
+
+impl NavGrid {
+    pub fn new(width: u32, height: u32, chunk_size: u32, costs: Vec<f32>) -> Self {
+        let chunk_size = chunk_size.max(1);
+        let cells = (width * height) as usize;
+        let chunk_rows = height.div_ceil(chunk_size);
+        let chunk_cols = width.div_ceil(chunk_size);
+        let mut grid = Self {
+            width,
+            height,
+            chunk_size,
+            chunk_rows,
+            chunk_cols,
+            costs: if costs.len() == cells {
+                costs
+            } else {
+                vec![0.0; cells]
+            },
+            uniform: vec![false; cells],
+            border_links: HashMap::new(),
+            intra_edges: vec![Vec::new(); (chunk_rows * chunk_cols) as usize],
+            scratch: Mutex::new(SearchScratch::new(cells)),
+        };
+        grid.refresh_uniform(grid.full_bounds());
+        for chunk_row in 0..chunk_rows {
+            for chunk_col in 0..chunk_cols {
+                grid.rebuild_border(chunk_row, chunk_col, chunk_row, chunk_col + 1);
+                grid.rebuild_border(chunk_row, chunk_col, chunk_row + 1, chunk_col);
+            }
+        }
+        for chunk_row in 0..chunk_rows {
+            for chunk_col in 0..chunk_cols {
+                grid.rebuild_intra_edges(chunk_row, chunk_col);
+            }
+        }
+        grid
+    }
+
+    pub fn index_of(&self, row: i32, col: i32) -> Option<u32> {
+        if row < 0 || col < 0 || row >= self.height as i32 || col >= self.width as i32 {
+            return None;
+        }
+        Some(row as u32 * self.width + col as u32)
+    }
+
+    pub fn cost(&self, row: i32, col: i32) -> f32 {
+        self.index_of(row, col)
+            .map(|index| self.costs[index as usize])
+            .unwrap_or(0.0)
+    }
+
+    /// Origin and size of a chunk in cells, clipped to the map.
+    pub fn chunk_rect(&self, chunk_row: u32, chunk_col: u32) -> (i32, i32, u32, u32) {
+        let origin_row = chunk_row * self.chunk_size;
+        let origin_col = chunk_col * self.chunk_size;
+        let rows = self.chunk_size.min(self.height.saturating_sub(origin_row));
+        let cols = self.chunk_size.min(self.width.saturating_sub(origin_col));
+        (origin_row as i32, origin_col as i32, rows, cols)
+    }
+
+    /// Replaces the cost field of one chunk and rebuilds the portal graph around it.
+    pub fn update_chunk(&mut self, chunk_row: u32, chunk_col: u32, chunk_costs: &[f32]) {
+        let (origin_row, origin_col, rows, cols) = self.chunk_rect(chunk_row, chunk_col);
+        if chunk_costs.len() != (rows * cols) as usize {
+            return;
+        }
+        for local_row in 0..rows {
+            for local_col in 0..cols {
+                let row = origin_row + local_row as i32;
+                let col = origin_col + local_col as i32;
+                if let Some(index) = self.index_of(row, col) {
+                    self.costs[index as usize] =
+                        chunk_costs[(local_row * cols + local_col) as usize];
+                }
+            }
+        }
+        self.refresh_uniform(Bounds {
+            min_row: origin_row - 1,
+            min_col: origin_col - 1,
+            max_row: origin_row + rows as i32,
+            max_col: origin_col + cols as i32,
+        });
+
+        let neighbours = [
+            (chunk_row.wrapping_sub(1), chunk_col),
+            (chunk_row + 1, chunk_col),
+            (chunk_row, chunk_col.wrapping_sub(1)),
+            (chunk_row, chunk_col + 1),
+        ];
+        for (other_row, other_col) in neighbours {
+            if other_row < self.chunk_rows && other_col < self.chunk_cols {
+                if other_row < chunk_row || other_col < chunk_col {
+                    self.rebuild_border(other_row, other_col, chunk_row, chunk_col);
+                } else {
+                    self.rebuild_border(chunk_row, chunk_col, other_row, other_col);
+                }
+            }
+        }
+        self.rebuild_intra_edges(chunk_row, chunk_col);
+        for (other_row, other_col) in neighbours {
+            if other_row < self.chunk_rows && other_col < self.chunk_cols {
+                self.rebuild_intra_edges(other_row, other_col);
+            }
+        }
+    }
+
+    /// Finds a route from `start` to `goal` as `(row, col)` steps, excluding the start cell.
+    /// Cells in `occupied` are treated as blocked, except `ignore` (usually the mover's own
+    /// tile).
+    pub fn find_path(
+        &self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        occupied: &HashSet<u32>,
+        ignore: Option<u32>,
+    ) -> Option<Vec<(i32, i32)>> {
+        if start == goal {
+            return Some(Vec::new());
+        }
+        let start_index = self.index_of(start.0, start.1)?;
+        let goal_index = self.index_of(goal.0, goal.1)?;
+        let query = Query {
+            grid: self,
+            bounds: self.full_bounds(),
+            occupied: Some(occupied),
+            ignore,
+        };
+        if !query.walkable(goal.0, goal.1) {
+            return None;
+        }
+
+        let mut scratch = self
+            .scratch
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let (start_chunk_row, start_chunk_col) = self.chunk_of(start_index);
+        let (goal_chunk_row, goal_chunk_col) = self.chunk_of(goal_index);
+        let chunk_distance = start_chunk_row
+            .abs_diff(goal_chunk_row)
+            .max(start_chunk_col.abs_diff(goal_chunk_col));
+        if chunk_distance >= HIERARCHY_MIN_CHUNK_DISTANCE {
+            if let Some(path) =
+                self.hierarchical_path(&mut scratch, occupied, ignore, start_index, goal_index)
+            {
+                return Some(path);
+            }
+        }
+        self.jump_point_search(&mut scratch, &query, start_index, goal_index)
+    }
+
+    fn full_bounds(&self) -> Bounds {
+        Bounds {
+            min_row: 0,
+            min_col: 0,
+            max_row: self.height as i32 - 1,
+            max_col: self.width as i32 - 1,
+        }
+    }
+
+    fn chunk_bounds(&self, chunk_row: u32, chunk_col: u32) -> Bounds {
+        let (origin_row, origin_col, rows, cols) = self.chunk_rect(chunk_row, chunk_col);
+        Bounds {
+            min_row: origin_row,
+            min_col: origin_col,
+            max_row: origin_row + rows as i32 - 1,
+            max_col: origin_col + cols as i32 - 1,
+        }
+    }
+
+    fn row_col(&self, index: u32) -> (i32, i32) {
+        ((index / self.width) as i32, (index % self.width) as i32)
+    }
+
+    fn chunk_of(&self, index: u32) -> (u32, u32) {
+        let (row, col) = self.row_col(index);
+        (row as u32 / self.chunk_size, col as u32 / self.chunk_size)
+    }
+
+    fn chunk_index(&self, chunk_row: u32, chunk_col: u32) -> u32 {
+        chunk_row * self.chunk_cols + chunk_col
+    }
+
+    fn heuristic(&self, from: u32, to: u32) -> f32 {
+        let (from_row, from_col) = self.row_col(from);
+        let (to_row, to_col) = self.row_col(to);
+        let dx = (to_col - from_col).abs() as f32;
+        let dy = (to_row - from_row).abs() as f32;
+        (dx + dy) + (SQRT_2 - 2.0) * dx.min(dy)
+    }
+
+    /// A cell is uniform when it and all its neighbours are either blocked or cost exactly
+    /// `1.0`; jump point pruning is only valid across such cells.
+    fn refresh_uniform(&mut self, bounds: Bounds) {
+        for row in bounds.min_row.max(0)..=bounds.max_row.min(self.height as i32 - 1) {
+            for col in bounds.min_col.max(0)..=bounds.max_col.min(self.width as i32 - 1) {
+                let Some(index) = self.index_of(row, col) else {
+                    continue;
+                };
+                let uniform = DIRECTIONS
+                    .iter()
+                    .map(|(dr, dc)| self.cost(row + dr, col + dc))
+                    .chain(std::iter::once(self.costs[index as usize]))
+                    .all(|cost| cost <= 0.0 || cost == 1.0);
+                self.uniform[index as usize] = uniform;
+            }
+        }
+    }
+
+    fn rebuild_border(&mut self, chunk_row: u32, chunk_col: u32, other_row: u32, other_col: u32) {
+        if other_row >= self.chunk_rows || other_col >= self.chunk_cols {
+            return;
+        }
+        let bounds = self.chunk_bounds(chunk_row, chunk_col);
+        let pairs = if other_col > chunk_col {
+            (bounds.min_row..=bounds.max_row)
+                .map(|row| ((row, bounds.max_col), (row, bounds.max_col + 1)))
+                .collect::<Vec<_>>()
+        } else {
+            (bounds.min_col..=bounds.max_col)
+                .map(|col| ((bounds.max_row, col), (bounds.max_row + 1, col)))
+                .collect::<Vec<_>>()
+        };
+
+        let mut links = Vec::new();
+        let mut run = Vec::new();
+        for (inside, outside) in pairs
+            .into_iter()
+            .chain(std::iter::once(((-1, -1), (-1, -1))))
+        {
+            let open = self.cost(inside.0, inside.1) > 0.0 && self.cost(outside.0, outside.1) > 0.0;
+            if open {
+                run.push((inside, outside));
+                continue;
+            }
+            if run.is_empty() {
+                continue;
+            }
+            let picks = if run.len() > MAX_SINGLE_PORTAL_RUN {
+                vec![run[0], run[run.len() - 1]]
+            } else {
+                vec![run[run.len() / 2]]
+            };
+            for (inside, outside) in picks {
+                if let (Some(a), Some(b)) = (
+                    self.index_of(inside.0, inside.1),
+                    self.index_of(outside.0, outside.1),
+                ) {
+                    links.push((a, b));
+                }
+            }
+            run.clear();
+        }
+        let key = (
+            self.chunk_index(chunk_row, chunk_col),
+            self.chunk_index(other_row, other_col),
+        );
+        self.border_links.insert(key, links);
+    }
+
+    fn chunk_portals(&self, chunk_row: u32, chunk_col: u32) -> Vec<u32> {
+        let chunk = self.chunk_index(chunk_row, chunk_col);
+        let mut portals = self
+            .border_links
+            .iter()
+            .flat_map(|((a, b), links)| {
+                links.iter().filter_map(move |(left, right)| {
+                    if *a == chunk {
+                        Some(*left)
+                    } else if *b == chunk {
+                        Some(*right)
+                    } else {
+                        None
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        portals.sort_unstable();
+        portals.dedup();
+        portals
+    }
+
+    fn cross_links(&self, index: u32) -> Vec<u32> {
+        let (chunk_row, chunk_col) = self.chunk_of(index);
+        let chunk = self.chunk_index(chunk_row, chunk_col);
+        let mut partners = Vec::new();
+        let neighbours = [
+            (chunk_row.wrapping_sub(1), chunk_col),
+            (chunk_row + 1, chunk_col),
+            (chunk_row, chunk_col.wrapping_sub(1)),
+            (chunk_row, chunk_col + 1),
+        ];
+        for (other_row, other_col) in neighbours {
+            if other_row >= self.chunk_rows || other_col >= self.chunk_cols {
+                continue;
+            }
+            let other = self.chunk_index(other_row, other_col);
+            let key = (chunk.min(other), chunk.max(other));
+            let Some(links) = self.border_links.get(&key) else {
+                continue;
+            };
+            for (left, right) in links {
+                if *left == index {
+                    partners.push(*right);
+                } else if *right == index {
+                    partners.push(*left);
+                }
+            }
+        }
+        partners
+    }
+
+    fn rebuild_intra_edges(&mut self, chunk_row: u32, chunk_col: u32) {
+        let portals = self.chunk_portals(chunk_row, chunk_col);
+        let bounds = self.chunk_bounds(chunk_row, chunk_col);
+        let mut edges = Vec::new();
+        {
+            let mut scratch = self
+                .scratch
+                .lock()
+                .unwrap_or_else(|error| error.into_inner());
+            for portal in &portals {
+                let distances = self.dijkstra(&mut scratch, bounds, *portal, &portals);
+                for (other, cost) in distances {
+                    if other != *portal {
+                        edges.push((*portal, other, cost));
+                    }
+                }
+            }
+        }
+        let chunk = self.chunk_index(chunk_row, chunk_col) as usize;
+        self.intra_edges[chunk] = edges;
+    }
+
+    /// Costs from `source` to every reachable target inside `bounds`, ignoring pawns.
+    fn dijkstra(
+        &self,
+        scratch: &mut SearchScratch,
+        bounds: Bounds,
+        source: u32,
+        targets: &[u32],
+    ) -> Vec<(u32, f32)> {
+        let query = Query {
+            grid: self,
+            bounds,
+            occupied: None,
+            ignore: None,
+        };
+        scratch.begin();
+        scratch.relax(source, 0.0, NO_PARENT, 0.0);
+        let mut remaining = targets.len();
+        let mut found = Vec::new();
+        while let Some(entry) = scratch.pop() {
+            if targets.contains(&entry.index) {
+                found.push((entry.index, entry.g));
+                remaining -= 1;
+                if remaining == 0 {
+                    break;
+                }
+            }
+            let (row, col) = self.row_col(entry.index);
+            for (dr, dc) in DIRECTIONS {
+                if let Some((next, cost)) = self.step(&query, row, col, dr, dc) {
+                    scratch.relax(next, entry.g + cost, entry.index, 0.0);
+                }
+            }
+        }
+        found
+    }
+
+    /// Single move with the no-corner-cutting rule used by every search in this module.
+    fn step(&self, query: &Query, row: i32, col: i32, dr: i32, dc: i32) -> Option<(u32, f32)> {
+        let next_row = row + dr;
+        let next_col = col + dc;
+        if !query.walkable(next_row, next_col) {
+            return None;
+        }
+        let diagonal = dr != 0 && dc != 0;
+        if diagonal && !(query.walkable(row + dr, col) && query.walkable(row, col + dc)) {
+            return None;
+        }
+        let index = self.index_of(next_row, next_col)?;
+        let step_cost = if diagonal { SQRT_2 } else { 1.0 };
+        Some((index, step_cost * self.costs[index as usize]))
+    }
+
+    fn jump_point_search(
+        &self,
+        scratch: &mut SearchScratch,
+        query: &Query,
+        start: u32,
+        goal: u32,
+    ) -> Option<Vec<(i32, i32)>> {
+        scratch.begin();
+        scratch.relax(start, 0.0, NO_PARENT, self.heuristic(start, goal));
+        while let Some(entry) = scratch.pop() {
+            if entry.index == goal {
+                return Some(self.expand_jump_path(scratch, start, goal));
+            }
+            let (row, col) = self.row_col(entry.index);
+            for (dr, dc) in self.successor_directions(entry.index, scratch.parent(entry.index)) {
+                if let Some((next, cost)) = self.jump(query, row, col, dr, dc, goal) {
+                    scratch.relax(
+                        next,
+                        entry.g + cost,
+                        entry.index,
+                        self.heuristic(next, goal),
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    fn successor_directions(&self, index: u32, parent: u32) -> Vec<(i32, i32)> {
+        if parent == NO_PARENT || !self.uniform[index as usize] {
+            return DIRECTIONS.to_vec();
+        }
+        let (row, col) = self.row_col(index);
+        let (parent_row, parent_col) = self.row_col(parent);
+        let dr = (row - parent_row).signum();
+        let dc = (col - parent_col).signum();
+        if dr != 0 && dc != 0 {
+            vec![(dr, 0), (0, dc), (dr, dc)]
+        } else if dc != 0 {
+            vec![(0, dc), (1, dc), (-1, dc), (1, 0), (-1, 0)]
+        } else {
+            vec![(dr, 0), (dr, 1), (dr, -1), (0, 1), (0, -1)]
+        }
+    }
+
+    /// Walks from `(row, col)` in one direction until it reaches the goal, a forced neighbour,
+    /// or a cell whose surroundings are not uniform. Returns the jump point and the exact cost
+    /// of the walk.
+    fn jump(
+        &self,
+        query: &Query,
+        mut row: i32,
+        mut col: i32,
+        dr: i32,
+        dc: i32,
+        goal: u32,
+    ) -> Option<(u32, f32)> {
+        let mut total = 0.0;
+        loop {
+            let (index, cost) = self.step(query, row, col, dr, dc)?;
+            total += cost;
+            row += dr;
+            col += dc;
+            if index == goal || !self.uniform[index as usize] {
+                return Some((index, total));
+            }
+            if dr != 0 && dc != 0 {
+                if self.jump(query, row, col, dr, 0, goal).is_some()
+                    || self.jump(query, row, col, 0, dc, goal).is_some()
+                {
+                    return Some((index, total));
+                }
+            } else if dr != 0 {
+                if (query.walkable(row, col - 1) && !query.walkable(row - dr, col - 1))
+                    || (query.walkable(row, col + 1) && !query.walkable(row - dr, col + 1))
+                {
+                    return Some((index, total));
+                }
+            } else if (query.walkable(row - 1, col) && !query.walkable(row - 1, col - dc))
+                || (query.walkable(row + 1, col) && !query.walkable(row + 1, col - dc))
+            {
+                return Some((index, total));
+            }
+        }
+    }
+
+    fn expand_jump_path(&self, scratch: &SearchScratch, start: u32, goal: u32) -> Vec<(i32, i32)> {
+        let mut jump_points = vec![goal];
+        let mut walk = goal;
+        while walk != start {
+            walk = scratch.parent(walk);
+            if walk == NO_PARENT {
+                break;
+            }
+            jump_points.push(walk);
+        }
+        jump_points.reverse();
+
+        let mut path = Vec::new();
+        for pair in jump_points.windows(2) {
+            let (mut row, mut col) = self.row_col(pair[0]);
+            let (to_row, to_col) = self.row_col(pair[1]);
+            let dr = (to_row - row).signum();
+            let dc = (to_col - col).signum();
+            while (row, col) != (to_row, to_col) {
+                row += dr;
+                col += dc;
+                path.push((row, col));
+            }
+        }
+        path
+    }
+
+    /// Plans over the chunk portal graph, then refines each leg inside a single chunk. Returns
+    /// `None` when any leg is blocked by pawns so the caller can fall back to a full search.
+    fn hierarchical_path(
+        &self,
+        scratch: &mut SearchScratch,
+        occupied: &HashSet<u32>,
+        ignore: Option<u32>,
+        start: u32,
+        goal: u32,
+    ) -> Option<Vec<(i32, i32)>> {
+        let (start_chunk_row, start_chunk_col) = self.chunk_of(start);
+        let (goal_chunk_row, goal_chunk_col) = self.chunk_of(goal);
+        let start_portals = self.chunk_portals(start_chunk_row, start_chunk_col);
+        let goal_portals = self.chunk_portals(goal_chunk_row, goal_chunk_col);
+        let start_links = self.dijkstra(
+            scratch,
+            self.chunk_bounds(start_chunk_row, start_chunk_col),
+            start,
+            &start_portals,
+        );
+        let goal_links = self
+            .dijkstra(
+                scratch,
+                self.chunk_bounds(goal_chunk_row, goal_chunk_col),
+                goal,
+                &goal_portals,
+            )
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        if start_links.is_empty() || goal_links.is_empty() {
+            return None;
+        }
+
+        scratch.begin();
+        scratch.relax(start, 0.0, NO_PARENT, self.heuristic(start, goal));
+        let mut reached = false;
+        while let Some(entry) = scratch.pop() {
+            if entry.index == goal {
+                reached = true;
+                break;
+            }
+            let mut edges = Vec::new();
+            if entry.index == start {
+                edges.extend(start_links.iter().copied());
+            }
+            let (chunk_row, chunk_col) = self.chunk_of(entry.index);
+            let chunk = self.chunk_index(chunk_row, chunk_col) as usize;
+            edges.extend(
+                self.intra_edges[chunk]
+                    .iter()
+                    .filter(|(from, _, _)| *from == entry.index)
+                    .map(|(_, to, cost)| (*to, *cost)),
+            );
+            for partner in self.cross_links(entry.index) {
+                edges.push((partner, self.costs[partner as usize]));
+            }
+            if let Some(cost) = goal_links.get(&entry.index) {
+                edges.push((goal, *cost));
+            }
+            for (next, cost) in edges {
+                scratch.relax(
+                    next,
+                    entry.g + cost,
+                    entry.index,
+                    self.heuristic(next, goal),
+                );
+            }
+        }
+        if !reached {
+            return None;
+        }
+
+        let mut waypoints = vec![goal];
+        let mut walk = goal;
+        while walk != start {
+            walk = scratch.parent(walk);
+            if walk == NO_PARENT {
+                return None;
+            }
+            waypoints.push(walk);
+        }
+        waypoints.reverse();
+
+        let mut path = Vec::new();
+        for pair in waypoints.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let (to_row, to_col) = self.row_col(to);
+            if self.chunk_of(from) != self.chunk_of(to) {
+                let query = Query {
+                    grid: self,
+                    bounds: self.full_bounds(),
+                    occupied: Some(occupied),
+                    ignore,
+                };
+                if !query.walkable(to_row, to_col) {
+                    return None;
+                }
+                path.push((to_row, to_col));
+                continue;
+            }
+            let (chunk_row, chunk_col) = self.chunk_of(from);
+            let query = Query {
+                grid: self,
+                bounds: self.chunk_bounds(chunk_row, chunk_col),
+                occupied: Some(occupied),
+                ignore,
+            };
+            path.extend(self.jump_point_search(scratch, &query, from, to)?);
+        }
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Deterministic map with scattered rocks, a few walls with gaps and patches of rough
+    /// ground, so searches hit both uniform and weighted areas.
+    fn sample_costs(width: u32, height: u32, seed: u64) -> Vec<f32> {
+        let mut state = seed;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as u32
+        };
+        let mut costs = Vec::with_capacity((width * height) as usize);
+        for row in 0..height {
+            for col in 0..width {
+                let wall = col % 40 == 20 && row % 32 > 3;
+                let rock = next() % 100 < 12;
+                let rough = (row / 24 + col / 24) % 5 == 0;
+                costs.push(if wall || rock {
+                    0.0
+                } else if rough {
+                    1.5
+                } else {
+                    1.0
+                });
+            }
+        }
+        costs
+    }
+
+    fn reference_cost(grid: &NavGrid, start: (i32, i32), goal: (i32, i32)) -> Option<f32> {
+        let query = Query {
+            grid,
+            bounds: grid.full_bounds(),
+            occupied: None,
+            ignore: None,
+        };
+        let start = grid.index_of(start.0, start.1)?;
+        let goal = grid.index_of(goal.0, goal.1)?;
+        let mut scratch = grid.scratch.lock().unwrap();
+        scratch.begin();
+        scratch.relax(start, 0.0, NO_PARENT, 0.0);
+        while let Some(entry) = scratch.pop() {
+            if entry.index == goal {
+                return Some(entry.g);
+            }
+            let (row, col) = grid.row_col(entry.index);
+            for (dr, dc) in DIRECTIONS {
+                if let Some((next, cost)) = grid.step(&query, row, col, dr, dc) {
+                    scratch.relax(next, entry.g + cost, entry.index, 0.0);
+                }
+            }
+        }
+        None
+    }
+
+    fn path_cost(grid: &NavGrid, start: (i32, i32), path: &[(i32, i32)]) -> f32 {
+        let mut previous = start;
+        let mut total = 0.0;
+        for &(row, col) in path {
+            let dr = (row - previous.0).abs();
+            let dc = (col - previous.1).abs();
+            assert!(dr <= 1 && dc <= 1, "path must move one cell at a time");
+            assert!(
+                grid.cost(row, col) > 0.0,
+                "path must stay on walkable cells"
+            );
+            if dr == 1 && dc == 1 {
+                assert!(grid.cost(previous.0, col) > 0.0 && grid.cost(row, previous.1) > 0.0);
+            }
+            let step = if dr == 1 && dc == 1 { SQRT_2 } else { 1.0 };
+            total += step * grid.cost(row, col);
+            previous = (row, col);
+        }
+        total
+    }
+
+    fn open_cells(grid: &NavGrid, count: usize, seed: u64) -> Vec<(i32, i32)> {
+        let mut state = seed;
+        let mut cells = Vec::new();
+        while cells.len() < count {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let row = ((state >> 33) % grid.height as u64) as i32;
+            let col = ((state >> 17) % grid.width as u64) as i32;
+            if grid.cost(row, col) > 0.0 {
+                cells.push((row, col));
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn jump_point_search_matches_reference_costs() {
+        let grid = NavGrid::new(48, 48, 16, sample_costs(48, 48, 7));
+        let cells = open_cells(&grid, 40, 11);
+        let occupied = HashSet::new();
+        for pair in cells.chunks(2) {
+            let (start, goal) = (pair[0], pair[1]);
+            let expected = reference_cost(&grid, start, goal);
+            let query = Query {
+                grid: &grid,
+                bounds: grid.full_bounds(),
+                occupied: Some(&occupied),
+                ignore: None,
+            };
+            let start_index = grid.index_of(start.0, start.1).unwrap();
+            let goal_index = grid.index_of(goal.0, goal.1).unwrap();
+            let path = {
+                let mut scratch = grid.scratch.lock().unwrap();
+                grid.jump_point_search(&mut scratch, &query, start_index, goal_index)
+            };
+            match (expected, path) {
+                (Some(expected), Some(path)) => {
+                    let cost = path_cost(&grid, start, &path);
+                    assert!((cost - expected).abs() < 1e-3, "{cost} vs {expected}");
+                    assert_eq!(path.last().copied(), Some(goal));
+                }
+                (None, None) => {}
+                (expected, path) => panic!("reachability differs: {expected:?} vs {path:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn hierarchical_routes_are_valid_and_near_optimal() {
+        let grid = NavGrid::new(128, 128, 16, sample_costs(128, 128, 3));
+        let cells = open_cells(&grid, 20, 5);
+        let occupied = HashSet::new();
+        for pair in cells.chunks(2) {
+            let (start, goal) = (pair[0], pair[1]);
+            let Some(expected) = reference_cost(&grid, start, goal) else {
+                continue;
+            };
+            let path = grid.find_path(start, goal, &occupied, None).unwrap();
+            assert_eq!(path.last().copied(), Some(goal));
+            let cost = path_cost(&grid, start, &path);
+            assert!(cost <= expected * 1.35 + 2.0, "{cost} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn occupied_cells_block_routes_except_ignored() {
+        let grid = NavGrid::new(8, 3, 16, vec![1.0; 24]);
+        let occupied = (0..3)
+            .filter_map(|row| grid.index_of(row, 4))
+            .collect::<HashSet<_>>();
+        assert!(grid.find_path((1, 0), (1, 7), &occupied, None).is_none());
+        let ignore = grid.index_of(1, 4);
+        let path = grid.find_path((1, 0), (1, 7), &occupied, ignore).unwrap();
+        assert!(path.contains(&(1, 4)));
+    }
+
+    #[test]
+    fn updating_a_chunk_reopens_portals() {
+        let mut costs = vec![1.0; 64 * 16];
+        for row in 0..16 {
+            costs[row * 64 + 31] = 0.0;
+        }
+        let mut grid = NavGrid::new(64, 16, 16, costs);
+        let occupied = HashSet::new();
+        assert!(grid.find_path((8, 2), (8, 60), &occupied, None).is_none());
+
+        let (_, _, rows, cols) = grid.chunk_rect(0, 1);
+        let mut chunk_costs = vec![1.0; (rows * cols) as usize];
+        for row in 0..rows {
+            if row != 8 {
+                chunk_costs[(row * cols + 15) as usize] = 0.0;
+            }
+        }
+        grid.update_chunk(0, 1, &chunk_costs);
+        let path = grid.find_path((8, 2), (8, 60), &occupied, None).unwrap();
+        assert!(path.contains(&(8, 31)));
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with --ignored --nocapture"]
+    fn benchmark_256_map_route_queries() {
+        let build_started = Instant::now();
+        let grid = NavGrid::new(256, 256, 16, sample_costs(256, 256, 19));
+        let build_elapsed = build_started.elapsed();
+        let cells = open_cells(&grid, 200, 23);
+        let occupied = cells
+            .iter()
+            .step_by(2)
+            .filter_map(|(row, col)| grid.index_of(*row, *col))
+            .collect::<HashSet<_>>();
+
+        let started = Instant::now();
+        let mut found = 0;
+        for pair in cells.chunks(2) {
+            let ignore = grid.index_of(pair[0].0, pair[0].1);
+            if grid
+                .find_path(pair[0], pair[1], &occupied, ignore)
+                .is_some()
+            {
+                found += 1;
+            }
+        }
+        let query_elapsed = started.elapsed();
+
+        let reference_started = Instant::now();
+        for pair in cells.chunks(2) {
+            reference_cost(&grid, pair[0], pair[1]);
+        }
+        let reference_elapsed = reference_started.elapsed();
+        println!(
+            "256x256 grid: build {build_elapsed:?}, 100 routes {query_elapsed:?} ({found} found), reference A* {reference_elapsed:?}"
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use super::{
+    sim::{effective_move_speed, ExplorationSim},
+    types::{
+        ExplorationAwareness, ExplorationClientAction, ExplorationItemStack,
+        ExplorationMotionKeyframe, ExplorationMovementMode, ExplorationPawn,
+        ExplorationPawnCondition, ExplorationPawnPatch, ExplorationSessionConfig,
+        ExplorationSessionEvent, ExplorationVisibilityState, ExplorationWireEncoding,
+    },
+};
+
+/// Every pawn delta carries the full changed pawns.
+pub const PROTOCOL_VERSION_FULL: u32 = 1;
+/// Pawn updates are field patches with motion keyframes, limited to the subscribed view.
+pub const PROTOCOL_VERSION_DELTA: u32 = 2;
+/// Slowest pawn replication rate under back-off, in ticks between updates.
+const MAX_SEND_INTERVAL_TICKS: u64 = 8;
+/// Consecutive quick sends needed before the replication rate speeds back up.
+const FAST_SEND_STREAK: u32 = 20;
+
+/// Websocket with the encoding the client negotiated and the adaptive pawn replication rate.
+pub struct ExplorationClientLink {
+    socket: WebSocket,
+    encoding: ExplorationWireEncoding,
+    tick_interval: Duration,
+    send_interval_ticks: u64,
+    fast_streak: u32,
+}
+
+impl ExplorationClientLink {
+    pub fn new(socket: WebSocket, tick_interval: Duration) -> Self {
+        Self {
+            socket,
+            encoding: ExplorationWireEncoding::Json,
+            tick_interval,
+            send_interval_ticks: 1,
+            fast_streak: 0,
+        }
+    }
+
+    /// Applies the session config and resets the replication rate for a new tick rate.
+    pub fn configure(
+        &mut self,
+        config: Option<&ExplorationSessionConfig>,
+        tick_interval: Duration,
+    ) {
+        self.encoding = config.and_then(|entry| entry.encoding).unwrap_or_default();
+        self.tick_interval = tick_interval;
+        self.send_interval_ticks = 1;
+        self.fast_streak = 0;
+    }
+
+    /// Whether pawn updates go out on this tick. Slow sockets skip ticks; the replicator diffs
+    /// against what was last sent, so skipped ticks lose nothing.
+    pub fn replicates_on(&self, tick: u64) -> bool {
+        tick.is_multiple_of(self.send_interval_ticks)
+    }
+
+    pub async fn send(&mut self, event: &ExplorationSessionEvent) -> Result<(), ()> {
+        let message = encode_event(self.encoding, event)?;
+        let started = Instant::now();
+        self.socket.send(message).await.map_err(|_| ())?;
+        self.record_send(started.elapsed());
+        Ok(())
+    }
+
+    pub async fn recv(&mut self) -> Option<Result<Message, axum::Error>> {
+        self.socket.recv().await
+    }
+
+    fn record_send(&mut self, elapsed: Duration) {
+        if elapsed > self.tick_interval / 2 {
+            self.send_interval_ticks = (self.send_interval_ticks * 2).min(MAX_SEND_INTERVAL_TICKS);
+            self.fast_streak = 0;
+        } else if elapsed < self.tick_interval / 10 && self.send_interval_ticks > 1 {
+            self.fast_streak += 1;
+            if self.fast_streak >= FAST_SEND_STREAK {
+                self.send_interval_ticks /= 2;
+                self.fast_streak = 0;
+            }
+        }
+    }
+}
+
+pub fn encode_event(
+    encoding: ExplorationWireEncoding,
+    event: &ExplorationSessionEvent,
+) -> Result<Message, ()> {
+    match encoding {
+        ExplorationWireEncoding::Json => serde_json::to_string(event)
+            .map(|payload| Message::Text(payload.into()))
+            .map_err(|_| ()),
+        ExplorationWireEncoding::Msgpack => rmp_serde::to_vec_named(event)
+            .map(|payload| Message::Binary(payload.into()))
+            .map_err(|_| ()),
+    }
+}
+
+/// Reads a client action from a JSON text frame or a MessagePack binary frame. Other frames
+/// yield `None`.
+pub fn decode_action(message: &Message) -> Option<Result<ExplorationClientAction, String>> {
+    match message {
+        Message::Text(text) => Some(
+            serde_json::from_str::<ExplorationClientAction>(text)
+                .map_err(|error| error.to_string()),
+        ),
+        Message::Binary(bytes) => Some(
+            rmp_serde::from_slice::<ExplorationClientAction>(bytes)
+                .map_err(|error| error.to_string()),
+        ),
+        _ => None,
+    }
+}
+
+/// Fields of a pawn as the client last saw them.
+#[derive(Debug, Clone)]
+struct ReplicatedPawn {
+    x: f32,
+    y: f32,
+    level: i32,
+    moving: bool,
+    facing: Option<String>,
+    movement_mode: ExplorationMovementMode,
+    current_intent: Option<String>,
+    conditions: Vec<ExplorationPawnCondition>,
+    awareness: Option<ExplorationAwareness>,
+    inventory: Vec<ExplorationItemStack>,
+    /// Route index and destination tile of the keyframe in flight.
+    segment: Option<(usize, i32, i32)>,
+}
+
+impl ReplicatedPawn {
+    fn capture(pawn: &ExplorationPawn) -> Self {
+        Self {
+            x: pawn.x,
+            y: pawn.y,
+            level: pawn.level,
+            moving: pawn.moving,
+            facing: pawn.facing.clone(),
+            movement_mode: pawn.movement_mode,
+            current_intent: pawn.current_intent.clone(),
+            conditions: pawn.conditions.clone(),
+            awareness: pawn.awareness.clone(),
+            inventory: pawn.inventory.clone(),
+            segment: current_segment(pawn),
+        }
+    }
+}
+
+/// End of the straight run of route nodes the pawn is walking, as route index and tile. One
+/// keyframe covers the whole run.
+fn current_segment(pawn: &ExplorationPawn) -> Option<(usize, i32, i32)> {
+    if !pawn.moving {
+        return None;
+    }
+    let first = pawn
+        .route
+        .get(pawn.route_index)
+        .filter(|node| node.level == pawn.level)?;
+    let lead = (
+        step_sign(first.row as f32 - pawn.y),
+        step_sign(first.col as f32 - pawn.x),
+    );
+    let mut direction = (lead != (0, 0)).then_some(lead);
+    let mut end = pawn.route_index;
+    while let Some(next) = pawn.route.get(end + 1) {
+        let current = &pawn.route[end];
+        let step = (next.row - current.row, next.col - current.col);
+        if next.level != pawn.level || direction.is_some_and(|direction| direction != step) {
+            break;
+        }
+        direction = Some(step);
+        end += 1;
+    }
+    let node = &pawn.route[end];
+    Some((end, node.row, node.col))
+}
+
+fn step_sign(delta: f32) -> i32 {
+    if delta.abs() < 0.01 {
+        0
+    } else {
+        delta.signum() as i32
+    }
+}
+
+fn keyframe(
+    pawn: &ExplorationPawn,
+    tick: u64,
+    tick_rate_hz: u64,
+) -> Option<ExplorationMotionKeyframe> {
+    let (_, row, col) = current_segment(pawn)?;
+    let (to_x, to_y) = (col as f32, row as f32);
+    let distance = ((to_x - pawn.x).powi(2) + (to_y - pawn.y).powi(2)).sqrt();
+    let ticks = (distance / effective_move_speed(pawn) * tick_rate_hz as f32).ceil() as u64;
+    Some(ExplorationMotionKeyframe {
+        from_x: pawn.x,
+        from_y: pawn.y,
+        to_x,
+        to_y,
+        start_tick: tick,
+        arrive_tick: tick + ticks.max(1),
+    })
+}
+
+/// Per-client record of what has been sent, used to build protocol 2 pawn patches.
+pub struct PawnReplicator {
+    version: u32,
+    known: HashMap<String, ReplicatedPawn>,
+    selected_pawn_id: Option<Option<String>>,
+    visibility: Option<ExplorationVisibilityState>,
+}
+
+impl PawnReplicator {
+    pub fn new(config: Option<&ExplorationSessionConfig>) -> Self {
+        Self {
+            version: config
+                .and_then(|entry| entry.protocol_version)
+                .filter(|version| *version == PROTOCOL_VERSION_DELTA)
+                .unwrap_or(PROTOCOL_VERSION_FULL),
+            known: HashMap::new(),
+            selected_pawn_id: None,
+            visibility: None,
+        }
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn is_delta(&self) -> bool {
+        self.version == PROTOCOL_VERSION_DELTA
+    }
+
+    /// Builds the next pawn update. Protocol 1 always yields a full `PawnDelta` for
+    /// `changed_pawn_ids`; protocol 2 yields a `PawnPatch` only when the client's view changed.
+    pub fn pawn_update(
+        &mut self,
+        sim: &ExplorationSim,
+        changed_pawn_ids: &[String],
+    ) -> Option<ExplorationSessionEvent> {
+        if !self.is_delta() {
+            return Some(sim.pawn_delta(changed_pawn_ids));
+        }
+
+        let mut added = Vec::new();
+        let mut patches = Vec::new();
+        let mut in_view = HashSet::new();
+        for pawn in &sim.pawns {
+            let interested = pawn.faction_id == "player"
+                || sim.in_subscribed_view(pawn.level, pawn.tile_row, pawn.tile_col);
+            if !interested {
+                continue;
+            }
+            in_view.insert(pawn.id.as_str());
+            let current = ReplicatedPawn::capture(pawn);
+            let Some(known) = self.known.get(&pawn.id) else {
+                let mut entry = pawn.clone();
+                entry.path = None;
+                entry.route = Vec::new();
+                entry.route_index = 0;
+                added.push(entry);
+                if let Some(keyframe) = keyframe(pawn, sim.tick, sim.tick_rate_hz) {
+                    patches.push(ExplorationPawnPatch {
+                        id: pawn.id.clone(),
+                        keyframe: Some(keyframe),
+                        ..Default::default()
+                    });
+                }
+                self.known.insert(pawn.id.clone(), current);
+                continue;
+            };
+            if let Some(patch) = diff_pawn(pawn, known, &current, sim) {
+                patches.push(patch);
+            }
+            self.known.insert(pawn.id.clone(), current);
+        }
+
+        let mut removed_pawn_ids = self
+            .known
+            .keys()
+            .filter(|id| !in_view.contains(id.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        removed_pawn_ids.sort();
+        for id in &removed_pawn_ids {
+            self.known.remove(id);
+        }
+
+        let selected_pawn_id = (self.selected_pawn_id.as_ref() != Some(&sim.selected_pawn_id))
+            .then(|| sim.selected_pawn_id.clone());
+        if let Some(selected) = &selected_pawn_id {
+            self.selected_pawn_id = Some(selected.clone());
+        }
+        let visibility =
+            (self.visibility.as_ref() != Some(&sim.visibility)).then(|| sim.visibility.clone());
+        if let Some(visibility) = &visibility {
+            self.visibility = Some(visibility.clone());
+        }
+
+        if added.is_empty()
+            && patches.is_empty()
+            && removed_pawn_ids.is_empty()
+            && selected_pawn_id.is_none()
+            && visibility.is_none()
+        {
+            return None;
+        }
+        Some(ExplorationSessionEvent::PawnPatch {
+            tick: sim.tick,
+            added,
+            patches,
+            removed_pawn_ids,
+            selected_pawn_id,
+            visibility,
+        })
+    }
+}
+
+fn diff_pawn(
+    pawn: &ExplorationPawn,
+    known: &ReplicatedPawn,
+    current: &ReplicatedPawn,
+    sim: &ExplorationSim,
+) -> Option<ExplorationPawnPatch> {
+    let mut patch = ExplorationPawnPatch {
+        id: pawn.id.clone(),
+        ..Default::default()
+    };
+    let mut changed = false;
+    let level_changed = known.level != current.level;
+    if level_changed {
+        patch.level = Some(current.level);
+        changed = true;
+    }
+    // Moving pawns are interpolated from keyframes; positions go out when they stop or jump
+    // between levels.
+    let position_changed = known.x != current.x || known.y != current.y;
+    let stopped = known.moving && !current.moving;
+    if level_changed || stopped || (position_changed && !current.moving) {
+        patch.x = Some(current.x);
+        patch.y = Some(current.y);
+        changed = true;
+    }
+    if known.moving != current.moving {
+        patch.moving = Some(current.moving);
+        changed = true;
+    }
+    if known.facing != current.facing {
+        patch.facing = Some(current.facing.clone());
+        changed = true;
+    }
+    if known.movement_mode != current.movement_mode {
+        patch.movement_mode = Some(current.movement_mode);
+        changed = true;
+    }
+    if known.current_intent != current.current_intent {
+        patch.current_intent = Some(current.current_intent.clone());
+        changed = true;
+    }
+    if known.conditions != current.conditions {
+        patch.conditions = Some(current.conditions.clone());
+        changed = true;
+    }
+    if known.awareness.as_ref().map(|awareness| awareness.state)
+        != current.awareness.as_ref().map(|awareness| awareness.state)
+        || known
+            .awareness
+            .as_ref()
+            .map(|awareness| &awareness.target_pawn_id)
+            != current
+                .awareness
+                .as_ref()
+                .map(|awareness| &awareness.target_pawn_id)
+    {
+        patch.awareness = Some(current.awareness.clone());
+        changed = true;
+    }
+    if known.inventory != current.inventory {
+        patch.inventory = Some(current.inventory.clone());
+        changed = true;
+    }
+    // Speed changes re-time the segment in flight.
+    let retimed =
+        known.movement_mode != current.movement_mode || known.conditions != current.conditions;
+    if current.segment.is_some() && (known.segment != current.segment || retimed) {
+        patch.keyframe = keyframe(pawn, sim.tick, sim.tick_rate_hz);
+        changed = true;
+    }
+    changed.then_some(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exploration_engine::sim::tests::sample_sim;
+    use crate::exploration_engine::types::ExplorationSessionConfig;
+
+    fn delta_config() -> ExplorationSessionConfig {
+        ExplorationSessionConfig {
+            session_name: None,
+            tick_rate_hz: None,
+            protocol_version: Some(PROTOCOL_VERSION_DELTA),
+            encoding: Some(ExplorationWireEncoding::Msgpack),
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn moving_pawns_are_patched_with_keyframes_instead_of_positions() {
+        let mut sim = sample_sim();
+        let mut replicator = PawnReplicator::new(Some(&delta_config()));
+        let Some(ExplorationSessionEvent::PawnPatch { added, .. }) =
+            replicator.pawn_update(&sim, &[])
+        else {
+            panic!("expected an initial patch");
+        };
+        assert_eq!(added.len(), 2);
+        assert!(added.iter().all(|pawn| pawn.route.is_empty()));
+        assert!(replicator.pawn_update(&sim, &[]).is_none());
+
+        sim.move_pawn("player", 8, 12, true).expect("move");
+        sim.advance(0.1);
+        let event = replicator.pawn_update(&sim, &[]);
+        assert!(encode_event(ExplorationWireEncoding::Msgpack, event.as_ref().unwrap()).is_ok());
+        let Some(ExplorationSessionEvent::PawnPatch { patches, .. }) = event else {
+            panic!("expected a movement patch");
+        };
+        let patch = patches
+            .iter()
+            .find(|patch| patch.id == "player")
+            .expect("player patch");
+        assert!(patch.keyframe.is_some());
+        assert!(patch.x.is_none() && patch.y.is_none());
+
+        sim.advance(0.1);
+        let player_patched = match replicator.pawn_update(&sim, &[]) {
+            Some(ExplorationSessionEvent::PawnPatch { patches, .. }) => {
+                patches.iter().any(|patch| patch.id == "player")
+            }
+            _ => false,
+        };
+        assert!(!player_patched, "mid-segment ticks need no update");
+    }
+
+    #[test]
+    fn pawns_leaving_the_view_are_removed() {
+        let mut sim = sample_sim();
+        let mut replicator = PawnReplicator::new(Some(&delta_config()));
+        replicator.pawn_update(&sim, &[]);
+        sim.pawns[1].level = 1;
+        let Some(ExplorationSessionEvent::PawnPatch {
+            removed_pawn_ids, ..
+        }) = replicator.pawn_update(&sim, &[])
+        else {
+            panic!("expected a removal patch");
+        };
+        assert_eq!(removed_pawn_ids, vec!["npc".to_string()]);
+    }
+}
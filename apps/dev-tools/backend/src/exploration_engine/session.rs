@@ -5,45 +5,101 @@ use axum::{
     },
     response::IntoResponse,
 };
+use rand::{rngs::StdRng, SeedableRng};
 use serde_json::Value;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use tokio::task;
 use tokio::time::{interval, Duration};
 use tracing::{info, warn};
 
+use crate::ecology::{fauna_for_province, load_ecology_bundle};
 use crate::exploration_jobs::{ensure_test_exploration_location, TEST_EXPLORATION_LOCATION_ID};
+use crate::jobs::now_ms;
+use crate::locations::read_locations;
 use crate::AppState;
 
 use super::{
-    manifest::{load_all_chunks, load_storage_manifest},
-    sim::ExplorationSim,
+    manifest::{load_chunks_in_radius, load_storage_manifest, ChunkSource},
+    protocol::{decode_action, ExplorationClientLink, PawnReplicator},
+    sim::{session_seed, ExplorationSim, WildlifeEvent, DEFAULT_SUBSCRIPTION_RADIUS},
+    state::{load_session_state, save_session_state},
+    stealth::armor_noise,
     types::{
-        ExplorationChunk, ExplorationClientAction, ExplorationManifestDescriptor, ExplorationPawn,
-        ExplorationSessionEvent,
+        ExplorationChunk, ExplorationClientAction, ExplorationManifestDescriptor,
+        ExplorationMovementMode, ExplorationPawn, ExplorationPawnStats, ExplorationSessionEvent,
+        ExplorationTile,
     },
+    wildlife::{day_phase_at, spawn_wildlife},
 };
 
 const DEFAULT_TICK_RATE_HZ: u64 = 10;
+const AUTOSAVE_INTERVAL_SECONDS: u64 = 30;
 
 pub async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
     info!("Exploration WebSocket connection request");
     ws.on_upgrade(move |socket| handle_socket(state, socket))
 }
 
-async fn handle_socket(state: AppState, mut socket: WebSocket) {
+async fn handle_socket(state: AppState, socket: WebSocket) {
     info!("Exploration WebSocket connected");
     let mut session: Option<ExplorationSim> = None;
+    let mut socket = ExplorationClientLink::new(
+        socket,
+        Duration::from_millis(1000 / DEFAULT_TICK_RATE_HZ.max(1)),
+    );
+    let mut replicator = PawnReplicator::new(None);
     let mut ticker = interval(Duration::from_millis(1000 / DEFAULT_TICK_RATE_HZ.max(1)));
+    let mut autosave = interval(Duration::from_secs(AUTOSAVE_INTERVAL_SECONDS));
 
     loop {
         tokio::select! {
+            _ = autosave.tick() => {
+                if let Some(active_session) = session.as_ref() {
+                    persist_session(&state, active_session).await;
+                }
+            }
             _ = ticker.tick() => {
                 if let Some(active_session) = session.as_mut() {
                     let advance = active_session.advance(1.0 / active_session.tick_rate_hz as f32);
-                    if (!advance.changed_pawn_ids.is_empty() || advance.visibility_changed)
-                        && send_event(&mut socket, &active_session.pawn_delta(&advance.changed_pawn_ids)).await.is_err() {
+                    let replicate = if replicator.is_delta() {
+                        socket.replicates_on(active_session.tick)
+                    } else {
+                        !advance.changed_pawn_ids.is_empty() || advance.visibility_changed
+                    };
+                    if replicate
+                        && send_pawn_update(&mut socket, &mut replicator, active_session, &advance.changed_pawn_ids).await.is_err() {
                         break;
                     }
+                    if advance.doors_changed {
+                        let event = active_session.chunk_delta(
+                            active_session.current_subscription_chunks(),
+                            Vec::new(),
+                        );
+                        if send_event(&mut socket, &event).await.is_err() {
+                            break;
+                        }
+                    }
+                    let mut closed = false;
+                    let fired_triggers = !advance.trigger_events.is_empty() || advance.doors_changed;
+                    for event in advance
+                        .wildlife_events
+                        .into_iter()
+                        .map(WildlifeEvent::into_session_event)
+                        .chain(advance.awareness_events)
+                        .chain(advance.trigger_events)
+                    {
+                        if send_event(&mut socket, &event).await.is_err() {
+                            closed = true;
+                            break;
+                        }
+                    }
+                    if closed {
+                        break;
+                    }
+                    if fired_triggers {
+                        persist_session(&state, active_session).await;
+                    }
                 }
             }
             message = socket.recv() => {
@@ -58,209 +114,270 @@ async fn handle_socket(state: AppState, mut socket: WebSocket) {
                     }
                 };
 
-                match message {
-                    Message::Text(text) => {
-                        let action = match serde_json::from_str::<ExplorationClientAction>(&text) {
-                            Ok(action) => action,
-                            Err(error) => {
-                                if send_event(&mut socket, &ExplorationSessionEvent::Error {
-                                    message: format!("Invalid exploration action: {error}"),
-                                }).await.is_err() {
-                                    break;
-                                }
-                                continue;
-                            }
-                        };
+                if matches!(message, Message::Close(_)) {
+                    break;
+                }
+                let Some(action) = decode_action(&message) else {
+                    continue;
+                };
+                let action = match action {
+                    Ok(action) => action,
+                    Err(error) => {
+                        if send_event(&mut socket, &ExplorationSessionEvent::Error {
+                            message: format!("Invalid exploration action: {error}"),
+                        }).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
 
-                        match action {
-                            ExplorationClientAction::StartSession { world_id, location_id, selected_character_ids, config } => {
-                                let tick_rate_hz = config
-                                    .and_then(|entry| entry.tick_rate_hz)
-                                    .filter(|value| *value > 0)
-                                    .unwrap_or(DEFAULT_TICK_RATE_HZ as u32);
+                let received_ms = now_ms();
+                if let Some(active_session) = session.as_mut() {
+                    active_session.record_input(&action, received_ms);
+                }
+                match action {
+                    ExplorationClientAction::StartSession { world_id, location_id, selected_character_ids, config } => {
+                        let tick_rate_hz = config
+                            .as_ref()
+                            .and_then(|entry| entry.tick_rate_hz)
+                            .filter(|value| *value > 0)
+                            .unwrap_or(DEFAULT_TICK_RATE_HZ as u32);
+                        let seed = config
+                            .as_ref()
+                            .and_then(|entry| entry.seed)
+                            .unwrap_or_else(|| {
+                                let session_name = config.as_ref().and_then(|entry| entry.session_name.as_deref());
+                                session_seed(&world_id, &location_id, session_name.unwrap_or_default())
+                            });
+                        info!(
+                            world_id = %world_id,
+                            location_id = %location_id,
+                            selected_character_count = selected_character_ids.len(),
+                            tick_rate_hz,
+                            "Exploration start_session requested"
+                        );
+                        if let Some(previous_session) = session.take() {
+                            persist_session(&state, &previous_session).await;
+                        }
+                        let state_for_load = state.clone();
+                        let world_id_for_load = world_id.clone();
+                        let location_id_for_load = location_id.clone();
+                        let selected_character_ids_for_load = selected_character_ids.clone();
+                        session = match task::spawn_blocking(move || {
+                            load_simulation(
+                                &state_for_load,
+                                &world_id_for_load,
+                                &location_id_for_load,
+                                &selected_character_ids_for_load,
+                                tick_rate_hz as u64,
+                                seed,
+                            )
+                        }).await {
+                            Ok(Ok(active_session)) => {
                                 info!(
                                     world_id = %world_id,
                                     location_id = %location_id,
-                                    selected_character_count = selected_character_ids.len(),
-                                    tick_rate_hz,
-                                    "Exploration start_session requested"
+                                    chunk_count = active_session.chunks.len(),
+                                    pawn_count = active_session.pawns.len(),
+                                    "Exploration session loaded"
                                 );
-                                let state_for_load = state.clone();
-                                let world_id_for_load = world_id.clone();
-                                let location_id_for_load = location_id.clone();
-                                let selected_character_ids_for_load = selected_character_ids.clone();
-                                session = match task::spawn_blocking(move || {
-                                    load_simulation(
-                                        &state_for_load,
-                                        &world_id_for_load,
-                                        &location_id_for_load,
-                                        &selected_character_ids_for_load,
-                                        tick_rate_hz as u64,
-                                    )
-                                }).await {
-                                    Ok(Ok(active_session)) => {
-                                        info!(
-                                            world_id = %world_id,
-                                            location_id = %location_id,
-                                            chunk_count = active_session.chunks.len(),
-                                            pawn_count = active_session.pawns.len(),
-                                            "Exploration session loaded"
-                                        );
-                                        Some(active_session)
-                                    }
-                                    Ok(Err(message)) => {
-                                        warn!(
-                                            world_id = %world_id,
-                                            location_id = %location_id,
-                                            error = %message,
-                                            "Exploration session failed to load"
-                                        );
-                                        if send_event(&mut socket, &ExplorationSessionEvent::Error { message }).await.is_err() {
-                                            break;
-                                        }
-                                        None
-                                    }
-                                    Err(error) => {
-                                        let message = format!("Exploration session task failed: {error}");
-                                        warn!(
-                                            world_id = %world_id,
-                                            location_id = %location_id,
-                                            error = %message,
-                                            "Exploration session task join failed"
-                                        );
-                                        if send_event(&mut socket, &ExplorationSessionEvent::Error { message }).await.is_err() {
-                                            break;
-                                        }
-                                        None
-                                    }
-                                };
-                                if let Some(active_session) = session.as_ref() {
-                                    ticker = interval(Duration::from_millis(1000 / active_session.tick_rate_hz.max(1)));
-                                    if send_event(&mut socket, &ExplorationSessionEvent::SessionReady { state: active_session.snapshot() }).await.is_err() {
-                                        break;
-                                    }
-                                }
+                                Some(active_session)
                             }
-                            ExplorationClientAction::SubscribeView { center_row, center_col, radius } => {
-                                let Some(active_session) = session.as_mut() else {
-                                    continue;
-                                };
-                                let event = active_session.subscribe_view(center_row, center_col, radius);
-                                if send_event(&mut socket, &event).await.is_err() {
+                            Ok(Err(message)) => {
+                                warn!(
+                                    world_id = %world_id,
+                                    location_id = %location_id,
+                                    error = %message,
+                                    "Exploration session failed to load"
+                                );
+                                if send_event(&mut socket, &ExplorationSessionEvent::Error { message }).await.is_err() {
                                     break;
                                 }
+                                None
                             }
-                            ExplorationClientAction::MoveTo { pawn_id, target_row, target_col } => {
-                                let Some(active_session) = session.as_mut() else {
-                                    if send_event(&mut socket, &ExplorationSessionEvent::Error {
-                                        message: "No active exploration session".to_string(),
-                                    }).await.is_err() {
-                                        break;
-                                    }
-                                    continue;
-                                };
-                                match active_session.move_pawn(&pawn_id, target_row as i32, target_col as i32, true) {
-                                    Ok(changed_pawn_ids) => {
-                                        if send_event(&mut socket, &active_session.pawn_delta(&changed_pawn_ids)).await.is_err() {
-                                            break;
-                                        }
-                                    }
-                                    Err(message) => {
-                                        if send_event(&mut socket, &ExplorationSessionEvent::Error { message }).await.is_err() {
-                                            break;
-                                        }
-                                    }
+                            Err(error) => {
+                                let message = format!("Exploration session task failed: {error}");
+                                warn!(
+                                    world_id = %world_id,
+                                    location_id = %location_id,
+                                    error = %message,
+                                    "Exploration session task join failed"
+                                );
+                                if send_event(&mut socket, &ExplorationSessionEvent::Error { message }).await.is_err() {
+                                    break;
                                 }
+                                None
                             }
-                            ExplorationClientAction::SetSelectedPawn { pawn_id } => {
-                                if let Some(active_session) = session.as_mut() {
-                                    active_session.set_selected_pawn(pawn_id);
-                                    if send_event(&mut socket, &active_session.pawn_delta(&[])).await.is_err() {
-                                        break;
-                                    }
-                                }
+                        };
+                        if let Some(active_session) = session.as_ref() {
+                            let tick_interval = Duration::from_millis(1000 / active_session.tick_rate_hz.max(1));
+                            ticker = interval(tick_interval);
+                            socket.configure(config.as_ref(), tick_interval);
+                            replicator = PawnReplicator::new(config.as_ref());
+                            let mut snapshot = active_session.snapshot();
+                            if replicator.is_delta() {
+                                // Pawns follow in the first patch, limited to the view.
+                                snapshot.pawns.clear();
                             }
-                            ExplorationClientAction::Interact { row, col, object_id, actor_id } => {
-                                let Some(active_session) = session.as_mut() else {
-                                    continue;
-                                };
-                                match active_session.handle_interaction(row, col, object_id.clone(), actor_id.clone()) {
-                                    Ok(result) => {
-                                        if result.chunks_changed {
-                                            let event = active_session.chunk_delta(
-                                                active_session.current_subscription_chunks(),
-                                                Vec::new(),
-                                            );
-                                            if send_event(&mut socket, &event).await.is_err() {
-                                                break;
-                                            }
-                                        }
-                                        if !result.changed_pawn_ids.is_empty()
-                                            && send_event(&mut socket, &active_session.pawn_delta(&result.changed_pawn_ids)).await.is_err() {
-                                            break;
-                                        }
-                                        if send_event(&mut socket, &ExplorationSessionEvent::Interaction {
-                                            label: result.label,
-                                            row,
-                                            col,
-                                            object_id,
-                                            actor_id,
-                                        }).await.is_err() {
-                                            break;
-                                        }
-                                    }
-                                    Err(message) => {
-                                        if send_event(&mut socket, &ExplorationSessionEvent::Error { message }).await.is_err() {
-                                            break;
-                                        }
-                                    }
-                                }
+                            if send_event(&mut socket, &ExplorationSessionEvent::SessionReady {
+                                state: Box::new(snapshot),
+                                protocol_version: replicator.version(),
+                            }).await.is_err() {
+                                break;
+                            }
+                            if replicator.is_delta()
+                                && send_pawn_update(&mut socket, &mut replicator, active_session, &[]).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    ExplorationClientAction::Ping => {
+                        let tick = session.as_ref().map(|entry| entry.tick).unwrap_or(0);
+                        if send_event(&mut socket, &ExplorationSessionEvent::Pong { tick }).await.is_err() {
+                            break;
+                        }
+                    }
+                    action => {
+                        let Some(active_session) = session.as_mut() else {
+                            if send_event(&mut socket, &ExplorationSessionEvent::Error {
+                                message: "No active exploration session".to_string(),
+                            }).await.is_err() {
+                                break;
                             }
-                            ExplorationClientAction::Ping => {
-                                let tick = session.as_ref().map(|entry| entry.tick).unwrap_or(0);
-                                if send_event(&mut socket, &ExplorationSessionEvent::Pong { tick }).await.is_err() {
+                            continue;
+                        };
+                        let result = match active_session.apply_input(&action, received_ms) {
+                            Ok(result) => result,
+                            Err(message) => {
+                                if send_event(&mut socket, &ExplorationSessionEvent::Error { message }).await.is_err() {
                                     break;
                                 }
+                                continue;
                             }
+                        };
+                        if result.save_now {
+                            persist_session(&state, active_session).await;
+                        }
+                        let mut closed = false;
+                        for event in &result.events {
+                            if send_event(&mut socket, event).await.is_err() {
+                                closed = true;
+                                break;
+                            }
+                        }
+                        if closed {
+                            break;
+                        }
+                        if (result.refresh_pawns || !result.changed_pawn_ids.is_empty())
+                            && send_pawn_update(&mut socket, &mut replicator, active_session, &result.changed_pawn_ids).await.is_err() {
+                            break;
                         }
                     }
-                    Message::Close(_) => {
-                        break;
-                    }
-                    _ => {}
                 }
             }
         }
     }
 
+    if let Some(active_session) = session.as_ref() {
+        persist_session(&state, active_session).await;
+    }
     info!("Exploration WebSocket disconnected");
 }
 
+async fn persist_session(state: &AppState, session: &ExplorationSim) {
+    let planets_dir = state.planets_dir.clone();
+    let world_id = session.descriptor.world_id.clone();
+    let location_id = session.descriptor.location_id.clone();
+    let saved = session.save_state(now_ms());
+    match task::spawn_blocking(move || {
+        save_session_state(&planets_dir, &world_id, &location_id, &saved)
+    })
+    .await
+    {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => warn!(error = %error, "Exploration session state write failed"),
+        Err(error) => warn!(error = %error, "Exploration session state task failed"),
+    }
+}
+
 fn load_simulation(
     state: &AppState,
     world_id: &str,
     location_id: &str,
     selected_character_ids: &[String],
     tick_rate_hz: u64,
+    seed: u64,
 ) -> Result<ExplorationSim, String> {
     if location_id == TEST_EXPLORATION_LOCATION_ID {
         ensure_test_exploration_location(&state.planets_dir, world_id)?;
     }
     let storage = load_storage_manifest(&state.planets_dir, world_id, location_id)?;
-    let chunks = load_all_chunks(&state.planets_dir, world_id, location_id)?
-        .into_iter()
-        .map(|chunk| ((chunk.chunk_row, chunk.chunk_col), chunk))
-        .collect::<HashMap<_, _>>();
+    let source = ChunkSource::open(&state.planets_dir, world_id, location_id)?;
+    // Only the view around the spawn is read up front; the sim pulls in the rest as it goes.
+    let chunks = load_chunks_in_radius(
+        &state.planets_dir,
+        world_id,
+        location_id,
+        0,
+        storage.descriptor.spawn.row,
+        storage.descriptor.spawn.col,
+        DEFAULT_SUBSCRIPTION_RADIUS,
+    )?
+    .into_iter()
+    .map(|chunk| ((chunk.level, chunk.chunk_row, chunk.chunk_col), chunk))
+    .collect::<HashMap<_, _>>();
 
+    let saved = load_session_state(&state.planets_dir, &storage.descriptor)?;
+    let resumed_pawns = match saved.as_ref() {
+        Some(saved) => saved
+            .pawns
+            .iter()
+            .filter(|pawn| pawn.faction_id != "player" || selected_character_ids.contains(&pawn.id))
+            .cloned()
+            .collect::<Vec<_>>(),
+        None => storage.pawns,
+    };
+    let new_character_ids = selected_character_ids
+        .iter()
+        .filter(|id| !resumed_pawns.iter().any(|pawn| &pawn.id == *id))
+        .cloned()
+        .collect::<Vec<_>>();
     let player_pawns = spawn_player_pawns(
         &state.characters_dir,
-        selected_character_ids,
+        &new_character_ids,
         &storage.descriptor,
         &chunks,
-        &storage.pawns,
+        &resumed_pawns,
     );
     let mut pawns = player_pawns;
-    pawns.extend(storage.pawns);
+    pawns.extend(resumed_pawns);
+
+    let province_id = read_locations(&state.planets_dir, world_id)
+        .into_iter()
+        .find(|location| location.id == location_id)
+        .map(|location| location.province_id)
+        .filter(|_| saved.is_none());
+    // Without the ecology bundle the location still opens, just without wildlife or harvests.
+    let bundle = match load_ecology_bundle(&state.planets_dir, world_id) {
+        Ok(bundle) => Some(bundle),
+        Err(error) => {
+            warn!(error = %error, "Exploration ecology unavailable; no wildlife or harvestables");
+            None
+        }
+    };
+    if let (Some(province_id), Some(bundle)) = (province_id, bundle.as_ref()) {
+        let fauna = fauna_for_province(bundle, province_id);
+        let wildlife = spawn_wildlife(
+            &fauna,
+            &storage.descriptor,
+            &pawns,
+            day_phase_at(now_ms()),
+            outdoor_walkable(&chunks, &source, storage.descriptor.chunk_size),
+            &mut StdRng::seed_from_u64(seed),
+        );
+        pawns.extend(wildlife);
+    }
 
     let selected_pawn_id = pawns
         .iter()
@@ -268,28 +385,55 @@ fn load_simulation(
         .map(|pawn| pawn.id.clone())
         .or_else(|| pawns.first().map(|pawn| pawn.id.clone()));
 
-    Ok(ExplorationSim::new(
+    // Chunks read later may hold other flora, so keep the planet's whole list.
+    let flora = bundle.map(|bundle| bundle.flora).unwrap_or_default();
+
+    let mut sim = ExplorationSim::new(
         storage.descriptor,
         chunks,
         pawns,
         selected_pawn_id,
         tick_rate_hz,
-    ))
+    );
+    sim.set_chunk_source(source);
+    match saved {
+        Some(saved) => {
+            sim.restore_state(&saved);
+            sim.set_harvest_context(flora, saved.harvest_nodes);
+        }
+        None => sim.set_harvest_context(flora, Vec::new()),
+    }
+    sim.triggers = storage.triggers;
+    sim.day_start_ms = now_ms().saturating_sub(sim.tick * 1000 / sim.tick_rate_hz);
+    sim.reseed(seed);
+    Ok(sim)
 }
 
-async fn send_event(socket: &mut WebSocket, event: &ExplorationSessionEvent) -> Result<(), ()> {
-    let payload = serde_json::to_string(event).map_err(|_| ())?;
-    socket
-        .send(Message::Text(payload.into()))
-        .await
-        .map_err(|_| ())
+async fn send_event(
+    socket: &mut ExplorationClientLink,
+    event: &ExplorationSessionEvent,
+) -> Result<(), ()> {
+    socket.send(event).await
+}
+
+async fn send_pawn_update(
+    socket: &mut ExplorationClientLink,
+    replicator: &mut PawnReplicator,
+    session: &ExplorationSim,
+    changed_pawn_ids: &[String],
+) -> Result<(), ()> {
+    if let Some(event) = replicator.pawn_update(session, changed_pawn_ids) {
+        socket.send(&event).await
+    } else {
+        Ok(())
+    }
 }
 
 fn spawn_player_pawns(
     characters_dir: &std::path::Path,
     selected_character_ids: &[String],
     descriptor: &ExplorationManifestDescriptor,
-    chunks: &HashMap<(u32, u32), ExplorationChunk>,
+    chunks: &HashMap<(i32, u32, u32), ExplorationChunk>,
     existing_pawns: &[ExplorationPawn],
 ) -> Vec<ExplorationPawn> {
     let positions = find_spawn_positions(
@@ -319,6 +463,7 @@ fn spawn_player_pawns(
                     .unwrap_or_else(|| "Colonist".to_string()),
                 x: position.1 as f32,
                 y: position.0 as f32,
+                level: 0,
                 tile_row: position.0 as i32,
                 tile_col: position.1 as i32,
                 target_x: None,
@@ -336,7 +481,7 @@ fn spawn_player_pawns(
                     .map(CharacterSummary::pawn_type)
                     .unwrap_or_else(|| "human".to_string()),
                 texture_url: None,
-                sprite: character.and_then(|entry| entry.sprite),
+                sprite: character.as_ref().and_then(|entry| entry.sprite.clone()),
                 facing: Some("south".to_string()),
                 is_npc: Some(false),
                 interaction_label: None,
@@ -345,6 +490,16 @@ fn spawn_player_pawns(
                 current_anchor_id: None,
                 current_intent: None,
                 next_decision_at_tick: None,
+                stats: character.as_ref().and_then(|entry| entry.stats.clone()),
+                conditions: Vec::new(),
+                wildlife: None,
+                movement_mode: ExplorationMovementMode::Walk,
+                armor_noise: character
+                    .as_ref()
+                    .map(|entry| entry.armor_noise)
+                    .unwrap_or(0.0),
+                awareness: None,
+                inventory: Vec::new(),
             }
         })
         .collect()
@@ -356,6 +511,8 @@ struct CharacterSummary {
     name: Option<String>,
     kind: Option<String>,
     sprite: Option<Value>,
+    stats: Option<ExplorationPawnStats>,
+    armor_noise: f32,
 }
 
 impl CharacterSummary {
@@ -378,21 +535,33 @@ fn load_character_summary(
         .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())?;
     Some(CharacterSummary {
         id: value.get("id").and_then(Value::as_str).map(str::to_string),
-        name: value.get("name").and_then(Value::as_str).map(str::to_string),
-        kind: value.get("type").and_then(Value::as_str).map(str::to_string),
+        name: value
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        kind: value
+            .get("type")
+            .and_then(Value::as_str)
+            .map(str::to_string),
         sprite: value.get("explorationSprite").cloned(),
+        stats: value
+            .get("stats")
+            .cloned()
+            .and_then(|stats| serde_json::from_value::<ExplorationPawnStats>(stats).ok()),
+        armor_noise: value.get("equipped").map(armor_noise).unwrap_or(0.0),
     })
 }
 
 fn find_spawn_positions(
     descriptor: &ExplorationManifestDescriptor,
-    chunks: &HashMap<(u32, u32), ExplorationChunk>,
+    chunks: &HashMap<(i32, u32, u32), ExplorationChunk>,
     existing_pawns: &[ExplorationPawn],
     count: usize,
 ) -> Vec<(u32, u32)> {
     let mut candidates = Vec::new();
     let mut occupied = existing_pawns
         .iter()
+        .filter(|pawn| pawn.level == 0)
         .map(|pawn| format!("{}:{}", pawn.y.round() as i32, pawn.x.round() as i32))
         .collect::<HashSet<_>>();
 
@@ -432,23 +601,122 @@ fn find_spawn_positions(
 }
 
 fn get_tile_from_chunks<'a>(
-    chunks: &'a HashMap<(u32, u32), ExplorationChunk>,
+    chunks: &'a HashMap<(i32, u32, u32), ExplorationChunk>,
     chunk_size: u32,
     row: i32,
     col: i32,
-) -> Option<&'a super::types::ExplorationTile> {
+) -> Option<&'a ExplorationTile> {
     if row < 0 || col < 0 {
         return None;
     }
     let row = row as u32;
     let col = col as u32;
-    let chunk_row = row / chunk_size.max(1);
-    let chunk_col = col / chunk_size.max(1);
-    let chunk = chunks.get(&(chunk_row, chunk_col))?;
+    // Players and wildlife arrive on the ground level.
+    let chunk = chunks.get(&(0, row / chunk_size.max(1), col / chunk_size.max(1)))?;
+    tile_in_chunk(chunk, row, col)
+}
+
+fn tile_in_chunk(chunk: &ExplorationChunk, row: u32, col: u32) -> Option<&ExplorationTile> {
     let local_row = row.saturating_sub(chunk.origin_row);
     let local_col = col.saturating_sub(chunk.origin_col);
     if local_row >= chunk.height || local_col >= chunk.width {
         return None;
     }
-    chunk.tiles.get((local_row * chunk.width + local_col) as usize)
+    chunk
+        .tiles
+        .get((local_row * chunk.width + local_col) as usize)
+}
+
+/// Outdoor ground tiles wildlife may stand on. Wildlife anchors are sampled across the whole
+/// map, so chunks outside the spawn view are read from `source` as the samples reach them.
+fn outdoor_walkable<'a>(
+    chunks: &'a HashMap<(i32, u32, u32), ExplorationChunk>,
+    source: &'a ChunkSource,
+    chunk_size: u32,
+) -> impl Fn(i32, i32) -> bool + 'a {
+    let read = RefCell::new(HashMap::<(u32, u32), Option<ExplorationChunk>>::new());
+    move |row, col| {
+        let outdoor = |tile: &ExplorationTile| tile.walkable && tile.interior_id.is_none();
+        if let Some(tile) = get_tile_from_chunks(chunks, chunk_size, row, col) {
+            return outdoor(tile);
+        }
+        if row < 0 || col < 0 {
+            return false;
+        }
+        let (row, col) = (row as u32, col as u32);
+        let key = (row / chunk_size.max(1), col / chunk_size.max(1));
+        let mut read = read.borrow_mut();
+        let chunk = read.entry(key).or_insert_with(|| {
+            source.load_chunk(0, key.0, key.1).unwrap_or_else(|error| {
+                warn!(error = %error, "Skipping exploration chunk for wildlife");
+                None
+            })
+        });
+        chunk
+            .as_ref()
+            .and_then(|chunk| tile_in_chunk(chunk, row, col))
+            .is_some_and(outdoor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exploration_engine::manifest::{manifest_path, write_chunked_location};
+    use crate::exploration_engine::types::ExplorationMap;
+
+    #[test]
+    fn wildlife_reads_ground_outside_the_spawn_view() {
+        let tiles = (0..64u32 * 64)
+            .map(|index| {
+                let (row, col) = (index / 64, index % 64);
+                ExplorationTile {
+                    r#type: "grass".to_string(),
+                    walkable: true,
+                    move_cost: 1.0,
+                    texture_url: None,
+                    is_spawn_zone: None,
+                    interior_id: (row >= 48 && col >= 48).then(|| "barn".to_string()),
+                    light_level: None,
+                    blocks_light: None,
+                    door_id: None,
+                    level_link: None,
+                }
+            })
+            .collect();
+        let map = ExplorationMap {
+            id: "meadow".to_string(),
+            width: 64,
+            height: 64,
+            tiles,
+            pawns: Vec::new(),
+            objects: Vec::new(),
+            name: None,
+            fog_of_war: None,
+            ambient_light: None,
+            version: None,
+            render_mode: None,
+            metadata: None,
+            levels: Vec::new(),
+            triggers: Vec::new(),
+        };
+        let dir = std::env::temp_dir().join(format!("ashtrail-wildlife-reads-{}", now_ms()));
+        std::fs::create_dir_all(manifest_path(&dir, "world", "meadow").parent().unwrap()).unwrap();
+        let descriptor = write_chunked_location(&dir, "world", "meadow", &map).unwrap();
+        let spawn_view = load_chunks_in_radius(&dir, "world", "meadow", 0, 0, 0, 0)
+            .unwrap()
+            .into_iter()
+            .map(|chunk| ((chunk.level, chunk.chunk_row, chunk.chunk_col), chunk))
+            .collect::<HashMap<_, _>>();
+        let source = ChunkSource::open(&dir, "world", "meadow").unwrap();
+
+        let walkable = outdoor_walkable(&spawn_view, &source, descriptor.chunk_size);
+        assert!(walkable(2, 2));
+        assert!(walkable(40, 20));
+        assert!(!walkable(50, 50));
+        assert!(!walkable(64, 0));
+        assert_eq!(spawn_view.len(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
@@ -1,19 +1,82 @@
-use rand::Rng;
-use std::collections::{HashMap, HashSet};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 
+use crate::ecology::FloraEntry;
+use crate::jobs::seed_from_key;
+
+use super::harvest::{condition_speed_factor, regrowth_deadline_ms, resolve_harvest, stack_items};
+use super::manifest::ChunkSource;
+use super::party::{formation_offsets, heading_between, heading_from_facing, slot_cell};
+use super::pathfinding::NavGrid;
+use super::state::{new_save_state, ExplorationSaveState};
+use super::stealth::{
+    detection_gain, light_at, noise_emission, perception_score, step_awareness, SNEAK_SPEED_FACTOR,
+};
+use super::triggers::{pawns_in_area, trigger_conditions_hold};
 use super::types::{
-    ExplorationChunk, ExplorationChunkSync, ExplorationManifestDescriptor, ExplorationObject,
-    ExplorationPawn, ExplorationSessionEvent, ExplorationSessionSnapshot, ExplorationTile,
-    ExplorationVisibilityState, PathNode, RouteNode,
+    ExplorationAwarenessState, ExplorationChunk, ExplorationClientAction, ExplorationFormation,
+    ExplorationHarvestNodeState, ExplorationInputEntry, ExplorationInputLog, ExplorationItemStack,
+    ExplorationManifestDescriptor, ExplorationMovementMode, ExplorationObject, ExplorationParty,
+    ExplorationPawn, ExplorationPawnCondition, ExplorationPawnStats, ExplorationSessionEvent,
+    ExplorationSessionSnapshot, ExplorationTile, ExplorationTrigger, ExplorationTriggerAction,
+    ExplorationVisibilityState, ExplorationWildlifeBehavior, PathNode, RouteNode,
 };
+use super::wildlife::{day_phase_at, detection_chance, detection_radius, spot_radius, DayPhase};
 
 pub const DEFAULT_SUBSCRIPTION_RADIUS: u32 = 1;
 const EDGE_WANDER_RADIUS: i32 = 6;
+const WILDLIFE_ROAM_RADIUS: i32 = 5;
+const WILDLIFE_FLEE_DISTANCE: f32 = 8.0;
+const WILDLIFE_STALK_DISTANCE: f32 = 3.0;
+const TERRITORIAL_WARNING_SECONDS: u64 = 3;
+const FORMATION_SLOT_SEARCH_RADIUS: i32 = 3;
+
+/// A cell on a specific floor: `(level, row, col)`.
+type LevelCell = (i32, i32, i32);
 
 #[derive(Debug, Clone)]
 pub struct AdvanceResult {
     pub changed_pawn_ids: Vec<String>,
     pub visibility_changed: bool,
+    pub wildlife_events: Vec<WildlifeEvent>,
+    /// Session events emitted by triggers that fired this tick.
+    pub trigger_events: Vec<ExplorationSessionEvent>,
+    pub doors_changed: bool,
+    /// NPCs and creatures whose awareness of the party changed state this tick.
+    pub awareness_events: Vec<ExplorationSessionEvent>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WildlifeEvent {
+    pub label: String,
+    pub pawn_id: String,
+    pub fauna_id: String,
+    pub intent: String,
+    pub target_pawn_id: Option<String>,
+}
+
+impl WildlifeEvent {
+    pub fn into_session_event(self) -> ExplorationSessionEvent {
+        ExplorationSessionEvent::Wildlife {
+            label: self.label,
+            pawn_id: self.pawn_id,
+            fauna_id: self.fauna_id,
+            intent: self.intent,
+            target_pawn_id: self.target_pawn_id,
+        }
+    }
+}
+
+/// A party member as heard and seen by the NPCs around it this tick.
+struct NoticeablePawn {
+    id: String,
+    level: i32,
+    x: f32,
+    y: f32,
+    noise: f32,
+    light: f32,
+    stats: ExplorationPawnStats,
 }
 
 #[derive(Debug, Clone)]
@@ -23,9 +86,43 @@ pub struct InteractionResult {
     pub changed_pawn_ids: Vec<String>,
 }
 
+/// Outcome of one client action: the pawns to replicate and the events to send back.
+#[derive(Debug, Clone, Default)]
+pub struct InputResult {
+    pub changed_pawn_ids: Vec<String>,
+    pub events: Vec<ExplorationSessionEvent>,
+    /// Re-send pawns even when none changed, because the selection or the view did.
+    pub refresh_pawns: bool,
+    /// Harvest node state changed and should be saved without waiting for the autosave.
+    pub save_now: bool,
+}
+
+impl InputResult {
+    fn pawns(changed_pawn_ids: Vec<String>) -> Self {
+        Self {
+            changed_pawn_ids,
+            refresh_pawns: true,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HarvestResult {
+    pub label: String,
+    pub object_id: String,
+    pub flora_id: String,
+    pub actor_id: String,
+    pub success: bool,
+    pub items: Vec<ExplorationItemStack>,
+    pub conditions: Vec<ExplorationPawnCondition>,
+    pub regrows_at_ms: Option<u64>,
+}
+
 pub struct ExplorationSim {
     pub descriptor: ExplorationManifestDescriptor,
-    pub chunks: HashMap<(u32, u32), ExplorationChunk>,
+    /// Chunks keyed `(level, chunk_row, chunk_col)`.
+    pub chunks: HashMap<(i32, u32, u32), ExplorationChunk>,
     pub objects: Vec<ExplorationObject>,
     pub pawns: Vec<ExplorationPawn>,
     pub selected_pawn_id: Option<String>,
@@ -35,27 +132,75 @@ pub struct ExplorationSim {
     pub subscribed_center_row: u32,
     pub subscribed_center_col: u32,
     pub subscribed_radius: u32,
+    pub subscribed_level: i32,
+    pub flora: HashMap<String, FloraEntry>,
+    pub harvest_nodes: HashMap<String, ExplorationHarvestNodeState>,
+    /// One navigation grid per level; level `0` always exists.
+    pub navs: HashMap<i32, NavGrid>,
+    /// Stairs and ladders as `(from, to)` cells, each `(level, row, col)`.
+    pub level_links: Vec<(LevelCell, LevelCell)>,
+    pub party: Option<ExplorationParty>,
+    pub triggers: Vec<ExplorationTrigger>,
+    pub fired_trigger_ids: Vec<String>,
+    pub active_trigger_ids: Vec<String>,
+    pub quest_flags: Vec<String>,
+    /// Wall-clock time of tick `0`; the day phase advances with the tick from here.
+    pub day_start_ms: u64,
+    pub seed: u64,
+    /// Source of every random decision, so a seed and an input log reproduce a session.
+    rng: StdRng,
+    /// Client actions since the last reseed. Kept in memory for replay tests, never saved.
+    pub input_log: ExplorationInputLog,
+    /// Stored revision that chunks outside the loaded set are read from when first needed.
+    chunk_source: Option<ChunkSource>,
 }
 
 impl ExplorationSim {
     pub fn new(
         descriptor: ExplorationManifestDescriptor,
-        chunks: HashMap<(u32, u32), ExplorationChunk>,
+        chunks: HashMap<(i32, u32, u32), ExplorationChunk>,
         pawns: Vec<ExplorationPawn>,
         selected_pawn_id: Option<String>,
         tick_rate_hz: u64,
     ) -> Self {
-        let normalized_pawns = pawns.into_iter().map(normalize_pawn_runtime).collect::<Vec<_>>();
+        let normalized_pawns = pawns
+            .into_iter()
+            .map(normalize_pawn_runtime)
+            .collect::<Vec<_>>();
         let mut objects = chunks
             .values()
             .flat_map(|chunk| chunk.objects.iter().cloned())
             .collect::<Vec<_>>();
         objects.sort_by(|left, right| left.id.cmp(&right.id));
         objects.dedup_by(|left, right| left.id == right.id);
+        let mut levels = chunks.keys().map(|key| key.0).collect::<HashSet<_>>();
+        levels.insert(0);
+        let navs = levels
+            .into_iter()
+            .map(|level| {
+                let nav = NavGrid::new(
+                    descriptor.width,
+                    descriptor.height,
+                    descriptor.chunk_size,
+                    region_costs(
+                        &chunks,
+                        descriptor.chunk_size,
+                        level,
+                        &objects,
+                        &[],
+                        (0, 0, descriptor.height, descriptor.width),
+                    ),
+                );
+                (level, nav)
+            })
+            .collect();
+        let level_links = collect_level_links(&chunks);
+        let seed = session_seed(&descriptor.world_id, &descriptor.location_id, "");
         let mut sim = Self {
             subscribed_center_row: descriptor.spawn.row,
             subscribed_center_col: descriptor.spawn.col,
             subscribed_radius: DEFAULT_SUBSCRIPTION_RADIUS,
+            subscribed_level: 0,
             descriptor,
             chunks,
             objects,
@@ -65,14 +210,469 @@ impl ExplorationSim {
                 revealed_interior_id: None,
                 revealed_roof_group_ids: Vec::new(),
                 opened_door_ids: Vec::new(),
+                current_level: 0,
+                locked_door_ids: Vec::new(),
             },
             tick: 0,
             tick_rate_hz: tick_rate_hz.max(1),
+            flora: HashMap::new(),
+            harvest_nodes: HashMap::new(),
+            navs,
+            level_links,
+            party: None,
+            triggers: Vec::new(),
+            fired_trigger_ids: Vec::new(),
+            active_trigger_ids: Vec::new(),
+            quest_flags: Vec::new(),
+            day_start_ms: 0,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            input_log: ExplorationInputLog {
+                seed,
+                tick_rate_hz: tick_rate_hz.max(1),
+                ..Default::default()
+            },
+            chunk_source: None,
         };
         sim.refresh_visibility();
         sim
     }
 
+    /// Lets the sim read chunks it was not created with from `source` as views, pawns and routes
+    /// reach them. Every stored level gets a grid up front; unread chunks are blocked until
+    /// loaded.
+    pub fn set_chunk_source(&mut self, source: ChunkSource) {
+        let cells = (self.descriptor.width * self.descriptor.height) as usize;
+        for level in source.level_ids() {
+            self.navs.entry(level).or_insert_with(|| {
+                NavGrid::new(
+                    self.descriptor.width,
+                    self.descriptor.height,
+                    self.descriptor.chunk_size,
+                    vec![0.0; cells],
+                )
+            });
+        }
+        self.chunk_source = Some(source);
+        let pawn_cells = self
+            .pawns
+            .iter()
+            .map(|pawn| (pawn.level, pawn.tile_row, pawn.tile_col))
+            .collect::<Vec<_>>();
+        for (level, row, col) in pawn_cells {
+            self.load_chunks_between(level, (row, col), (row, col), 1);
+        }
+    }
+
+    /// Reads the missing chunks of `level` covering the cells from `from` to `to`, widened by
+    /// `padding` chunks, and folds them into the objects, level links and nav grid.
+    fn load_chunks_between(&mut self, level: i32, from: (i32, i32), to: (i32, i32), padding: u32) {
+        let Some(source) = self.chunk_source.as_ref() else {
+            return;
+        };
+        let chunk_size = self.descriptor.chunk_size.max(1);
+        let last_row = self.descriptor.height.saturating_sub(1) / chunk_size;
+        let last_col = self.descriptor.width.saturating_sub(1) / chunk_size;
+        let chunk_of = |cell: i32, last: u32| (cell.max(0) as u32 / chunk_size).min(last);
+        let min_row = chunk_of(from.0.min(to.0), last_row).saturating_sub(padding);
+        let max_row = (chunk_of(from.0.max(to.0), last_row) + padding).min(last_row);
+        let min_col = chunk_of(from.1.min(to.1), last_col).saturating_sub(padding);
+        let max_col = (chunk_of(from.1.max(to.1), last_col) + padding).min(last_col);
+
+        let mut loaded = Vec::new();
+        for chunk_row in min_row..=max_row {
+            for chunk_col in min_col..=max_col {
+                if self.chunks.contains_key(&(level, chunk_row, chunk_col))
+                    || !source.has_chunk(level, chunk_row, chunk_col)
+                {
+                    continue;
+                }
+                match source.load_chunk(level, chunk_row, chunk_col) {
+                    Ok(Some(chunk)) => loaded.push(chunk),
+                    Ok(None) => {}
+                    Err(error) => tracing::warn!(
+                        "Skipping exploration chunk {level}/{chunk_row}/{chunk_col}: {error}"
+                    ),
+                }
+            }
+        }
+        self.insert_chunks(loaded);
+    }
+
+    fn insert_chunks(&mut self, loaded: Vec<ExplorationChunk>) {
+        if loaded.is_empty() {
+            return;
+        }
+        let chunk_size = self.descriptor.chunk_size.max(1);
+        let mut known_ids = self
+            .objects
+            .iter()
+            .map(|object| object.id.clone())
+            .collect::<HashSet<_>>();
+        let mut dirty_chunks = HashSet::new();
+        for chunk in loaded {
+            dirty_chunks.insert((chunk.level, chunk.chunk_row, chunk.chunk_col));
+            for object in &chunk.objects {
+                if !known_ids.insert(object.id.clone()) {
+                    continue;
+                }
+                for row in object.y..object.y + object.height.max(1) {
+                    for col in object.x..object.x + object.width.max(1) {
+                        dirty_chunks.insert((object.level, row / chunk_size, col / chunk_size));
+                    }
+                }
+                self.objects.push(object.clone());
+            }
+            self.chunks
+                .insert((chunk.level, chunk.chunk_row, chunk.chunk_col), chunk);
+        }
+        self.objects.sort_by(|left, right| left.id.cmp(&right.id));
+        self.level_links = collect_level_links(&self.chunks);
+        self.refresh_chunk_costs(dirty_chunks);
+    }
+
+    /// Reads the chunks an interior spans before it is searched. An interior already in the
+    /// loaded chunks pulls in the chunks around it, in case it crosses into unread ones; one
+    /// that is nowhere in view means reading the rest of the level.
+    fn load_interior_chunks(&mut self, level: i32, interior_id: &str) {
+        if self.chunk_source.is_none() {
+            return;
+        }
+        match loaded_interior_bounds(self, level, interior_id) {
+            Some((from, to)) => self.load_chunks_between(level, from, to, 1),
+            None => self.load_chunks_between(
+                level,
+                (0, 0),
+                (
+                    self.descriptor.height as i32 - 1,
+                    self.descriptor.width as i32 - 1,
+                ),
+                0,
+            ),
+        }
+    }
+
+    /// Reseeds the sim from the current tick and starts a fresh input log, so the log replays
+    /// from the sim's present state and clock.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(tick_seed(seed, self.tick));
+        self.input_log = ExplorationInputLog {
+            seed,
+            start_tick: self.tick,
+            day_start_ms: self.day_start_ms,
+            tick_rate_hz: self.tick_rate_hz,
+            entries: Vec::new(),
+        };
+    }
+
+    /// Appends a client action to the input log. Actions that do not touch the sim are skipped.
+    pub fn record_input(&mut self, action: &ExplorationClientAction, at_ms: u64) {
+        if matches!(
+            action,
+            ExplorationClientAction::StartSession { .. } | ExplorationClientAction::Ping
+        ) {
+            return;
+        }
+        self.input_log.entries.push(ExplorationInputEntry {
+            tick: self.tick,
+            at_ms,
+            action: action.clone(),
+        });
+    }
+
+    /// Applies a client action. The live session and replays both go through here, so a log of
+    /// these inputs reproduces the session.
+    pub fn apply_input(
+        &mut self,
+        action: &ExplorationClientAction,
+        at_ms: u64,
+    ) -> Result<InputResult, String> {
+        match action {
+            ExplorationClientAction::StartSession { .. } | ExplorationClientAction::Ping => {
+                Ok(InputResult::default())
+            }
+            ExplorationClientAction::SubscribeView {
+                center_row,
+                center_col,
+                radius,
+                level,
+            } => {
+                let event = self.subscribe_view(*center_row, *center_col, *radius, *level);
+                Ok(InputResult {
+                    events: vec![event],
+                    ..InputResult::pawns(Vec::new())
+                })
+            }
+            ExplorationClientAction::MoveTo {
+                pawn_id,
+                target_row,
+                target_col,
+                level,
+            } => {
+                self.leave_formation(pawn_id);
+                match level {
+                    Some(level) => self.move_pawn_to_level(
+                        pawn_id,
+                        *level,
+                        *target_row as i32,
+                        *target_col as i32,
+                        true,
+                    ),
+                    None => self.move_pawn(pawn_id, *target_row as i32, *target_col as i32, true),
+                }
+                .map(InputResult::pawns)
+            }
+            ExplorationClientAction::MoveParty {
+                leader_id,
+                target_row,
+                target_col,
+                level,
+                formation,
+            } => self
+                .move_party(
+                    leader_id.as_deref(),
+                    *level,
+                    *target_row as i32,
+                    *target_col as i32,
+                    *formation,
+                )
+                .map(InputResult::pawns),
+            ExplorationClientAction::SetSelectedPawn { pawn_id } => {
+                self.set_selected_pawn(pawn_id.clone());
+                Ok(InputResult::pawns(Vec::new()))
+            }
+            ExplorationClientAction::Interact {
+                row,
+                col,
+                object_id,
+                actor_id,
+            } => {
+                let mut result =
+                    self.handle_interaction(*row, *col, object_id.clone(), actor_id.clone())?;
+                if self.party.as_ref().is_some_and(|party| party.following) {
+                    result.changed_pawn_ids.extend(self.regroup_party());
+                }
+                let mut events = Vec::new();
+                if result.chunks_changed {
+                    events.push(self.chunk_delta(self.current_subscription_chunks(), Vec::new()));
+                }
+                events.push(ExplorationSessionEvent::Interaction {
+                    label: result.label,
+                    row: *row,
+                    col: *col,
+                    object_id: object_id.clone(),
+                    actor_id: actor_id.clone(),
+                });
+                Ok(InputResult {
+                    changed_pawn_ids: result.changed_pawn_ids,
+                    events,
+                    ..InputResult::default()
+                })
+            }
+            ExplorationClientAction::Harvest {
+                object_id,
+                actor_id,
+            } => {
+                let result = self.harvest(object_id, actor_id.as_deref(), at_ms)?;
+                let mut changed_pawn_ids = Vec::new();
+                if self.party.as_ref().is_some_and(|party| party.following) {
+                    changed_pawn_ids.extend(self.regroup_party());
+                }
+                if result.success || !result.conditions.is_empty() {
+                    changed_pawn_ids.push(result.actor_id.clone());
+                }
+                Ok(InputResult {
+                    changed_pawn_ids,
+                    events: vec![ExplorationSessionEvent::Harvest {
+                        label: result.label,
+                        object_id: result.object_id,
+                        flora_id: result.flora_id,
+                        actor_id: result.actor_id,
+                        success: result.success,
+                        items: result.items,
+                        conditions: result.conditions,
+                        regrows_at_ms: result.regrows_at_ms,
+                    }],
+                    refresh_pawns: false,
+                    save_now: result.success,
+                })
+            }
+            ExplorationClientAction::SetMovementMode { pawn_id, mode } => self
+                .set_movement_mode(pawn_id.as_deref(), *mode)
+                .map(InputResult::pawns),
+            ExplorationClientAction::SetQuestFlags { flags } => {
+                self.set_quest_flags(flags.clone());
+                Ok(InputResult::default())
+            }
+        }
+    }
+
+    /// Navigation grid of a level; unknown levels fall back to the ground floor.
+    pub fn nav(&self, level: i32) -> &NavGrid {
+        self.navs
+            .get(&level)
+            .or_else(|| self.navs.get(&0))
+            .expect("ground level navigation grid")
+    }
+
+    pub fn set_harvest_context(
+        &mut self,
+        flora: Vec<FloraEntry>,
+        nodes: Vec<ExplorationHarvestNodeState>,
+    ) {
+        self.flora = flora
+            .into_iter()
+            .map(|entry| (entry.id.clone(), entry))
+            .collect();
+        self.harvest_nodes = nodes
+            .into_iter()
+            .map(|node| (node.object_id.clone(), node))
+            .collect();
+    }
+
+    /// Forgets depleted nodes whose regrowth deadline has passed.
+    fn prune_regrown_nodes(&mut self, now_ms: u64) {
+        self.harvest_nodes
+            .retain(|_, node| node.regrows_at_ms > now_ms);
+    }
+
+    pub fn harvest_node_states(&self) -> Vec<ExplorationHarvestNodeState> {
+        let mut nodes = self.harvest_nodes.values().cloned().collect::<Vec<_>>();
+        nodes.sort_by(|left, right| left.object_id.cmp(&right.object_id));
+        nodes
+    }
+
+    /// Marks doors as open and refreshes the cached cost fields of every chunk they touch.
+    /// Locked doors stay shut.
+    pub fn open_doors(&mut self, door_ids: Vec<String>) {
+        let newly_opened = door_ids
+            .into_iter()
+            .filter(|door_id| {
+                !self.visibility.opened_door_ids.contains(door_id)
+                    && !self.visibility.locked_door_ids.contains(door_id)
+            })
+            .collect::<HashSet<_>>();
+        if newly_opened.is_empty() {
+            return;
+        }
+        self.visibility
+            .opened_door_ids
+            .extend(newly_opened.iter().cloned());
+        self.visibility.opened_door_ids.sort();
+        self.visibility.opened_door_ids.dedup();
+        self.refresh_door_costs(&newly_opened);
+    }
+
+    /// Closes and locks doors until a trigger unlocks them.
+    pub fn lock_doors(&mut self, door_ids: Vec<String>) {
+        let newly_locked = door_ids
+            .into_iter()
+            .filter(|door_id| !self.visibility.locked_door_ids.contains(door_id))
+            .collect::<HashSet<_>>();
+        if newly_locked.is_empty() {
+            return;
+        }
+        self.visibility
+            .opened_door_ids
+            .retain(|door_id| !newly_locked.contains(door_id));
+        self.visibility
+            .locked_door_ids
+            .extend(newly_locked.iter().cloned());
+        self.visibility.locked_door_ids.sort();
+        self.refresh_door_costs(&newly_locked);
+    }
+
+    fn refresh_door_costs(&mut self, changed_door_ids: &HashSet<String>) {
+        let chunk_size = self.descriptor.chunk_size.max(1);
+        let mut dirty_chunks = HashSet::new();
+        for chunk in self.chunks.values() {
+            if chunk.tiles.iter().any(|tile| {
+                tile.door_id
+                    .as_ref()
+                    .is_some_and(|door_id| changed_door_ids.contains(door_id))
+            }) {
+                dirty_chunks.insert((chunk.level, chunk.chunk_row, chunk.chunk_col));
+            }
+        }
+        for object in &self.objects {
+            if !object
+                .door_id
+                .as_ref()
+                .is_some_and(|door_id| changed_door_ids.contains(door_id))
+            {
+                continue;
+            }
+            for row in object.y..object.y + object.height.max(1) {
+                for col in object.x..object.x + object.width.max(1) {
+                    dirty_chunks.insert((object.level, row / chunk_size, col / chunk_size));
+                }
+            }
+        }
+
+        self.refresh_chunk_costs(dirty_chunks);
+    }
+
+    /// Recomputes the nav cost field of each `(level, chunk_row, chunk_col)` from the loaded
+    /// chunks, objects and door state.
+    fn refresh_chunk_costs(&mut self, dirty_chunks: HashSet<(i32, u32, u32)>) {
+        let chunk_size = self.descriptor.chunk_size.max(1);
+        for (level, chunk_row, chunk_col) in dirty_chunks {
+            let Some(nav) = self.navs.get(&level) else {
+                continue;
+            };
+            let (origin_row, origin_col, rows, cols) = nav.chunk_rect(chunk_row, chunk_col);
+            let costs = region_costs(
+                &self.chunks,
+                chunk_size,
+                level,
+                &self.objects,
+                &self.visibility.opened_door_ids,
+                (origin_row as u32, origin_col as u32, rows, cols),
+            );
+            if let Some(nav) = self.navs.get_mut(&level) {
+                nav.update_chunk(chunk_row, chunk_col, &costs);
+            }
+        }
+    }
+
+    pub fn save_state(&self, now_ms: u64) -> ExplorationSaveState {
+        let mut saved = new_save_state(&self.descriptor, now_ms, self.tick);
+        saved.pawns = self.pawns.clone();
+        saved.selected_pawn_id = self.selected_pawn_id.clone();
+        saved.visibility = self.visibility.clone();
+        saved.harvest_nodes = self.harvest_node_states();
+        saved.party = self.party.clone();
+        saved.fired_trigger_ids = self.fired_trigger_ids.clone();
+        saved.active_trigger_ids = self.active_trigger_ids.clone();
+        saved.quest_flags = self.quest_flags.clone();
+        saved
+    }
+
+    /// Restores the clock, selection, party, doors, trigger state and quest flags from a saved
+    /// state. Pawns and harvest nodes are handed to the sim by the loader.
+    pub fn restore_state(&mut self, saved: &ExplorationSaveState) {
+        self.tick = saved.tick;
+        if let Some(selected_pawn_id) = saved
+            .selected_pawn_id
+            .as_ref()
+            .filter(|id| self.pawns.iter().any(|pawn| &pawn.id == *id))
+        {
+            self.selected_pawn_id = Some(selected_pawn_id.clone());
+        }
+        self.party = saved
+            .party
+            .clone()
+            .filter(|party| self.pawns.iter().any(|pawn| pawn.id == party.leader_id));
+        self.lock_doors(saved.visibility.locked_door_ids.clone());
+        self.open_doors(saved.visibility.opened_door_ids.clone());
+        self.fired_trigger_ids = saved.fired_trigger_ids.clone();
+        self.active_trigger_ids = saved.active_trigger_ids.clone();
+        self.quest_flags = saved.quest_flags.clone();
+        self.rng = StdRng::seed_from_u64(tick_seed(self.seed, self.tick));
+        self.refresh_visibility();
+    }
+
     pub fn snapshot(&self) -> ExplorationSessionSnapshot {
         ExplorationSessionSnapshot {
             descriptor: self.descriptor.clone(),
@@ -82,15 +682,8 @@ impl ExplorationSim {
             visibility: self.visibility.clone(),
             tick: self.tick,
             connection_state: "active".to_string(),
-        }
-    }
-
-    pub fn current_chunk_sync(&self) -> ExplorationSessionEvent {
-        ExplorationSessionEvent::ChunkSync {
-            sync: ExplorationChunkSync {
-                descriptor_id: self.descriptor.id.clone(),
-                chunks: self.current_subscription_chunks(),
-            },
+            depleted_nodes: self.harvest_node_states(),
+            party: self.party.clone(),
         }
     }
 
@@ -127,23 +720,15 @@ impl ExplorationSim {
         }
     }
 
-    pub fn pawn_sync(&self) -> ExplorationSessionEvent {
-        ExplorationSessionEvent::PawnSync {
-            pawns: self.pawns.clone(),
-            selected_pawn_id: self.selected_pawn_id.clone(),
-            visibility: self.visibility.clone(),
-            tick: self.tick,
-            connection_state: "active".to_string(),
-        }
-    }
-
     pub fn subscribe_view(
         &mut self,
         center_row: u32,
         center_col: u32,
         radius: u32,
+        level: Option<i32>,
     ) -> ExplorationSessionEvent {
-        let previous_ids = self.current_subscription_chunks()
+        let previous_ids = self
+            .current_subscription_chunks()
             .into_iter()
             .map(|chunk| chunk.id)
             .collect::<HashSet<_>>();
@@ -151,6 +736,19 @@ impl ExplorationSim {
         self.subscribed_center_row = center_row.min(self.descriptor.height.saturating_sub(1));
         self.subscribed_center_col = center_col.min(self.descriptor.width.saturating_sub(1));
         self.subscribed_radius = radius.min(2);
+        if let Some(level) = level.filter(|level| self.navs.contains_key(level)) {
+            self.subscribed_level = level;
+        }
+        let center = (
+            self.subscribed_center_row as i32,
+            self.subscribed_center_col as i32,
+        );
+        self.load_chunks_between(
+            self.subscribed_level,
+            center,
+            center,
+            self.subscribed_radius,
+        );
 
         let next_chunks = self.current_subscription_chunks();
         let next_ids = next_chunks
@@ -164,20 +762,40 @@ impl ExplorationSim {
         self.chunk_delta(next_chunks, removed_chunk_ids)
     }
 
-    pub fn current_subscription_chunks(&self) -> Vec<ExplorationChunk> {
+    /// Inclusive chunk row/col bounds of the subscribed view.
+    fn subscription_bounds(&self) -> (u32, u32, u32, u32) {
         let chunk_size = self.descriptor.chunk_size.max(1);
         let center_chunk_row = self.subscribed_center_row / chunk_size;
         let center_chunk_col = self.subscribed_center_col / chunk_size;
-        let min_row = center_chunk_row.saturating_sub(self.subscribed_radius);
-        let min_col = center_chunk_col.saturating_sub(self.subscribed_radius);
-        let max_row = center_chunk_row + self.subscribed_radius;
-        let max_col = center_chunk_col + self.subscribed_radius;
+        (
+            center_chunk_row.saturating_sub(self.subscribed_radius),
+            center_chunk_col.saturating_sub(self.subscribed_radius),
+            center_chunk_row + self.subscribed_radius,
+            center_chunk_col + self.subscribed_radius,
+        )
+    }
+
+    /// Whether a tile lies inside the chunks the client subscribed to.
+    pub fn in_subscribed_view(&self, level: i32, row: i32, col: i32) -> bool {
+        if level != self.subscribed_level || row < 0 || col < 0 {
+            return false;
+        }
+        let chunk_size = self.descriptor.chunk_size.max(1);
+        let (min_row, min_col, max_row, max_col) = self.subscription_bounds();
+        let chunk_row = row as u32 / chunk_size;
+        let chunk_col = col as u32 / chunk_size;
+        chunk_row >= min_row && chunk_row <= max_row && chunk_col >= min_col && chunk_col <= max_col
+    }
+
+    pub fn current_subscription_chunks(&self) -> Vec<ExplorationChunk> {
+        let (min_row, min_col, max_row, max_col) = self.subscription_bounds();
 
         let mut chunks = self
             .chunks
             .iter()
-            .filter_map(|((chunk_row, chunk_col), chunk)| {
-                if *chunk_row >= min_row
+            .filter_map(|((level, chunk_row, chunk_col), chunk)| {
+                if *level == self.subscribed_level
+                    && *chunk_row >= min_row
                     && *chunk_row <= max_row
                     && *chunk_col >= min_col
                     && *chunk_col <= max_col
@@ -210,16 +828,17 @@ impl ExplorationSim {
             return changed;
         };
 
+        let level = selected_pawn.level;
         let (row, col) = resolve_pawn_navigation_origin(self, selected_pawn);
-        let tile = get_tile(self, row, col);
+        let tile = get_tile(self, level, row, col);
         let revealed_interior_id = tile
             .and_then(|entry| entry.interior_id.clone())
-            .or_else(|| adjacent_doorway_interior(self, row, col))
+            .or_else(|| adjacent_doorway_interior(self, level, row, col))
             .or_else(|| selected_pawn.home_interior_id.clone());
         let mut revealed_roof_group_ids = Vec::new();
         if let Some(interior_id) = revealed_interior_id.as_deref() {
             for object in &self.objects {
-                if object.interior_id.as_deref() == Some(interior_id) {
+                if object.level == level && object.interior_id.as_deref() == Some(interior_id) {
                     if let Some(roof_group_id) = object.roof_group_id.clone() {
                         if !revealed_roof_group_ids.contains(&roof_group_id) {
                             revealed_roof_group_ids.push(roof_group_id);
@@ -230,7 +849,9 @@ impl ExplorationSim {
         }
 
         let changed = self.visibility.revealed_interior_id != revealed_interior_id
-            || self.visibility.revealed_roof_group_ids != revealed_roof_group_ids;
+            || self.visibility.revealed_roof_group_ids != revealed_roof_group_ids
+            || self.visibility.current_level != level;
+        self.visibility.current_level = level;
         self.visibility.revealed_interior_id = revealed_interior_id;
         self.visibility.revealed_roof_group_ids = revealed_roof_group_ids;
         changed
@@ -242,51 +863,67 @@ impl ExplorationSim {
         target_row: i32,
         target_col: i32,
         allow_partial: bool,
+    ) -> Result<Vec<String>, String> {
+        let level = self
+            .pawns
+            .iter()
+            .find(|entry| entry.id == pawn_id)
+            .map(|pawn| pawn.level)
+            .ok_or_else(|| "Unknown pawn".to_string())?;
+        self.move_pawn_to_level(pawn_id, level, target_row, target_col, allow_partial)
+    }
+
+    /// Like `move_pawn`, but the target may be on another level; the route then takes the
+    /// cheapest chain of stairs and ladders.
+    pub fn move_pawn_to_level(
+        &mut self,
+        pawn_id: &str,
+        target_level: i32,
+        target_row: i32,
+        target_col: i32,
+        allow_partial: bool,
     ) -> Result<Vec<String>, String> {
         let Some(start_index) = self.pawns.iter().position(|entry| entry.id == pawn_id) else {
             return Err("Unknown pawn".to_string());
         };
+        if !self.navs.contains_key(&target_level) {
+            return Err(format!("Unknown level {target_level}"));
+        }
+        let level = self.pawns[start_index].level;
         let (start_row, start_col) = resolve_pawn_navigation_origin(self, &self.pawns[start_index]);
-        if start_row == target_row && start_col == target_col {
+        // Routes rarely stray far outside the box between their ends, so that box (plus a chunk
+        // of margin) is what gets read before searching.
+        for route_level in [level, target_level] {
+            self.load_chunks_between(
+                route_level,
+                (start_row, start_col),
+                (target_row, target_col),
+                1,
+            );
+        }
+        if level == target_level && start_row == target_row && start_col == target_col {
             let pawn = &mut self.pawns[start_index];
             clear_route_state(pawn);
             return Ok(vec![pawn_id.to_string()]);
         }
 
-        let target_interior_id = get_tile(self, target_row, target_col).and_then(|tile| tile.interior_id);
-        if let Some(interior_id) = target_interior_id.as_deref() {
-            let _ = open_adjacent_interior_door(self, start_row, start_col, interior_id);
+        let path = if level == target_level {
+            self.same_level_path(
+                pawn_id,
+                level,
+                (start_row, start_col),
+                (target_row, target_col),
+                allow_partial,
+            )
+        } else {
+            find_route(
+                self,
+                Some(pawn_id),
+                (level, start_row, start_col),
+                (target_level, target_row, target_col),
+            )
         }
-
-        let path = find_path(self, Some(pawn_id), start_row, start_col, target_row, target_col)
-            .or_else(|| {
-                target_interior_id
-                    .as_deref()
-                    .and_then(|interior_id| {
-                        find_interior_entry_path(
-                            self,
-                            Some(pawn_id),
-                            start_row,
-                            start_col,
-                            &interior_id,
-                        )
-                    })
-            })
-            .or_else(|| {
-                if allow_partial {
-                    find_nearest_reachable_target(
-                        self,
-                        Some(pawn_id),
-                        start_row,
-                        start_col,
-                        target_row,
-                        target_col,
-                    )
-                } else {
-                    None
-                }
-            })
-            .ok_or_else(|| "No valid path to target".to_string())?;
+        .ok_or_else(|| "No valid path to target".to_string())?;
 
         let pawn = &mut self.pawns[start_index];
         pawn.route = to_route_nodes(&path);
@@ -299,6 +936,319 @@ impl ExplorationSim {
         Ok(vec![pawn_id.to_string()])
     }
 
+    fn same_level_path(
+        &mut self,
+        pawn_id: &str,
+        level: i32,
+        (start_row, start_col): (i32, i32),
+        (target_row, target_col): (i32, i32),
+        allow_partial: bool,
+    ) -> Option<Vec<PathNode>> {
+        let target_interior_id =
+            get_tile(self, level, target_row, target_col).and_then(|tile| tile.interior_id);
+        if let Some(interior_id) = target_interior_id.as_deref() {
+            self.load_interior_chunks(level, interior_id);
+            let _ = open_adjacent_interior_door(self, level, start_row, start_col, interior_id);
+        }
+
+        find_path(
+            self,
+            Some(pawn_id),
+            level,
+            start_row,
+            start_col,
+            target_row,
+            target_col,
+        )
+        .or_else(|| {
+            target_interior_id.as_deref().and_then(|interior_id| {
+                find_interior_entry_path(
+                    self,
+                    Some(pawn_id),
+                    level,
+                    start_row,
+                    start_col,
+                    &interior_id,
+                )
+            })
+        })
+        .or_else(|| {
+            if allow_partial {
+                find_nearest_reachable_target(
+                    self,
+                    Some(pawn_id),
+                    level,
+                    start_row,
+                    start_col,
+                    target_row,
+                    target_col,
+                )
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Routes the leader to the target and starts the other player pawns following it in
+    /// formation. Slots are re-assigned behind the leader's current tile on every step it takes.
+    pub fn move_party(
+        &mut self,
+        leader_id: Option<&str>,
+        target_level: Option<i32>,
+        target_row: i32,
+        target_col: i32,
+        formation: Option<ExplorationFormation>,
+    ) -> Result<Vec<String>, String> {
+        let member_ids = self
+            .pawns
+            .iter()
+            .filter(|pawn| pawn.faction_id == "player")
+            .map(|pawn| pawn.id.clone())
+            .collect::<Vec<_>>();
+        let leader_id = match leader_id
+            .map(str::to_string)
+            .or_else(|| self.selected_pawn_id.clone())
+        {
+            Some(id) if member_ids.contains(&id) => id,
+            Some(_) => return Err("Party leader must be a player pawn".to_string()),
+            None => member_ids
+                .first()
+                .cloned()
+                .ok_or_else(|| "No party members to move".to_string())?,
+        };
+        let formation = formation
+            .or_else(|| self.party.as_ref().map(|party| party.formation))
+            .unwrap_or_default();
+        let Some(leader_index) = self.pawns.iter().position(|pawn| pawn.id == leader_id) else {
+            return Err("Unknown pawn".to_string());
+        };
+        let target_level = target_level.unwrap_or(self.pawns[leader_index].level);
+        let mut changed =
+            self.move_pawn_to_level(&leader_id, target_level, target_row, target_col, true)?;
+        let leader = &self.pawns[leader_index];
+        let cell = (leader.level, leader.tile_row, leader.tile_col);
+        let heading = match leader.route.get(leader.route_index) {
+            Some(next) if next.level == cell.0 && (next.row, next.col) != (cell.1, cell.2) => {
+                heading_between((cell.1, cell.2), (next.row, next.col))
+            }
+            _ => heading_from_facing(leader.facing.as_deref()),
+        };
+        self.party = Some(ExplorationParty {
+            leader_id,
+            formation,
+            member_ids,
+            following: true,
+            last_step: Some((cell, heading)),
+        });
+        changed.extend(self.assign_formation_slots(cell, heading));
+        Ok(changed)
+    }
+
+    /// Sends formation members back to their slots around the leader, e.g. after an
+    /// interaction.
+    pub fn regroup_party(&mut self) -> Vec<String> {
+        let Some(leader) = self
+            .party
+            .as_ref()
+            .and_then(|party| self.pawns.iter().find(|pawn| pawn.id == party.leader_id))
+        else {
+            return Vec::new();
+        };
+        let anchor = (leader.level, leader.tile_row, leader.tile_col);
+        let heading = self
+            .party
+            .as_ref()
+            .and_then(|party| party.last_step)
+            .map(|(_, heading)| heading)
+            .unwrap_or_else(|| heading_from_facing(leader.facing.as_deref()));
+        self.assign_formation_slots(anchor, heading)
+    }
+
+    /// Keeps followers behind the leader while a party order is under way, re-targeting their
+    /// slots whenever the leader reaches a new tile. The order ends once the leader stops.
+    fn follow_leader(&mut self) -> Vec<String> {
+        let Some(party) = self.party.as_ref().filter(|party| party.following) else {
+            return Vec::new();
+        };
+        let Some(leader) = self.pawns.iter().find(|pawn| pawn.id == party.leader_id) else {
+            return Vec::new();
+        };
+        let cell = (leader.level, leader.tile_row, leader.tile_col);
+        let moving = leader.moving;
+        let fallback = heading_from_facing(leader.facing.as_deref());
+        let heading = match party.last_step {
+            Some((previous, heading)) if previous == cell => heading,
+            Some((previous, _)) if previous.0 == cell.0 => {
+                heading_between((previous.1, previous.2), (cell.1, cell.2))
+            }
+            _ => fallback,
+        };
+        let stepped = party.last_step.map(|(previous, _)| previous) != Some(cell);
+        if let Some(party) = self.party.as_mut() {
+            party.last_step = Some((cell, heading));
+            party.following = moving;
+        }
+        if moving && !stepped {
+            return Vec::new();
+        }
+        self.assign_formation_slots(cell, heading)
+    }
+
+    /// Takes a follower moved on its own out of the formation, and ends the party order when
+    /// the leader is given a move of its own.
+    fn leave_formation(&mut self, pawn_id: &str) {
+        let Some(party) = self.party.as_mut() else {
+            return;
+        };
+        if party.leader_id == pawn_id {
+            party.following = false;
+        } else {
+            party.member_ids.retain(|id| id != pawn_id);
+        }
+    }
+
+    fn assign_formation_slots(&mut self, anchor: LevelCell, heading: (f32, f32)) -> Vec<String> {
+        let Some(party) = self.party.clone() else {
+            return Vec::new();
+        };
+        let Some(leader) = self.pawns.iter().find(|pawn| pawn.id == party.leader_id) else {
+            return Vec::new();
+        };
+        let leader_moving = leader.moving;
+        let leader_speed = leader.move_speed_tiles_per_second.max(1.0);
+        let followers = party
+            .member_ids
+            .iter()
+            .filter(|id| **id != party.leader_id)
+            .filter_map(|id| self.pawns.iter().position(|pawn| &pawn.id == id))
+            .collect::<Vec<_>>();
+        let offsets = formation_offsets(party.formation, followers.len());
+        let (level, anchor_row, anchor_col) = anchor;
+        let nav = self.nav(level);
+        let anchor_interior_id =
+            get_tile(self, level, anchor_row, anchor_col).and_then(|tile| tile.interior_id);
+
+        // The party moves together, so only outsiders block routes; each claimed slot is added
+        // as it is handed out so followers never share a tile.
+        let mut occupied = self
+            .pawns
+            .iter()
+            .filter(|pawn| pawn.level == level && !party.member_ids.contains(&pawn.id))
+            .filter_map(|pawn| nav.index_of(pawn.tile_row, pawn.tile_col))
+            .collect::<HashSet<_>>();
+        occupied.extend(nav.index_of(anchor_row, anchor_col));
+
+        let mut changed = Vec::new();
+        for (pawn_index, offset) in followers.into_iter().zip(offsets) {
+            let follower = &self.pawns[pawn_index];
+            let follower_id = follower.id.clone();
+            let follower_level = follower.level;
+            let (start_row, start_col) = resolve_pawn_navigation_origin(self, follower);
+            let ignore = self.nav(level).index_of(start_row, start_col);
+            let desired = slot_cell((anchor_row, anchor_col), heading, offset);
+            let assignment = self
+                .formation_slot_candidates(desired)
+                .into_iter()
+                .filter(|&(row, col)| {
+                    let interior_id = anchor_interior_id.as_deref();
+                    self.formation_slot_valid(anchor, interior_id, &occupied, row, col)
+                })
+                .find_map(|(row, col)| {
+                    if follower_level != level {
+                        // Followers on another floor take the stairs before joining the slot.
+                        return find_route(
+                            self,
+                            Some(&follower_id),
+                            (follower_level, start_row, start_col),
+                            (level, row, col),
+                        )
+                        .map(|path| (row, col, path));
+                    }
+                    if (row, col) == (start_row, start_col) {
+                        return Some((row, col, Vec::new()));
+                    }
+                    find_path_in(
+                        self, level, &occupied, ignore, start_row, start_col, row, col,
+                    )
+                    .map(|path| (row, col, path))
+                });
+            let Some((row, col, path)) = assignment else {
+                continue;
+            };
+            occupied.extend(self.nav(level).index_of(row, col));
+
+            let pawn = &mut self.pawns[pawn_index];
+            if path.is_empty() {
+                clear_route_state(pawn);
+            } else {
+                // Match the leader's pace, hurrying when a follower has fallen more than a step
+                // behind its slot.
+                let base_speed = pawn.speed.max(1.0);
+                pawn.move_speed_tiles_per_second = if leader_moving {
+                    (leader_speed * path.len() as f32).clamp(base_speed * 0.5, base_speed * 1.5)
+                } else {
+                    base_speed
+                };
+                pawn.route = to_route_nodes(&path);
+                pawn.route_index = 0;
+                pawn.segment_progress = 0.0;
+                pawn.moving = true;
+                pawn.path = Some(path);
+                update_route_targets(pawn);
+            }
+            changed.push(pawn.id.clone());
+        }
+        changed
+    }
+
+    /// Tiles to try for a slot, nearest to the ideal position first.
+    fn formation_slot_candidates(&self, desired: (i32, i32)) -> Vec<(i32, i32)> {
+        let radius = FORMATION_SLOT_SEARCH_RADIUS;
+        let mut candidates = Vec::new();
+        for row in (desired.0 - radius)..=(desired.0 + radius) {
+            for col in (desired.1 - radius)..=(desired.1 + radius) {
+                candidates.push((row, col));
+            }
+        }
+        candidates.sort_by_key(|&(row, col)| {
+            let dr = row - desired.0;
+            let dc = col - desired.1;
+            (dr * dr + dc * dc, dr.abs().max(dc.abs()))
+        });
+        candidates
+    }
+
+    /// A slot must be free, off doorways, in the leader's interior (or outside with it) and
+    /// reachable from the leader without a long detour around a wall.
+    fn formation_slot_valid(
+        &self,
+        (level, anchor_row, anchor_col): LevelCell,
+        anchor_interior_id: Option<&str>,
+        occupied: &HashSet<u32>,
+        row: i32,
+        col: i32,
+    ) -> bool {
+        let Some(index) = self.nav(level).index_of(row, col) else {
+            return false;
+        };
+        if occupied.contains(&index) || get_cell_move_cost(self, level, row, col) <= 0.0 {
+            return false;
+        }
+        let Some(tile) = get_tile(self, level, row, col) else {
+            return false;
+        };
+        if tile.door_id.is_some()
+            || tile.level_link.is_some()
+            || tile.interior_id.as_deref() != anchor_interior_id
+        {
+            return false;
+        }
+        let spread = (row - anchor_row).abs().max((col - anchor_col).abs()) as usize;
+        self.nav(level)
+            .find_path((anchor_row, anchor_col), (row, col), &HashSet::new(), None)
+            .is_some_and(|steps| steps.len() <= spread * 2 + 2)
+    }
+
     pub fn handle_interaction(
         &mut self,
         row: Option<u32>,
@@ -327,12 +1277,24 @@ impl ExplorationSim {
             .or_else(|| {
                 row.zip(col)
                     .and_then(|(entry_row, entry_col)| {
-                        get_tile(self, entry_row as i32, entry_col as i32)
+                        get_tile(
+                            self,
+                            self.visibility.current_level,
+                            entry_row as i32,
+                            entry_col as i32,
+                        )
                     })
                     .and_then(|tile| tile.door_id.clone())
             });
 
         if let Some(door_id) = door_id {
+            if self.visibility.locked_door_ids.contains(&door_id) {
+                return Ok(InteractionResult {
+                    label: "Door is locked".to_string(),
+                    chunks_changed: false,
+                    changed_pawn_ids: Vec::new(),
+                });
+            }
             if self.visibility.opened_door_ids.contains(&door_id) {
                 return Ok(InteractionResult {
                     label: "Doorway".to_string(),
@@ -347,9 +1309,7 @@ impl ExplorationSim {
                     changed_pawn_ids: Vec::new(),
                 });
             }
-            self.visibility.opened_door_ids.push(door_id.clone());
-            self.visibility.opened_door_ids.sort();
-            self.visibility.opened_door_ids.dedup();
+            self.open_doors(vec![door_id.clone()]);
             self.refresh_visibility();
             return Ok(InteractionResult {
                 label: format!("Opened {door_id}"),
@@ -369,7 +1329,9 @@ impl ExplorationSim {
         }
 
         if let (Some(row), Some(col)) = (row, col) {
-            if let Some(tile) = get_tile(self, row as i32, col as i32) {
+            if let Some(tile) =
+                get_tile(self, self.visibility.current_level, row as i32, col as i32)
+            {
                 if let Some(interior_id) = tile.interior_id.as_deref() {
                     return Ok(InteractionResult {
                         label: format!("Interior {interior_id}"),
@@ -379,43 +1341,406 @@ impl ExplorationSim {
                 }
             }
         }
-
-        Ok(InteractionResult {
-            label: "Inspect".to_string(),
-            chunks_changed: false,
-            changed_pawn_ids: Vec::new(),
-        })
+
+        Ok(InteractionResult {
+            label: "Inspect".to_string(),
+            chunks_changed: false,
+            changed_pawn_ids: Vec::new(),
+        })
+    }
+
+    pub fn harvest(
+        &mut self,
+        object_id: &str,
+        actor_id: Option<&str>,
+        now_ms: u64,
+    ) -> Result<HarvestResult, String> {
+        let object = self
+            .objects
+            .iter()
+            .find(|entry| entry.id == object_id)
+            .cloned()
+            .ok_or_else(|| "Unknown object".to_string())?;
+        let flora_id = object
+            .flora_id
+            .clone()
+            .ok_or_else(|| "Nothing to harvest here".to_string())?;
+        let entry = self
+            .flora
+            .get(&flora_id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown flora {flora_id}"))?;
+        let actor_id = actor_id
+            .map(str::to_string)
+            .or_else(|| self.selected_pawn_id.clone())
+            .ok_or_else(|| "No pawn selected".to_string())?;
+        let actor_index = self
+            .pawns
+            .iter()
+            .position(|pawn| pawn.id == actor_id)
+            .ok_or_else(|| "Unknown pawn".to_string())?;
+
+        let mut result = HarvestResult {
+            label: String::new(),
+            object_id: object.id.clone(),
+            flora_id: flora_id.clone(),
+            actor_id: actor_id.clone(),
+            success: false,
+            items: Vec::new(),
+            conditions: Vec::new(),
+            regrows_at_ms: None,
+        };
+        self.prune_regrown_nodes(now_ms);
+        if let Some(node) = self.harvest_nodes.get(&object.id) {
+            if node.regrows_at_ms > now_ms {
+                result.label = format!("{} has not regrown yet", entry.name);
+                result.regrows_at_ms = Some(node.regrows_at_ms);
+                return Ok(result);
+            }
+        }
+        let (pawn_row, pawn_col) = resolve_pawn_navigation_origin(self, &self.pawns[actor_index]);
+        if object.level != self.pawns[actor_index].level
+            || !object_within_reach(&object, pawn_row, pawn_col)
+        {
+            result.label = format!("{} is too far away", entry.name);
+            return Ok(result);
+        }
+
+        let stats = self.pawns[actor_index].stats.clone().unwrap_or_default();
+        let outcome = resolve_harvest(&entry, &stats, self.tick, self.tick_rate_hz, &mut self.rng);
+
+        let pawn = &mut self.pawns[actor_index];
+        for condition in &outcome.conditions {
+            pawn.conditions
+                .retain(|existing| existing.id != condition.id);
+            pawn.conditions.push(condition.clone());
+        }
+
+        stack_items(&mut pawn.inventory, &outcome.items);
+
+        result.success = outcome.success;
+        result.items = outcome.items;
+        result.conditions = outcome.conditions;
+        if outcome.success {
+            let regrows_at_ms = regrowth_deadline_ms(&entry, now_ms);
+            let harvest_count = self
+                .harvest_nodes
+                .get(&object.id)
+                .map(|node| node.harvest_count)
+                .unwrap_or(0);
+            self.harvest_nodes.insert(
+                object.id.clone(),
+                ExplorationHarvestNodeState {
+                    object_id: object.id.clone(),
+                    flora_id,
+                    regrows_at_ms,
+                    harvest_count: harvest_count + 1,
+                },
+            );
+            result.regrows_at_ms = Some(regrows_at_ms);
+            let quantity = result.items.iter().map(|item| item.quantity).sum::<u32>();
+            result.label = format!("Harvested {quantity} {}", entry.name);
+        } else {
+            result.label = format!("Failed to harvest {}", entry.name);
+        }
+        Ok(result)
+    }
+
+    pub fn advance(&mut self, delta_seconds: f32) -> AdvanceResult {
+        self.tick = self.tick.saturating_add(1);
+        self.prune_regrown_nodes(self.clock_ms());
+        let mut changed_pawn_ids = self.assign_npc_behavior();
+        let wildlife_events = self.assign_wildlife_behavior(&mut changed_pawn_ids);
+        for pawn in &mut self.pawns {
+            let before = pawn.conditions.len();
+            pawn.conditions
+                .retain(|condition| condition.expires_at_tick > self.tick);
+            if pawn.conditions.len() != before {
+                changed_pawn_ids.push(pawn.id.clone());
+            }
+        }
+
+        let mut updated = Vec::with_capacity(self.pawns.len());
+        for pawn in &self.pawns {
+            let (next_pawn, pawn_changed) =
+                advance_pawn(pawn, delta_seconds, self.tick, self.tick_rate_hz);
+            if pawn_changed {
+                changed_pawn_ids.push(next_pawn.id.clone());
+            }
+            updated.push(next_pawn);
+        }
+        self.pawns = updated;
+        changed_pawn_ids.extend(self.follow_leader());
+        let awareness_events = self.update_awareness(delta_seconds, &mut changed_pawn_ids);
+        let (trigger_events, doors_changed) = self.evaluate_triggers(&mut changed_pawn_ids);
+        changed_pawn_ids.sort();
+        changed_pawn_ids.dedup();
+        let visibility_changed = self.refresh_visibility();
+        AdvanceResult {
+            changed_pawn_ids,
+            visibility_changed,
+            wildlife_events,
+            trigger_events,
+            doors_changed,
+            awareness_events,
+        }
+    }
+
+    /// Sets the movement mode of one pawn, or of every party member when `pawn_id` is omitted.
+    pub fn set_movement_mode(
+        &mut self,
+        pawn_id: Option<&str>,
+        mode: ExplorationMovementMode,
+    ) -> Result<Vec<String>, String> {
+        let mut changed = Vec::new();
+        for pawn in &mut self.pawns {
+            let selected = match pawn_id {
+                Some(pawn_id) => pawn.id == pawn_id,
+                None => pawn.faction_id == "player",
+            };
+            if selected && pawn.movement_mode != mode {
+                pawn.movement_mode = mode;
+                changed.push(pawn.id.clone());
+            }
+        }
+        if let Some(pawn_id) = pawn_id {
+            if !self.pawns.iter().any(|pawn| pawn.id == pawn_id) {
+                return Err(format!("Unknown pawn {pawn_id}"));
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Lets every NPC and creature listen and look for party members on its level, raising or
+    /// decaying its awareness meter.
+    fn update_awareness(
+        &mut self,
+        delta_seconds: f32,
+        changed_pawn_ids: &mut Vec<String>,
+    ) -> Vec<ExplorationSessionEvent> {
+        let phase = self.day_phase();
+        let ambient_light = self.descriptor.ambient_light;
+        let party = self
+            .pawns
+            .iter()
+            .filter(|pawn| pawn.faction_id == "player")
+            .map(|pawn| {
+                let tile = get_tile(self, pawn.level, pawn.tile_row, pawn.tile_col);
+                NoticeablePawn {
+                    id: pawn.id.clone(),
+                    level: pawn.level,
+                    x: pawn.x,
+                    y: pawn.y,
+                    noise: noise_emission(pawn, tile.as_ref()),
+                    light: light_at(tile.as_ref(), ambient_light, phase),
+                    stats: pawn.stats.clone().unwrap_or_default(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut events = Vec::new();
+        for pawn in &mut self.pawns {
+            if pawn.faction_id == "player" || pawn.is_npc != Some(true) {
+                continue;
+            }
+            let perception = perception_score(pawn);
+            let strongest = party
+                .iter()
+                .filter(|target| target.level == pawn.level)
+                .map(|target| {
+                    let distance =
+                        ((target.x - pawn.x).powi(2) + (target.y - pawn.y).powi(2)).sqrt();
+                    let gain = detection_gain(
+                        perception,
+                        target.noise,
+                        target.light,
+                        distance,
+                        &target.stats,
+                    );
+                    (target.id.as_str(), gain)
+                })
+                .max_by(|left, right| {
+                    left.1
+                        .partial_cmp(&right.1)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+            let previous_state = pawn.awareness.as_ref().map(|awareness| awareness.state);
+            pawn.awareness = step_awareness(pawn.awareness.as_ref(), strongest, delta_seconds);
+            let state = pawn.awareness.as_ref().map(|awareness| awareness.state);
+            if state.unwrap_or(ExplorationAwarenessState::Unaware)
+                == previous_state.unwrap_or(ExplorationAwarenessState::Unaware)
+            {
+                continue;
+            }
+            changed_pawn_ids.push(pawn.id.clone());
+            events.push(ExplorationSessionEvent::Awareness {
+                pawn_id: pawn.id.clone(),
+                target_pawn_id: pawn
+                    .awareness
+                    .as_ref()
+                    .and_then(|awareness| awareness.target_pawn_id.clone()),
+                state: state.unwrap_or(ExplorationAwarenessState::Unaware),
+            });
+        }
+        events
+    }
+
+    /// Replaces the quest flags, e.g. with the flags of the active quest run.
+    pub fn set_quest_flags(&mut self, mut flags: Vec<String>) {
+        flags.sort();
+        flags.dedup();
+        self.quest_flags = flags;
+    }
+
+    /// Wall-clock time of the current tick.
+    fn clock_ms(&self) -> u64 {
+        self.day_start_ms + self.tick * 1000 / self.tick_rate_hz
+    }
+
+    fn day_phase(&self) -> DayPhase {
+        day_phase_at(self.clock_ms())
+    }
+
+    /// Fires triggers whose conditions started to hold this tick, in manifest order.
+    fn evaluate_triggers(
+        &mut self,
+        changed_pawn_ids: &mut Vec<String>,
+    ) -> (Vec<ExplorationSessionEvent>, bool) {
+        let mut events = Vec::new();
+        let mut doors_changed = false;
+        let phase = self.day_phase();
+        for index in 0..self.triggers.len() {
+            let trigger = &self.triggers[index];
+            let holds = trigger_conditions_hold(trigger, &self.pawns, phase, &self.quest_flags);
+            let was_active = self.active_trigger_ids.contains(&trigger.id);
+            if !holds {
+                if was_active {
+                    let trigger_id = trigger.id.clone();
+                    self.active_trigger_ids.retain(|id| *id != trigger_id);
+                }
+                continue;
+            }
+            if was_active {
+                continue;
+            }
+            let trigger = trigger.clone();
+            self.active_trigger_ids.push(trigger.id.clone());
+            self.active_trigger_ids.sort();
+            if self.fired_trigger_ids.contains(&trigger.id) {
+                if !trigger.repeat {
+                    continue;
+                }
+            } else {
+                self.fired_trigger_ids.push(trigger.id.clone());
+                self.fired_trigger_ids.sort();
+            }
+            let actor = pawns_in_area(&trigger, &self.pawns)
+                .next()
+                .map(|pawn| (pawn.id.clone(), pawn.tile_row, pawn.tile_col));
+            for action in &trigger.actions {
+                self.apply_trigger_action(
+                    &trigger.id,
+                    action,
+                    actor.as_ref(),
+                    &mut events,
+                    changed_pawn_ids,
+                    &mut doors_changed,
+                );
+            }
+        }
+        (events, doors_changed)
     }
 
-    pub fn advance(&mut self, delta_seconds: f32) -> AdvanceResult {
-        self.tick = self.tick.saturating_add(1);
-        let mut changed_pawn_ids = self.assign_npc_behavior();
-
-        let mut updated = Vec::with_capacity(self.pawns.len());
-        for pawn in &self.pawns {
-            let (next_pawn, pawn_changed) = advance_pawn(pawn, delta_seconds, self.tick, self.tick_rate_hz);
-            if pawn_changed {
-                changed_pawn_ids.push(next_pawn.id.clone());
+    fn apply_trigger_action(
+        &mut self,
+        trigger_id: &str,
+        action: &ExplorationTriggerAction,
+        actor: Option<&(String, i32, i32)>,
+        events: &mut Vec<ExplorationSessionEvent>,
+        changed_pawn_ids: &mut Vec<String>,
+        doors_changed: &mut bool,
+    ) {
+        match action {
+            ExplorationTriggerAction::Narration { text } => {
+                events.push(ExplorationSessionEvent::Narration {
+                    trigger_id: trigger_id.to_string(),
+                    text: text.clone(),
+                });
+            }
+            ExplorationTriggerAction::StartDialogue {
+                dialogue_id,
+                pawn_id,
+            } => {
+                events.push(ExplorationSessionEvent::DialogueStarted {
+                    trigger_id: trigger_id.to_string(),
+                    dialogue_id: dialogue_id.clone(),
+                    pawn_id: pawn_id.clone(),
+                });
+            }
+            ExplorationTriggerAction::SpawnPawns { pawns } => {
+                for pawn in pawns {
+                    if self.pawns.iter().any(|entry| entry.id == pawn.id) {
+                        continue;
+                    }
+                    changed_pawn_ids.push(pawn.id.clone());
+                    self.pawns.push(normalize_pawn_runtime(pawn.clone()));
+                }
+            }
+            ExplorationTriggerAction::OpenDoors { door_ids } => {
+                self.visibility
+                    .locked_door_ids
+                    .retain(|door_id| !door_ids.contains(door_id));
+                self.open_doors(door_ids.clone());
+                *doors_changed = true;
+            }
+            ExplorationTriggerAction::LockDoors { door_ids } => {
+                self.lock_doors(door_ids.clone());
+                *doors_changed = true;
+            }
+            ExplorationTriggerAction::StartCombat {
+                encounter_id,
+                pawn_ids,
+            } => {
+                events.push(ExplorationSessionEvent::CombatStarted {
+                    trigger_id: trigger_id.to_string(),
+                    encounter_id: encounter_id.clone(),
+                    pawn_ids: pawn_ids.clone(),
+                });
+            }
+            ExplorationTriggerAction::SetQuestFlag { flag, clear } => {
+                let present = self.quest_flags.contains(flag);
+                if *clear && present {
+                    self.quest_flags.retain(|entry| entry != flag);
+                } else if !*clear && !present {
+                    self.quest_flags.push(flag.clone());
+                    self.quest_flags.sort();
+                } else {
+                    return;
+                }
+                events.push(ExplorationSessionEvent::QuestFlags {
+                    flags: self.quest_flags.clone(),
+                });
+            }
+            ExplorationTriggerAction::Interaction { label, object_id } => {
+                events.push(ExplorationSessionEvent::Interaction {
+                    label: label.clone(),
+                    row: actor.map(|(_, row, _)| *row as u32),
+                    col: actor.map(|(_, _, col)| *col as u32),
+                    object_id: object_id.clone(),
+                    actor_id: actor.map(|(id, _, _)| id.clone()),
+                });
             }
-            updated.push(next_pawn);
-        }
-        self.pawns = updated;
-        changed_pawn_ids.sort();
-        changed_pawn_ids.dedup();
-        let visibility_changed = self.refresh_visibility();
-        AdvanceResult {
-            changed_pawn_ids,
-            visibility_changed,
         }
     }
 
     fn assign_npc_behavior(&mut self) -> Vec<String> {
         let mut changed = Vec::new();
-        let mut rng = rand::rng();
+        let occupied_by_level = build_occupied_sets(self);
+        let no_pawns = HashSet::new();
         let pawn_count = self.pawns.len();
         for index in 0..pawn_count {
             let pawn = self.pawns[index].clone();
-            if pawn.is_npc != Some(true) || pawn.moving {
+            if pawn.is_npc != Some(true) || pawn.moving || pawn.wildlife.is_some() {
                 continue;
             }
             if pawn
@@ -425,10 +1750,13 @@ impl ExplorationSim {
                 continue;
             }
 
+            let level = pawn.level;
+            let occupied = occupied_by_level.get(&level).unwrap_or(&no_pawns);
             let (start_row, start_col) = resolve_pawn_navigation_origin(self, &pawn);
             let (anchor_row, anchor_col, intent, schedule_id) =
                 if let Some(home_interior_id) = pawn.home_interior_id.as_deref() {
-                    let (row, col) = first_tile_in_interior(self, home_interior_id)
+                    self.load_interior_chunks(level, home_interior_id);
+                    let (row, col) = first_tile_in_interior(self, level, home_interior_id)
                         .unwrap_or((start_row, start_col));
                     (
                         row,
@@ -446,22 +1774,24 @@ impl ExplorationSim {
                 };
 
             let target_row = clamp_i32(
-                anchor_row + rng.random_range(-EDGE_WANDER_RADIUS..=EDGE_WANDER_RADIUS),
+                anchor_row
+                    + self
+                        .rng
+                        .random_range(-EDGE_WANDER_RADIUS..=EDGE_WANDER_RADIUS),
                 0,
                 self.descriptor.height as i32 - 1,
             );
             let target_col = clamp_i32(
-                anchor_col + rng.random_range(-EDGE_WANDER_RADIUS..=EDGE_WANDER_RADIUS),
+                anchor_col
+                    + self
+                        .rng
+                        .random_range(-EDGE_WANDER_RADIUS..=EDGE_WANDER_RADIUS),
                 0,
                 self.descriptor.width as i32 - 1,
             );
-            if let Some(path) = find_path(
-                self,
-                Some(&pawn.id),
-                start_row,
-                start_col,
-                target_row,
-                target_col,
+            let ignore = self.nav(level).index_of(pawn.tile_row, pawn.tile_col);
+            if let Some(path) = find_path_in(
+                self, level, occupied, ignore, start_row, start_col, target_row, target_col,
             ) {
                 let next = &mut self.pawns[index];
                 next.route = to_route_nodes(&path);
@@ -485,6 +1815,241 @@ impl ExplorationSim {
         changed
     }
 
+    fn assign_wildlife_behavior(&mut self, changed: &mut Vec<String>) -> Vec<WildlifeEvent> {
+        let mut events = Vec::new();
+        // Taken out for the loop, which also borrows the sim; put back below.
+        let mut rng = std::mem::replace(&mut self.rng, StdRng::seed_from_u64(0));
+        let occupied_by_level = build_occupied_sets(self);
+        let no_pawns = HashSet::new();
+        for index in 0..self.pawns.len() {
+            let pawn = self.pawns[index].clone();
+            let Some(wildlife) = pawn.wildlife.as_ref() else {
+                continue;
+            };
+            if pawn
+                .next_decision_at_tick
+                .is_some_and(|next_tick| next_tick > self.tick)
+            {
+                continue;
+            }
+
+            let level = pawn.level;
+            let occupied = occupied_by_level.get(&level).unwrap_or(&no_pawns);
+            let tracked = self.track_party_member(&pawn, &mut rng);
+            let ignore = self.nav(level).index_of(pawn.tile_row, pawn.tile_col);
+            let previous_intent = pawn.current_intent.clone().unwrap_or_default();
+            let (start_row, start_col) = resolve_pawn_navigation_origin(self, &pawn);
+            let mut next_state = wildlife.clone();
+            let mut speed = pawn.speed.max(1.0);
+            let mut decision_seconds = 1;
+            let (intent, path) = match tracked {
+                None => {
+                    next_state.target_pawn_id = None;
+                    next_state.warned_at_tick = None;
+                    next_state.concealed = false;
+                    decision_seconds = 3;
+                    let target_row = clamp_i32(
+                        wildlife.home_row
+                            + rng.random_range(-WILDLIFE_ROAM_RADIUS..=WILDLIFE_ROAM_RADIUS),
+                        0,
+                        self.descriptor.height as i32 - 1,
+                    );
+                    let target_col = clamp_i32(
+                        wildlife.home_col
+                            + rng.random_range(-WILDLIFE_ROAM_RADIUS..=WILDLIFE_ROAM_RADIUS),
+                        0,
+                        self.descriptor.width as i32 - 1,
+                    );
+                    let path = find_path_in(
+                        self, level, occupied, ignore, start_row, start_col, target_row, target_col,
+                    );
+                    ("roaming", path)
+                }
+                Some((target, distance)) => {
+                    next_state.target_pawn_id = Some(target.id.clone());
+                    let (target_row, target_col) = resolve_pawn_navigation_origin(self, &target);
+                    match wildlife.behavior {
+                        ExplorationWildlifeBehavior::Passive => {
+                            let (row, col) = offset_point(
+                                start_row,
+                                start_col,
+                                target_row,
+                                target_col,
+                                -WILDLIFE_FLEE_DISTANCE,
+                            );
+                            let path = self
+                                .path_near(level, occupied, ignore, start_row, start_col, row, col);
+                            ("fleeing", path)
+                        }
+                        ExplorationWildlifeBehavior::Territorial => match wildlife.warned_at_tick {
+                            None => {
+                                next_state.warned_at_tick = Some(self.tick);
+                                ("warning", Some(Vec::new()))
+                            }
+                            Some(warned_at)
+                                if self.tick
+                                    < warned_at
+                                        + self.tick_rate_hz * TERRITORIAL_WARNING_SECONDS =>
+                            {
+                                ("warning", Some(Vec::new()))
+                            }
+                            Some(_) => {
+                                let path = self.path_near(
+                                    level, occupied, ignore, start_row, start_col, target_row,
+                                    target_col,
+                                );
+                                ("attacking", path)
+                            }
+                        },
+                        ExplorationWildlifeBehavior::Predator => {
+                            let spotted = distance
+                                <= spot_radius(
+                                    wildlife.stealth,
+                                    &target.stats.clone().unwrap_or_default(),
+                                );
+                            if distance <= WILDLIFE_STALK_DISTANCE {
+                                next_state.concealed = false;
+                                let path = self.path_near(
+                                    level, occupied, ignore, start_row, start_col, target_row,
+                                    target_col,
+                                );
+                                ("attacking", path)
+                            } else {
+                                next_state.concealed = !spotted;
+                                speed *= 0.5;
+                                let (row, col) = offset_point(
+                                    target_row,
+                                    target_col,
+                                    start_row,
+                                    start_col,
+                                    WILDLIFE_STALK_DISTANCE,
+                                );
+                                let path = self.path_near(
+                                    level, occupied, ignore, start_row, start_col, row, col,
+                                );
+                                ("stalking", path)
+                            }
+                        }
+                    }
+                }
+            };
+
+            if intent != previous_intent {
+                if let Some(label) = wildlife_label(
+                    &pawn.name,
+                    intent,
+                    next_state.target_pawn_id.as_deref(),
+                    self,
+                ) {
+                    events.push(WildlifeEvent {
+                        label,
+                        pawn_id: pawn.id.clone(),
+                        fauna_id: wildlife.fauna_id.clone(),
+                        intent: intent.to_string(),
+                        target_pawn_id: next_state.target_pawn_id.clone(),
+                    });
+                }
+            }
+
+            let tick = self.tick;
+            let tick_rate_hz = self.tick_rate_hz;
+            let next = &mut self.pawns[index];
+            match path {
+                Some(path) if !path.is_empty() => {
+                    next.route = to_route_nodes(&path);
+                    next.route_index = 0;
+                    next.segment_progress = 0.0;
+                    next.moving = true;
+                    next.move_speed_tiles_per_second = speed;
+                    next.path = Some(path);
+                    update_route_targets(next);
+                }
+                _ => clear_route_state(next),
+            }
+            next.current_intent = Some(intent.to_string());
+            next.next_decision_at_tick = Some(tick + tick_rate_hz * decision_seconds);
+            next.wildlife = Some(next_state);
+            changed.push(next.id.clone());
+        }
+        self.rng = rng;
+        events
+    }
+
+    /// Returns the party member a creature is tracking. A creature keeps tracking a target it
+    /// already noticed while it stays inside the detection radius; new targets need a roll.
+    fn track_party_member<R: Rng>(
+        &self,
+        creature: &ExplorationPawn,
+        rng: &mut R,
+    ) -> Option<(ExplorationPawn, f32)> {
+        let wildlife = creature.wildlife.as_ref()?;
+        let radius = detection_radius(wildlife.perception);
+        let mut party = self
+            .pawns
+            .iter()
+            .filter(|pawn| pawn.faction_id == "player" && pawn.level == creature.level)
+            .map(|pawn| {
+                let distance =
+                    ((pawn.x - creature.x).powi(2) + (pawn.y - creature.y).powi(2)).sqrt();
+                (pawn, distance)
+            })
+            .filter(|(_, distance)| *distance <= radius)
+            .collect::<Vec<_>>();
+        party.sort_by(|left, right| {
+            left.1
+                .partial_cmp(&right.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        if let Some(tracked) = party
+            .iter()
+            .find(|(pawn, _)| wildlife.target_pawn_id.as_deref() == Some(pawn.id.as_str()))
+        {
+            return Some((tracked.0.clone(), tracked.1));
+        }
+        // New targets are only picked up once the creature has heard or seen them: alert
+        // creatures commit, suspicious ones still have to roll.
+        let awareness = creature.awareness.as_ref()?;
+        let (pawn, distance) = party
+            .into_iter()
+            .find(|(pawn, _)| awareness.target_pawn_id.as_deref() == Some(pawn.id.as_str()))?;
+        match awareness.state {
+            ExplorationAwarenessState::Alert => Some((pawn.clone(), distance)),
+            ExplorationAwarenessState::Suspicious => {
+                let stats = pawn
+                    .stats
+                    .clone()
+                    .unwrap_or_else(ExplorationPawnStats::default);
+                rng.random_bool(detection_chance(wildlife.perception, &stats, distance))
+                    .then(|| (pawn.clone(), distance))
+            }
+            ExplorationAwarenessState::Unaware => None,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn path_near(
+        &self,
+        level: i32,
+        occupied: &HashSet<u32>,
+        ignore: Option<u32>,
+        start_row: i32,
+        start_col: i32,
+        target_row: i32,
+        target_col: i32,
+    ) -> Option<Vec<PathNode>> {
+        let target_row = clamp_i32(target_row, 0, self.descriptor.height as i32 - 1);
+        let target_col = clamp_i32(target_col, 0, self.descriptor.width as i32 - 1);
+        find_path_in(
+            self, level, occupied, ignore, start_row, start_col, target_row, target_col,
+        )
+        .or_else(|| {
+            find_nearest_reachable_target_in(
+                self, level, occupied, ignore, start_row, start_col, target_row, target_col,
+            )
+        })
+    }
+
     fn selected_pawn_adjacent_to(&self, row: Option<u32>, col: Option<u32>, door_id: &str) -> bool {
         let selected = self
             .selected_pawn_id
@@ -501,6 +2066,7 @@ impl ExplorationSim {
 
         for object in &self.objects {
             if object.door_id.as_deref() == Some(door_id)
+                && object.level == selected.level
                 && (pawn_row - object.y as i32).abs() <= 1
                 && (pawn_col - object.x as i32).abs() <= 1
             {
@@ -509,10 +2075,6 @@ impl ExplorationSim {
         }
         false
     }
-
-    pub fn all_objects(&self) -> Vec<ExplorationObject> {
-        self.objects.clone()
-    }
 }
 
 fn normalize_pawn_runtime(mut pawn: ExplorationPawn) -> ExplorationPawn {
@@ -534,6 +2096,7 @@ fn normalize_pawn_runtime(mut pawn: ExplorationPawn) -> ExplorationPawn {
             .map(|step| RouteNode {
                 row: step.y,
                 col: step.x,
+                level: step.level,
             })
             .collect();
     }
@@ -545,11 +2108,52 @@ fn normalize_pawn_runtime(mut pawn: ExplorationPawn) -> ExplorationPawn {
     pawn
 }
 
+/// Point `distance` tiles from the origin along the line towards `(toward_row, toward_col)`;
+/// a negative distance points away from it.
+fn offset_point(
+    origin_row: i32,
+    origin_col: i32,
+    toward_row: i32,
+    toward_col: i32,
+    distance: f32,
+) -> (i32, i32) {
+    let dy = (toward_row - origin_row) as f32;
+    let dx = (toward_col - origin_col) as f32;
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < 0.001 {
+        return (origin_row + distance.round() as i32, origin_col);
+    }
+    (
+        origin_row + (dy / length * distance).round() as i32,
+        origin_col + (dx / length * distance).round() as i32,
+    )
+}
+
+fn wildlife_label(
+    name: &str,
+    intent: &str,
+    target_pawn_id: Option<&str>,
+    session: &ExplorationSim,
+) -> Option<String> {
+    let target_name = target_pawn_id
+        .and_then(|id| session.pawns.iter().find(|pawn| pawn.id == id))
+        .map(|pawn| pawn.name.clone())
+        .unwrap_or_else(|| "the party".to_string());
+    match intent {
+        "fleeing" => Some(format!("{name} flees from {target_name}")),
+        "warning" => Some(format!("{name} warns {target_name} off its territory")),
+        "attacking" => Some(format!("{name} attacks {target_name}")),
+        "stalking" => Some(format!("{name} is stalking {target_name}")),
+        _ => None,
+    }
+}
+
 fn to_route_nodes(path: &[PathNode]) -> Vec<RouteNode> {
     path.iter()
         .map(|step| RouteNode {
             row: step.y,
             col: step.x,
+            level: step.level,
         })
         .collect()
 }
@@ -561,6 +2165,7 @@ fn remaining_path_from_route(route: &[RouteNode], route_index: usize) -> Option<
         .map(|step| PathNode {
             x: step.col,
             y: step.row,
+            level: step.level,
         })
         .collect::<Vec<_>>();
     if path.is_empty() {
@@ -593,6 +2198,27 @@ fn update_route_targets(pawn: &mut ExplorationPawn) {
     }
 }
 
+/// Default seed of a session: stable for a location and session name.
+pub fn session_seed(world_id: &str, location_id: &str, session_name: &str) -> u64 {
+    seed_from_key(&format!("{world_id}:{location_id}:{session_name}"))
+}
+
+/// Seed of the RNG stream starting at `tick`, so restored sessions continue deterministically.
+fn tick_seed(seed: u64, tick: u64) -> u64 {
+    seed ^ tick.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// Tiles per second a pawn actually covers, after its movement mode and conditions.
+pub fn effective_move_speed(pawn: &ExplorationPawn) -> f32 {
+    let mode_factor = match pawn.movement_mode {
+        ExplorationMovementMode::Walk => 1.0,
+        ExplorationMovementMode::Sneak => SNEAK_SPEED_FACTOR,
+    };
+    pawn.move_speed_tiles_per_second.max(1.0)
+        * mode_factor
+        * condition_speed_factor(&pawn.conditions)
+}
+
 fn advance_pawn(
     pawn: &ExplorationPawn,
     delta_seconds: f32,
@@ -601,11 +2227,12 @@ fn advance_pawn(
 ) -> (ExplorationPawn, bool) {
     let Some(target) = pawn.route.get(pawn.route_index) else {
         let mut next = pawn.clone();
-        if next.moving || next.target_x.is_some() || next.target_y.is_some() || next.path.is_some() {
+        if next.moving || next.target_x.is_some() || next.target_y.is_some() || next.path.is_some()
+        {
             clear_route_state(&mut next);
             next.tile_row = next.y.round() as i32;
             next.tile_col = next.x.round() as i32;
-            if next.is_npc == Some(true) {
+            if next.is_npc == Some(true) && next.wildlife.is_none() {
                 next.current_intent = Some("waiting".to_string());
                 next.next_decision_at_tick = Some(tick + tick_rate_hz);
             }
@@ -614,10 +2241,24 @@ fn advance_pawn(
         return (next, false);
     };
 
+    if target.level != pawn.level {
+        // Taking stairs or a ladder: arrive on the linked tile of the other level.
+        let mut next = pawn.clone();
+        next.level = target.level;
+        next.x = target.col as f32;
+        next.y = target.row as f32;
+        next.tile_row = target.row;
+        next.tile_col = target.col;
+        next.route_index = next.route_index.saturating_add(1);
+        next.segment_progress = 0.0;
+        update_route_targets(&mut next);
+        return (next, true);
+    }
+
     let dx = target.col as f32 - pawn.x;
     let dy = target.row as f32 - pawn.y;
     let distance = (dx * dx + dy * dy).sqrt();
-    let step = pawn.move_speed_tiles_per_second.max(1.0) * delta_seconds;
+    let step = effective_move_speed(pawn) * delta_seconds;
     let facing = if dx.abs() >= dy.abs() {
         if dx >= 0.0 {
             "east"
@@ -640,7 +2281,7 @@ fn advance_pawn(
         next.segment_progress = 0.0;
         next.facing = Some(facing.to_string());
         update_route_targets(&mut next);
-        if !next.moving && next.is_npc == Some(true) {
+        if !next.moving && next.is_npc == Some(true) && next.wildlife.is_none() {
             next.current_intent = Some("waiting".to_string());
             next.next_decision_at_tick = Some(tick + tick_rate_hz);
         }
@@ -661,17 +2302,25 @@ fn advance_pawn(
     next.y = pawn.y + (dy / distance) * step;
     next.tile_row = next.y.round() as i32;
     next.tile_col = next.x.round() as i32;
-    next.segment_progress = (1.0 - (((target.col as f32 - next.x).powi(2) + (target.row as f32 - next.y).powi(2)).sqrt() / segment_length)).clamp(0.0, 0.999);
+    next.segment_progress = (1.0
+        - (((target.col as f32 - next.x).powi(2) + (target.row as f32 - next.y).powi(2)).sqrt()
+            / segment_length))
+        .clamp(0.0, 0.999);
     next.facing = Some(facing.to_string());
     update_route_targets(&mut next);
     (next, true)
 }
 
-fn adjacent_doorway_interior(session: &ExplorationSim, row: i32, col: i32) -> Option<String> {
+fn adjacent_doorway_interior(
+    session: &ExplorationSim,
+    level: i32,
+    row: i32,
+    col: i32,
+) -> Option<String> {
     for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
         let next_row = row + dr;
         let next_col = col + dc;
-        let tile = get_tile(session, next_row, next_col)?;
+        let tile = get_tile(session, level, next_row, next_col)?;
         if tile.door_id.is_some() && tile.interior_id.is_some() {
             return tile.interior_id.clone();
         }
@@ -679,10 +2328,16 @@ fn adjacent_doorway_interior(session: &ExplorationSim, row: i32, col: i32) -> Op
     None
 }
 
-fn first_tile_in_interior(session: &ExplorationSim, interior_id: &str) -> Option<(i32, i32)> {
+fn first_tile_in_interior(
+    session: &ExplorationSim,
+    level: i32,
+    interior_id: &str,
+) -> Option<(i32, i32)> {
     for row in 0..session.descriptor.height as i32 {
         for col in 0..session.descriptor.width as i32 {
-            let tile = get_tile(session, row, col)?;
+            let Some(tile) = get_tile(session, level, row, col) else {
+                continue;
+            };
             if tile.interior_id.as_deref() == Some(interior_id) && tile.walkable {
                 return Some((row, col));
             }
@@ -691,6 +2346,57 @@ fn first_tile_in_interior(session: &ExplorationSim, interior_id: &str) -> Option
     None
 }
 
+/// Corners `((min_row, min_col), (max_row, max_col))` of the interior's tiles and objects among
+/// the loaded chunks of `level`.
+fn loaded_interior_bounds(
+    session: &ExplorationSim,
+    level: i32,
+    interior_id: &str,
+) -> Option<((i32, i32), (i32, i32))> {
+    let tiles = session
+        .chunks
+        .values()
+        .filter(|chunk| chunk.level == level)
+        .flat_map(|chunk| {
+            chunk
+                .tiles
+                .iter()
+                .enumerate()
+                .filter(|(_, tile)| tile.interior_id.as_deref() == Some(interior_id))
+                .map(|(offset, _)| {
+                    let width = chunk.width.max(1);
+                    (
+                        (chunk.origin_row + offset as u32 / width) as i32,
+                        (chunk.origin_col + offset as u32 % width) as i32,
+                    )
+                })
+        });
+    let objects = session
+        .objects
+        .iter()
+        .filter(|object| {
+            object.level == level && object.interior_id.as_deref() == Some(interior_id)
+        })
+        .flat_map(|object| {
+            [
+                (object.y as i32, object.x as i32),
+                (
+                    (object.y + object.height.max(1) - 1) as i32,
+                    (object.x + object.width.max(1) - 1) as i32,
+                ),
+            ]
+        });
+    tiles.chain(objects).fold(None, |bounds, (row, col)| {
+        Some(match bounds {
+            None => ((row, col), (row, col)),
+            Some(((min_row, min_col), (max_row, max_col))) => (
+                (min_row.min(row), min_col.min(col)),
+                (max_row.max(row), max_col.max(col)),
+            ),
+        })
+    })
+}
+
 pub fn resolve_pawn_navigation_origin(
     session: &ExplorationSim,
     pawn: &ExplorationPawn,
@@ -725,10 +2431,10 @@ pub fn resolve_pawn_navigation_origin(
         {
             continue;
         }
-        let Some(tile) = get_tile(session, row, col) else {
+        let Some(tile) = get_tile(session, pawn.level, row, col) else {
             continue;
         };
-        if !tile.walkable || get_cell_move_cost(session, row, col) <= 0.0 {
+        if !tile.walkable || get_cell_move_cost(session, pawn.level, row, col) <= 0.0 {
             continue;
         }
         let score = (pawn.y - row as f32).abs() + (pawn.x - col as f32).abs();
@@ -745,6 +2451,24 @@ pub fn resolve_pawn_navigation_origin(
 fn find_nearest_reachable_target(
     session: &ExplorationSim,
     selected_pawn_id: Option<&str>,
+    level: i32,
+    start_row: i32,
+    start_col: i32,
+    target_row: i32,
+    target_col: i32,
+) -> Option<Vec<PathNode>> {
+    let occupied = build_occupied_set(session, selected_pawn_id, level);
+    find_nearest_reachable_target_in(
+        session, level, &occupied, None, start_row, start_col, target_row, target_col,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn find_nearest_reachable_target_in(
+    session: &ExplorationSim,
+    level: i32,
+    occupied: &HashSet<u32>,
+    ignore: Option<u32>,
     start_row: i32,
     start_col: i32,
     target_row: i32,
@@ -757,15 +2481,21 @@ fn find_nearest_reachable_target(
                 if (row - target_row).abs() != radius && (col - target_col).abs() != radius {
                     continue;
                 }
-                if get_cell_move_cost(session, row, col) <= 0.0 {
+                if get_cell_move_cost(session, level, row, col) <= 0.0 {
                     continue;
                 }
-                candidates.push((row, col, (row - target_row).abs() + (col - target_col).abs()));
+                candidates.push((
+                    row,
+                    col,
+                    (row - target_row).abs() + (col - target_col).abs(),
+                ));
             }
         }
         candidates.sort_by_key(|entry| entry.2);
         for (row, col, _) in candidates {
-            if let Some(path) = find_path(session, selected_pawn_id, start_row, start_col, row, col) {
+            if let Some(path) = find_path_in(
+                session, level, occupied, ignore, start_row, start_col, row, col,
+            ) {
                 return Some(path);
             }
         }
@@ -776,14 +2506,27 @@ fn find_nearest_reachable_target(
 fn find_interior_entry_path(
     session: &ExplorationSim,
     selected_pawn_id: Option<&str>,
+    level: i32,
     start_row: i32,
     start_col: i32,
     interior_id: &str,
 ) -> Option<Vec<PathNode>> {
-    let mut doorway_candidates = collect_interior_doorway_candidates(session, interior_id);
-    doorway_candidates.sort_by(|left, right| left.2.partial_cmp(&right.2).unwrap_or(std::cmp::Ordering::Equal));
+    let mut doorway_candidates = collect_interior_doorway_candidates(session, level, interior_id);
+    doorway_candidates.sort_by(|left, right| {
+        left.2
+            .partial_cmp(&right.2)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
     for (row, col, _) in doorway_candidates {
-        if let Some(path) = find_path(session, selected_pawn_id, start_row, start_col, row, col) {
+        if let Some(path) = find_path(
+            session,
+            selected_pawn_id,
+            level,
+            start_row,
+            start_col,
+            row,
+            col,
+        ) {
             return Some(path);
         }
     }
@@ -792,19 +2535,30 @@ fn find_interior_entry_path(
 
 fn open_adjacent_interior_door(
     session: &mut ExplorationSim,
+    level: i32,
     start_row: i32,
     start_col: i32,
     interior_id: &str,
 ) -> bool {
     let mut door_ids_to_open = Vec::new();
     for object in &session.objects {
-        if object.interior_id.as_deref() != Some(interior_id) {
+        if object.level != level || object.interior_id.as_deref() != Some(interior_id) {
             continue;
         }
         let Some(door_id) = object.door_id.as_deref() else {
             continue;
         };
-        if session.visibility.opened_door_ids.iter().any(|entry| entry == door_id) {
+        if session
+            .visibility
+            .opened_door_ids
+            .iter()
+            .any(|entry| entry == door_id)
+            || session
+                .visibility
+                .locked_door_ids
+                .iter()
+                .any(|entry| entry == door_id)
+        {
             continue;
         }
         if (object.y as i32 - start_row).abs() <= 1 && (object.x as i32 - start_col).abs() <= 1 {
@@ -815,7 +2569,7 @@ fn open_adjacent_interior_door(
     for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
         let row = start_row + dr;
         let col = start_col + dc;
-        let Some(tile) = get_tile(session, row, col) else {
+        let Some(tile) = get_tile(session, level, row, col) else {
             continue;
         };
         if tile.interior_id.as_deref() != Some(interior_id) {
@@ -824,7 +2578,17 @@ fn open_adjacent_interior_door(
         let Some(door_id) = tile.door_id.as_deref() else {
             continue;
         };
-        if !session.visibility.opened_door_ids.iter().any(|entry| entry == door_id) {
+        if !session
+            .visibility
+            .opened_door_ids
+            .iter()
+            .any(|entry| entry == door_id)
+            && !session
+                .visibility
+                .locked_door_ids
+                .iter()
+                .any(|entry| entry == door_id)
+        {
             door_ids_to_open.push(door_id.to_string());
         }
     }
@@ -832,37 +2596,55 @@ fn open_adjacent_interior_door(
     if door_ids_to_open.is_empty() {
         return false;
     }
-    session.visibility.opened_door_ids.extend(door_ids_to_open);
-    session.visibility.opened_door_ids.sort();
-    session.visibility.opened_door_ids.dedup();
+    session.open_doors(door_ids_to_open);
     true
 }
 
 fn collect_interior_doorway_candidates(
     session: &ExplorationSim,
+    level: i32,
     interior_id: &str,
 ) -> Vec<(i32, i32, f32)> {
     let mut seen = HashSet::new();
     let mut candidates = Vec::new();
 
     for object in &session.objects {
-        if object.interior_id.as_deref() != Some(interior_id) || object.door_id.is_none() {
+        if object.level != level
+            || object.interior_id.as_deref() != Some(interior_id)
+            || object.door_id.is_none()
+        {
             continue;
         }
         let row = object.y as i32;
         let col = object.x as i32;
-        collect_doorway_approaches(session, interior_id, row, col, &mut seen, &mut candidates);
+        collect_doorway_approaches(
+            session,
+            level,
+            interior_id,
+            row,
+            col,
+            &mut seen,
+            &mut candidates,
+        );
     }
 
     for row in 0..session.descriptor.height as i32 {
         for col in 0..session.descriptor.width as i32 {
-            let Some(tile) = get_tile(session, row, col) else {
+            let Some(tile) = get_tile(session, level, row, col) else {
                 continue;
             };
             if tile.interior_id.as_deref() != Some(interior_id) || tile.door_id.is_none() {
                 continue;
             }
-            collect_doorway_approaches(session, interior_id, row, col, &mut seen, &mut candidates);
+            collect_doorway_approaches(
+                session,
+                level,
+                interior_id,
+                row,
+                col,
+                &mut seen,
+                &mut candidates,
+            );
         }
     }
 
@@ -871,17 +2653,17 @@ fn collect_interior_doorway_candidates(
 
 fn collect_doorway_approaches(
     session: &ExplorationSim,
+    level: i32,
     interior_id: &str,
     door_row: i32,
     door_col: i32,
-    seen: &mut HashSet<String>,
+    seen: &mut HashSet<(i32, i32)>,
     candidates: &mut Vec<(i32, i32, f32)>,
 ) {
-    if get_cell_move_cost(session, door_row, door_col) > 0.0 {
-        let key = key(door_row, door_col);
-        if seen.insert(key) {
-            candidates.push((door_row, door_col, 1.0));
-        }
+    if get_cell_move_cost(session, level, door_row, door_col) > 0.0
+        && seen.insert((door_row, door_col))
+    {
+        candidates.push((door_row, door_col, 1.0));
     }
 
     let mut exterior_candidates = Vec::new();
@@ -896,10 +2678,10 @@ fn collect_doorway_approaches(
         {
             continue;
         }
-        if get_cell_move_cost(session, row, col) <= 0.0 {
+        if get_cell_move_cost(session, level, row, col) <= 0.0 {
             continue;
         }
-        let is_same_interior = get_tile(session, row, col)
+        let is_same_interior = get_tile(session, level, row, col)
             .and_then(|entry| entry.interior_id)
             .as_deref()
             == Some(interior_id);
@@ -914,8 +2696,7 @@ fn collect_doorway_approaches(
         .into_iter()
         .chain(interior_adjacent_candidates.into_iter())
     {
-        let candidate_key = key(row, col);
-        if seen.insert(candidate_key) {
+        if seen.insert((row, col)) {
             let distance_from_door = ((row - door_row).abs() + (col - door_col).abs()) as f32;
             candidates.push((row, col, priority + distance_from_door));
         }
@@ -925,197 +2706,251 @@ fn collect_doorway_approaches(
 fn find_path(
     session: &ExplorationSim,
     selected_pawn_id: Option<&str>,
+    level: i32,
     start_row: i32,
     start_col: i32,
     target_row: i32,
     target_col: i32,
 ) -> Option<Vec<PathNode>> {
-    if start_row == target_row && start_col == target_col {
-        return Some(Vec::new());
-    }
-    if target_row < 0
-        || target_col < 0
-        || target_row >= session.descriptor.height as i32
-        || target_col >= session.descriptor.width as i32
-    {
-        return None;
-    }
-    if get_cell_move_cost(session, target_row, target_col) <= 0.0 {
-        return None;
-    }
+    let occupied = build_occupied_set(session, selected_pawn_id, level);
+    find_path_in(
+        session, level, &occupied, None, start_row, start_col, target_row, target_col,
+    )
+}
 
-    let occupied = build_occupied_set(session, selected_pawn_id);
-    if occupied.contains(&key(target_row, target_col)) {
-        return None;
-    }
+/// Path search against a prebuilt occupancy set, so behaviour passes can share one set across
+/// every pawn they route. `ignore` is the mover's own tile.
+#[allow(clippy::too_many_arguments)]
+fn find_path_in(
+    session: &ExplorationSim,
+    level: i32,
+    occupied: &HashSet<u32>,
+    ignore: Option<u32>,
+    start_row: i32,
+    start_col: i32,
+    target_row: i32,
+    target_col: i32,
+) -> Option<Vec<PathNode>> {
+    session
+        .nav(level)
+        .find_path(
+            (start_row, start_col),
+            (target_row, target_col),
+            occupied,
+            ignore,
+        )
+        .map(|steps| {
+            steps
+                .into_iter()
+                .map(|(row, col)| PathNode {
+                    x: col,
+                    y: row,
+                    level,
+                })
+                .collect()
+        })
+}
 
-    const DIRS: [(i32, i32, f32); 8] = [
-        (-1, 0, 1.0),
-        (1, 0, 1.0),
-        (0, -1, 1.0),
-        (0, 1, 1.0),
-        (-1, -1, std::f32::consts::SQRT_2),
-        (-1, 1, std::f32::consts::SQRT_2),
-        (1, -1, std::f32::consts::SQRT_2),
-        (1, 1, std::f32::consts::SQRT_2),
-    ];
+/// Cheapest route between cells `(level, row, col)` on different levels. Same-level legs use the
+/// level's grid; each stairs or ladder tile on the way adds one step onto the linked level.
+fn find_route(
+    session: &ExplorationSim,
+    selected_pawn_id: Option<&str>,
+    start: LevelCell,
+    target: LevelCell,
+) -> Option<Vec<PathNode>> {
+    let mut occupied_by_level = HashMap::new();
+    let mut leg = |from: LevelCell, to: (i32, i32)| -> Option<Vec<PathNode>> {
+        if (from.1, from.2) == to {
+            return Some(Vec::new());
+        }
+        let occupied = occupied_by_level
+            .entry(from.0)
+            .or_insert_with(|| build_occupied_set(session, selected_pawn_id, from.0));
+        find_path_in(session, from.0, occupied, None, from.1, from.2, to.0, to.1)
+    };
 
-    let mut open = vec![(
-        start_row,
-        start_col,
-        0.0_f32,
-        octile_heuristic(start_row, start_col, target_row, target_col),
-    )];
-    let mut came_from: HashMap<String, String> = HashMap::new();
-    let mut g_scores = HashMap::<String, f32>::from([(key(start_row, start_col), 0.0)]);
-    let mut closed = HashSet::<String>::new();
-
-    while !open.is_empty() {
-        open.sort_by(|left, right| {
-            left.3
-                .partial_cmp(&right.3)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        let (row, col, g, _) = open.remove(0);
-        let current_key = key(row, col);
-        if closed.contains(&current_key) {
+    // Dijkstra over the start cell and the landing cell of every link.
+    let mut routes = vec![(start, Vec::<PathNode>::new())];
+    let mut best = HashMap::from([(start, 0usize)]);
+    let mut queue = BinaryHeap::from([Reverse((0usize, 0usize))]);
+    let mut finished: Option<Vec<PathNode>> = None;
+    while let Some(Reverse((cost, index))) = queue.pop() {
+        if finished.as_ref().is_some_and(|route| route.len() <= cost) {
+            break;
+        }
+        let (cell, route) = routes[index].clone();
+        if best.get(&cell).is_some_and(|known| *known < cost) {
             continue;
         }
-        closed.insert(current_key.clone());
-
-        if row == target_row && col == target_col {
-            let mut path = Vec::<PathNode>::new();
-            let mut walk = current_key;
-            while let Some(previous) = came_from.get(&walk).cloned() {
-                let parts = walk.split(':').collect::<Vec<_>>();
-                let walk_row = parts.first().and_then(|entry| entry.parse::<i32>().ok()).unwrap_or(0);
-                let walk_col = parts.get(1).and_then(|entry| entry.parse::<i32>().ok()).unwrap_or(0);
-                path.push(PathNode {
-                    x: walk_col,
-                    y: walk_row,
-                });
-                walk = previous;
+        if cell.0 == target.0 {
+            if let Some(path) = leg(cell, (target.1, target.2)) {
+                let mut full = route.clone();
+                full.extend(path);
+                if finished
+                    .as_ref()
+                    .is_none_or(|known| full.len() < known.len())
+                {
+                    finished = Some(full);
+                }
             }
-            path.reverse();
-            return Some(path);
         }
-
-        for (dr, dc, dir_cost) in DIRS {
-            let next_row = row + dr;
-            let next_col = col + dc;
-            if next_row < 0
-                || next_col < 0
-                || next_row >= session.descriptor.height as i32
-                || next_col >= session.descriptor.width as i32
-            {
+        for (from, to) in &session.level_links {
+            if from.0 != cell.0 {
                 continue;
             }
-            let next_key = key(next_row, next_col);
-            if closed.contains(&next_key) || occupied.contains(&next_key) {
+            let Some(path) = leg(cell, (from.1, from.2)) else {
                 continue;
-            }
-            let move_cost = get_cell_move_cost(session, next_row, next_col);
-            if move_cost <= 0.0 {
+            };
+            let mut next = route.clone();
+            next.extend(path);
+            next.push(PathNode {
+                x: to.2,
+                y: to.1,
+                level: to.0,
+            });
+            if best.get(to).is_some_and(|known| *known <= next.len()) {
                 continue;
             }
-            if dr != 0 && dc != 0 {
-                let horizontal = get_cell_move_cost(session, row, next_col);
-                let vertical = get_cell_move_cost(session, next_row, col);
-                if horizontal <= 0.0 || vertical <= 0.0 {
-                    continue;
-                }
-            }
-
-            let tentative_g = g + dir_cost * move_cost;
-            if let Some(known) = g_scores.get(&next_key) {
-                if tentative_g >= *known {
-                    continue;
-                }
-            }
-
-            came_from.insert(next_key.clone(), current_key.clone());
-            g_scores.insert(next_key.clone(), tentative_g);
-            open.push((
-                next_row,
-                next_col,
-                tentative_g,
-                tentative_g + octile_heuristic(next_row, next_col, target_row, target_col),
-            ));
+            best.insert(*to, next.len());
+            queue.push(Reverse((next.len(), routes.len())));
+            routes.push((*to, next));
         }
     }
-
-    None
+    finished
 }
 
 fn build_occupied_set(
     session: &ExplorationSim,
     selected_pawn_id: Option<&str>,
-) -> HashSet<String> {
-    let mut occupied = HashSet::new();
+    level: i32,
+) -> HashSet<u32> {
+    let nav = session.nav(level);
+    session
+        .pawns
+        .iter()
+        .filter(|pawn| pawn.level == level && selected_pawn_id != Some(pawn.id.as_str()))
+        .filter_map(|pawn| nav.index_of(pawn.tile_row, pawn.tile_col))
+        .collect()
+}
+
+/// Occupied cells of every level, for behaviour passes that route many pawns.
+fn build_occupied_sets(session: &ExplorationSim) -> HashMap<i32, HashSet<u32>> {
+    let mut occupied = HashMap::<i32, HashSet<u32>>::new();
     for pawn in &session.pawns {
-        if selected_pawn_id == Some(pawn.id.as_str()) {
-            continue;
+        if let Some(index) = session
+            .nav(pawn.level)
+            .index_of(pawn.tile_row, pawn.tile_col)
+        {
+            occupied.entry(pawn.level).or_default().insert(index);
         }
-        occupied.insert(key(pawn.tile_row, pawn.tile_col));
     }
     occupied
 }
 
-fn get_cell_move_cost(session: &ExplorationSim, row: i32, col: i32) -> f32 {
-    let Some(tile) = get_tile(session, row, col) else {
-        return 0.0;
-    };
-    if !tile.walkable {
-        return 0.0;
-    }
-    if tile.door_id.as_deref().is_some_and(|door_id| {
-        !session
-            .visibility
-            .opened_door_ids
-            .iter()
-            .any(|entry| entry == door_id)
-    }) {
-        return 0.0;
-    }
+fn get_cell_move_cost(session: &ExplorationSim, level: i32, row: i32, col: i32) -> f32 {
+    session.nav(level).cost(row, col)
+}
 
-    let mut move_cost = if tile.move_cost > 0.0 { tile.move_cost } else { 1.0 };
-    for object in &session.objects {
-        if !object_footprint_contains(&object, row, col) {
-            continue;
-        }
-        if object.door_id.as_deref().is_some_and(|door_id| {
-            !session
-                .visibility
-                .opened_door_ids
-                .iter()
-                .any(|entry| entry == door_id)
-        }) {
-            return 0.0;
+/// Every stairs and ladder tile, paired with the cell it leads to.
+fn collect_level_links(
+    chunks: &HashMap<(i32, u32, u32), ExplorationChunk>,
+) -> Vec<(LevelCell, LevelCell)> {
+    let mut links = Vec::new();
+    for chunk in chunks.values() {
+        for (index, tile) in chunk.tiles.iter().enumerate() {
+            let Some(link) = tile.level_link.as_ref() else {
+                continue;
+            };
+            let row = chunk.origin_row + index as u32 / chunk.width.max(1);
+            let col = chunk.origin_col + index as u32 % chunk.width.max(1);
+            links.push((
+                (chunk.level, row as i32, col as i32),
+                (link.level, link.row as i32, link.col as i32),
+            ));
         }
-        if !object.passable {
-            return 0.0;
+    }
+    links.sort_unstable();
+    links
+}
+
+/// Move costs for a rectangle of cells (`origin_row, origin_col, rows, cols`), row-major. Closed
+/// doors and solid objects block their footprint; other objects can only raise the tile cost.
+fn region_costs(
+    chunks: &HashMap<(i32, u32, u32), ExplorationChunk>,
+    chunk_size: u32,
+    level: i32,
+    objects: &[ExplorationObject],
+    opened_door_ids: &[String],
+    (origin_row, origin_col, rows, cols): (u32, u32, u32, u32),
+) -> Vec<f32> {
+    let door_closed = |door_id: Option<&String>| {
+        door_id.is_some_and(|door_id| !opened_door_ids.contains(door_id))
+    };
+    let mut costs = Vec::with_capacity((rows * cols) as usize);
+    for row in origin_row..origin_row + rows {
+        for col in origin_col..origin_col + cols {
+            let cost = match get_tile_from_chunks(chunks, chunk_size, level, row as i32, col as i32)
+            {
+                Some(tile) if tile.walkable && !door_closed(tile.door_id.as_ref()) => {
+                    if tile.move_cost > 0.0 {
+                        tile.move_cost
+                    } else {
+                        1.0
+                    }
+                }
+                _ => 0.0,
+            };
+            costs.push(cost);
         }
-        if let Some(object_move_cost) = object.move_cost.filter(|value| *value > 0.0) {
-            move_cost = move_cost.max(object_move_cost);
+    }
+
+    for object in objects.iter().filter(|object| object.level == level) {
+        let blocks = door_closed(object.door_id.as_ref()) || !object.passable;
+        let object_cost = object.move_cost.filter(|value| *value > 0.0);
+        let min_row = object.y.max(origin_row);
+        let max_row = (object.y + object.height).min(origin_row + rows);
+        let min_col = object.x.max(origin_col);
+        let max_col = (object.x + object.width).min(origin_col + cols);
+        for row in min_row..max_row {
+            for col in min_col..max_col {
+                let cost = &mut costs[((row - origin_row) * cols + (col - origin_col)) as usize];
+                if *cost <= 0.0 {
+                    continue;
+                }
+                if blocks {
+                    *cost = 0.0;
+                } else if let Some(object_cost) = object_cost {
+                    *cost = cost.max(object_cost);
+                }
+            }
         }
     }
-    move_cost
+    costs
 }
 
-fn object_footprint_contains(object: &ExplorationObject, row: i32, col: i32) -> bool {
-    row >= object.y as i32
-        && row < object.y as i32 + object.height as i32
-        && col >= object.x as i32
-        && col < object.x as i32 + object.width as i32
+fn object_within_reach(object: &ExplorationObject, row: i32, col: i32) -> bool {
+    row >= object.y as i32 - 1
+        && row <= (object.y + object.height) as i32
+        && col >= object.x as i32 - 1
+        && col <= (object.x + object.width) as i32
 }
 
-fn get_tile(session: &ExplorationSim, row: i32, col: i32) -> Option<ExplorationTile> {
-    get_tile_from_chunks(&session.chunks, session.descriptor.chunk_size, row, col).cloned()
+fn get_tile(session: &ExplorationSim, level: i32, row: i32, col: i32) -> Option<ExplorationTile> {
+    get_tile_from_chunks(
+        &session.chunks,
+        session.descriptor.chunk_size,
+        level,
+        row,
+        col,
+    )
+    .cloned()
 }
 
 fn get_tile_from_chunks<'a>(
-    chunks: &'a HashMap<(u32, u32), ExplorationChunk>,
+    chunks: &'a HashMap<(i32, u32, u32), ExplorationChunk>,
     chunk_size: u32,
+    level: i32,
     row: i32,
     col: i32,
 ) -> Option<&'a ExplorationTile> {
@@ -1126,23 +2961,15 @@ fn get_tile_from_chunks<'a>(
     let col = col as u32;
     let chunk_row = row / chunk_size.max(1);
     let chunk_col = col / chunk_size.max(1);
-    let chunk = chunks.get(&(chunk_row, chunk_col))?;
+    let chunk = chunks.get(&(level, chunk_row, chunk_col))?;
     let local_row = row.saturating_sub(chunk.origin_row);
     let local_col = col.saturating_sub(chunk.origin_col);
     if local_row >= chunk.height || local_col >= chunk.width {
         return None;
     }
-    chunk.tiles.get((local_row * chunk.width + local_col) as usize)
-}
-
-fn octile_heuristic(row: i32, col: i32, target_row: i32, target_col: i32) -> f32 {
-    let dx = (target_col - col).abs() as f32;
-    let dy = (target_row - row).abs() as f32;
-    (dx + dy) + (std::f32::consts::SQRT_2 - 2.0) * dx.min(dy)
-}
-
-fn key(row: i32, col: i32) -> String {
-    format!("{row}:{col}")
+    chunk
+        .tiles
+        .get((local_row * chunk.width + local_col) as usize)
 }
 
 fn clamp_i32(value: i32, min: i32, max: i32) -> i32 {
@@ -1150,9 +2977,18 @@ fn clamp_i32(value: i32, min: i32, max: i32) -> i32 {
 }
 
 #[cfg(test)]
-mod tests {
+pub(super) mod tests {
     use super::*;
-    use crate::exploration_engine::types::{ExplorationSpawnPoint, ExplorationTile};
+    use crate::ecology::{
+        EcologyStatSource, EntryStatus, FloraBodyProfile, FloraCategory, FloraEdibility,
+        FloraHazardProfile, FloraResourceProfile,
+    };
+    use crate::exploration_engine::manifest::{manifest_path, write_chunked_location};
+    use crate::exploration_engine::types::{
+        ExplorationLevelLink, ExplorationMap, ExplorationSpawnPoint, ExplorationTile,
+        ExplorationTriggerArea, ExplorationTriggerCondition, ExplorationWildlifeState,
+    };
+    use crate::jobs::now_ms;
 
     fn sample_chunk() -> ExplorationChunk {
         let mut tiles = Vec::new();
@@ -1191,11 +3027,13 @@ mod tests {
                     } else {
                         None
                     },
+                    level_link: None,
                 });
             }
         }
         ExplorationChunk {
             id: "chunk-0-0".to_string(),
+            level: 0,
             chunk_row: 0,
             chunk_col: 0,
             origin_row: 0,
@@ -1222,6 +3060,8 @@ mod tests {
                     roof_group_id: Some("roof-a".to_string()),
                     height_tiles: Some(2),
                     blocks_light: Some(false),
+                    flora_id: None,
+                    level: 0,
                 },
                 ExplorationObject {
                     id: "tree-1".to_string(),
@@ -1241,12 +3081,14 @@ mod tests {
                     roof_group_id: None,
                     height_tiles: Some(1),
                     blocks_light: Some(false),
+                    flora_id: None,
+                    level: 0,
                 },
             ],
         }
     }
 
-    fn sample_sim() -> ExplorationSim {
+    pub(in crate::exploration_engine) fn sample_sim() -> ExplorationSim {
         let descriptor = ExplorationManifestDescriptor {
             id: "desc".to_string(),
             world_id: "world".to_string(),
@@ -1256,20 +3098,23 @@ mod tests {
             height: 16,
             chunk_size: 16,
             version: 3,
+            revision: 1,
             render_mode: "isometric".to_string(),
             ambient_light: 0.76,
             spawn: ExplorationSpawnPoint { row: 8, col: 8 },
             metadata: None,
+            levels: Vec::new(),
         };
         ExplorationSim::new(
             descriptor,
-            HashMap::from([((0, 0), sample_chunk())]),
+            HashMap::from([((0, 0, 0), sample_chunk())]),
             vec![
                 ExplorationPawn {
                     id: "player".to_string(),
                     name: "Player".to_string(),
                     x: 8.0,
                     y: 8.0,
+                    level: 0,
                     tile_row: 8,
                     tile_col: 8,
                     target_x: None,
@@ -1293,12 +3138,20 @@ mod tests {
                     current_anchor_id: None,
                     current_intent: None,
                     next_decision_at_tick: None,
+                    stats: None,
+                    conditions: Vec::new(),
+                    wildlife: None,
+                    movement_mode: ExplorationMovementMode::Walk,
+                    armor_noise: 0.0,
+                    awareness: None,
+                    inventory: Vec::new(),
                 },
                 ExplorationPawn {
                     id: "npc".to_string(),
                     name: "NPC".to_string(),
                     x: 7.0,
                     y: 7.0,
+                    level: 0,
                     tile_row: 7,
                     tile_col: 7,
                     target_x: None,
@@ -1322,6 +3175,13 @@ mod tests {
                     current_anchor_id: None,
                     current_intent: Some("idle".to_string()),
                     next_decision_at_tick: None,
+                    stats: None,
+                    conditions: Vec::new(),
+                    wildlife: None,
+                    movement_mode: ExplorationMovementMode::Walk,
+                    armor_noise: 0.0,
+                    awareness: None,
+                    inventory: Vec::new(),
                 },
             ],
             Some("player".to_string()),
@@ -1332,15 +3192,15 @@ mod tests {
     #[test]
     fn closed_doors_block_path_until_opened() {
         let mut sim = sample_sim();
-        assert!(find_path(&sim, Some("player"), 8, 8, 4, 6).is_none());
-        sim.visibility.opened_door_ids.push("door-a".to_string());
-        assert!(find_path(&sim, Some("player"), 8, 8, 4, 6).is_some());
+        assert!(find_path(&sim, Some("player"), 0, 8, 8, 4, 6).is_none());
+        sim.open_doors(vec!["door-a".to_string()]);
+        assert!(find_path(&sim, Some("player"), 0, 8, 8, 4, 6).is_some());
     }
 
     #[test]
     fn solid_objects_block_pathing() {
         let sim = sample_sim();
-        assert!(find_path(&sim, Some("player"), 8, 8, 10, 10).is_none());
+        assert!(find_path(&sim, Some("player"), 0, 8, 8, 10, 10).is_none());
     }
 
     #[test]
@@ -1354,13 +3214,16 @@ mod tests {
             .handle_interaction(Some(4), Some(6), Some("door-object".to_string()), None)
             .unwrap();
         assert_eq!(result.label, "Opened door-a");
-        assert!(sim.visibility.opened_door_ids.contains(&"door-a".to_string()));
+        assert!(sim
+            .visibility
+            .opened_door_ids
+            .contains(&"door-a".to_string()));
     }
 
     #[test]
     fn fractional_interior_position_resolves_to_walkable_origin() {
         let mut sim = sample_sim();
-        sim.visibility.opened_door_ids.push("door-a".to_string());
+        sim.open_doors(vec!["door-a".to_string()]);
         sim.pawns[0].x = 4.4;
         sim.pawns[0].y = 5.4;
 
@@ -1394,7 +3257,10 @@ mod tests {
         let result = sim.move_pawn("player", 6, 6, true);
 
         assert!(result.is_ok());
-        assert!(sim.visibility.opened_door_ids.contains(&"door-a".to_string()));
+        assert!(sim
+            .visibility
+            .opened_door_ids
+            .contains(&"door-a".to_string()));
         let route = &sim.pawns[0].route;
         assert!(!route.is_empty());
         let last = route.last().expect("route should have an endpoint");
@@ -1405,7 +3271,7 @@ mod tests {
     fn npc_wandering_starts_from_current_position() {
         let mut sim = sample_sim();
         sim.tick = sim.tick_rate_hz * 3;
-        sim.visibility.opened_door_ids.push("door-a".to_string());
+        sim.open_doors(vec!["door-a".to_string()]);
         sim.pawns[1].x = 11.0;
         sim.pawns[1].y = 11.0;
         sim.pawns[1].tile_row = 11;
@@ -1426,10 +3292,965 @@ mod tests {
         }
     }
 
+    pub(in crate::exploration_engine) fn sample_flora() -> FloraEntry {
+        FloraEntry {
+            id: "flora-berry".to_string(),
+            status: EntryStatus::Approved,
+            name: "Ash Berry".to_string(),
+            category: FloraCategory::Shrub,
+            description: String::new(),
+            ecological_roles: Vec::new(),
+            adaptations: Vec::new(),
+            edibility: FloraEdibility::Common,
+            agriculture_value: 20,
+            biome_ids: Vec::new(),
+            vegetation_asset_batch_ids: Vec::new(),
+            illustration_asset_batch_ids: Vec::new(),
+            illustration_assets: Vec::new(),
+            body_profile: FloraBodyProfile::default(),
+            resource_profile: FloraResourceProfile {
+                yield_per_harvest: 40,
+                regrowth_days: 2,
+                harvest_difficulty: 10,
+                nutrition_value: 60,
+                ..FloraResourceProfile::default()
+            },
+            hazard_profile: FloraHazardProfile::default(),
+            stats_version: "v1".to_string(),
+            stats_source: EcologyStatSource::Manual,
+            approved_at: None,
+        }
+    }
+
+    fn harvest_sim() -> ExplorationSim {
+        let mut sim = sample_sim();
+        sim.objects.push(ExplorationObject {
+            id: "berry-1".to_string(),
+            r#type: "shrub".to_string(),
+            x: 12,
+            y: 8,
+            width: 1,
+            height: 1,
+            passable: false,
+            texture_url: None,
+            is_natural: Some(true),
+            is_hidden: Some(false),
+            move_cost: Some(1.4),
+            fertility: Some(1.0),
+            door_id: None,
+            interior_id: None,
+            roof_group_id: None,
+            height_tiles: Some(1),
+            blocks_light: Some(false),
+            flora_id: Some("flora-berry".to_string()),
+            level: 0,
+        });
+        sim.set_harvest_context(vec![sample_flora()], Vec::new());
+        sim
+    }
+
+    #[test]
+    fn harvest_requires_reach_and_depletes_node_until_regrown() {
+        let mut sim = harvest_sim();
+        let far = sim.harvest("berry-1", Some("player"), 1_000).unwrap();
+        assert!(!far.success);
+        assert!(far.label.contains("too far"));
+
+        sim.pawns[0].x = 11.0;
+        sim.pawns[0].tile_col = 11;
+        let mut harvested = None;
+        for _ in 0..64 {
+            let result = sim.harvest("berry-1", Some("player"), 1_000).unwrap();
+            if result.success {
+                harvested = Some(result);
+                break;
+            }
+        }
+        let harvested = harvested.expect("harvest should eventually succeed");
+        assert_eq!(harvested.items.len(), 1);
+        assert!(harvested.items[0].quantity >= 1);
+        let regrows_at_ms = harvested.regrows_at_ms.expect("node should be on a timer");
+        assert!(regrows_at_ms > 1_000);
+        assert_eq!(sim.harvest_node_states().len(), 1);
+
+        assert_eq!(sim.pawns[0].inventory, harvested.items);
+
+        let depleted = sim.harvest("berry-1", Some("player"), 2_000).unwrap();
+        assert!(!depleted.success);
+        assert_eq!(depleted.regrows_at_ms, Some(regrows_at_ms));
+        assert!(depleted.items.is_empty());
+        assert_eq!(sim.pawns[0].inventory, harvested.items);
+
+        sim.day_start_ms = regrows_at_ms;
+        sim.advance(0.1);
+        assert!(sim.harvest_node_states().is_empty());
+        let mut regrown = None;
+        for _ in 0..64 {
+            let result = sim
+                .harvest("berry-1", Some("player"), regrows_at_ms)
+                .unwrap();
+            if result.success {
+                regrown = Some(result);
+                break;
+            }
+        }
+        let regrown = regrown.expect("regrown node should be harvestable");
+        assert_eq!(sim.pawns[0].inventory.len(), 1);
+        assert_eq!(
+            sim.pawns[0].inventory[0].quantity,
+            harvested.items[0].quantity + regrown.items[0].quantity
+        );
+    }
+
+    #[test]
+    fn harvest_rejects_objects_without_flora() {
+        let mut sim = harvest_sim();
+        assert!(sim.harvest("tree-1", Some("player"), 0).is_err());
+    }
+
+    fn wildlife_sim(behavior: ExplorationWildlifeBehavior) -> ExplorationSim {
+        let mut sim = sample_sim();
+        sim.pawns.truncate(1);
+        sim.pawns[0].x = 11.0;
+        sim.pawns[0].y = 11.0;
+        sim.pawns[0].tile_row = 11;
+        sim.pawns[0].tile_col = 11;
+        let mut creature = sim.pawns[0].clone();
+        creature.id = "pack-0".to_string();
+        creature.name = "Ridgeback".to_string();
+        creature.x = 13.0;
+        creature.tile_col = 13;
+        creature.faction_id = "wildlife".to_string();
+        creature.is_npc = Some(true);
+        // Midday, and the creature holds still long enough to spot the party before roaming.
+        sim.day_start_ms = crate::exploration_engine::harvest::EXPLORATION_DAY_MS / 2;
+        creature.next_decision_at_tick = Some(sim.tick_rate_hz * 2);
+        creature.wildlife = Some(ExplorationWildlifeState {
+            fauna_id: "fauna-ridgeback".to_string(),
+            pack_id: "pack".to_string(),
+            behavior,
+            perception: 100,
+            stealth: 20,
+            danger_level: 30,
+            home_row: 11,
+            home_col: 13,
+            target_pawn_id: None,
+            warned_at_tick: None,
+            concealed: false,
+        });
+        sim.pawns.push(creature);
+        sim
+    }
+
+    fn advance_until_wildlife_event(sim: &mut ExplorationSim) -> WildlifeEvent {
+        for _ in 0..200 {
+            if let Some(event) = sim.advance(0.1).wildlife_events.into_iter().next() {
+                return event;
+            }
+        }
+        panic!("wildlife never noticed the party");
+    }
+
+    /// Replays an input log up to `until_tick`, applying each action on the tick it was logged
+    /// at. Starting from the state the log was recorded against, this reproduces the session.
+    fn replay(sim: &mut ExplorationSim, log: &ExplorationInputLog, until_tick: u64) {
+        let delta_seconds = 1.0 / sim.tick_rate_hz as f32;
+        let mut entries = log.entries.iter().peekable();
+        loop {
+            while let Some(entry) = entries.next_if(|entry| entry.tick <= sim.tick) {
+                // Rejected actions left the live session unchanged too.
+                let _ = sim.apply_input(&entry.action, entry.at_ms);
+            }
+            if sim.tick >= until_tick {
+                break;
+            }
+            sim.advance(delta_seconds);
+        }
+    }
+
+    #[test]
+    fn replaying_the_input_log_reproduces_the_session() {
+        let actions = [
+            (
+                3,
+                ExplorationClientAction::MoveTo {
+                    pawn_id: "player".to_string(),
+                    target_row: 12,
+                    target_col: 4,
+                    level: None,
+                },
+            ),
+            (
+                12,
+                ExplorationClientAction::SetMovementMode {
+                    pawn_id: None,
+                    mode: ExplorationMovementMode::Sneak,
+                },
+            ),
+            (
+                20,
+                ExplorationClientAction::MoveTo {
+                    pawn_id: "player".to_string(),
+                    target_row: 2,
+                    target_col: 13,
+                    level: None,
+                },
+            ),
+        ];
+        let mut live = sample_sim();
+        live.reseed(42);
+        while live.tick < 120 {
+            for (tick, action) in &actions {
+                if *tick == live.tick {
+                    live.record_input(action, 1_000);
+                    let _ = live.apply_input(action, 1_000);
+                }
+            }
+            live.advance(0.1);
+        }
+
+        let log = serde_json::from_value::<ExplorationInputLog>(
+            serde_json::to_value(&live.input_log).expect("serialize log"),
+        )
+        .expect("parse log");
+        assert_eq!(log.entries.len(), 3);
+        let mut replayed = sample_sim();
+        replayed.reseed(log.seed);
+        replay(&mut replayed, &log, live.tick);
+        assert_eq!(
+            serde_json::to_value(replayed.snapshot()).expect("replayed snapshot"),
+            serde_json::to_value(live.snapshot()).expect("live snapshot")
+        );
+    }
+
+    #[test]
+    fn nearby_npc_becomes_alert_to_the_party() {
+        let mut sim = sample_sim();
+        let event = (0..200)
+            .flat_map(|_| sim.advance(0.1).awareness_events)
+            .find(|event| {
+                matches!(
+                    event,
+                    ExplorationSessionEvent::Awareness {
+                        state: ExplorationAwarenessState::Alert,
+                        ..
+                    }
+                )
+            })
+            .expect("npc never became alert");
+        let ExplorationSessionEvent::Awareness {
+            pawn_id,
+            target_pawn_id,
+            ..
+        } = event
+        else {
+            unreachable!();
+        };
+        assert_eq!(pawn_id, "npc");
+        assert_eq!(target_pawn_id.as_deref(), Some("player"));
+        assert!(sim.pawns[0].awareness.is_none());
+    }
+
+    #[test]
+    fn passive_wildlife_flees_from_party() {
+        let mut sim = wildlife_sim(ExplorationWildlifeBehavior::Passive);
+        let party = (sim.pawns[0].tile_row, sim.pawns[0].tile_col);
+        let start = (sim.pawns[1].tile_row, sim.pawns[1].tile_col);
+        let event = advance_until_wildlife_event(&mut sim);
+        assert_eq!(event.intent, "fleeing");
+        assert_eq!(event.target_pawn_id.as_deref(), Some("player"));
+        let creature = &sim.pawns[1];
+        let destination = creature.route.last().expect("flee route");
+        let chebyshev = |(row, col): (i32, i32)| (row - party.0).abs().max((col - party.1).abs());
+        assert!(chebyshev((destination.row, destination.col)) > chebyshev(start));
+    }
+
+    #[test]
+    fn territorial_wildlife_warns_before_attacking() {
+        let mut sim = wildlife_sim(ExplorationWildlifeBehavior::Territorial);
+        let warning = advance_until_wildlife_event(&mut sim);
+        assert_eq!(warning.intent, "warning");
+        assert!(!sim.pawns[1].moving);
+        let attack = advance_until_wildlife_event(&mut sim);
+        assert_eq!(attack.intent, "attacking");
+    }
+
+    #[test]
+    fn saved_state_restores_doors_clock_and_harvests() {
+        let mut sim = harvest_sim();
+        sim.tick = 120;
+        sim.open_doors(vec!["door-a".to_string()]);
+        sim.harvest_nodes.insert(
+            "berry-1".to_string(),
+            ExplorationHarvestNodeState {
+                object_id: "berry-1".to_string(),
+                flora_id: "flora-berry".to_string(),
+                regrows_at_ms: 5_000,
+                harvest_count: 1,
+            },
+        );
+        let saved = sim.save_state(1_000);
+        assert_eq!(saved.pawns.len(), sim.pawns.len());
+
+        let mut restored = harvest_sim();
+        restored.restore_state(&saved);
+        restored.set_harvest_context(vec![sample_flora()], saved.harvest_nodes.clone());
+        assert_eq!(restored.tick, 120);
+        assert!(restored
+            .visibility
+            .opened_door_ids
+            .contains(&"door-a".to_string()));
+        assert_eq!(restored.harvest_node_states().len(), 1);
+        assert!(find_path(&restored, Some("player"), 0, 8, 8, 4, 6).is_some());
+    }
+
+    fn trigger_sim(conditions: Vec<ExplorationTriggerCondition>, repeat: bool) -> ExplorationSim {
+        let mut sim = sample_sim();
+        sim.pawns.retain(|pawn| pawn.faction_id == "player");
+        sim.triggers = vec![ExplorationTrigger {
+            id: "hall".to_string(),
+            level: 0,
+            area: Some(ExplorationTriggerArea::Rect {
+                row: 8,
+                col: 8,
+                width: 2,
+                height: 2,
+            }),
+            conditions,
+            actions: vec![
+                ExplorationTriggerAction::Narration {
+                    text: "The hall falls silent.".to_string(),
+                },
+                ExplorationTriggerAction::LockDoors {
+                    door_ids: vec!["door-a".to_string()],
+                },
+                ExplorationTriggerAction::SetQuestFlag {
+                    flag: "hall-entered".to_string(),
+                    clear: false,
+                },
+            ],
+            repeat,
+        }];
+        sim
+    }
+
+    fn place_player(sim: &mut ExplorationSim, row: i32, col: i32) {
+        let pawn = &mut sim.pawns[0];
+        pawn.x = col as f32;
+        pawn.y = row as f32;
+        pawn.tile_row = row;
+        pawn.tile_col = col;
+    }
+
+    fn narrations(result: &AdvanceResult) -> usize {
+        result
+            .trigger_events
+            .iter()
+            .filter(|event| matches!(event, ExplorationSessionEvent::Narration { .. }))
+            .count()
+    }
+
+    #[test]
+    fn party_trigger_fires_once_and_locks_doors() {
+        let mut sim = trigger_sim(vec![ExplorationTriggerCondition::PartyEnters], false);
+        let result = sim.advance(0.1);
+        assert_eq!(narrations(&result), 1);
+        assert!(result.doors_changed);
+        assert_eq!(sim.quest_flags, vec!["hall-entered".to_string()]);
+        assert_eq!(sim.advance(0.1).trigger_events.len(), 0);
+
+        sim.open_doors(vec!["door-a".to_string()]);
+        assert!(find_path(&sim, Some("player"), 0, 8, 8, 4, 6).is_none());
+        let interaction = sim
+            .handle_interaction(Some(4), Some(6), Some("door-object".to_string()), None)
+            .unwrap();
+        assert_eq!(interaction.label, "Door is locked");
+
+        place_player(&mut sim, 12, 3);
+        sim.advance(0.1);
+        place_player(&mut sim, 8, 8);
+        assert_eq!(narrations(&sim.advance(0.1)), 0);
+
+        let mut restored = trigger_sim(vec![ExplorationTriggerCondition::PartyEnters], false);
+        restored.restore_state(&sim.save_state(1_000));
+        assert_eq!(restored.fired_trigger_ids, vec!["hall".to_string()]);
+        assert!(restored
+            .visibility
+            .locked_door_ids
+            .contains(&"door-a".to_string()));
+        assert_eq!(restored.quest_flags, sim.quest_flags);
+        assert_eq!(restored.advance(0.1).trigger_events.len(), 0);
+    }
+
+    #[test]
+    fn repeat_triggers_fire_on_each_rising_edge() {
+        let mut sim = trigger_sim(
+            vec![
+                ExplorationTriggerCondition::PawnEnters {
+                    pawn_id: "player".to_string(),
+                },
+                ExplorationTriggerCondition::FlagSet {
+                    flag: "alarm".to_string(),
+                },
+            ],
+            true,
+        );
+        assert_eq!(narrations(&sim.advance(0.1)), 0);
+        sim.set_quest_flags(vec!["alarm".to_string()]);
+        assert_eq!(narrations(&sim.advance(0.1)), 1);
+        assert_eq!(narrations(&sim.advance(0.1)), 0);
+
+        place_player(&mut sim, 12, 3);
+        sim.advance(0.1);
+        place_player(&mut sim, 9, 9);
+        assert_eq!(narrations(&sim.advance(0.1)), 1);
+    }
+
+    #[test]
+    fn each_condition_fires_once_then_honours_repeat() {
+        type Step = fn(&mut ExplorationSim);
+        let cases: Vec<(ExplorationTriggerCondition, Step, Step)> = vec![
+            (
+                ExplorationTriggerCondition::PartyEnters,
+                |sim| place_player(sim, 8, 8),
+                |sim| place_player(sim, 12, 3),
+            ),
+            (
+                ExplorationTriggerCondition::PawnEnters {
+                    pawn_id: "player".to_string(),
+                },
+                |sim| place_player(sim, 9, 9),
+                |sim| place_player(sim, 12, 3),
+            ),
+            (
+                ExplorationTriggerCondition::TimeOfDay {
+                    phases: vec![DayPhase::Night],
+                },
+                |sim| sim.day_start_ms = 0,
+                |sim| sim.day_start_ms = crate::exploration_engine::harvest::EXPLORATION_DAY_MS / 2,
+            ),
+            (
+                ExplorationTriggerCondition::FlagSet {
+                    flag: "alarm".to_string(),
+                },
+                |sim| sim.set_quest_flags(vec!["alarm".to_string()]),
+                |sim| sim.set_quest_flags(Vec::new()),
+            ),
+            (
+                ExplorationTriggerCondition::FlagUnset {
+                    flag: "calm".to_string(),
+                },
+                |sim| sim.set_quest_flags(Vec::new()),
+                |sim| sim.set_quest_flags(vec!["calm".to_string()]),
+            ),
+        ];
+        for (condition, hold, release) in cases {
+            for repeat in [false, true] {
+                let mut sim = trigger_sim(vec![condition.clone()], repeat);
+                release(&mut sim);
+                assert_eq!(narrations(&sim.advance(0.1)), 0, "{condition:?} released");
+                hold(&mut sim);
+                assert_eq!(narrations(&sim.advance(0.1)), 1, "{condition:?} first hold");
+                assert_eq!(narrations(&sim.advance(0.1)), 0, "{condition:?} still held");
+                release(&mut sim);
+                sim.advance(0.1);
+                hold(&mut sim);
+                assert_eq!(
+                    narrations(&sim.advance(0.1)),
+                    usize::from(repeat),
+                    "{condition:?} held again, repeat {repeat}"
+                );
+            }
+        }
+    }
+
+    fn party_sim() -> ExplorationSim {
+        let mut sim = sample_sim();
+        sim.pawns.retain(|pawn| pawn.faction_id == "player");
+        let template = sim.pawns[0].clone();
+        sim.pawns.clear();
+        for (index, (row, col)) in [(11, 3), (12, 2), (13, 2), (13, 3)].into_iter().enumerate() {
+            let mut pawn = template.clone();
+            pawn.id = format!("member-{index}");
+            pawn.x = col as f32;
+            pawn.y = row as f32;
+            pawn.tile_row = row;
+            pawn.tile_col = col;
+            sim.pawns.push(pawn);
+        }
+        sim.selected_pawn_id = Some("member-0".to_string());
+        sim
+    }
+
+    fn advance_until_idle(sim: &mut ExplorationSim) {
+        for _ in 0..400 {
+            if sim.pawns.iter().all(|pawn| !pawn.moving) {
+                return;
+            }
+            sim.advance(0.1);
+        }
+        panic!("party never came to rest");
+    }
+
+    fn assert_in_formation(sim: &ExplorationSim) {
+        let leader = sim.pawns.iter().find(|pawn| pawn.id == "member-0").unwrap();
+        let mut tiles = HashSet::new();
+        for pawn in &sim.pawns {
+            assert!(
+                tiles.insert((pawn.tile_row, pawn.tile_col)),
+                "{} shares a tile",
+                pawn.id
+            );
+            let tile = get_tile(sim, pawn.level, pawn.tile_row, pawn.tile_col).unwrap();
+            assert!(tile.walkable && tile.interior_id.is_none());
+            assert!(
+                pawn.id == "member-0" || tile.door_id.is_none(),
+                "{} blocks the door",
+                pawn.id
+            );
+            let spread = (pawn.tile_row - leader.tile_row)
+                .abs()
+                .max((pawn.tile_col - leader.tile_col).abs());
+            assert!(
+                spread <= 3 + FORMATION_SLOT_SEARCH_RADIUS,
+                "{} strayed",
+                pawn.id
+            );
+        }
+    }
+
+    #[test]
+    fn party_moves_in_formation_behind_leader() {
+        let mut sim = party_sim();
+        let changed = sim
+            .move_party(None, None, 11, 12, Some(ExplorationFormation::Column))
+            .unwrap();
+        assert_eq!(changed.len(), 4);
+        advance_until_idle(&mut sim);
+
+        let positions = sim
+            .pawns
+            .iter()
+            .map(|pawn| (pawn.tile_row, pawn.tile_col))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![(11, 12), (11, 11), (11, 10), (11, 9)]);
+        assert_eq!(
+            sim.snapshot().party.map(|party| party.formation),
+            Some(ExplorationFormation::Column)
+        );
+    }
+
+    #[test]
+    fn followers_trail_the_leader_while_it_walks() {
+        let mut sim = party_sim();
+        sim.move_party(None, None, 11, 12, Some(ExplorationFormation::Column))
+            .unwrap();
+        let mut steps = 0;
+        while sim.pawns[0].moving {
+            sim.advance(0.1);
+            steps += 1;
+            assert!(steps < 400, "leader never arrived");
+            let leader = &sim.pawns[0];
+            for follower in &sim.pawns[1..] {
+                let spread = (follower.tile_row - leader.tile_row)
+                    .abs()
+                    .max((follower.tile_col - leader.tile_col).abs());
+                assert!(spread <= 4, "{} fell behind", follower.id);
+                assert!(
+                    follower.tile_col <= leader.tile_col,
+                    "{} overtook",
+                    follower.id
+                );
+            }
+        }
+        assert!(!sim.party.as_ref().unwrap().following);
+    }
+
+    #[test]
+    fn followers_moved_by_hand_leave_the_formation() {
+        let mut sim = party_sim();
+        sim.move_party(None, None, 11, 12, Some(ExplorationFormation::Line))
+            .unwrap();
+        advance_until_idle(&mut sim);
+
+        let placed = ExplorationClientAction::MoveTo {
+            pawn_id: "member-3".to_string(),
+            target_row: 2,
+            target_col: 2,
+            level: None,
+        };
+        sim.apply_input(&placed, 0).unwrap();
+        advance_until_idle(&mut sim);
+        let changed = sim.regroup_party();
+        assert!(!changed.contains(&"member-3".to_string()));
+        let member = sim.pawns.iter().find(|pawn| pawn.id == "member-3").unwrap();
+        assert_eq!((member.tile_row, member.tile_col), (2, 2));
+    }
+
+    #[test]
+    fn formation_slots_avoid_doorways_and_walls() {
+        let mut sim = party_sim();
+        sim.move_party(
+            Some("member-0"),
+            None,
+            3,
+            6,
+            Some(ExplorationFormation::Wedge),
+        )
+        .unwrap();
+        advance_until_idle(&mut sim);
+        let leader = sim.pawns.iter().find(|pawn| pawn.id == "member-0").unwrap();
+        assert_eq!((leader.tile_row, leader.tile_col), (3, 6));
+        assert_in_formation(&sim);
+    }
+
+    #[test]
+    fn party_regroups_around_leader_after_scattering() {
+        let mut sim = party_sim();
+        sim.move_party(None, None, 11, 12, Some(ExplorationFormation::Line))
+            .unwrap();
+        advance_until_idle(&mut sim);
+        assert_in_formation(&sim);
+
+        let straggler = sim
+            .pawns
+            .iter_mut()
+            .find(|pawn| pawn.id == "member-3")
+            .unwrap();
+        straggler.x = 2.0;
+        straggler.y = 2.0;
+        straggler.tile_row = 2;
+        straggler.tile_col = 2;
+        let changed = sim.regroup_party();
+        assert!(changed.contains(&"member-3".to_string()));
+        advance_until_idle(&mut sim);
+        assert_in_formation(&sim);
+    }
+
+    #[test]
+    fn party_leader_must_be_a_player() {
+        let mut sim = sample_sim();
+        assert!(sim.move_party(Some("npc"), None, 12, 12, None).is_err());
+    }
+
+    /// The sample map with stairs at (13, 12) down to a cellar spanning rows 11..=14.
+    fn cellar_sim() -> ExplorationSim {
+        let stairs = |level: i32| ExplorationLevelLink {
+            level,
+            row: 13,
+            col: 12,
+            kind: "stairs".to_string(),
+        };
+        let mut ground = sample_chunk();
+        ground.tiles[13 * 16 + 12].level_link = Some(stairs(-1));
+        let mut cellar = sample_chunk();
+        cellar.id = "chunk-l-1-0-0".to_string();
+        cellar.level = -1;
+        cellar.objects.clear();
+        for (index, tile) in cellar.tiles.iter_mut().enumerate() {
+            let (row, col) = (index / 16, index % 16);
+            let open = (11..=14).contains(&row) && (2..=13).contains(&col);
+            tile.r#type = if open { "cellar-floor" } else { "rock" }.to_string();
+            tile.walkable = open;
+            tile.move_cost = if open { 1.0 } else { 0.0 };
+            tile.interior_id = None;
+            tile.door_id = None;
+        }
+        cellar.tiles[13 * 16 + 12].level_link = Some(stairs(0));
+
+        let template = sample_sim();
+        let mut sim = ExplorationSim::new(
+            template.descriptor.clone(),
+            HashMap::from([((0, 0, 0), ground), ((-1, 0, 0), cellar)]),
+            template.pawns.clone(),
+            Some("player".to_string()),
+            10,
+        );
+        sim.pawns.retain(|pawn| pawn.id == "player");
+        sim
+    }
+
+    #[test]
+    fn routes_take_stairs_between_levels() {
+        let mut sim = cellar_sim();
+        assert!(find_path(&sim, Some("player"), -1, 8, 8, 12, 3).is_none());
+
+        sim.move_pawn_to_level("player", -1, 12, 3, false).unwrap();
+        let route = &sim.pawns[0].route;
+        let landing = route.iter().position(|node| node.level == -1).unwrap();
+        assert_eq!(
+            (
+                route[landing - 1].row,
+                route[landing - 1].col,
+                route[landing - 1].level
+            ),
+            (13, 12, 0)
+        );
+        assert_eq!((route[landing].row, route[landing].col), (13, 12));
+        assert!(route[landing..].iter().all(|node| node.level == -1));
+
+        advance_until_idle(&mut sim);
+        let pawn = &sim.pawns[0];
+        assert_eq!((pawn.level, pawn.tile_row, pawn.tile_col), (-1, 12, 3));
+        assert_eq!(sim.visibility.current_level, -1);
+
+        sim.move_pawn_to_level("player", 0, 11, 9, false).unwrap();
+        advance_until_idle(&mut sim);
+        let pawn = &sim.pawns[0];
+        assert_eq!((pawn.level, pawn.tile_row, pawn.tile_col), (0, 11, 9));
+        assert_eq!(sim.visibility.current_level, 0);
+    }
+
+    #[test]
+    fn subscriptions_and_roofs_follow_the_level() {
+        let mut sim = cellar_sim();
+        match sim.subscribe_view(8, 8, 1, Some(-1)) {
+            ExplorationSessionEvent::ChunkDelta {
+                chunks,
+                removed_chunk_ids,
+                ..
+            } => {
+                assert_eq!(chunks.len(), 1);
+                assert_eq!(chunks[0].id, "chunk-l-1-0-0");
+                assert_eq!(removed_chunk_ids, vec!["chunk-0-0".to_string()]);
+            }
+            _ => panic!("expected chunk delta"),
+        }
+        // Unknown levels keep the current subscription.
+        sim.subscribe_view(8, 8, 1, Some(3));
+        assert_eq!(sim.subscribed_level, -1);
+
+        sim.pawns[0].x = 6.0;
+        sim.pawns[0].y = 6.0;
+        sim.pawns[0].tile_row = 6;
+        sim.pawns[0].tile_col = 6;
+        sim.refresh_visibility();
+        assert_eq!(
+            sim.visibility.revealed_roof_group_ids,
+            vec!["roof-a".to_string()]
+        );
+        sim.pawns[0].level = -1;
+        sim.refresh_visibility();
+        assert_eq!(sim.visibility.current_level, -1);
+        assert!(sim.visibility.revealed_roof_group_ids.is_empty());
+    }
+
+    fn large_sim(size: u32, npc_count: usize) -> ExplorationSim {
+        let chunk_size = 16;
+        let mut chunks = HashMap::new();
+        for chunk_row in 0..size / chunk_size {
+            for chunk_col in 0..size / chunk_size {
+                let mut tiles = Vec::new();
+                for local_row in 0..chunk_size {
+                    for local_col in 0..chunk_size {
+                        let row = chunk_row * chunk_size + local_row;
+                        let col = chunk_col * chunk_size + local_col;
+                        let wall =
+                            (col % 24 == 12 && row % 20 > 2) || (row * 31 + col * 17) % 23 == 0;
+                        tiles.push(ExplorationTile {
+                            r#type: if wall { "wall" } else { "grass" }.to_string(),
+                            walkable: !wall,
+                            move_cost: if wall { 0.0 } else { 1.0 },
+                            texture_url: None,
+                            is_spawn_zone: None,
+                            interior_id: None,
+                            light_level: None,
+                            blocks_light: None,
+                            door_id: None,
+                            level_link: None,
+                        });
+                    }
+                }
+                chunks.insert(
+                    (0, chunk_row, chunk_col),
+                    ExplorationChunk {
+                        id: format!("chunk-{chunk_row}-{chunk_col}"),
+                        level: 0,
+                        chunk_row,
+                        chunk_col,
+                        origin_row: chunk_row * chunk_size,
+                        origin_col: chunk_col * chunk_size,
+                        width: chunk_size,
+                        height: chunk_size,
+                        tiles,
+                        objects: Vec::new(),
+                    },
+                );
+            }
+        }
+        let template = sample_sim().pawns[1].clone();
+        let pawns = (0..npc_count)
+            .map(|index| {
+                let mut pawn = template.clone();
+                let row = (index as u32 * 37 + 5) % size;
+                let col = (index as u32 * 53 + 9) % size;
+                pawn.id = format!("npc-{index}");
+                pawn.home_interior_id = None;
+                pawn.x = col as f32;
+                pawn.y = row as f32;
+                pawn.tile_row = row as i32;
+                pawn.tile_col = col as i32;
+                pawn
+            })
+            .collect();
+        let descriptor = ExplorationManifestDescriptor {
+            id: "large".to_string(),
+            world_id: "world".to_string(),
+            location_id: "large".to_string(),
+            name: "Large".to_string(),
+            width: size,
+            height: size,
+            chunk_size,
+            version: 3,
+            revision: 1,
+            render_mode: "isometric".to_string(),
+            ambient_light: 0.76,
+            spawn: ExplorationSpawnPoint { row: 1, col: 1 },
+            metadata: None,
+            levels: Vec::new(),
+        };
+        ExplorationSim::new(descriptor, chunks, pawns, None, 10)
+    }
+
+    #[test]
+    fn long_routes_cross_chunks_around_walls() {
+        let sim = large_sim(64, 0);
+        let path = find_path(&sim, None, 0, 2, 2, 60, 61).expect("route across the map");
+        let last = path.last().unwrap();
+        assert_eq!((last.y, last.x), (60, 61));
+        assert!(path
+            .iter()
+            .all(|step| get_cell_move_cost(&sim, 0, step.y, step.x) > 0.0));
+    }
+
+    /// Stores the tiles of `full` as a chunked location, after `edit` has touched each one.
+    fn store_large_map(
+        full: &ExplorationSim,
+        name: &str,
+        edit: impl Fn(u32, u32, &mut ExplorationTile),
+    ) -> std::path::PathBuf {
+        let size = full.descriptor.width;
+        let mut tiles = Vec::new();
+        for row in 0..size {
+            for col in 0..size {
+                let mut tile = full.chunks[&(0, row / 16, col / 16)].tiles
+                    [((row % 16) * 16 + col % 16) as usize]
+                    .clone();
+                edit(row, col, &mut tile);
+                tiles.push(tile);
+            }
+        }
+        let map = ExplorationMap {
+            id: "large".to_string(),
+            width: size,
+            height: size,
+            tiles,
+            pawns: Vec::new(),
+            objects: Vec::new(),
+            name: None,
+            fog_of_war: None,
+            ambient_light: None,
+            version: None,
+            render_mode: None,
+            metadata: None,
+            levels: Vec::new(),
+            triggers: Vec::new(),
+        };
+        let dir = std::env::temp_dir().join(format!("ashtrail-{name}-{}", now_ms()));
+        let manifest = manifest_path(&dir, "world", "large");
+        std::fs::create_dir_all(manifest.parent().unwrap()).unwrap();
+        write_chunked_location(&dir, "world", "large", &map).unwrap();
+        dir
+    }
+
+    #[test]
+    fn chunks_load_when_a_route_or_view_reaches_them() {
+        let full = large_sim(64, 1);
+        let dir = store_large_map(&full, "lazy-chunks", |_, _, _| {});
+
+        let start = HashMap::from([((0, 0, 0), full.chunks[&(0, 0, 0)].clone())]);
+        let mut sim =
+            ExplorationSim::new(full.descriptor.clone(), start, full.pawns.clone(), None, 10);
+        sim.set_chunk_source(ChunkSource::open(&dir, "world", "large").unwrap());
+        // The pawn stands in chunk (0, 0), so only its neighbours are read.
+        assert_eq!(sim.chunks.len(), 4);
+
+        let ExplorationSessionEvent::ChunkDelta { chunks, .. } = sim.subscribe_view(60, 2, 1, None)
+        else {
+            panic!("expected a chunk delta");
+        };
+        assert!(chunks
+            .iter()
+            .any(|chunk| (chunk.chunk_row, chunk.chunk_col) == (3, 0)));
+        assert_eq!(sim.chunks.len(), 8);
+
+        sim.move_pawn("npc-0", 60, 61, false)
+            .expect("route into unread chunks");
+        assert!(sim.chunks.contains_key(&(0, 3, 3)));
+        assert!(sim.pawns[0].moving);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn interiors_outside_the_view_are_read_before_searching() {
+        let full = large_sim(64, 1);
+        let dir = store_large_map(&full, "lazy-interiors", |row, col, tile| {
+            if (50..54).contains(&row) && (50..54).contains(&col) {
+                tile.walkable = true;
+                tile.move_cost = 1.0;
+                tile.interior_id = Some("hut".to_string());
+                if (row, col) == (50, 52) {
+                    tile.door_id = Some("hut-door".to_string());
+                }
+            }
+        });
+
+        let start = HashMap::from([((0, 0, 0), full.chunks[&(0, 0, 0)].clone())]);
+        let mut sim =
+            ExplorationSim::new(full.descriptor.clone(), start, full.pawns.clone(), None, 10);
+        sim.set_chunk_source(ChunkSource::open(&dir, "world", "large").unwrap());
+        assert_eq!(first_tile_in_interior(&sim, 0, "hut"), None);
+
+        sim.pawns[0].home_interior_id = Some("hut".to_string());
+        sim.pawns[0].next_decision_at_tick = None;
+        sim.advance(0.1);
+        assert!(sim.chunks.contains_key(&(0, 3, 3)));
+        assert_eq!(first_tile_in_interior(&sim, 0, "hut"), Some((50, 50)));
+        assert!(collect_interior_doorway_candidates(&sim, 0, "hut")
+            .iter()
+            .any(|(row, col, _)| (*row, *col) == (49, 52)));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    #[ignore = "benchmark; run with --ignored --nocapture"]
+    fn benchmark_256_map_with_100_npcs() {
+        let started = std::time::Instant::now();
+        let mut sim = large_sim(256, 100);
+        let build_elapsed = started.elapsed();
+
+        let started = std::time::Instant::now();
+        let ticks = 100;
+        for _ in 0..ticks {
+            for pawn in &mut sim.pawns {
+                pawn.next_decision_at_tick = None;
+                pawn.moving = false;
+            }
+            sim.advance(0.1);
+        }
+        let tick_elapsed = started.elapsed();
+        println!(
+            "256x256 map, 100 NPCs: build {build_elapsed:?}, {ticks} re-planning ticks {tick_elapsed:?} ({:?}/tick)",
+            tick_elapsed / ticks
+        );
+    }
+
     #[test]
     fn subscription_returns_expected_chunks() {
         let mut sim = sample_sim();
-        let event = sim.subscribe_view(8, 8, 1);
+        let event = sim.subscribe_view(8, 8, 1, None);
         match event {
             ExplorationSessionEvent::ChunkDelta { chunks, .. } => assert_eq!(chunks.len(), 1),
             _ => panic!("expected chunk delta"),
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use super::{
+    manifest::session_state_path,
+    types::{
+        ExplorationHarvestNodeState, ExplorationManifestDescriptor, ExplorationParty,
+        ExplorationPawn, ExplorationVisibilityState,
+    },
+};
+
+const EXPLORATION_STATE_VERSION: u32 = 1;
+
+/// Saved runtime state for one location. Only valid for the manifest it was saved against;
+/// regenerating the map bumps the manifest revision and invalidates it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationSaveState {
+    pub version: u32,
+    pub manifest_version: u32,
+    #[serde(default)]
+    pub manifest_revision: u64,
+    pub saved_at_ms: u64,
+    pub tick: u64,
+    pub pawns: Vec<ExplorationPawn>,
+    #[serde(default)]
+    pub selected_pawn_id: Option<String>,
+    pub visibility: ExplorationVisibilityState,
+    #[serde(default)]
+    pub harvest_nodes: Vec<ExplorationHarvestNodeState>,
+    #[serde(default)]
+    pub party: Option<ExplorationParty>,
+    /// Triggers that have fired at least once.
+    #[serde(default)]
+    pub fired_trigger_ids: Vec<String>,
+    /// Triggers whose conditions held on the last tick.
+    #[serde(default)]
+    pub active_trigger_ids: Vec<String>,
+    #[serde(default)]
+    pub quest_flags: Vec<String>,
+}
+
+impl ExplorationSaveState {
+    pub fn matches(&self, descriptor: &ExplorationManifestDescriptor) -> bool {
+        self.version == EXPLORATION_STATE_VERSION
+            && self.manifest_version == descriptor.version
+            && self.manifest_revision == descriptor.revision
+    }
+}
+
+pub fn new_save_state(
+    descriptor: &ExplorationManifestDescriptor,
+    saved_at_ms: u64,
+    tick: u64,
+) -> ExplorationSaveState {
+    ExplorationSaveState {
+        version: EXPLORATION_STATE_VERSION,
+        manifest_version: descriptor.version,
+        manifest_revision: descriptor.revision,
+        saved_at_ms,
+        tick,
+        pawns: Vec::new(),
+        selected_pawn_id: None,
+        visibility: ExplorationVisibilityState {
+            revealed_interior_id: None,
+            revealed_roof_group_ids: Vec::new(),
+            opened_door_ids: Vec::new(),
+            current_level: 0,
+            locked_door_ids: Vec::new(),
+        },
+        harvest_nodes: Vec::new(),
+        party: None,
+        fired_trigger_ids: Vec::new(),
+        active_trigger_ids: Vec::new(),
+        quest_flags: Vec::new(),
+    }
+}
+
+/// Loads the saved state for a location, discarding it when it was written against another
+/// manifest or cannot be parsed.
+pub fn load_session_state(
+    planets_dir: &Path,
+    descriptor: &ExplorationManifestDescriptor,
+) -> Result<Option<ExplorationSaveState>, String> {
+    let path = session_state_path(planets_dir, &descriptor.world_id, &descriptor.location_id);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|error| format!("Failed to read exploration session state: {error}"))?;
+    let saved = serde_json::from_str::<ExplorationSaveState>(&content)
+        .ok()
+        .filter(|saved| saved.matches(descriptor));
+    if saved.is_none() {
+        fs::remove_file(&path).map_err(|error| {
+            format!("Failed to discard stale exploration session state: {error}")
+        })?;
+    }
+    Ok(saved)
+}
+
+pub fn save_session_state(
+    planets_dir: &Path,
+    world_id: &str,
+    location_id: &str,
+    saved: &ExplorationSaveState,
+) -> Result<(), String> {
+    let path = session_state_path(planets_dir, world_id, location_id);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("Failed to create exploration location directory: {error}"))?;
+    }
+    let content = serde_json::to_string_pretty(saved)
+        .map_err(|error| format!("Failed to serialize exploration session state: {error}"))?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, content)
+        .map_err(|error| format!("Failed to write exploration session state: {error}"))?;
+    fs::rename(&temp_path, &path)
+        .map_err(|error| format!("Failed to replace exploration session state: {error}"))
+}
@@ -0,0 +1,197 @@
+use serde_json::Value;
+
+use super::{
+    types::{
+        ExplorationAwareness, ExplorationAwarenessState, ExplorationMovementMode, ExplorationPawn,
+        ExplorationPawnStats, ExplorationTile,
+    },
+    wildlife::DayPhase,
+};
+
+/// Sneaking pawns move at this fraction of their normal speed.
+pub const SNEAK_SPEED_FACTOR: f32 = 0.5;
+const SNEAK_NOISE_FACTOR: f32 = 0.35;
+const SUSPICIOUS_THRESHOLD: f32 = 0.3;
+/// Alert observers stay alert until the meter drops below this.
+const ALERT_RELEASE_THRESHOLD: f32 = 0.5;
+const AWARENESS_DECAY_PER_SECOND: f32 = 0.08;
+
+fn terrain_noise_factor(tile_type: &str) -> f32 {
+    match tile_type {
+        "gravel" | "rubble" | "scree" | "rock" => 1.4,
+        "wood" | "floor" | "plank" | "bridge" => 1.25,
+        "water" | "shallow_water" | "marsh" | "swamp" => 1.5,
+        "snow" | "sand" | "moss" | "carpet" => 0.7,
+        "grass" | "meadow" | "dirt" | "path" => 0.9,
+        _ => 1.0,
+    }
+}
+
+/// Noise from equipped armor, read from the `armor` effects of embedded items.
+pub fn armor_noise(equipped: &Value) -> f32 {
+    let Some(slots) = equipped.as_object() else {
+        return 0.0;
+    };
+    let armor = slots
+        .values()
+        .filter(|item| item.get("category").and_then(Value::as_str) == Some("armor"))
+        .filter_map(|item| item.get("effects").and_then(Value::as_array))
+        .flatten()
+        .filter(|effect| effect.get("target").and_then(Value::as_str) == Some("armor"))
+        .filter_map(|effect| {
+            let value = effect.get("value")?;
+            value
+                .as_f64()
+                .or_else(|| value.as_str().and_then(|raw| raw.parse::<f64>().ok()))
+        })
+        .sum::<f64>();
+    (armor as f32 / 20.0).clamp(0.0, 1.0)
+}
+
+/// Loudness of a pawn this tick, roughly 0 for a still sneaker to above 1 for a running
+/// armored pawn on gravel.
+pub fn noise_emission(pawn: &ExplorationPawn, tile: Option<&ExplorationTile>) -> f32 {
+    let terrain = tile
+        .map(|tile| terrain_noise_factor(&tile.r#type))
+        .unwrap_or(1.0);
+    let noise = if pawn.moving {
+        (0.3 + pawn.move_speed_tiles_per_second.max(0.0) * 0.08) * terrain + pawn.armor_noise
+    } else {
+        0.05 + pawn.armor_noise * 0.2
+    };
+    match pawn.movement_mode {
+        ExplorationMovementMode::Walk => noise,
+        ExplorationMovementMode::Sneak => noise * SNEAK_NOISE_FACTOR,
+    }
+}
+
+/// Wildlife use their fauna perception; everyone else derives it from wisdom.
+pub fn perception_score(pawn: &ExplorationPawn) -> i32 {
+    match (&pawn.wildlife, &pawn.stats) {
+        (Some(wildlife), _) => wildlife.perception,
+        (None, Some(stats)) => stats.wisdom * 10,
+        (None, None) => ExplorationPawnStats::default().wisdom * 10,
+    }
+    .clamp(0, 100)
+}
+
+/// Light on a tile. Outdoor tiles dim with the time of day; interiors keep their own lighting.
+pub fn light_at(tile: Option<&ExplorationTile>, ambient_light: f32, phase: DayPhase) -> f32 {
+    let base = tile
+        .and_then(|tile| tile.light_level)
+        .unwrap_or(ambient_light);
+    let indoors = tile.is_some_and(|tile| tile.interior_id.is_some());
+    let phase_factor = match phase {
+        _ if indoors => 1.0,
+        DayPhase::Day => 1.0,
+        DayPhase::Dawn | DayPhase::Dusk => 0.6,
+        DayPhase::Night => 0.3,
+    };
+    (base * phase_factor).clamp(0.0, 1.0)
+}
+
+/// Awareness an observer gains per second of hearing and seeing a target. Agility stands in
+/// for the target's stealth.
+pub fn detection_gain(
+    perception: i32,
+    noise: f32,
+    light: f32,
+    distance: f32,
+    target: &ExplorationPawnStats,
+) -> f32 {
+    let acuity = 2.0 + perception.clamp(0, 100) as f32 / 10.0;
+    let hearing_range = noise * (acuity + 2.0);
+    // Darkness shortens sight but never blinds an observer to what is right next to it.
+    let sight_range = acuity * (0.25 + light * 0.75);
+    let heard = if hearing_range > 0.0 {
+        (1.0 - distance / hearing_range).max(0.0)
+    } else {
+        0.0
+    };
+    let seen = if sight_range > 0.0 {
+        (1.0 - distance / sight_range).max(0.0)
+    } else {
+        0.0
+    };
+    let evasion = (1.0 - target.agility as f32 * 0.03).clamp(0.4, 1.0);
+    (heard * 0.6 + seen * 0.9) * evasion
+}
+
+/// Advances an observer's awareness meter. `strongest` is the most noticeable target this tick
+/// and its gain per second. Returns `None` once the observer has fully calmed down.
+pub fn step_awareness(
+    previous: Option<&ExplorationAwareness>,
+    strongest: Option<(&str, f32)>,
+    delta_seconds: f32,
+) -> Option<ExplorationAwareness> {
+    let mut meter = previous.map(|awareness| awareness.meter).unwrap_or(0.0);
+    let mut target_pawn_id = previous.and_then(|awareness| awareness.target_pawn_id.clone());
+    match strongest {
+        Some((pawn_id, gain)) if gain > 0.0 => {
+            meter += gain * delta_seconds;
+            target_pawn_id = Some(pawn_id.to_string());
+        }
+        _ => meter -= AWARENESS_DECAY_PER_SECOND * delta_seconds,
+    }
+    let meter = meter.clamp(0.0, 1.0);
+    let was_alert =
+        previous.is_some_and(|awareness| awareness.state == ExplorationAwarenessState::Alert);
+    let state = if meter >= 1.0 || (was_alert && meter >= ALERT_RELEASE_THRESHOLD) {
+        ExplorationAwarenessState::Alert
+    } else if meter >= SUSPICIOUS_THRESHOLD {
+        ExplorationAwarenessState::Suspicious
+    } else {
+        ExplorationAwarenessState::Unaware
+    };
+    if meter <= 0.0 {
+        return None;
+    }
+    Some(ExplorationAwareness {
+        state,
+        target_pawn_id,
+        meter,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sneaking_in_the_dark_is_harder_to_notice() {
+        let stats = ExplorationPawnStats::default();
+        let loud = detection_gain(50, 1.0, 1.0, 5.0, &stats);
+        let quiet = detection_gain(50, 0.35, 0.3, 5.0, &stats);
+        assert!(loud > quiet);
+        assert_eq!(detection_gain(50, 0.05, 0.0, 5.0, &stats), 0.0);
+    }
+
+    #[test]
+    fn awareness_escalates_and_holds_alert_with_hysteresis() {
+        let mut awareness = None;
+        for _ in 0..10 {
+            awareness = step_awareness(awareness.as_ref(), Some(("player", 0.5)), 0.1);
+        }
+        assert_eq!(
+            awareness.as_ref().map(|entry| entry.state),
+            Some(ExplorationAwarenessState::Suspicious)
+        );
+        for _ in 0..20 {
+            awareness = step_awareness(awareness.as_ref(), Some(("player", 0.5)), 0.1);
+        }
+        assert_eq!(
+            awareness.as_ref().map(|entry| entry.state),
+            Some(ExplorationAwarenessState::Alert)
+        );
+        awareness = step_awareness(awareness.as_ref(), None, 4.0);
+        assert_eq!(
+            awareness.as_ref().map(|entry| entry.state),
+            Some(ExplorationAwarenessState::Alert)
+        );
+        awareness = step_awareness(awareness.as_ref(), None, 4.0);
+        assert_eq!(
+            awareness.as_ref().map(|entry| entry.state),
+            Some(ExplorationAwarenessState::Suspicious)
+        );
+    }
+}
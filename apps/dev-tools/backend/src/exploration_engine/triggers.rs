@@ -0,0 +1,145 @@
+use super::types::{
+    ExplorationPawn, ExplorationTrigger, ExplorationTriggerArea, ExplorationTriggerCondition,
+};
+use super::wildlife::DayPhase;
+
+pub fn area_contains(area: &ExplorationTriggerArea, row: i32, col: i32) -> bool {
+    let (Ok(row), Ok(col)) = (u32::try_from(row), u32::try_from(col)) else {
+        return false;
+    };
+    match area {
+        ExplorationTriggerArea::Rect {
+            row: top,
+            col: left,
+            width,
+            height,
+        } => row >= *top && row < top + height && col >= *left && col < left + width,
+        ExplorationTriggerArea::Tiles { tiles } => tiles.contains(&[row, col]),
+    }
+}
+
+/// Pawns standing inside the trigger's area on its level, in pawn order.
+pub fn pawns_in_area<'a>(
+    trigger: &'a ExplorationTrigger,
+    pawns: &'a [ExplorationPawn],
+) -> impl Iterator<Item = &'a ExplorationPawn> + 'a {
+    pawns.iter().filter(move |pawn| {
+        pawn.level == trigger.level
+            && trigger
+                .area
+                .as_ref()
+                .is_some_and(|area| area_contains(area, pawn.tile_row, pawn.tile_col))
+    })
+}
+
+/// Whether every condition of the trigger holds; a trigger without conditions never does.
+pub fn trigger_conditions_hold(
+    trigger: &ExplorationTrigger,
+    pawns: &[ExplorationPawn],
+    phase: DayPhase,
+    quest_flags: &[String],
+) -> bool {
+    !trigger.conditions.is_empty()
+        && trigger.conditions.iter().all(|condition| match condition {
+            ExplorationTriggerCondition::PartyEnters => {
+                pawns_in_area(trigger, pawns).any(|pawn| pawn.faction_id == "player")
+            }
+            ExplorationTriggerCondition::PawnEnters { pawn_id } => {
+                pawns_in_area(trigger, pawns).any(|pawn| &pawn.id == pawn_id)
+            }
+            ExplorationTriggerCondition::TimeOfDay { phases } => phases.contains(&phase),
+            ExplorationTriggerCondition::FlagSet { flag } => quest_flags.contains(flag),
+            ExplorationTriggerCondition::FlagUnset { flag } => !quest_flags.contains(flag),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exploration_engine::sim::tests::sample_sim;
+    use crate::exploration_engine::types::ExplorationTriggerAction;
+
+    fn trigger(conditions: Vec<ExplorationTriggerCondition>) -> ExplorationTrigger {
+        ExplorationTrigger {
+            id: "porch".to_string(),
+            level: 0,
+            area: Some(ExplorationTriggerArea::Tiles {
+                tiles: vec![[8, 8]],
+            }),
+            conditions,
+            actions: Vec::new(),
+            repeat: false,
+        }
+    }
+
+    #[test]
+    fn each_condition_holds_when_met() {
+        let pawns = sample_sim().pawns;
+        let flags = vec!["met-guard".to_string()];
+        let holds = |condition: ExplorationTriggerCondition| {
+            trigger_conditions_hold(&trigger(vec![condition]), &pawns, DayPhase::Dusk, &flags)
+        };
+        assert!(holds(ExplorationTriggerCondition::PartyEnters));
+        assert!(holds(ExplorationTriggerCondition::PawnEnters {
+            pawn_id: "player".to_string()
+        }));
+        assert!(!holds(ExplorationTriggerCondition::PawnEnters {
+            pawn_id: "npc".to_string()
+        }));
+        assert!(holds(ExplorationTriggerCondition::TimeOfDay {
+            phases: vec![DayPhase::Dawn, DayPhase::Dusk]
+        }));
+        assert!(!holds(ExplorationTriggerCondition::TimeOfDay {
+            phases: vec![DayPhase::Night]
+        }));
+        assert!(holds(ExplorationTriggerCondition::FlagSet {
+            flag: "met-guard".to_string()
+        }));
+        assert!(holds(ExplorationTriggerCondition::FlagUnset {
+            flag: "alarm".to_string()
+        }));
+        assert!(!holds(ExplorationTriggerCondition::FlagUnset {
+            flag: "met-guard".to_string()
+        }));
+    }
+
+    #[test]
+    fn area_and_flag_conditions_combine() {
+        let trigger = ExplorationTrigger {
+            id: "gate".to_string(),
+            level: 0,
+            area: Some(ExplorationTriggerArea::Rect {
+                row: 4,
+                col: 4,
+                width: 2,
+                height: 2,
+            }),
+            conditions: vec![
+                ExplorationTriggerCondition::PartyEnters,
+                ExplorationTriggerCondition::FlagSet {
+                    flag: "met-guard".to_string(),
+                },
+            ],
+            actions: vec![ExplorationTriggerAction::Narration {
+                text: "The gate creaks.".to_string(),
+            }],
+            repeat: false,
+        };
+        let flags = vec!["met-guard".to_string()];
+        assert!(area_contains(trigger.area.as_ref().unwrap(), 5, 5));
+        assert!(!area_contains(trigger.area.as_ref().unwrap(), 6, 5));
+        assert!(area_contains(
+            &ExplorationTriggerArea::Tiles {
+                tiles: vec![[2, 3]]
+            },
+            2,
+            3
+        ));
+        assert!(!trigger_conditions_hold(
+            &trigger,
+            &[],
+            DayPhase::Day,
+            &flags
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::wildlife::DayPhase;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExplorationTile {
@@ -18,6 +20,18 @@ pub struct ExplorationTile {
     pub blocks_light: Option<bool>,
     #[serde(default)]
     pub door_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level_link: Option<ExplorationLevelLink>,
+}
+
+/// Stairs or ladder on a tile: stepping onto it moves the pawn to `row`/`col` on `level`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationLevelLink {
+    pub level: i32,
+    pub row: u32,
+    pub col: u32,
+    pub kind: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +42,8 @@ pub struct ExplorationPawn {
     pub x: f32,
     pub y: f32,
     #[serde(default)]
+    pub level: i32,
+    #[serde(default)]
     pub tile_row: i32,
     #[serde(default)]
     pub tile_col: i32,
@@ -70,6 +86,139 @@ pub struct ExplorationPawn {
     pub current_intent: Option<String>,
     #[serde(default)]
     pub next_decision_at_tick: Option<u64>,
+    #[serde(default)]
+    pub stats: Option<ExplorationPawnStats>,
+    #[serde(default)]
+    pub conditions: Vec<ExplorationPawnCondition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wildlife: Option<ExplorationWildlifeState>,
+    #[serde(default)]
+    pub movement_mode: ExplorationMovementMode,
+    /// Extra noise from worn armor, 0 for none up to 1 for the heaviest.
+    #[serde(default)]
+    pub armor_noise: f32,
+    /// How aware this NPC or creature is of the party.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub awareness: Option<ExplorationAwareness>,
+    /// Items the pawn carries, such as harvested flora.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inventory: Vec<ExplorationItemStack>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationPawnStats {
+    pub strength: i32,
+    pub agility: i32,
+    pub intelligence: i32,
+    pub wisdom: i32,
+    pub endurance: i32,
+    pub charisma: i32,
+}
+
+impl Default for ExplorationPawnStats {
+    fn default() -> Self {
+        Self {
+            strength: 5,
+            agility: 5,
+            intelligence: 5,
+            wisdom: 5,
+            endurance: 5,
+            charisma: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationPawnCondition {
+    pub id: String,
+    pub label: String,
+    pub severity: i32,
+    pub expires_at_tick: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplorationWildlifeBehavior {
+    Passive,
+    Territorial,
+    Predator,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationWildlifeState {
+    pub fauna_id: String,
+    pub pack_id: String,
+    pub behavior: ExplorationWildlifeBehavior,
+    pub perception: i32,
+    pub stealth: i32,
+    pub danger_level: i32,
+    pub home_row: i32,
+    pub home_col: i32,
+    #[serde(default)]
+    pub target_pawn_id: Option<String>,
+    #[serde(default)]
+    pub warned_at_tick: Option<u64>,
+    #[serde(default)]
+    pub concealed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplorationMovementMode {
+    #[default]
+    Walk,
+    /// Slower, but much quieter.
+    Sneak,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplorationAwarenessState {
+    Unaware,
+    Suspicious,
+    Alert,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationAwareness {
+    pub state: ExplorationAwarenessState,
+    #[serde(default)]
+    pub target_pawn_id: Option<String>,
+    pub meter: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplorationFormation {
+    Line,
+    #[default]
+    Column,
+    Wedge,
+    Loose,
+}
+
+/// Leader tile `(level, row, col)` and the heading formation slots were assigned with.
+pub type ExplorationPartyStep = ((i32, i32, i32), (f32, f32));
+
+/// Player pawns moving together: followers hold formation slots around the leader.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationParty {
+    pub leader_id: String,
+    pub formation: ExplorationFormation,
+    /// Pawns holding a slot. Moving a follower on its own takes it out of the formation.
+    pub member_ids: Vec<String>,
+    /// True while a MoveParty order is under way; followers re-take their slots each time the
+    /// leader steps onto a new tile.
+    #[serde(default)]
+    pub following: bool,
+    /// Leader step the slots were last assigned from.
+    #[serde(skip)]
+    pub last_step: Option<ExplorationPartyStep>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,6 +251,23 @@ pub struct ExplorationObject {
     pub height_tiles: Option<u32>,
     #[serde(default)]
     pub blocks_light: Option<bool>,
+    #[serde(default)]
+    pub flora_id: Option<String>,
+    #[serde(default)]
+    pub level: i32,
+}
+
+/// Extra floor of a map, e.g. a basement (`level < 0`) or an upper storey. Same size as the
+/// ground level, whose tiles and objects stay on the map itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationMapLevel {
+    pub level: i32,
+    #[serde(default)]
+    pub name: Option<String>,
+    pub tiles: Vec<ExplorationTile>,
+    #[serde(default)]
+    pub objects: Vec<ExplorationObject>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -125,6 +291,103 @@ pub struct ExplorationMap {
     pub render_mode: Option<String>,
     #[serde(default)]
     pub metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    pub levels: Vec<ExplorationMapLevel>,
+    #[serde(default)]
+    pub triggers: Vec<ExplorationTrigger>,
+}
+
+/// Scripted map event. A trigger fires when all of its conditions start to hold; `repeat`
+/// triggers fire again each time that happens, the others at most once per save.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationTrigger {
+    pub id: String,
+    #[serde(default)]
+    pub level: i32,
+    /// Region the enter conditions watch; without one they never hold.
+    #[serde(default)]
+    pub area: Option<ExplorationTriggerArea>,
+    #[serde(default)]
+    pub conditions: Vec<ExplorationTriggerCondition>,
+    pub actions: Vec<ExplorationTriggerAction>,
+    #[serde(default)]
+    pub repeat: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExplorationTriggerArea {
+    Rect {
+        row: u32,
+        col: u32,
+        width: u32,
+        height: u32,
+    },
+    /// Explicit `[row, col]` cells.
+    Tiles { tiles: Vec<[u32; 2]> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExplorationTriggerCondition {
+    /// Any player pawn stands in the area.
+    PartyEnters,
+    #[serde(rename_all = "camelCase")]
+    PawnEnters {
+        pawn_id: String,
+    },
+    TimeOfDay {
+        phases: Vec<DayPhase>,
+    },
+    FlagSet {
+        flag: String,
+    },
+    FlagUnset {
+        flag: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExplorationTriggerAction {
+    Narration {
+        text: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    StartDialogue {
+        dialogue_id: String,
+        #[serde(default)]
+        pawn_id: Option<String>,
+    },
+    SpawnPawns {
+        pawns: Vec<ExplorationPawn>,
+    },
+    #[serde(rename_all = "camelCase")]
+    OpenDoors {
+        door_ids: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    LockDoors {
+        door_ids: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    StartCombat {
+        encounter_id: String,
+        #[serde(default)]
+        pawn_ids: Vec<String>,
+    },
+    SetQuestFlag {
+        flag: String,
+        #[serde(default)]
+        clear: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    Interaction {
+        label: String,
+        #[serde(default)]
+        object_id: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,17 +408,31 @@ pub struct ExplorationManifestDescriptor {
     pub height: u32,
     pub chunk_size: u32,
     pub version: u32,
+    #[serde(default)]
+    pub revision: u64,
     pub render_mode: String,
     pub ambient_light: f32,
     pub spawn: ExplorationSpawnPoint,
     #[serde(default)]
     pub metadata: Option<serde_json::Value>,
+    /// Every floor of the location, ground (`0`) included. Empty for single-level maps.
+    #[serde(default)]
+    pub levels: Vec<ExplorationLevel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationLevel {
+    pub level: i32,
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExplorationChunk {
     pub id: String,
+    #[serde(default)]
+    pub level: i32,
     pub chunk_row: u32,
     pub chunk_col: u32,
     pub origin_row: u32,
@@ -166,19 +443,18 @@ pub struct ExplorationChunk {
     pub objects: Vec<ExplorationObject>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ExplorationChunkSync {
-    pub descriptor_id: String,
-    pub chunks: Vec<ExplorationChunk>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ExplorationVisibilityState {
     pub revealed_interior_id: Option<String>,
     pub revealed_roof_group_ids: Vec<String>,
     pub opened_door_ids: Vec<String>,
+    /// Floor of the selected pawn; only this floor is revealed.
+    #[serde(default)]
+    pub current_level: i32,
+    /// Doors locked by triggers; they stay shut until a trigger opens them.
+    #[serde(default)]
+    pub locked_door_ids: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,6 +462,8 @@ pub struct ExplorationVisibilityState {
 pub struct PathNode {
     pub x: i32,
     pub y: i32,
+    #[serde(default)]
+    pub level: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -193,6 +471,27 @@ pub struct PathNode {
 pub struct RouteNode {
     pub row: i32,
     pub col: i32,
+    #[serde(default)]
+    pub level: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationHarvestNodeState {
+    pub object_id: String,
+    pub flora_id: String,
+    pub regrows_at_ms: u64,
+    #[serde(default)]
+    pub harvest_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationItemStack {
+    pub item_id: String,
+    pub name: String,
+    pub category: String,
+    pub quantity: u32,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -205,18 +504,81 @@ pub struct ExplorationSessionSnapshot {
     pub visibility: ExplorationVisibilityState,
     pub tick: u64,
     pub connection_state: String,
+    pub depleted_nodes: Vec<ExplorationHarvestNodeState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub party: Option<ExplorationParty>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExplorationSessionConfig {
     #[serde(default)]
     pub session_name: Option<String>,
     #[serde(default)]
     pub tick_rate_hz: Option<u32>,
+    /// 1 sends full pawns in every delta; 2 sends field patches and motion keyframes.
+    #[serde(default)]
+    pub protocol_version: Option<u32>,
+    #[serde(default)]
+    pub encoding: Option<ExplorationWireEncoding>,
+    /// Seed for NPC and wildlife decisions; derived from the location and session name when
+    /// omitted.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Framing of server events: JSON text frames or MessagePack binary frames.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplorationWireEncoding {
+    #[default]
+    Json,
+    Msgpack,
+}
+
+/// Straight-line movement the client interpolates between `startTick` and `arriveTick`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationMotionKeyframe {
+    pub from_x: f32,
+    pub from_y: f32,
+    pub to_x: f32,
+    pub to_y: f32,
+    pub start_tick: u64,
+    pub arrive_tick: u64,
+}
+
+/// Fields of a known pawn that changed since the client last heard about it. Nullable fields
+/// are sent as `null` when cleared.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationPawnPatch {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moving: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facing: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movement_mode: Option<ExplorationMovementMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_intent: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<ExplorationPawnCondition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub awareness: Option<Option<ExplorationAwareness>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inventory: Option<Vec<ExplorationItemStack>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyframe: Option<ExplorationMotionKeyframe>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ExplorationClientAction {
     #[serde(rename_all = "camelCase")]
@@ -234,12 +596,27 @@ pub enum ExplorationClientAction {
         center_row: u32,
         center_col: u32,
         radius: u32,
+        #[serde(default)]
+        level: Option<i32>,
     },
     #[serde(rename_all = "camelCase")]
     MoveTo {
         pawn_id: String,
         target_row: u32,
         target_col: u32,
+        #[serde(default)]
+        level: Option<i32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    MoveParty {
+        #[serde(default)]
+        leader_id: Option<String>,
+        target_row: u32,
+        target_col: u32,
+        #[serde(default)]
+        level: Option<i32>,
+        #[serde(default)]
+        formation: Option<ExplorationFormation>,
     },
     #[serde(rename_all = "camelCase")]
     SetSelectedPawn {
@@ -257,15 +634,56 @@ pub enum ExplorationClientAction {
         #[serde(default)]
         actor_id: Option<String>,
     },
+    #[serde(rename_all = "camelCase")]
+    Harvest {
+        object_id: String,
+        #[serde(default)]
+        actor_id: Option<String>,
+    },
+    /// Switches a pawn, or the whole party when `pawn_id` is omitted, between walking and
+    /// sneaking.
+    #[serde(rename_all = "camelCase")]
+    SetMovementMode {
+        #[serde(default)]
+        pawn_id: Option<String>,
+        mode: ExplorationMovementMode,
+    },
+    /// Replaces the session's quest flags, e.g. with the active quest run's flags.
+    SetQuestFlags {
+        flags: Vec<String>,
+    },
     Ping,
 }
 
+/// A client action as applied to the sim, with the tick it landed on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationInputEntry {
+    pub tick: u64,
+    /// Wall-clock time the action arrived; harvest regrowth is timed from it.
+    pub at_ms: u64,
+    pub action: ExplorationClientAction,
+}
+
+/// Everything needed to replay a session from its starting state: the seed, the clock and the
+/// client actions in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplorationInputLog {
+    pub seed: u64,
+    pub start_tick: u64,
+    pub day_start_ms: u64,
+    pub tick_rate_hz: u64,
+    pub entries: Vec<ExplorationInputEntry>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ExplorationSessionEvent {
     #[serde(rename_all = "camelCase")]
     SessionReady {
-        state: ExplorationSessionSnapshot,
+        state: Box<ExplorationSessionSnapshot>,
+        protocol_version: u32,
     },
     #[serde(rename_all = "camelCase")]
     ChunkDelta {
@@ -274,10 +692,6 @@ pub enum ExplorationSessionEvent {
         removed_chunk_ids: Vec<String>,
     },
     #[serde(rename_all = "camelCase")]
-    ChunkSync {
-        sync: ExplorationChunkSync,
-    },
-    #[serde(rename_all = "camelCase")]
     PawnDelta {
         pawns: Vec<ExplorationPawn>,
         removed_pawn_ids: Vec<String>,
@@ -286,13 +700,21 @@ pub enum ExplorationSessionEvent {
         tick: u64,
         connection_state: String,
     },
+    /// Protocol 2 replacement for `PawnDelta`: pawns entering the client's view in full, field
+    /// patches for known pawns and ids of pawns that left the view.
     #[serde(rename_all = "camelCase")]
-    PawnSync {
-        pawns: Vec<ExplorationPawn>,
-        selected_pawn_id: Option<String>,
-        visibility: ExplorationVisibilityState,
+    PawnPatch {
         tick: u64,
-        connection_state: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        added: Vec<ExplorationPawn>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        patches: Vec<ExplorationPawnPatch>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        removed_pawn_ids: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        selected_pawn_id: Option<Option<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        visibility: Option<ExplorationVisibilityState>,
     },
     #[serde(rename_all = "camelCase")]
     Interaction {
@@ -307,6 +729,55 @@ pub enum ExplorationSessionEvent {
         actor_id: Option<String>,
     },
     #[serde(rename_all = "camelCase")]
+    Harvest {
+        label: String,
+        object_id: String,
+        flora_id: String,
+        actor_id: String,
+        success: bool,
+        items: Vec<ExplorationItemStack>,
+        conditions: Vec<ExplorationPawnCondition>,
+        regrows_at_ms: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Wildlife {
+        label: String,
+        pawn_id: String,
+        fauna_id: String,
+        intent: String,
+        #[serde(default)]
+        target_pawn_id: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Awareness {
+        pawn_id: String,
+        #[serde(default)]
+        target_pawn_id: Option<String>,
+        state: ExplorationAwarenessState,
+    },
+    #[serde(rename_all = "camelCase")]
+    Narration {
+        trigger_id: String,
+        text: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    DialogueStarted {
+        trigger_id: String,
+        dialogue_id: String,
+        #[serde(default)]
+        pawn_id: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    CombatStarted {
+        trigger_id: String,
+        encounter_id: String,
+        pawn_ids: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    QuestFlags {
+        flags: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
     Pong {
         tick: u64,
     },
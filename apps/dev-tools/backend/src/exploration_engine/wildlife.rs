@@ -0,0 +1,342 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::ecology::{
+    ActivityCycle, FaunaCategory, FaunaEntry, FaunaLocomotion, FaunaSizeClass, FaunaTemperament,
+};
+
+use super::{
+    harvest::EXPLORATION_DAY_MS,
+    types::{
+        ExplorationManifestDescriptor, ExplorationMovementMode, ExplorationPawn,
+        ExplorationPawnStats, ExplorationWildlifeBehavior, ExplorationWildlifeState,
+    },
+};
+
+const TILES_PER_PACK: u32 = 32 * 32;
+const MAX_PACKS: u32 = 6;
+const MAX_PACK_SIZE: i32 = 8;
+const MIN_SPAWN_DISTANCE: f32 = 12.0;
+const ANCHOR_ATTEMPTS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DayPhase {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+/// Maps wall-clock time onto the shared exploration day used by regrowth timers.
+pub fn day_phase_at(now_ms: u64) -> DayPhase {
+    let fraction = (now_ms % EXPLORATION_DAY_MS) as f64 / EXPLORATION_DAY_MS as f64;
+    match fraction {
+        value if value < 0.2 => DayPhase::Night,
+        value if value < 0.3 => DayPhase::Dawn,
+        value if value < 0.7 => DayPhase::Day,
+        value if value < 0.8 => DayPhase::Dusk,
+        _ => DayPhase::Night,
+    }
+}
+
+pub fn fauna_active(cycle: &ActivityCycle, phase: DayPhase) -> bool {
+    match cycle {
+        ActivityCycle::Any => true,
+        ActivityCycle::Diurnal => matches!(phase, DayPhase::Dawn | DayPhase::Day | DayPhase::Dusk),
+        ActivityCycle::Nocturnal => {
+            matches!(phase, DayPhase::Dusk | DayPhase::Night | DayPhase::Dawn)
+        }
+        ActivityCycle::Crepuscular => matches!(phase, DayPhase::Dawn | DayPhase::Dusk),
+    }
+}
+
+/// Land-bound fauna only; aquatic species need water tiles the exploration maps do not model.
+pub fn fauna_spawnable(entry: &FaunaEntry) -> bool {
+    !matches!(entry.category, FaunaCategory::Aquatic)
+        && !matches!(entry.body_profile.locomotion, FaunaLocomotion::Swimmer)
+}
+
+pub fn wildlife_behavior(entry: &FaunaEntry) -> ExplorationWildlifeBehavior {
+    match entry.behavior_profile.temperament {
+        FaunaTemperament::Docile | FaunaTemperament::Skittish => {
+            ExplorationWildlifeBehavior::Passive
+        }
+        FaunaTemperament::Territorial if !matches!(entry.category, FaunaCategory::Predator) => {
+            ExplorationWildlifeBehavior::Territorial
+        }
+        _ => ExplorationWildlifeBehavior::Predator,
+    }
+}
+
+pub fn fauna_move_speed(entry: &FaunaEntry) -> f32 {
+    let base = match entry.body_profile.locomotion {
+        FaunaLocomotion::Runner | FaunaLocomotion::Flier => 7.5,
+        FaunaLocomotion::Walker | FaunaLocomotion::Amphibious => 5.5,
+        FaunaLocomotion::Climber | FaunaLocomotion::Slitherer => 4.5,
+        FaunaLocomotion::Burrower | FaunaLocomotion::Swimmer => 3.5,
+    };
+    let size = match entry.body_profile.size_class {
+        FaunaSizeClass::Tiny => 0.8,
+        FaunaSizeClass::Small => 0.9,
+        FaunaSizeClass::Medium => 1.0,
+        FaunaSizeClass::Large => 1.1,
+        FaunaSizeClass::Huge => 0.9,
+    };
+    base * size
+}
+
+/// Radius in tiles within which a creature can notice the party.
+pub fn detection_radius(perception: i32) -> f32 {
+    3.0 + perception.clamp(0, 100) as f32 / 10.0
+}
+
+/// Chance per decision that a creature notices a party member at the given distance. Agility
+/// stands in for the party's stealth.
+pub fn detection_chance(perception: i32, target: &ExplorationPawnStats, distance: f32) -> f64 {
+    let radius = detection_radius(perception);
+    if distance > radius {
+        return 0.0;
+    }
+    let awareness = (50 + perception - target.agility * 3) as f64 / 100.0;
+    let proximity = 1.0 - (distance / radius) as f64 * 0.5;
+    (awareness * proximity).clamp(0.05, 0.95)
+}
+
+/// Distance at which the party spots a concealed creature. Wisdom widens it, stealth narrows it.
+pub fn spot_radius(stealth: i32, observer: &ExplorationPawnStats) -> f32 {
+    (8.0 - stealth.clamp(0, 100) as f32 / 15.0 + observer.wisdom as f32 / 5.0).max(1.5)
+}
+
+pub fn spawn_wildlife<R: Rng>(
+    fauna: &[FaunaEntry],
+    descriptor: &ExplorationManifestDescriptor,
+    existing_pawns: &[ExplorationPawn],
+    phase: DayPhase,
+    walkable: impl Fn(i32, i32) -> bool,
+    rng: &mut R,
+) -> Vec<ExplorationPawn> {
+    let candidates = fauna
+        .iter()
+        .filter(|entry| {
+            fauna_spawnable(entry) && fauna_active(&entry.behavior_profile.activity_cycle, phase)
+        })
+        .collect::<Vec<_>>();
+    if candidates.is_empty() || descriptor.width == 0 || descriptor.height == 0 {
+        return Vec::new();
+    }
+
+    let mut occupied = existing_pawns
+        .iter()
+        .map(|pawn| (pawn.y.round() as i32, pawn.x.round() as i32))
+        .collect::<HashSet<_>>();
+    let min_distance = MIN_SPAWN_DISTANCE.min(descriptor.width.min(descriptor.height) as f32 / 3.0);
+    let pack_count = (descriptor.width * descriptor.height / TILES_PER_PACK).clamp(1, MAX_PACKS);
+
+    let mut pawns = Vec::new();
+    for pack_index in 0..pack_count {
+        let Some(entry) = pick_fauna(&candidates, rng) else {
+            break;
+        };
+        let Some((anchor_row, anchor_col)) =
+            find_anchor(descriptor, &occupied, min_distance, &walkable, rng)
+        else {
+            continue;
+        };
+        let profile = &entry.behavior_profile;
+        let min_size = profile.pack_size_min.clamp(1, MAX_PACK_SIZE);
+        let max_size = profile.pack_size_max.clamp(min_size, MAX_PACK_SIZE);
+        let pack_size = rng.random_range(min_size..=max_size) as usize;
+        let pack_id = format!("pack-{}-{pack_index}", entry.id);
+        let positions = pack_positions(anchor_row, anchor_col, pack_size, &occupied, &walkable);
+        for (member_index, (row, col)) in positions.into_iter().enumerate() {
+            occupied.insert((row, col));
+            pawns.push(build_wildlife_pawn(
+                entry,
+                &pack_id,
+                member_index,
+                row,
+                col,
+                anchor_row,
+                anchor_col,
+            ));
+        }
+    }
+    pawns
+}
+
+fn pick_fauna<'a, R: Rng>(candidates: &[&'a FaunaEntry], rng: &mut R) -> Option<&'a FaunaEntry> {
+    let total = candidates
+        .iter()
+        .map(|entry| fauna_spawn_weight(entry))
+        .sum::<u32>();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = rng.random_range(0..total);
+    for entry in candidates {
+        let weight = fauna_spawn_weight(entry);
+        if roll < weight {
+            return Some(entry);
+        }
+        roll -= weight;
+    }
+    candidates.last().copied()
+}
+
+fn fauna_spawn_weight(entry: &FaunaEntry) -> u32 {
+    (110 - entry.danger_level.clamp(0, 100)) as u32
+}
+
+fn find_anchor<R: Rng>(
+    descriptor: &ExplorationManifestDescriptor,
+    occupied: &HashSet<(i32, i32)>,
+    min_distance: f32,
+    walkable: &impl Fn(i32, i32) -> bool,
+    rng: &mut R,
+) -> Option<(i32, i32)> {
+    let spawn_row = descriptor.spawn.row as f32;
+    let spawn_col = descriptor.spawn.col as f32;
+    for _ in 0..ANCHOR_ATTEMPTS {
+        let row = rng.random_range(0..descriptor.height) as i32;
+        let col = rng.random_range(0..descriptor.width) as i32;
+        let distance = ((row as f32 - spawn_row).powi(2) + (col as f32 - spawn_col).powi(2)).sqrt();
+        if distance >= min_distance && !occupied.contains(&(row, col)) && walkable(row, col) {
+            return Some((row, col));
+        }
+    }
+    None
+}
+
+fn pack_positions(
+    anchor_row: i32,
+    anchor_col: i32,
+    count: usize,
+    occupied: &HashSet<(i32, i32)>,
+    walkable: &impl Fn(i32, i32) -> bool,
+) -> Vec<(i32, i32)> {
+    let mut positions = Vec::with_capacity(count);
+    for radius in 0..=3 {
+        for row in (anchor_row - radius)..=(anchor_row + radius) {
+            for col in (anchor_col - radius)..=(anchor_col + radius) {
+                if (row - anchor_row).abs() != radius && (col - anchor_col).abs() != radius {
+                    continue;
+                }
+                if positions.len() >= count {
+                    return positions;
+                }
+                if !occupied.contains(&(row, col)) && walkable(row, col) {
+                    positions.push((row, col));
+                }
+            }
+        }
+    }
+    positions
+}
+
+fn build_wildlife_pawn(
+    entry: &FaunaEntry,
+    pack_id: &str,
+    member_index: usize,
+    row: i32,
+    col: i32,
+    anchor_row: i32,
+    anchor_col: i32,
+) -> ExplorationPawn {
+    let combat = &entry.combat_profile;
+    let speed = fauna_move_speed(entry);
+    ExplorationPawn {
+        id: format!("{pack_id}-{member_index}"),
+        name: entry.name.clone(),
+        x: col as f32,
+        y: row as f32,
+        level: 0,
+        tile_row: row,
+        tile_col: col,
+        target_x: None,
+        target_y: None,
+        path: None,
+        route: Vec::new(),
+        route_index: 0,
+        segment_progress: 0.0,
+        moving: false,
+        move_speed_tiles_per_second: speed,
+        speed,
+        faction_id: "wildlife".to_string(),
+        r#type: "fauna".to_string(),
+        texture_url: None,
+        sprite: entry
+            .exploration_sprite
+            .as_ref()
+            .and_then(|binding| serde_json::to_value(binding).ok()),
+        facing: Some("south".to_string()),
+        is_npc: Some(true),
+        interaction_label: None,
+        home_interior_id: None,
+        schedule_id: Some(format!("wildlife:{pack_id}")),
+        current_anchor_id: Some(format!("{anchor_row}:{anchor_col}")),
+        current_intent: Some("roaming".to_string()),
+        next_decision_at_tick: None,
+        stats: Some(ExplorationPawnStats {
+            strength: combat.strength,
+            agility: combat.agility,
+            intelligence: combat.intelligence,
+            wisdom: combat.wisdom,
+            endurance: combat.endurance,
+            charisma: combat.charisma,
+        }),
+        conditions: Vec::new(),
+        wildlife: Some(ExplorationWildlifeState {
+            fauna_id: entry.id.clone(),
+            pack_id: pack_id.to_string(),
+            behavior: wildlife_behavior(entry),
+            perception: entry.behavior_profile.perception,
+            stealth: entry.behavior_profile.stealth,
+            danger_level: entry.danger_level,
+            home_row: anchor_row,
+            home_col: anchor_col,
+            target_pawn_id: None,
+            warned_at_tick: None,
+            concealed: false,
+        }),
+        movement_mode: ExplorationMovementMode::Walk,
+        armor_noise: 0.0,
+        awareness: None,
+        inventory: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activity_cycles_follow_day_phases() {
+        assert!(fauna_active(&ActivityCycle::Nocturnal, day_phase_at(0)));
+        assert!(!fauna_active(&ActivityCycle::Diurnal, day_phase_at(0)));
+        let noon = EXPLORATION_DAY_MS / 2;
+        assert!(fauna_active(&ActivityCycle::Diurnal, day_phase_at(noon)));
+        assert!(!fauna_active(
+            &ActivityCycle::Crepuscular,
+            day_phase_at(noon)
+        ));
+        assert!(fauna_active(&ActivityCycle::Any, day_phase_at(noon)));
+    }
+
+    #[test]
+    fn detection_fades_with_distance_and_agility() {
+        let clumsy = ExplorationPawnStats {
+            agility: 2,
+            ..ExplorationPawnStats::default()
+        };
+        let nimble = ExplorationPawnStats {
+            agility: 18,
+            ..ExplorationPawnStats::default()
+        };
+        let near = detection_chance(60, &clumsy, 1.0);
+        assert!(near > detection_chance(60, &clumsy, 8.0));
+        assert!(near > detection_chance(60, &nimble, 1.0));
+        assert_eq!(detection_chance(60, &clumsy, 20.0), 0.0);
+    }
+}
@@ -1,6 +1,6 @@
 use axum::{
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
@@ -18,12 +18,16 @@ use std::{
 use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use crate::{
+    asset_packs::{self, BlockPaletteRules},
+    ecology::{self, FloraEntry},
     exploration_engine::{
+        chunk_store::decode_chunk,
+        harvest::{flora_blocks_movement, natural_object_type_for_flora, pick_flora},
         manifest::{
-            load_chunk, load_manifest_descriptor, manifest_path as chunked_manifest_path,
+            load_chunk_bytes, load_manifest_descriptor, manifest_path as chunked_manifest_path,
             write_chunked_location,
         },
-        types::ExplorationMap as RuntimeExplorationMap,
+        types::{ExplorationLevelLink, ExplorationMap as RuntimeExplorationMap},
     },
     gemini,
     jobs::{now_ms, JobOutputRef, JobRecord, JobRouteRef, JobStatus},
@@ -34,6 +38,12 @@ use crate::{
     AppState,
 };
 
+mod layout;
+mod wfc;
+
+use layout::{generate_layout, LayoutKind, LayoutRequest};
+use wfc::WfcPalette;
+
 const DEFAULT_ROWS: u32 = 64;
 const DEFAULT_COLS: u32 = 64;
 pub const TEST_EXPLORATION_LOCATION_ID: &str = "__test_exploration__";
@@ -166,6 +176,8 @@ struct ExplorationTile {
     blocks_light: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     door_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    level_link: Option<ExplorationLevelLink>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -198,6 +210,8 @@ struct ExplorationObject {
     height_tiles: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     blocks_light: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flora_id: Option<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -215,6 +229,17 @@ struct ExplorationMapManifest {
     fog_of_war: Option<Vec<bool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     ambient_light: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    levels: Vec<ExplorationMapLevelManifest>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorationMapLevelManifest {
+    level: i32,
+    name: String,
+    tiles: Vec<ExplorationTile>,
+    objects: Vec<ExplorationObject>,
 }
 
 struct RoomCarveResult {
@@ -231,6 +256,9 @@ struct GenerationContext {
     location_lore: String,
     location_type: String,
     generation_mode: String,
+    /// Layout generator resolved from the generation mode and location.
+    #[serde(default)]
+    layout: String,
     asset_mode: String,
     rows: u32,
     cols: u32,
@@ -480,9 +508,20 @@ pub async fn get_exploration_manifest(
     }
 }
 
+#[derive(Deserialize)]
+pub struct ExplorationChunkQuery {
+    #[serde(default)]
+    level: i32,
+}
+
+/// Serves a chunk with its content hash as the ETag. Clients that send
+/// `Accept: application/octet-stream` get the stored binary encoding, everyone else JSON.
+/// `?level=` selects the floor; it defaults to the ground level.
 pub async fn get_exploration_chunk(
     State(state): State<AppState>,
     Path((world_id, location_id, chunk_row, chunk_col)): Path<(String, String, u32, u32)>,
+    Query(query): Query<ExplorationChunkQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     if is_test_exploration_location(&location_id) {
         if let Err(error) = ensure_test_exploration_location(&state.planets_dir, &world_id) {
@@ -490,17 +529,70 @@ pub async fn get_exploration_chunk(
         }
     }
 
-    match load_chunk(
+    let (bytes, hash) = match load_chunk_bytes(
         &state.planets_dir,
         &world_id,
         &location_id,
+        query.level,
         chunk_row,
         chunk_col,
     ) {
-        Ok(Some(chunk)) => (StatusCode::OK, Json(chunk)).into_response(),
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            "Exploration chunk not found".to_string(),
+        Ok(Some(entry)) => entry,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                "Exploration chunk not found".to_string(),
+            )
+                .into_response()
+        }
+        Err(error) => return (StatusCode::INTERNAL_SERVER_ERROR, error).into_response(),
+    };
+
+    let wants_binary = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("application/octet-stream"));
+    // The binary and JSON bodies differ, so each representation gets its own validator.
+    let etag = if wants_binary {
+        format!("\"{hash}\"")
+    } else {
+        format!("\"{hash}-json\"")
+    };
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    if not_modified {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::VARY, header::ACCEPT.to_string()),
+            ],
+        )
+            .into_response();
+    }
+
+    if wants_binary {
+        return (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                (header::ETAG, etag),
+                (header::VARY, header::ACCEPT.to_string()),
+            ],
+            bytes,
+        )
+            .into_response();
+    }
+    match decode_chunk(&bytes) {
+        Ok(chunk) => (
+            StatusCode::OK,
+            [
+                (header::ETAG, etag),
+                (header::VARY, header::ACCEPT.to_string()),
+            ],
+            Json(chunk),
         )
             .into_response(),
         Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error).into_response(),
@@ -699,7 +791,31 @@ async fn run_generate_location_job(
         return;
     }
 
-    let context = build_generation_context(&payload, &location_record);
+    // A palette that was asked for but cannot be used fails the job rather than quietly
+    // producing a different layout.
+    let block_rules = match payload.block_palette_id.as_deref().map(|pack_id| {
+        asset_packs::load_block_rules(&state.packs_dir, pack_id).and_then(|rules| {
+            if let Some(rules) = rules.as_ref() {
+                WfcPalette::compile(rules)?;
+            }
+            Ok(rules)
+        })
+    }) {
+        Some(Ok(rules)) => rules,
+        Some(Err(error)) => {
+            runtime.update_job(
+                &job_id,
+                JobStatus::Failed,
+                100.0,
+                "Failed",
+                None,
+                Some(error),
+            );
+            return;
+        }
+        None => None,
+    };
+    let mut context = build_generation_context(&payload, &location_record, block_rules.is_some());
     let semantics_job = runtime
         .create_job(
             "exploration.generate-semantics.v1",
@@ -749,12 +865,29 @@ async fn run_generate_location_job(
         None,
     );
 
-    let manifest = build_manifest(
+    let flora = ecology::load_ecology_bundle(&state.planets_dir, &payload.world_id)
+        .map(|bundle| ecology::flora_for_province(&bundle, location_record.province_id))
+        .unwrap_or_default();
+    let (manifest, layout_kind) = build_manifest(
         &payload,
         &location_record,
         &context,
         semantics_summary.as_deref(),
+        &flora,
+        block_rules.as_ref(),
     );
+    let mut warnings = Vec::new();
+    if layout_kind.as_str() != context.layout {
+        let warning = format!(
+            "The {} layout could not be built; kept the {} layout instead",
+            context.layout,
+            layout_kind.as_str()
+        );
+        tracing::warn!("Exploration job {job_id}: {warning}");
+        runtime.update_job(&job_id, JobStatus::Running, 50.0, &warning, None, None);
+        warnings.push(warning);
+        context.layout = layout_kind.as_str().to_string();
+    }
     let manifest_value = build_manifest_value(&manifest, &payload, &context);
     let runtime_map = match serde_json::from_value::<RuntimeExplorationMap>(manifest_value) {
         Ok(value) => value,
@@ -895,6 +1028,8 @@ async fn run_generate_location_job(
             "locationId": payload.location_id,
             "locationName": context.location_name,
             "mapName": manifest.name,
+            "layout": context.layout,
+            "warnings": warnings,
             "manifestAvailable": true,
             "generatedAt": now_ms(),
         })),
@@ -1039,7 +1174,12 @@ fn complete_child_job(
 fn build_generation_context(
     payload: &GenerateExplorationLocationRequest,
     location: &LocationRecord,
+    has_block_rules: bool,
 ) -> GenerationContext {
+    let generation_mode = payload
+        .generation_mode
+        .clone()
+        .unwrap_or_else(|| "procedural".to_string());
     GenerationContext {
         location_name: payload
             .location_name
@@ -1047,10 +1187,10 @@ fn build_generation_context(
             .unwrap_or_else(|| location.name.clone()),
         location_lore: location.lore.clone(),
         location_type: location.type_label.clone(),
-        generation_mode: payload
-            .generation_mode
-            .clone()
-            .unwrap_or_else(|| "procedural".to_string()),
+        layout: LayoutKind::select(&generation_mode, location, has_block_rules)
+            .as_str()
+            .to_string(),
+        generation_mode,
         asset_mode: payload
             .asset_mode
             .clone()
@@ -1127,13 +1267,13 @@ fn build_test_manifest_value(world_id: &str) -> Value {
             "pathing lane".to_string(),
         ],
         seed: Some(1337),
-        generation_mode: Some("procedural".to_string()),
+        generation_mode: Some("rooms".to_string()),
         block_palette_id: None,
         asset_mode: Some("textureless".to_string()),
     };
     let location = build_test_location_record(world_id);
-    let context = build_generation_context(&payload, &location);
-    let manifest = build_manifest(&payload, &location, &context, Some("sandbox"));
+    let context = build_generation_context(&payload, &location, false);
+    let (manifest, _) = build_manifest(&payload, &location, &context, Some("sandbox"), &[], None);
     build_manifest_value(&manifest, &payload, &context)
 }
 
@@ -1143,11 +1283,12 @@ pub fn ensure_test_exploration_location(
 ) -> Result<(), String> {
     let path = chunked_manifest_path(planets_dir, world_id, TEST_EXPLORATION_LOCATION_ID);
     if path.exists() {
-        let existing_version = load_manifest_descriptor(planets_dir, world_id, TEST_EXPLORATION_LOCATION_ID)
-            .ok()
-            .and_then(|descriptor| descriptor.metadata)
-            .and_then(|metadata| metadata.get("testLayoutVersion").and_then(Value::as_u64))
-            .unwrap_or(0);
+        let existing_version =
+            load_manifest_descriptor(planets_dir, world_id, TEST_EXPLORATION_LOCATION_ID)
+                .ok()
+                .and_then(|descriptor| descriptor.metadata)
+                .and_then(|metadata| metadata.get("testLayoutVersion").and_then(Value::as_u64))
+                .unwrap_or(0);
         if existing_version >= TEST_EXPLORATION_LAYOUT_VERSION {
             return Ok(());
         }
@@ -1161,12 +1302,15 @@ pub fn ensure_test_exploration_location(
     write_chunked_location(planets_dir, world_id, TEST_EXPLORATION_LOCATION_ID, &map).map(|_| ())
 }
 
+/// Lays out and furnishes the map, returning it with the layout generator that produced it.
 fn build_manifest(
     payload: &GenerateExplorationLocationRequest,
     location: &LocationRecord,
     context: &GenerationContext,
     semantics_summary: Option<&str>,
-) -> ExplorationMapManifest {
+    flora: &[FloraEntry],
+    block_rules: Option<&BlockPaletteRules>,
+) -> (ExplorationMapManifest, LayoutKind) {
     let width = context.cols;
     let height = context.rows;
     let mut tiles = vec![
@@ -1180,6 +1324,7 @@ fn build_manifest(
             light_level: Some(0.82),
             blocks_light: None,
             door_id: None,
+            level_link: None,
         };
         (width * height) as usize
     ];
@@ -1196,173 +1341,179 @@ fn build_manifest(
         set_wall(&mut tiles, width, width.saturating_sub(1), y);
     }
 
-    let structure_count = payload
-        .structure_names
-        .len()
-        .max(
-            if matches!(
-                location.category,
-                locations::LocationCategory::Settlement | locations::LocationCategory::Ruin
-            ) {
-                2
-            } else {
-                1
-            },
+    let layout_request = LayoutRequest {
+        location,
+        structure_names: &payload.structure_names,
+        biome_hint: format!(
+            "{} {} {} {}",
+            payload.biome_name.as_deref().unwrap_or(""),
+            payload.prompt,
+            location.subtype,
+            location.lore
         )
-        .min(5);
-    let center_clear_x = width / 2;
-    let center_clear_y = height / 2;
-    let mut placed = Vec::<(u32, u32, u32, u32)>::new();
+        .to_lowercase(),
+        block_rules,
+    };
+    let layout = generate_layout(
+        LayoutKind::select(&context.generation_mode, location, block_rules.is_some()),
+        &mut tiles,
+        width,
+        height,
+        &layout_request,
+        &mut rng,
+    );
+    let (center_clear_x, center_clear_y) = layout.spawn;
     let mut carved_room_interior_ids = Vec::<String>::new();
 
-    for index in 0..structure_count {
-        let label = payload
-            .structure_names
-            .get(index)
-            .cloned()
-            .unwrap_or_else(|| match location.category {
-                locations::LocationCategory::Settlement => "building".to_string(),
-                locations::LocationCategory::Ruin => "ruin".to_string(),
-                _ => "outpost".to_string(),
-            });
-
-        let mut attempt = 0;
-        while attempt < 24 {
-            attempt += 1;
-            let room_w = rng.random_range(7..=12).min(width.saturating_sub(4));
-            let room_h = rng.random_range(6..=10).min(height.saturating_sub(4));
-            let x = rng.random_range(2..=width.saturating_sub(room_w + 2));
-            let y = rng.random_range(2..=height.saturating_sub(room_h + 2));
-            if rect_intersects_center(x, y, room_w, room_h, center_clear_x, center_clear_y) {
-                continue;
-            }
-            if placed
-                .iter()
-                .any(|(px, py, pw, ph)| rects_overlap(x, y, room_w, room_h, *px, *py, *pw, *ph))
-            {
-                continue;
-            }
-            placed.push((x, y, room_w, room_h));
-            let room = carve_room(&mut tiles, width, x, y, room_w, room_h, &mut rng, index);
-            carved_room_interior_ids.push(room.interior_id.clone());
-            objects.push(ExplorationObject {
-                id: format!("obj-roof-{index}"),
-                r#type: format!("{}-roof", label.to_lowercase().replace(' ', "-")),
-                x,
-                y,
-                width: room_w,
-                height: room_h,
-                passable: true,
-                texture_url: None,
-                is_natural: Some(false),
-                is_hidden: Some(false),
-                move_cost: None,
-                fertility: None,
-                door_id: None,
-                interior_id: Some(room.interior_id.clone()),
-                roof_group_id: Some(room.roof_group_id.clone()),
-                height_tiles: Some(2),
-                blocks_light: Some(true),
-            });
+    for room in &layout.rooms {
+        let (index, x, y, room_w, room_h) = (room.index, room.x, room.y, room.width, room.height);
+        let label = &room.label;
+        let room = &room.carve;
+        carved_room_interior_ids.push(room.interior_id.clone());
+        objects.push(ExplorationObject {
+            id: format!("obj-roof-{index}"),
+            r#type: format!("{}-roof", label.to_lowercase().replace(' ', "-")),
+            x,
+            y,
+            width: room_w,
+            height: room_h,
+            passable: true,
+            texture_url: None,
+            is_natural: Some(false),
+            is_hidden: Some(false),
+            move_cost: None,
+            fertility: None,
+            door_id: None,
+            interior_id: Some(room.interior_id.clone()),
+            roof_group_id: Some(room.roof_group_id.clone()),
+            height_tiles: Some(2),
+            blocks_light: Some(true),
+            flora_id: None,
+        });
+        objects.push(ExplorationObject {
+            id: format!("obj-door-{index}"),
+            r#type: "door".to_string(),
+            x: room.door_x,
+            y: room.door_y,
+            width: 1,
+            height: 1,
+            passable: true,
+            texture_url: None,
+            is_natural: Some(false),
+            is_hidden: Some(false),
+            move_cost: None,
+            fertility: None,
+            door_id: Some(format!("door-{index}")),
+            interior_id: Some(room.interior_id.clone()),
+            roof_group_id: Some(room.roof_group_id.clone()),
+            height_tiles: Some(2),
+            blocks_light: Some(false),
+            flora_id: None,
+        });
+        if room_w > 4 && room_h > 4 {
             objects.push(ExplorationObject {
-                id: format!("obj-door-{index}"),
-                r#type: "door".to_string(),
-                x: room.door_x,
-                y: room.door_y,
+                id: format!("obj-furniture-{index}"),
+                r#type: if matches!(location.category, locations::LocationCategory::Settlement) {
+                    "crate".to_string()
+                } else {
+                    "rubble".to_string()
+                },
+                x: x + room_w / 2,
+                y: y + room_h / 2,
                 width: 1,
                 height: 1,
-                passable: true,
+                passable: false,
                 texture_url: None,
                 is_natural: Some(false),
                 is_hidden: Some(false),
-                move_cost: None,
+                move_cost: Some(1.2),
                 fertility: None,
-                door_id: Some(format!("door-{index}")),
+                door_id: None,
                 interior_id: Some(room.interior_id.clone()),
-                roof_group_id: Some(room.roof_group_id.clone()),
-                height_tiles: Some(2),
+                roof_group_id: None,
+                height_tiles: Some(1),
                 blocks_light: Some(false),
+                flora_id: None,
             });
-            if room_w > 4 && room_h > 4 {
-                objects.push(ExplorationObject {
-                    id: format!("obj-furniture-{index}"),
-                    r#type: if matches!(location.category, locations::LocationCategory::Settlement)
-                    {
-                        "crate".to_string()
-                    } else {
-                        "rubble".to_string()
-                    },
-                    x: x + room_w / 2,
-                    y: y + room_h / 2,
-                    width: 1,
-                    height: 1,
-                    passable: false,
-                    texture_url: None,
-                    is_natural: Some(false),
-                    is_hidden: Some(false),
-                    move_cost: Some(1.2),
-                    fertility: None,
-                    door_id: None,
-                    interior_id: Some(room.interior_id.clone()),
-                    roof_group_id: None,
-                    height_tiles: Some(1),
-                    blocks_light: Some(false),
-                });
-            }
-            let npc_name = match location.category {
-                locations::LocationCategory::Settlement => format!("Resident {}", index + 1),
-                locations::LocationCategory::Ruin => format!("Scavenger {}", index + 1),
-                _ => format!("Wanderer {}", index + 1),
-            };
-            pawns.push(json!({
-                "id": format!("npc-{index}"),
-                "name": npc_name,
-                "x": ((x + room.door_x).div_ceil(2)),
-                "y": ((y + room.door_y).div_ceil(2)),
-                "speed": 2.25,
-                "factionId": "ambient",
-                "type": "human",
-                "facing": "south",
-                "isNpc": true,
-                "interactionLabel": "Talk",
-                "homeInteriorId": room.interior_id.clone(),
-            }));
-            break;
         }
+        let npc_name = match location.category {
+            locations::LocationCategory::Settlement => format!("Resident {}", index + 1),
+            locations::LocationCategory::Ruin => format!("Scavenger {}", index + 1),
+            _ => format!("Wanderer {}", index + 1),
+        };
+        pawns.push(json!({
+            "id": format!("npc-{index}"),
+            "name": npc_name,
+            "x": ((x + room.door_x).div_ceil(2)),
+            "y": ((y + room.door_y).div_ceil(2)),
+            "speed": 2.25,
+            "factionId": "ambient",
+            "type": "human",
+            "facing": "south",
+            "isNpc": true,
+            "interactionLabel": "Talk",
+            "homeInteriorId": room.interior_id.clone(),
+        }));
+    }
+
+    if layout.river {
+        carve_river(
+            &mut tiles,
+            width,
+            height,
+            &objects,
+            center_clear_x,
+            center_clear_y,
+            &mut rng,
+        );
+    }
+
+    if layout.clear_spawn {
+        clear_spawn_zone(&mut tiles, width, height, center_clear_x, center_clear_y);
+    } else {
+        mark_spawn_zone(&mut tiles, width, height, center_clear_x, center_clear_y);
     }
 
-    carve_river(
+    let levels = build_extra_levels(
+        location,
         &mut tiles,
         width,
         height,
-        &objects,
-        center_clear_x,
-        center_clear_y,
+        layout.extra_level_anchor(),
         &mut rng,
     );
 
-    clear_spawn_zone(&mut tiles, width, height, center_clear_x, center_clear_y);
-
-    let natural_count = natural_object_count(payload, location, semantics_summary);
+    let natural_count = (natural_object_count(payload, location, semantics_summary) as f32
+        * layout.scatter_scale)
+        .round() as usize;
     for index in 0..natural_count {
-        let x = rng.random_range(2..width.saturating_sub(2));
-        let y = rng.random_range(2..height.saturating_sub(2));
+        let (x, y) = if layout.groves.is_empty() {
+            (
+                rng.random_range(2..width.saturating_sub(2)),
+                rng.random_range(2..height.saturating_sub(2)),
+            )
+        } else {
+            let (grove_x, grove_y) = layout.groves[rng.random_range(0..layout.groves.len())];
+            (
+                (grove_x as i32 + rng.random_range(-4..=4)).clamp(2, width as i32 - 3) as u32,
+                (grove_y as i32 + rng.random_range(-4..=4)).clamp(2, height as i32 - 3) as u32,
+            )
+        };
         let idx = tile_index(width, x, y);
-        if !tiles[idx].walkable
-            || tiles[idx].interior_id.is_some()
-            || tiles[idx].is_spawn_zone.is_some()
-            || tiles[idx].r#type != "floor"
-        {
+        if !supports_natural_object(&tiles[idx], block_rules) {
             continue;
         }
+        let flora_entry = pick_flora(flora, &mut rng);
         objects.push(ExplorationObject {
             id: format!("obj-natural-{index}"),
-            r#type: infer_natural_object_type(payload, location),
+            r#type: flora_entry
+                .map(|entry| natural_object_type_for_flora(entry).to_string())
+                .unwrap_or_else(|| infer_natural_object_type(payload, location)),
             x,
             y,
             width: 1,
             height: 1,
-            passable: false,
+            passable: flora_entry.is_some_and(|entry| !flora_blocks_movement(entry)),
             texture_url: None,
             is_natural: Some(true),
             is_hidden: Some(false),
@@ -1373,6 +1524,7 @@ fn build_manifest(
             roof_group_id: None,
             height_tiles: Some(1),
             blocks_light: Some(false),
+            flora_id: flora_entry.map(|entry| entry.id.clone()),
         });
     }
 
@@ -1425,7 +1577,7 @@ fn build_manifest(
         }));
     }
 
-    ExplorationMapManifest {
+    let manifest = ExplorationMapManifest {
         id: format!("explore-{}-{}", payload.world_id, payload.location_id),
         width,
         height,
@@ -1435,6 +1587,169 @@ fn build_manifest(
         name: Some(context.location_name.clone()),
         fog_of_war: None,
         ambient_light: Some(0.76),
+        levels,
+    };
+    (manifest, layout.kind)
+}
+
+/// Floors above or below the first structure, or the layout's anchor when it has none: a cellar
+/// under ruins, a mine under resource sites and an upper storey in settlements. Each is reached
+/// by stairs or a ladder inside the footprint.
+fn build_extra_levels(
+    location: &LocationRecord,
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    anchor: Option<(u32, u32, u32, u32)>,
+    rng: &mut StdRng,
+) -> Vec<ExplorationMapLevelManifest> {
+    let Some((x, y, room_w, room_h)) = anchor else {
+        return Vec::new();
+    };
+    let (level, name, kind) = match location.category {
+        locations::LocationCategory::Ruin => (-1, "Cellar", "stairs"),
+        locations::LocationCategory::Resource => (-1, "Mine", "ladder"),
+        locations::LocationCategory::Settlement => (1, "Upper Floor", "stairs"),
+        _ => return Vec::new(),
+    };
+    let mut level_tiles = vec![
+        if level < 0 {
+            ExplorationTile {
+                r#type: "rock".to_string(),
+                walkable: false,
+                move_cost: 0.0,
+                texture_url: None,
+                is_spawn_zone: None,
+                interior_id: None,
+                light_level: Some(0.1),
+                blocks_light: Some(true),
+                door_id: None,
+                level_link: None,
+            }
+        } else {
+            ExplorationTile {
+                r#type: "open-air".to_string(),
+                walkable: false,
+                move_cost: 0.0,
+                texture_url: None,
+                is_spawn_zone: None,
+                interior_id: None,
+                light_level: Some(0.9),
+                blocks_light: Some(false),
+                door_id: None,
+                level_link: None,
+            }
+        };
+        (width * height) as usize
+    ];
+    let interior_id = format!("{}-0", name.to_lowercase().replace(' ', "-"));
+    let floor_type = match location.category {
+        locations::LocationCategory::Resource => "tunnel",
+        locations::LocationCategory::Ruin => "cellar-floor",
+        _ => "interior-floor",
+    };
+    for ty in y..(y + room_h) {
+        for tx in x..(x + room_w) {
+            let idx = tile_index(width, tx, ty);
+            let border = tx == x || tx == x + room_w - 1 || ty == y || ty == y + room_h - 1;
+            let tile = &mut level_tiles[idx];
+            tile.interior_id = Some(interior_id.clone());
+            if border {
+                tile.r#type = "wall".to_string();
+                tile.light_level = Some(0.24);
+                tile.blocks_light = Some(true);
+            } else {
+                tile.r#type = floor_type.to_string();
+                tile.walkable = true;
+                tile.move_cost = 1.0;
+                tile.light_level = Some(if level < 0 { 0.38 } else { 0.56 });
+                tile.blocks_light = Some(false);
+            }
+        }
+    }
+    if matches!(location.category, locations::LocationCategory::Resource) {
+        carve_mine_tunnels(
+            &mut level_tiles,
+            width,
+            height,
+            x + room_w / 2,
+            y + room_h / 2,
+            &interior_id,
+            rng,
+        );
+    }
+
+    let (link_x, link_y) = (x + 1, y + 1);
+    let up_kind = if level < 0 {
+        format!("{kind}-up")
+    } else {
+        format!("{kind}-down")
+    };
+    let down_kind = if level < 0 {
+        format!("{kind}-down")
+    } else {
+        format!("{kind}-up")
+    };
+    let ground = &mut tiles[tile_index(width, link_x, link_y)];
+    ground.r#type = down_kind.clone();
+    ground.level_link = Some(ExplorationLevelLink {
+        level,
+        row: link_y,
+        col: link_x,
+        kind: down_kind,
+    });
+    let landing = &mut level_tiles[tile_index(width, link_x, link_y)];
+    landing.r#type = up_kind.clone();
+    landing.level_link = Some(ExplorationLevelLink {
+        level: 0,
+        row: link_y,
+        col: link_x,
+        kind: up_kind,
+    });
+
+    vec![ExplorationMapLevelManifest {
+        level,
+        name: name.to_string(),
+        tiles: level_tiles,
+        objects: Vec::new(),
+    }]
+}
+
+/// Random-walk tunnels branching out of the mine chamber.
+fn carve_mine_tunnels(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    start_x: u32,
+    start_y: u32,
+    interior_id: &str,
+    rng: &mut StdRng,
+) {
+    for _ in 0..3 {
+        let (mut x, mut y) = (start_x as i32, start_y as i32);
+        let mut direction = rng.random_range(0..4);
+        for _ in 0..rng.random_range(12..=24) {
+            if rng.random_bool(0.25) {
+                direction = rng.random_range(0..4);
+            }
+            let (dx, dy) = [(1, 0), (-1, 0), (0, 1), (0, -1)][direction];
+            let next_x = x + dx;
+            let next_y = y + dy;
+            if next_x < 1 || next_y < 1 || next_x >= width as i32 - 1 || next_y >= height as i32 - 1
+            {
+                direction = rng.random_range(0..4);
+                continue;
+            }
+            x = next_x;
+            y = next_y;
+            let tile = &mut tiles[tile_index(width, x as u32, y as u32)];
+            tile.r#type = "tunnel".to_string();
+            tile.walkable = true;
+            tile.move_cost = 1.1;
+            tile.interior_id = Some(interior_id.to_string());
+            tile.light_level = Some(0.3);
+            tile.blocks_light = Some(false);
+        }
     }
 }
 
@@ -1454,6 +1769,7 @@ fn build_manifest_value(
             "metadata".to_string(),
             json!({
                 "generationMode": context.generation_mode,
+                "layout": context.layout,
                 "assetMode": context.asset_mode,
                 "seed": context.seed,
                 "worldId": payload.world_id,
@@ -1898,6 +2214,24 @@ fn clear_spawn_zone(
     }
 }
 
+/// Tags walkable outdoor tiles around the spawn without reshaping the layout.
+fn mark_spawn_zone(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    center_x: u32,
+    center_y: u32,
+) {
+    for y in center_y.saturating_sub(2)..=(center_y + 2).min(height.saturating_sub(1)) {
+        for x in center_x.saturating_sub(2)..=(center_x + 2).min(width.saturating_sub(1)) {
+            let tile = &mut tiles[tile_index(width, x, y)];
+            if tile.walkable && tile.interior_id.is_none() {
+                tile.is_spawn_zone = Some("player".to_string());
+            }
+        }
+    }
+}
+
 fn set_sand_tile(tile: &mut ExplorationTile) {
     tile.r#type = "sand".to_string();
     tile.walkable = true;
@@ -1916,6 +2250,24 @@ fn set_water_tile(tile: &mut ExplorationTile) {
     tile.door_id = None;
 }
 
+/// Whether flora and other natural objects may be scattered on a tile: walkable outdoor ground
+/// outside the spawn zone. Block palette layouts name their own ground tiles, so only their path
+/// and river stay clear.
+fn supports_natural_object(
+    tile: &ExplorationTile,
+    block_rules: Option<&BlockPaletteRules>,
+) -> bool {
+    tile.walkable
+        && tile.interior_id.is_none()
+        && tile.is_spawn_zone.is_none()
+        && tile.door_id.is_none()
+        && tile.r#type != "door"
+        && block_rules.is_none_or(|rules| {
+            tile.r#type != rules.path_tile
+                && rules.river_tile.as_deref() != Some(tile.r#type.as_str())
+        })
+}
+
 fn can_overwrite_outdoor_tile(tile: &ExplorationTile) -> bool {
     tile.interior_id.is_none()
         && tile.is_spawn_zone.is_none()
@@ -1957,7 +2309,9 @@ fn carve_river(
                 continue;
             }
             let idx = tile_index(width, x, y as u32);
-            if !can_overwrite_outdoor_tile(&tiles[idx]) || object_occupies_cell(objects, x, y as u32) {
+            if !can_overwrite_outdoor_tile(&tiles[idx])
+                || object_occupies_cell(objects, x, y as u32)
+            {
                 continue;
             }
             if offset.abs() <= 1 {
@@ -1973,7 +2327,9 @@ fn carve_river(
                 continue;
             }
             let idx = tile_index(width, x, y as u32);
-            if !can_overwrite_outdoor_tile(&tiles[idx]) || object_occupies_cell(objects, x, y as u32) {
+            if !can_overwrite_outdoor_tile(&tiles[idx])
+                || object_occupies_cell(objects, x, y as u32)
+            {
                 continue;
             }
             if tiles[idx].r#type == "floor" && bank_offset.abs() >= 2 {
@@ -2010,7 +2366,10 @@ fn carve_river(
 
 fn object_occupies_cell(objects: &[ExplorationObject], x: u32, y: u32) -> bool {
     objects.iter().any(|object| {
-        x >= object.x && x < object.x + object.width && y >= object.y && y < object.y + object.height
+        x >= object.x
+            && x < object.x + object.width
+            && y >= object.y
+            && y < object.y + object.height
     })
 }
 
@@ -2076,6 +2435,23 @@ fn carve_room(
     room_h: u32,
     rng: &mut StdRng,
     room_index: usize,
+) -> RoomCarveResult {
+    let door_side = rng.random_range(0..4);
+    carve_room_with_door(tiles, width, x, y, room_w, room_h, door_side, room_index)
+}
+
+/// Carves a walled interior with its door centred on `door_side`: 0 top, 1 bottom, 2 left,
+/// 3 right.
+#[allow(clippy::too_many_arguments)]
+fn carve_room_with_door(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    x: u32,
+    y: u32,
+    room_w: u32,
+    room_h: u32,
+    door_side: u32,
+    room_index: usize,
 ) -> RoomCarveResult {
     let interior_id = format!("interior-{room_index}");
     let roof_group_id = format!("roof-{room_index}");
@@ -2100,7 +2476,6 @@ fn carve_room(
         }
     }
 
-    let door_side = rng.random_range(0..4);
     let (door_x, door_y) = match door_side {
         0 => (x + room_w / 2, y),
         1 => (x + room_w / 2, y + room_h - 1),
@@ -2305,3 +2680,47 @@ fn default_rows() -> u32 {
 fn default_cols() -> u32 {
     DEFAULT_COLS
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ground(tile_type: &str, walkable: bool) -> ExplorationTile {
+        ExplorationTile {
+            r#type: tile_type.to_string(),
+            walkable,
+            move_cost: if walkable { 1.0 } else { 0.0 },
+            texture_url: None,
+            is_spawn_zone: None,
+            interior_id: None,
+            light_level: Some(0.82),
+            blocks_light: None,
+            door_id: None,
+            level_link: None,
+        }
+    }
+
+    #[test]
+    fn natural_objects_grow_on_walkable_palette_ground() {
+        let rules = BlockPaletteRules {
+            tiles: Vec::new(),
+            adjacency: Vec::new(),
+            path_tile: "path".to_string(),
+            river_tile: Some("stream".to_string()),
+        };
+        let grows = |tile: ExplorationTile| supports_natural_object(&tile, Some(&rules));
+        assert!(supports_natural_object(&ground("floor", true), None));
+        assert!(grows(ground("grass", true)));
+        assert!(grows(ground("moss", true)));
+        assert!(!grows(ground("tree", false)));
+        assert!(!grows(ground("path", true)));
+        assert!(!grows(ground("stream", true)));
+
+        let mut indoors = ground("grass", true);
+        indoors.interior_id = Some("hut".to_string());
+        assert!(!grows(indoors));
+        let mut spawn = ground("grass", true);
+        spawn.is_spawn_zone = Some("party".to_string());
+        assert!(!grows(spawn));
+    }
+}
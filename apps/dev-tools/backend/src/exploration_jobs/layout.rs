@@ -0,0 +1,1077 @@
+use rand::{rngs::StdRng, Rng};
+use std::collections::VecDeque;
+
+use crate::asset_packs::BlockPaletteRules;
+use crate::locations::{LocationCategory, LocationRecord, LocationScale};
+
+use super::wfc::{collapse, WfcPalette};
+use super::{
+    carve_room, carve_room_with_door, rect_intersects_center, rects_overlap, set_sand_tile,
+    set_water_tile, tile_index, ExplorationTile, RoomCarveResult,
+};
+
+const BSP_MIN_LEAF_WIDTH: u32 = 10;
+const BSP_MIN_LEAF_HEIGHT: u32 = 9;
+const BSP_MAX_DEPTH: u32 = 5;
+const CAVE_SMOOTHING_PASSES: usize = 5;
+const CAVE_ROCK_CHANCE: f64 = 0.45;
+const MAIN_STREET_WIDTH: u32 = 3;
+const SIDE_STREET_WIDTH: u32 = 2;
+
+/// Map layout generator. `generation_mode` can name one directly; other modes use WFC when the
+/// block palette has adjacency rules and otherwise pick from the location's category and subtype.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LayoutKind {
+    /// Up to five free-standing structures around a central clearing.
+    Rooms,
+    /// Room-and-corridor dungeon from binary space partitioning.
+    Bsp,
+    /// Street grid with building plots, denser for larger settlements.
+    Settlement,
+    /// Cellular-automata cave system.
+    Cave,
+    /// Open terrain with biome-driven features and scatter.
+    Wilderness,
+    /// Free-standing structures with the outdoors tiled by wave-function collapse over the
+    /// block palette's adjacency rules.
+    Wfc,
+}
+
+impl LayoutKind {
+    pub(super) fn select(
+        generation_mode: &str,
+        location: &LocationRecord,
+        has_block_rules: bool,
+    ) -> Self {
+        match generation_mode {
+            "rooms" => return Self::Rooms,
+            "bsp" | "dungeon" => return Self::Bsp,
+            "settlement" | "streets" => return Self::Settlement,
+            "cave" | "caves" => return Self::Cave,
+            "wilderness" => return Self::Wilderness,
+            _ if has_block_rules => return Self::Wfc,
+            _ => {}
+        }
+        let subtype = location.subtype.to_lowercase();
+        let subtype_has =
+            |keywords: &[&str]| keywords.iter().any(|keyword| subtype.contains(keyword));
+        if subtype_has(&[
+            "bunker", "vault", "dungeon", "crypt", "tomb", "catacomb", "prison",
+        ]) {
+            return Self::Bsp;
+        }
+        if subtype_has(&["cave", "cavern", "grotto", "mine", "quarry"]) {
+            return Self::Cave;
+        }
+        match location.category {
+            LocationCategory::Ruin | LocationCategory::Military => Self::Bsp,
+            LocationCategory::Settlement => Self::Settlement,
+            LocationCategory::Resource => Self::Cave,
+            LocationCategory::Wild | LocationCategory::Hazard | LocationCategory::Landmark => {
+                Self::Wilderness
+            }
+            LocationCategory::Infrastructure | LocationCategory::Religious => Self::Rooms,
+        }
+    }
+
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Rooms => "rooms",
+            Self::Bsp => "bsp",
+            Self::Settlement => "settlement",
+            Self::Cave => "cave",
+            Self::Wilderness => "wilderness",
+            Self::Wfc => "wfc",
+        }
+    }
+}
+
+pub(super) struct LayoutRoom {
+    /// Index used in the room's interior, roof, door and NPC ids.
+    pub index: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub label: String,
+    pub carve: RoomCarveResult,
+}
+
+pub(super) struct Layout {
+    /// Generator that produced the tiles; differs from the requested kind after a fallback.
+    pub kind: LayoutKind,
+    pub rooms: Vec<LayoutRoom>,
+    pub spawn: (u32, u32),
+    /// Whether to clear a plaza around the spawn; layouts that carve their own entry only tag it.
+    pub clear_spawn: bool,
+    pub river: bool,
+    /// Footprint extra levels hang under when the layout has no rooms.
+    pub level_anchor: Option<(u32, u32, u32, u32)>,
+    /// Centres natural objects cluster around; empty scatters them uniformly.
+    pub groves: Vec<(u32, u32)>,
+    pub scatter_scale: f32,
+}
+
+impl Layout {
+    fn new(kind: LayoutKind, spawn: (u32, u32)) -> Self {
+        Self {
+            kind,
+            rooms: Vec::new(),
+            spawn,
+            clear_spawn: true,
+            river: false,
+            level_anchor: None,
+            groves: Vec::new(),
+            scatter_scale: 1.0,
+        }
+    }
+
+    pub fn extra_level_anchor(&self) -> Option<(u32, u32, u32, u32)> {
+        self.level_anchor.or_else(|| {
+            self.rooms
+                .first()
+                .map(|room| (room.x, room.y, room.width, room.height))
+        })
+    }
+}
+
+/// Inputs shared by every layout generator.
+pub(super) struct LayoutRequest<'a> {
+    pub location: &'a LocationRecord,
+    pub structure_names: &'a [String],
+    /// Biome name, prompt and lore, lowercased, for keyword matching.
+    pub biome_hint: String,
+    pub block_rules: Option<&'a BlockPaletteRules>,
+}
+
+impl LayoutRequest<'_> {
+    fn room_label(&self, index: usize) -> String {
+        self.structure_names
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| match self.location.category {
+                LocationCategory::Settlement => "building".to_string(),
+                LocationCategory::Ruin => "ruin".to_string(),
+                _ => "outpost".to_string(),
+            })
+    }
+}
+
+/// Runs the generator over a walled grid of floor tiles. The result is fully determined by
+/// the inputs and the rng state.
+pub(super) fn generate_layout(
+    kind: LayoutKind,
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    request: &LayoutRequest,
+    rng: &mut StdRng,
+) -> Layout {
+    match kind {
+        LayoutKind::Rooms => rooms_layout(tiles, width, height, request, rng),
+        LayoutKind::Bsp => bsp_layout(tiles, width, height, request, rng),
+        LayoutKind::Settlement => settlement_layout(tiles, width, height, request, rng),
+        LayoutKind::Cave => cave_layout(tiles, width, height, rng),
+        LayoutKind::Wilderness => wilderness_layout(tiles, width, height, request, rng),
+        LayoutKind::Wfc => wfc_layout(tiles, width, height, request, rng),
+    }
+}
+
+fn rooms_layout(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    request: &LayoutRequest,
+    rng: &mut StdRng,
+) -> Layout {
+    let mut layout = Layout::new(LayoutKind::Rooms, (width / 2, height / 2));
+    layout.river = true;
+    let (center_x, center_y) = layout.spawn;
+    let structure_count = request
+        .structure_names
+        .len()
+        .max(
+            if matches!(
+                request.location.category,
+                LocationCategory::Settlement | LocationCategory::Ruin
+            ) {
+                2
+            } else {
+                1
+            },
+        )
+        .min(5);
+
+    for index in 0..structure_count {
+        for _ in 0..24 {
+            let room_w = rng.random_range(7..=12).min(width.saturating_sub(4));
+            let room_h = rng.random_range(6..=10).min(height.saturating_sub(4));
+            let x = rng.random_range(2..=width.saturating_sub(room_w + 2));
+            let y = rng.random_range(2..=height.saturating_sub(room_h + 2));
+            if rect_intersects_center(x, y, room_w, room_h, center_x, center_y) {
+                continue;
+            }
+            if layout.rooms.iter().any(|room| {
+                rects_overlap(
+                    x,
+                    y,
+                    room_w,
+                    room_h,
+                    room.x,
+                    room.y,
+                    room.width,
+                    room.height,
+                )
+            }) {
+                continue;
+            }
+            let carve = carve_room(tiles, width, x, y, room_w, room_h, rng, index);
+            layout.rooms.push(LayoutRoom {
+                index,
+                x,
+                y,
+                width: room_w,
+                height: room_h,
+                label: request.room_label(index),
+                carve,
+            });
+            break;
+        }
+    }
+    layout
+}
+
+fn bsp_layout(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    request: &LayoutRequest,
+    rng: &mut StdRng,
+) -> Layout {
+    fill_rock(tiles, width, height);
+    let mut leaves = Vec::new();
+    split_leaf(
+        (1, 1, width.saturating_sub(2), height.saturating_sub(2)),
+        0,
+        rng,
+        &mut leaves,
+    );
+    let (center_x, center_y) = (width / 2, height / 2);
+    let hall_index = leaves
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, (x, y, w, h))| {
+            (x + w / 2).abs_diff(center_x) + (y + h / 2).abs_diff(center_y)
+        })
+        .map(|(index, _)| index)
+        .unwrap_or(0);
+
+    let (hall_x, hall_y, hall_w, hall_h) = leaves[hall_index];
+    let hall = (
+        hall_x + 1,
+        hall_y + 1,
+        hall_w.saturating_sub(2),
+        hall_h.saturating_sub(2),
+    );
+    let mut connected = vec![false; (width * height) as usize];
+    for y in hall.1..hall.1 + hall.3 {
+        for x in hall.0..hall.0 + hall.2 {
+            set_open(&mut tiles[tile_index(width, x, y)], "floor", 0.62);
+            connected[tile_index(width, x, y)] = true;
+        }
+    }
+    let mut layout = Layout::new(LayoutKind::Bsp, (hall.0 + hall.2 / 2, hall.1 + hall.3 / 2));
+    layout.clear_spawn = false;
+
+    let mut entrances = Vec::new();
+    for (leaf_index, &(leaf_x, leaf_y, leaf_w, leaf_h)) in leaves.iter().enumerate() {
+        if leaf_index == hall_index || leaf_w < 7 || leaf_h < 7 {
+            continue;
+        }
+        let room_w = rng.random_range(5..=(leaf_w - 2).min(12));
+        let room_h = rng.random_range(5..=(leaf_h - 2).min(10));
+        let x = rng.random_range(leaf_x + 1..=leaf_x + leaf_w - 1 - room_w);
+        let y = rng.random_range(leaf_y + 1..=leaf_y + leaf_h - 1 - room_h);
+        let dx = (x + room_w / 2) as i32 - layout.spawn.0 as i32;
+        let dy = (y + room_h / 2) as i32 - layout.spawn.1 as i32;
+        let door_side = if dx.abs() > dy.abs() {
+            if dx > 0 {
+                2
+            } else {
+                3
+            }
+        } else if dy > 0 {
+            0
+        } else {
+            1
+        };
+        let index = layout.rooms.len();
+        let carve = carve_room_with_door(tiles, width, x, y, room_w, room_h, door_side, index);
+        entrances.push(match door_side {
+            0 => (carve.door_x, carve.door_y - 1),
+            1 => (carve.door_x, carve.door_y + 1),
+            2 => (carve.door_x - 1, carve.door_y),
+            _ => (carve.door_x + 1, carve.door_y),
+        });
+        layout.rooms.push(LayoutRoom {
+            index,
+            x,
+            y,
+            width: room_w,
+            height: room_h,
+            label: request.room_label(index),
+            carve,
+        });
+    }
+
+    entrances.sort_by_key(|(x, y)| x.abs_diff(layout.spawn.0) + y.abs_diff(layout.spawn.1));
+    for entrance in entrances {
+        carve_corridor(tiles, width, height, &mut connected, entrance);
+    }
+    layout
+}
+
+/// Splits the longer axis until leaves are too small to hold two rooms.
+fn split_leaf(
+    leaf: (u32, u32, u32, u32),
+    depth: u32,
+    rng: &mut StdRng,
+    leaves: &mut Vec<(u32, u32, u32, u32)>,
+) {
+    let (x, y, w, h) = leaf;
+    let can_split_x = w >= BSP_MIN_LEAF_WIDTH * 2;
+    let can_split_y = h >= BSP_MIN_LEAF_HEIGHT * 2;
+    if depth >= BSP_MAX_DEPTH || (!can_split_x && !can_split_y) {
+        leaves.push(leaf);
+        return;
+    }
+    let split_x = can_split_x && (!can_split_y || w > h || (w == h && rng.random_bool(0.5)));
+    if split_x {
+        let cut = rng.random_range(BSP_MIN_LEAF_WIDTH..=w - BSP_MIN_LEAF_WIDTH);
+        split_leaf((x, y, cut, h), depth + 1, rng, leaves);
+        split_leaf((x + cut, y, w - cut, h), depth + 1, rng, leaves);
+    } else {
+        let cut = rng.random_range(BSP_MIN_LEAF_HEIGHT..=h - BSP_MIN_LEAF_HEIGHT);
+        split_leaf((x, y, w, cut), depth + 1, rng, leaves);
+        split_leaf((x, y + cut, w, h - cut), depth + 1, rng, leaves);
+    }
+}
+
+/// Carves the shortest corridor through rock from `start` to the connected network.
+fn carve_corridor(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    connected: &mut [bool],
+    start: (u32, u32),
+) {
+    let start_index = tile_index(width, start.0, start.1);
+    let mut previous = vec![usize::MAX; connected.len()];
+    previous[start_index] = start_index;
+    let mut queue = VecDeque::from([start_index]);
+    let mut reached = None;
+    while let Some(index) = queue.pop_front() {
+        if connected[index] {
+            reached = Some(index);
+            break;
+        }
+        let (x, y) = (index as u32 % width, index as u32 / width);
+        for (dx, dy) in [(1_i32, 0_i32), (-1, 0), (0, 1), (0, -1)] {
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx < 1 || ny < 1 || nx >= width as i32 - 1 || ny >= height as i32 - 1 {
+                continue;
+            }
+            let next = tile_index(width, nx as u32, ny as u32);
+            if previous[next] != usize::MAX || tiles[next].interior_id.is_some() {
+                continue;
+            }
+            previous[next] = index;
+            queue.push_back(next);
+        }
+    }
+    let Some(mut index) = reached else {
+        return;
+    };
+    loop {
+        if !connected[index] {
+            set_open(&mut tiles[index], "corridor", 0.5);
+            connected[index] = true;
+        }
+        if index == start_index {
+            break;
+        }
+        index = previous[index];
+    }
+}
+
+fn settlement_layout(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    request: &LayoutRequest,
+    rng: &mut StdRng,
+) -> Layout {
+    let mut layout = Layout::new(LayoutKind::Settlement, (width / 2, height / 2));
+    layout.scatter_scale = 0.6;
+    let (center_x, center_y) = layout.spawn;
+    let (spacing, max_buildings) = match request.location.scale {
+        LocationScale::Minor => (None, 4),
+        LocationScale::Small => (Some(24), 8),
+        LocationScale::Medium => (Some(18), 12),
+        LocationScale::Major => (Some(14), 16),
+        LocationScale::Grand => (Some(12), 20),
+    };
+    let vertical = street_bands(width, center_x, spacing);
+    let horizontal = street_bands(height, center_y, spacing);
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            let on_street = vertical.iter().any(|(start, end)| x >= *start && x < *end)
+                || horizontal
+                    .iter()
+                    .any(|(start, end)| y >= *start && y < *end)
+                || (x.abs_diff(center_x) <= 4 && y.abs_diff(center_y) <= 4);
+            if on_street {
+                set_open(&mut tiles[tile_index(width, x, y)], "road", 0.84);
+                tiles[tile_index(width, x, y)].move_cost = 0.8;
+            }
+        }
+    }
+
+    for (block_y, block_h, street_above, street_below) in blocks_between(&horizontal, height) {
+        for (block_x, block_w, _, _) in blocks_between(&vertical, width) {
+            let mut x = block_x + 1;
+            while layout.rooms.len() < max_buildings {
+                let room_w = rng.random_range(6..=9);
+                let room_h = rng.random_range(5..=7);
+                if x + room_w + 1 > block_x + block_w || room_h > block_h {
+                    break;
+                }
+                let mut rows = Vec::new();
+                if street_above {
+                    rows.push((block_y, 0));
+                }
+                if street_below && (!street_above || block_h > room_h * 2) {
+                    rows.push((block_y + block_h - room_h, 1));
+                }
+                for (y, door_side) in rows {
+                    if layout.rooms.len() >= max_buildings
+                        || rect_intersects_center(x, y, room_w, room_h, center_x, center_y)
+                    {
+                        continue;
+                    }
+                    let index = layout.rooms.len();
+                    let carve =
+                        carve_room_with_door(tiles, width, x, y, room_w, room_h, door_side, index);
+                    layout.rooms.push(LayoutRoom {
+                        index,
+                        x,
+                        y,
+                        width: room_w,
+                        height: room_h,
+                        label: request.room_label(index),
+                        carve,
+                    });
+                }
+                x += room_w + rng.random_range(1..=2);
+            }
+        }
+    }
+    layout
+}
+
+/// Street bands `[start, end)` along one axis: a main street through the centre and, for larger
+/// settlements, side streets every `spacing` tiles.
+fn street_bands(extent: u32, center: u32, spacing: Option<u32>) -> Vec<(u32, u32)> {
+    let main_start = center.saturating_sub(MAIN_STREET_WIDTH / 2);
+    let mut bands = vec![(main_start, main_start + MAIN_STREET_WIDTH)];
+    if let Some(spacing) = spacing {
+        let mut offset = spacing;
+        while offset + SIDE_STREET_WIDTH + 4 < center {
+            bands.push((center - offset, center - offset + SIDE_STREET_WIDTH));
+            if center + offset + SIDE_STREET_WIDTH + 4 < extent {
+                bands.push((center + offset, center + offset + SIDE_STREET_WIDTH));
+            }
+            offset += spacing;
+        }
+    }
+    bands.sort();
+    bands
+}
+
+/// Gaps between street bands as `(start, len, street_before, street_after)`, inside the border.
+fn blocks_between(bands: &[(u32, u32)], extent: u32) -> Vec<(u32, u32, bool, bool)> {
+    let mut blocks = Vec::new();
+    let mut start = 1;
+    let mut street_before = false;
+    for (band_start, band_end) in bands {
+        if *band_start > start {
+            blocks.push((start, band_start - start, street_before, true));
+        }
+        start = *band_end;
+        street_before = true;
+    }
+    let end = extent.saturating_sub(1);
+    if end > start {
+        blocks.push((start, end - start, street_before, false));
+    }
+    blocks
+}
+
+fn cave_layout(tiles: &mut [ExplorationTile], width: u32, height: u32, rng: &mut StdRng) -> Layout {
+    let mut layout = Layout::new(LayoutKind::Cave, (width / 2, height / 2));
+    layout.clear_spawn = false;
+    layout.scatter_scale = 0.6;
+    let (center_x, center_y) = layout.spawn;
+    let keep_open = |x: u32, y: u32| x.abs_diff(center_x) <= 3 && y.abs_diff(center_y) <= 3;
+    let inside = |x: u32, y: u32| x >= 1 && y >= 1 && x + 1 < width && y + 1 < height;
+
+    let mut open = vec![false; (width * height) as usize];
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            open[tile_index(width, x, y)] = keep_open(x, y) || !rng.random_bool(CAVE_ROCK_CHANCE);
+        }
+    }
+    for _ in 0..CAVE_SMOOTHING_PASSES {
+        let mut next = open.clone();
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                let mut rock = 0;
+                for ny in y - 1..=y + 1 {
+                    for nx in x - 1..=x + 1 {
+                        if (nx, ny) != (x, y)
+                            && (!inside(nx, ny) || !open[tile_index(width, nx, ny)])
+                        {
+                            rock += 1;
+                        }
+                    }
+                }
+                next[tile_index(width, x, y)] = keep_open(x, y) || rock < 5;
+            }
+        }
+        open = next;
+    }
+
+    let reachable = flood_fill(&open, width, height, layout.spawn);
+    for (index, tile) in tiles.iter_mut().enumerate() {
+        if reachable[index] {
+            set_open(tile, "floor", 0.5);
+        } else {
+            set_rock(tile);
+        }
+    }
+
+    let mut chambers = (2..height.saturating_sub(4))
+        .flat_map(|y| (2..width.saturating_sub(4)).map(move |x| (x, y)))
+        .filter(|(x, y)| x.abs_diff(center_x) >= 6 || y.abs_diff(center_y) >= 6)
+        .filter(|(x, y)| {
+            (y - 1..=y + 1).all(|ny| (x - 1..=x + 1).all(|nx| reachable[tile_index(width, nx, ny)]))
+        })
+        .collect::<Vec<_>>();
+    if !chambers.is_empty() {
+        let (x, y) = chambers.swap_remove(rng.random_range(0..chambers.len()));
+        layout.level_anchor = Some((x - 1, y - 1, 5, 5));
+    }
+    for _ in 0..3 {
+        if chambers.is_empty() {
+            break;
+        }
+        layout
+            .groves
+            .push(chambers.swap_remove(rng.random_range(0..chambers.len())));
+    }
+    layout
+}
+
+fn flood_fill(open: &[bool], width: u32, height: u32, start: (u32, u32)) -> Vec<bool> {
+    let mut reached = vec![false; open.len()];
+    let start_index = tile_index(width, start.0, start.1);
+    if !open[start_index] {
+        return reached;
+    }
+    reached[start_index] = true;
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, y)) = queue.pop_front() {
+        for (dx, dy) in [(1_i32, 0_i32), (-1, 0), (0, 1), (0, -1)] {
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                continue;
+            }
+            let index = tile_index(width, nx as u32, ny as u32);
+            if open[index] && !reached[index] {
+                reached[index] = true;
+                queue.push_back((nx as u32, ny as u32));
+            }
+        }
+    }
+    reached
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WildBiome {
+    Forest,
+    Swamp,
+    Desert,
+    Tundra,
+    Grassland,
+}
+
+fn infer_wild_biome(hint: &str) -> WildBiome {
+    let has = |keywords: &[&str]| keywords.iter().any(|keyword| hint.contains(keyword));
+    if has(&["swamp", "marsh", "bog", "wetland", "mangrove"]) {
+        WildBiome::Swamp
+    } else if has(&["forest", "jungle", "wood", "taiga", "grove"]) {
+        WildBiome::Forest
+    } else if has(&["desert", "dune", "badland", "waste", "arid"]) {
+        WildBiome::Desert
+    } else if has(&[
+        "tundra", "snow", "ice", "glacier", "frozen", "mountain", "alpine",
+    ]) {
+        WildBiome::Tundra
+    } else {
+        WildBiome::Grassland
+    }
+}
+
+fn wilderness_layout(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    request: &LayoutRequest,
+    rng: &mut StdRng,
+) -> Layout {
+    let mut layout = Layout::new(LayoutKind::Wilderness, (width / 2, height / 2));
+    let biome = infer_wild_biome(&request.biome_hint);
+    let (groves, outcrops, ponds, sand_patches) = match biome {
+        WildBiome::Forest => (6, 1, 0, 0),
+        WildBiome::Swamp => (4, 0, 5, 0),
+        WildBiome::Desert => (0, 4, 0, 6),
+        WildBiome::Tundra => (1, 5, 1, 0),
+        WildBiome::Grassland => (3, 1, 1, 0),
+    };
+    layout.river = matches!(biome, WildBiome::Forest | WildBiome::Grassland);
+    layout.scatter_scale = match biome {
+        WildBiome::Forest => 2.2,
+        WildBiome::Swamp => 1.4,
+        WildBiome::Desert => 0.4,
+        WildBiome::Tundra => 0.5,
+        WildBiome::Grassland => 1.0,
+    };
+
+    for _ in 0..outcrops {
+        paint_blob(tiles, width, height, layout.spawn, rng, 10..=24, set_rock);
+    }
+    for _ in 0..ponds {
+        paint_blob(
+            tiles,
+            width,
+            height,
+            layout.spawn,
+            rng,
+            8..=18,
+            set_water_tile,
+        );
+    }
+    for _ in 0..sand_patches {
+        paint_blob(
+            tiles,
+            width,
+            height,
+            layout.spawn,
+            rng,
+            20..=40,
+            set_sand_tile,
+        );
+    }
+    for _ in 0..groves {
+        let x = rng.random_range(4..width.saturating_sub(4).max(5));
+        let y = rng.random_range(4..height.saturating_sub(4).max(5));
+        if x.abs_diff(layout.spawn.0) > 5 || y.abs_diff(layout.spawn.1) > 5 {
+            layout.groves.push((x, y));
+        }
+    }
+    layout
+}
+
+/// Random-walk blob of outdoor tiles, kept off the border and away from the spawn.
+fn paint_blob(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    spawn: (u32, u32),
+    rng: &mut StdRng,
+    size: std::ops::RangeInclusive<u32>,
+    paint: fn(&mut ExplorationTile),
+) {
+    if width < 8 || height < 8 {
+        return;
+    }
+    let mut x = rng.random_range(2..width - 2) as i32;
+    let mut y = rng.random_range(2..height - 2) as i32;
+    for _ in 0..rng.random_range(size) {
+        let (dx, dy) = [(1, 0), (-1, 0), (0, 1), (0, -1)][rng.random_range(0..4)];
+        x = (x + dx).clamp(2, width as i32 - 3);
+        y = (y + dy).clamp(2, height as i32 - 3);
+        if x.abs_diff(spawn.0 as i32) <= 4 && y.abs_diff(spawn.1 as i32) <= 4 {
+            continue;
+        }
+        let tile = &mut tiles[tile_index(width, x as u32, y as u32)];
+        if tile.r#type == "floor" {
+            paint(tile);
+        }
+    }
+}
+
+/// Places structures like [`LayoutKind::Rooms`], then tiles everything outdoors with the block
+/// palette. The spawn, a path to every door and an optional river are pinned before collapsing.
+/// Without usable rules, or when the pins cannot be tiled, the plain rooms layout is kept and
+/// reported as [`LayoutKind::Rooms`].
+fn wfc_layout(
+    tiles: &mut [ExplorationTile],
+    width: u32,
+    height: u32,
+    request: &LayoutRequest,
+    rng: &mut StdRng,
+) -> Layout {
+    let mut layout = rooms_layout(tiles, width, height, request, rng);
+    let Some(palette) = request
+        .block_rules
+        .and_then(|rules| WfcPalette::compile(rules).ok())
+    else {
+        return layout;
+    };
+
+    let active = (0..width * height)
+        .map(|index| {
+            let (x, y) = (index % width, index / width);
+            x > 0
+                && y > 0
+                && x + 1 < width
+                && y + 1 < height
+                && tiles[index as usize].interior_id.is_none()
+        })
+        .collect::<Vec<_>>();
+    let mut pins = vec![None; active.len()];
+    let (spawn_x, spawn_y) = layout.spawn;
+
+    if let Some(river) = palette.river {
+        let mut river_y = if rng.random_bool(0.5) {
+            height / 4
+        } else {
+            height * 3 / 4
+        };
+        for x in 1..width.saturating_sub(1) {
+            if x % 4 == 0 {
+                river_y =
+                    (river_y as i32 + rng.random_range(-1..=1)).clamp(2, height as i32 - 3) as u32;
+            }
+            if x.abs_diff(spawn_x) <= 3 && river_y.abs_diff(spawn_y) <= 3 {
+                continue;
+            }
+            let index = tile_index(width, x, river_y);
+            if active[index] {
+                pins[index] = Some(river);
+            }
+        }
+    }
+
+    let mut connected = vec![false; active.len()];
+    for y in spawn_y.saturating_sub(2)..=(spawn_y + 2).min(height - 2) {
+        for x in spawn_x.saturating_sub(2)..=(spawn_x + 2).min(width - 2) {
+            let index = tile_index(width, x, y);
+            if active[index] {
+                pins[index] = Some(palette.path);
+                connected[index] = true;
+            }
+        }
+    }
+    for room in &layout.rooms {
+        let door = (room.carve.door_x, room.carve.door_y);
+        let front = if door.1 == room.y {
+            (door.0, door.1.saturating_sub(1))
+        } else if door.1 == room.y + room.height - 1 {
+            (door.0, door.1 + 1)
+        } else if door.0 == room.x {
+            (door.0.saturating_sub(1), door.1)
+        } else {
+            (door.0 + 1, door.1)
+        };
+        for index in path_to_network(&active, &connected, width, height, front) {
+            pins[index] = Some(palette.path);
+            connected[index] = true;
+        }
+    }
+
+    let Ok(cells) = collapse(&palette, width, height, &active, &pins, rng) else {
+        return layout;
+    };
+    for (index, cell) in cells.into_iter().enumerate() {
+        if let Some(tile) = cell {
+            tiles[index] = palette.tile(tile);
+        }
+    }
+    layout.kind = LayoutKind::Wfc;
+    layout.clear_spawn = false;
+    layout.river = false;
+    layout
+}
+
+/// Shortest path of active cells from `start` to any connected cell, including `start`.
+fn path_to_network(
+    active: &[bool],
+    connected: &[bool],
+    width: u32,
+    height: u32,
+    start: (u32, u32),
+) -> Vec<usize> {
+    let start_index = tile_index(width, start.0, start.1);
+    if !active[start_index] {
+        return Vec::new();
+    }
+    let mut previous = vec![usize::MAX; active.len()];
+    previous[start_index] = start_index;
+    let mut queue = VecDeque::from([start_index]);
+    while let Some(index) = queue.pop_front() {
+        if connected[index] {
+            let mut path = Vec::new();
+            let mut cursor = previous[index];
+            while cursor != start_index {
+                path.push(cursor);
+                cursor = previous[cursor];
+            }
+            path.push(start_index);
+            return path;
+        }
+        let (x, y) = (index as u32 % width, index as u32 / width);
+        for (dx, dy) in [(1_i32, 0_i32), (-1, 0), (0, 1), (0, -1)] {
+            let nx = x as i32 + dx;
+            let ny = y as i32 + dy;
+            if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                continue;
+            }
+            let next = tile_index(width, nx as u32, ny as u32);
+            if active[next] && previous[next] == usize::MAX {
+                previous[next] = index;
+                queue.push_back(next);
+            }
+        }
+    }
+    Vec::new()
+}
+
+fn fill_rock(tiles: &mut [ExplorationTile], width: u32, height: u32) {
+    for y in 1..height.saturating_sub(1) {
+        for x in 1..width.saturating_sub(1) {
+            set_rock(&mut tiles[tile_index(width, x, y)]);
+        }
+    }
+}
+
+fn set_rock(tile: &mut ExplorationTile) {
+    tile.r#type = "rock".to_string();
+    tile.walkable = false;
+    tile.move_cost = 0.0;
+    tile.light_level = Some(0.12);
+    tile.blocks_light = Some(true);
+}
+
+fn set_open(tile: &mut ExplorationTile, kind: &str, light_level: f32) {
+    tile.r#type = kind.to_string();
+    tile.walkable = true;
+    tile.move_cost = 1.0;
+    tile.light_level = Some(light_level);
+    tile.blocks_light = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{build_test_location_record, set_wall};
+    use super::*;
+    use rand::SeedableRng;
+
+    fn floor_grid(width: u32, height: u32) -> Vec<ExplorationTile> {
+        let mut tiles = vec![
+            ExplorationTile {
+                r#type: "floor".to_string(),
+                walkable: true,
+                move_cost: 1.0,
+                texture_url: None,
+                is_spawn_zone: None,
+                interior_id: None,
+                light_level: Some(0.82),
+                blocks_light: None,
+                door_id: None,
+                level_link: None,
+            };
+            (width * height) as usize
+        ];
+        for y in 0..height {
+            for x in 0..width {
+                if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                    set_wall(&mut tiles, width, x, y);
+                }
+            }
+        }
+        tiles
+    }
+
+    fn location(category: LocationCategory, scale: LocationScale) -> LocationRecord {
+        let mut location = build_test_location_record("world");
+        location.category = category;
+        location.subtype = "generic".to_string();
+        location.scale = scale;
+        location
+    }
+
+    fn generate(
+        kind: LayoutKind,
+        location: &LocationRecord,
+        seed: u64,
+    ) -> (Vec<ExplorationTile>, Layout) {
+        let rules = meadow_rules();
+        let mut tiles = floor_grid(64, 64);
+        let request = LayoutRequest {
+            location,
+            structure_names: &[],
+            biome_hint: "temperate forest".to_string(),
+            block_rules: Some(&rules),
+        };
+        let layout = generate_layout(
+            kind,
+            &mut tiles,
+            64,
+            64,
+            &request,
+            &mut StdRng::seed_from_u64(seed),
+        );
+        (tiles, layout)
+    }
+
+    fn meadow_rules() -> BlockPaletteRules {
+        serde_json::from_value(serde_json::json!({
+            "tiles": [
+                { "type": "grass", "weight": 4.0, "walkable": true },
+                { "type": "path", "walkable": true },
+                { "type": "stream", "walkable": true, "moveCost": 1.9 },
+                { "type": "thicket", "weight": 2.0, "walkable": false, "blocksLight": true }
+            ],
+            "adjacency": [
+                { "a": "grass", "b": "grass" },
+                { "a": "path", "b": "path" },
+                { "a": "stream", "b": "stream" },
+                { "a": "thicket", "b": "thicket", "weight": 3.0 },
+                { "a": "grass", "b": "path" },
+                { "a": "grass", "b": "stream" },
+                { "a": "grass", "b": "thicket" },
+                { "a": "path", "b": "stream" }
+            ],
+            "pathTile": "path",
+            "riverTile": "stream"
+        }))
+        .unwrap()
+    }
+
+    /// Every door must be reachable from the spawn through walkable tiles.
+    fn assert_doors_reachable(tiles: &[ExplorationTile], layout: &Layout) {
+        let open = tiles.iter().map(|tile| tile.walkable).collect::<Vec<_>>();
+        let reached = flood_fill(&open, 64, 64, layout.spawn);
+        for room in &layout.rooms {
+            assert!(
+                reached[tile_index(64, room.carve.door_x, room.carve.door_y)],
+                "door of room {} is unreachable",
+                room.index
+            );
+        }
+    }
+
+    #[test]
+    fn layouts_are_deterministic_and_connected() {
+        for (kind, category) in [
+            (LayoutKind::Bsp, LocationCategory::Ruin),
+            (LayoutKind::Settlement, LocationCategory::Settlement),
+            (LayoutKind::Cave, LocationCategory::Resource),
+            (LayoutKind::Wilderness, LocationCategory::Wild),
+            (LayoutKind::Wfc, LocationCategory::Religious),
+        ] {
+            let location = location(category, LocationScale::Medium);
+            let has_rules = kind == LayoutKind::Wfc;
+            assert_eq!(LayoutKind::select("procedural", &location, has_rules), kind);
+            let (tiles, layout) = generate(kind, &location, 7);
+            let (again, _) = generate(kind, &location, 7);
+            assert_eq!(
+                serde_json::to_string(&tiles).unwrap(),
+                serde_json::to_string(&again).unwrap()
+            );
+            assert!(tiles[tile_index(64, layout.spawn.0, layout.spawn.1)].walkable);
+            assert_doors_reachable(&tiles, &layout);
+        }
+    }
+
+    #[test]
+    fn bsp_and_settlement_layouts_place_rooms() {
+        let ruin = location(LocationCategory::Ruin, LocationScale::Medium);
+        let (_, dungeon) = generate(LayoutKind::Bsp, &ruin, 11);
+        assert!(dungeon.rooms.len() >= 4);
+
+        let minor = location(LocationCategory::Settlement, LocationScale::Minor);
+        let grand = location(LocationCategory::Settlement, LocationScale::Grand);
+        let (_, hamlet) = generate(LayoutKind::Settlement, &minor, 11);
+        let (_, city) = generate(LayoutKind::Settlement, &grand, 11);
+        assert!(!hamlet.rooms.is_empty() && hamlet.rooms.len() <= 4);
+        assert!(city.rooms.len() > hamlet.rooms.len());
+    }
+
+    #[test]
+    fn wfc_layout_tiles_outdoors_from_the_palette() {
+        let shrine = location(LocationCategory::Religious, LocationScale::Medium);
+        let (tiles, layout) = generate(LayoutKind::Wfc, &shrine, 3);
+        assert_eq!(layout.kind, LayoutKind::Wfc);
+        let outdoor = tiles
+            .iter()
+            .filter(|tile| tile.interior_id.is_none() && tile.r#type != "wall")
+            .map(|tile| tile.r#type.as_str())
+            .collect::<std::collections::HashSet<_>>();
+        assert!(outdoor.is_subset(&["grass", "path", "stream", "thicket"].into()));
+        assert!(outdoor.contains("path") && outdoor.contains("stream"));
+        assert_eq!(
+            tiles[tile_index(64, layout.spawn.0, layout.spawn.1)].r#type,
+            "path"
+        );
+    }
+
+    #[test]
+    fn wfc_layout_reports_the_rooms_fallback() {
+        // Path tiles may not touch each other, so the pinned plaza cannot be tiled.
+        let rules: BlockPaletteRules = serde_json::from_value(serde_json::json!({
+            "tiles": [
+                { "type": "grass", "walkable": true },
+                { "type": "path", "walkable": true }
+            ],
+            "adjacency": [
+                { "a": "grass", "b": "grass" },
+                { "a": "grass", "b": "path" }
+            ],
+            "pathTile": "path"
+        }))
+        .unwrap();
+        let shrine = location(LocationCategory::Religious, LocationScale::Medium);
+        let mut tiles = floor_grid(64, 64);
+        let request = LayoutRequest {
+            location: &shrine,
+            structure_names: &[],
+            biome_hint: String::new(),
+            block_rules: Some(&rules),
+        };
+        let layout = generate_layout(
+            LayoutKind::Wfc,
+            &mut tiles,
+            64,
+            64,
+            &request,
+            &mut StdRng::seed_from_u64(3),
+        );
+        assert_eq!(layout.kind, LayoutKind::Rooms);
+        assert!(layout.clear_spawn);
+    }
+}
@@ -0,0 +1,422 @@
+use rand::{rngs::StdRng, Rng};
+
+use crate::asset_packs::BlockPaletteRules;
+
+use super::ExplorationTile;
+
+const MAX_PALETTE_TILES: usize = 64;
+const MAX_BACKTRACKS: usize = 4096;
+
+/// Block palette rules compiled to bitmasks for wave-function collapse.
+pub(super) struct WfcPalette {
+    rules: BlockPaletteRules,
+    weights: Vec<f64>,
+    /// Bitmask of tiles allowed next to each tile.
+    compatible: Vec<u64>,
+    /// Row-major `[placed][candidate]` preference weights.
+    pair_weights: Vec<f64>,
+    pub path: usize,
+    pub river: Option<usize>,
+}
+
+impl WfcPalette {
+    pub fn compile(rules: &BlockPaletteRules) -> Result<Self, String> {
+        let count = rules.tiles.len();
+        if count == 0 || count > MAX_PALETTE_TILES {
+            return Err(format!(
+                "Block palette must define between 1 and {MAX_PALETTE_TILES} tiles, found {count}"
+            ));
+        }
+        let index_of = |tile_type: &str| {
+            rules
+                .tiles
+                .iter()
+                .position(|tile| tile.tile_type == tile_type)
+                .ok_or_else(|| format!("Block palette rule references unknown tile '{tile_type}'"))
+        };
+        let mut compatible = vec![0_u64; count];
+        let mut pair_weights = vec![0.0; count * count];
+        for rule in &rules.adjacency {
+            let a = index_of(&rule.a)?;
+            let b = index_of(&rule.b)?;
+            if rule.weight <= 0.0 {
+                return Err(format!(
+                    "Block palette rule {}-{} needs a positive weight",
+                    rule.a, rule.b
+                ));
+            }
+            compatible[a] |= 1 << b;
+            compatible[b] |= 1 << a;
+            pair_weights[a * count + b] = rule.weight as f64;
+            pair_weights[b * count + a] = rule.weight as f64;
+        }
+        let path = index_of(&rules.path_tile)?;
+        if !rules.tiles[path].walkable {
+            return Err(format!(
+                "Block palette path tile '{}' must be walkable",
+                rules.path_tile
+            ));
+        }
+        let river = rules.river_tile.as_deref().map(index_of).transpose()?;
+        Ok(Self {
+            weights: rules
+                .tiles
+                .iter()
+                .map(|tile| tile.weight.max(0.0) as f64)
+                .collect(),
+            rules: rules.clone(),
+            compatible,
+            pair_weights,
+            path,
+            river,
+        })
+    }
+
+    pub fn tile(&self, index: usize) -> ExplorationTile {
+        let tile = &self.rules.tiles[index];
+        ExplorationTile {
+            r#type: tile.tile_type.clone(),
+            walkable: tile.walkable,
+            move_cost: if tile.walkable { tile.move_cost } else { 0.0 },
+            texture_url: tile.texture_url.clone(),
+            is_spawn_zone: None,
+            interior_id: None,
+            light_level: Some(tile.light_level.unwrap_or(0.82)),
+            blocks_light: tile.blocks_light,
+            door_id: None,
+            level_link: None,
+        }
+    }
+
+    fn full_mask(&self) -> u64 {
+        if self.weights.len() == 64 {
+            u64::MAX
+        } else {
+            (1 << self.weights.len()) - 1
+        }
+    }
+}
+
+struct Decision {
+    trail_len: usize,
+    cell: usize,
+    tried: u64,
+}
+
+/// Collapses the `active` cells of a `width` x `height` grid so every pair of active neighbours
+/// satisfies the palette's adjacency rules. Pinned cells keep their tile; inactive cells neither
+/// collapse nor constrain their neighbours. Contradictions are resolved by backtracking the
+/// most recent choice.
+pub(super) fn collapse(
+    palette: &WfcPalette,
+    width: u32,
+    height: u32,
+    active: &[bool],
+    pins: &[Option<usize>],
+    rng: &mut StdRng,
+) -> Result<Vec<Option<usize>>, String> {
+    let full = palette.full_mask();
+    let mut domains = active
+        .iter()
+        .zip(pins)
+        .map(|(active, pin)| match (active, pin) {
+            (false, _) => 0,
+            (true, Some(tile)) => 1 << tile,
+            (true, None) => full,
+        })
+        .collect::<Vec<u64>>();
+    let mut trail = Vec::<(usize, u64)>::new();
+    let all_active = (0..domains.len()).filter(|index| active[*index]).collect();
+    if !propagate(
+        palette,
+        width,
+        height,
+        active,
+        &mut domains,
+        &mut trail,
+        all_active,
+    ) {
+        return Err("Block palette pins contradict the adjacency rules".to_string());
+    }
+
+    let mut decisions = Vec::<Decision>::new();
+    let mut backtracks = 0;
+    while let Some(cell) = lowest_entropy_cell(&domains, rng) {
+        let tile = choose_tile(palette, width, height, active, &domains, cell, rng);
+        decisions.push(Decision {
+            trail_len: trail.len(),
+            cell,
+            tried: 1 << tile,
+        });
+        trail.push((cell, domains[cell]));
+        domains[cell] = 1 << tile;
+        let mut consistent = propagate(
+            palette,
+            width,
+            height,
+            active,
+            &mut domains,
+            &mut trail,
+            vec![cell],
+        );
+        while !consistent {
+            backtracks += 1;
+            let decision = decisions
+                .pop()
+                .filter(|_| backtracks <= MAX_BACKTRACKS)
+                .ok_or_else(|| {
+                    "Failed to collapse block palette layout: no consistent tiling".to_string()
+                })?;
+            while trail.len() > decision.trail_len {
+                let (index, domain) = trail.pop().expect("trail entry");
+                domains[index] = domain;
+            }
+            let remaining = domains[decision.cell] & !decision.tried;
+            trail.push((decision.cell, domains[decision.cell]));
+            domains[decision.cell] = remaining;
+            consistent = remaining != 0
+                && propagate(
+                    palette,
+                    width,
+                    height,
+                    active,
+                    &mut domains,
+                    &mut trail,
+                    vec![decision.cell],
+                );
+        }
+    }
+
+    Ok(domains
+        .iter()
+        .map(|domain| (*domain != 0).then(|| domain.trailing_zeros() as usize))
+        .collect())
+}
+
+fn propagate(
+    palette: &WfcPalette,
+    width: u32,
+    height: u32,
+    active: &[bool],
+    domains: &mut [u64],
+    trail: &mut Vec<(usize, u64)>,
+    mut queue: Vec<usize>,
+) -> bool {
+    while let Some(cell) = queue.pop() {
+        let mut allowed = 0;
+        let mut bits = domains[cell];
+        while bits != 0 {
+            allowed |= palette.compatible[bits.trailing_zeros() as usize];
+            bits &= bits - 1;
+        }
+        for neighbor in neighbors(width, height, cell) {
+            if !active[neighbor] {
+                continue;
+            }
+            let narrowed = domains[neighbor] & allowed;
+            if narrowed == domains[neighbor] {
+                continue;
+            }
+            if narrowed == 0 {
+                return false;
+            }
+            trail.push((neighbor, domains[neighbor]));
+            domains[neighbor] = narrowed;
+            queue.push(neighbor);
+        }
+    }
+    true
+}
+
+/// Undecided cell with the fewest candidates, ties broken at random.
+fn lowest_entropy_cell(domains: &[u64], rng: &mut StdRng) -> Option<usize> {
+    let mut best = u32::MAX;
+    let mut candidates = Vec::new();
+    for (index, domain) in domains.iter().enumerate() {
+        let options = domain.count_ones();
+        if options < 2 || options > best {
+            continue;
+        }
+        if options < best {
+            best = options;
+            candidates.clear();
+        }
+        candidates.push(index);
+    }
+    (!candidates.is_empty()).then(|| candidates[rng.random_range(0..candidates.len())])
+}
+
+/// Weighted pick among a cell's candidates, favouring pairings preferred by placed neighbours.
+fn choose_tile(
+    palette: &WfcPalette,
+    width: u32,
+    height: u32,
+    active: &[bool],
+    domains: &[u64],
+    cell: usize,
+    rng: &mut StdRng,
+) -> usize {
+    let count = palette.weights.len();
+    let mut options = Vec::new();
+    let mut bits = domains[cell];
+    while bits != 0 {
+        let tile = bits.trailing_zeros() as usize;
+        bits &= bits - 1;
+        let mut weight = palette.weights[tile];
+        for neighbor in neighbors(width, height, cell) {
+            if active[neighbor] && domains[neighbor].count_ones() == 1 {
+                let placed = domains[neighbor].trailing_zeros() as usize;
+                weight *= palette.pair_weights[placed * count + tile];
+            }
+        }
+        options.push((tile, weight));
+    }
+    let total = options.iter().map(|(_, weight)| weight).sum::<f64>();
+    if total <= 0.0 {
+        return options[rng.random_range(0..options.len())].0;
+    }
+    let mut roll = rng.random_range(0.0..total);
+    for (tile, weight) in &options {
+        if roll < *weight {
+            return *tile;
+        }
+        roll -= weight;
+    }
+    options[options.len() - 1].0
+}
+
+fn neighbors(width: u32, height: u32, cell: usize) -> impl Iterator<Item = usize> {
+    let (width, height) = (width as usize, height as usize);
+    let (x, y) = (cell % width, cell / width);
+    [
+        (x > 0).then(|| cell - 1),
+        (x + 1 < width).then_some(cell + 1),
+        (y > 0).then(|| cell - width),
+        (y + 1 < height).then_some(cell + width),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset_packs::{BlockAdjacencyRule, BlockPaletteTile};
+    use rand::SeedableRng;
+
+    fn palette_tile(tile_type: &str, walkable: bool, weight: f32) -> BlockPaletteTile {
+        BlockPaletteTile {
+            tile_type: tile_type.to_string(),
+            weight,
+            walkable,
+            move_cost: 1.0,
+            texture_url: None,
+            blocks_light: None,
+            light_level: None,
+        }
+    }
+
+    fn rule(a: &str, b: &str) -> BlockAdjacencyRule {
+        BlockAdjacencyRule {
+            a: a.to_string(),
+            b: b.to_string(),
+            weight: 1.0,
+        }
+    }
+
+    fn meadow_rules() -> BlockPaletteRules {
+        BlockPaletteRules {
+            tiles: vec![
+                palette_tile("grass", true, 4.0),
+                palette_tile("path", true, 1.0),
+                palette_tile("water", true, 1.0),
+                palette_tile("tree", false, 2.0),
+            ],
+            adjacency: vec![
+                rule("grass", "grass"),
+                rule("path", "path"),
+                rule("water", "water"),
+                rule("tree", "tree"),
+                rule("grass", "path"),
+                rule("grass", "water"),
+                rule("grass", "tree"),
+                rule("path", "water"),
+            ],
+            path_tile: "path".to_string(),
+            river_tile: Some("water".to_string()),
+        }
+    }
+
+    #[test]
+    fn collapse_honours_pins_and_adjacency() {
+        let palette = WfcPalette::compile(&meadow_rules()).unwrap();
+        let (width, height) = (20, 16);
+        let mut active = vec![true; 320];
+        active[5 * 20 + 5] = false;
+        let mut pins = vec![None; 320];
+        pins[0] = Some(palette.path);
+        pins[319] = palette.river;
+
+        let first = collapse(
+            &palette,
+            width,
+            height,
+            &active,
+            &pins,
+            &mut StdRng::seed_from_u64(3),
+        )
+        .unwrap();
+        let second = collapse(
+            &palette,
+            width,
+            height,
+            &active,
+            &pins,
+            &mut StdRng::seed_from_u64(3),
+        )
+        .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first[0], Some(palette.path));
+        assert_eq!(first[319], palette.river);
+        assert_eq!(first[105], None);
+        for (cell, tile) in first.iter().enumerate() {
+            let Some(tile) = tile else {
+                continue;
+            };
+            for neighbor in neighbors(width, height, cell) {
+                if let Some(other) = first[neighbor] {
+                    assert!(palette.compatible[*tile] & (1 << other) != 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn contradictory_pins_are_rejected() {
+        let rules = BlockPaletteRules {
+            tiles: vec![
+                palette_tile("light", true, 1.0),
+                palette_tile("dark", true, 1.0),
+            ],
+            adjacency: vec![rule("light", "dark")],
+            path_tile: "light".to_string(),
+            river_tile: None,
+        };
+        let palette = WfcPalette::compile(&rules).unwrap();
+        let active = vec![true; 16];
+        let mut pins = vec![None; 16];
+        let mut rng = StdRng::seed_from_u64(1);
+        let checkerboard = collapse(&palette, 4, 4, &active, &pins, &mut rng).unwrap();
+        assert_ne!(checkerboard[0], checkerboard[1]);
+        assert_eq!(checkerboard[0], checkerboard[5]);
+
+        pins[0] = Some(0);
+        pins[1] = Some(0);
+        assert!(collapse(&palette, 4, 4, &active, &pins, &mut rng).is_err());
+        assert!(WfcPalette::compile(&BlockPaletteRules {
+            path_tile: "missing".to_string(),
+            ..rules
+        })
+        .is_err());
+    }
+}
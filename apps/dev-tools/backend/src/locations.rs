@@ -4,14 +4,21 @@ use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use worldgen_core::biome::{BiomePaletteEntry, BIOME_PALETTE_FILE};
 use worldgen_core::cluster::{DuchyRecord, KingdomRecord, ProvinceRecord};
 use worldgen_core::graph::ProvinceAdjacency;
+use worldgen_core::raster::distance_transform;
+use worldgen_core::roads::{RoadWaypoint, ROAD_WAYPOINTS_FILE};
 
 use crate::gemini;
 
 const PROVINCE_REGION_PREFIX: &str = "wgen_provinces_";
 const BATCH_SIZE: usize = 12;
 const MAX_GENERATED_LORE_SNIPPETS: usize = 8;
+/// Pixels from a road within which a candidate point counts as on the road.
+const ROAD_REACH: f32 = 6.0;
+/// Importance from which a standing settlement or infrastructure becomes a road waypoint.
+const ROAD_WAYPOINT_IMPORTANCE: u32 = 60;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -364,6 +371,7 @@ struct CandidatePoint {
     y: f32,
     river: bool,
     coastal: bool,
+    road: bool,
     slope: f32,
     elevation: f32,
     score: f32,
@@ -678,6 +686,12 @@ pub async fn simulate_locations(
 
     let biome_report: Option<BiomeReportLite> =
         read_optional_json(worldgen_dir.join("biome_report.json"));
+    let biome_palette: HashMap<u16, String> =
+        read_optional_json::<Vec<BiomePaletteEntry>>(worldgen_dir.join(BIOME_PALETTE_FILE))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| (entry.index, entry.biome_id))
+            .collect();
     let ecology_records: Vec<EcologyProvinceRecordLite> = read_optional_json(
         planets_dir
             .join(world_id)
@@ -700,14 +714,34 @@ pub async fn simulate_locations(
     let landmask = read_luma_u8(worldgen_dir.join("landmask.png"))?;
     let river_mask = read_luma_u8(worldgen_dir.join("river_mask.png"))?;
     let height = read_luma_u16(worldgen_dir.join("height16.png"))?;
+    let road_distance = match read_luma_u8(worldgen_dir.join("road_mask.png")) {
+        Ok(roads) if roads.dimensions() != province_id_image.dimensions() => {
+            return Err(format!(
+                "road_mask.png is {}x{} but province_id.png is {}x{}",
+                roads.width(),
+                roads.height(),
+                province_id_image.width(),
+                province_id_image.height()
+            ));
+        }
+        Ok(roads) => {
+            let paved = roads
+                .pixels()
+                .map(|pixel| pixel.0[0] > 0)
+                .collect::<Vec<_>>();
+            Some(distance_transform(&paved, roads.width(), roads.height()))
+        }
+        Err(_) => None,
+    };
 
     let raster_stats = analyze_rasters(
         &province_id_image,
         &landmask,
         &river_mask,
         &height,
+        road_distance.as_deref(),
         cell_features.as_ref(),
-    );
+    )?;
 
     let continent_by_province = build_continent_index(&continents);
     let duchy_map: HashMap<u32, DuchyRecord> = duchies.into_iter().map(|d| (d.id, d)).collect();
@@ -740,6 +774,7 @@ pub async fn simulate_locations(
                 .get(&province.id)
                 .and_then(|entry| entry.biome_primary_id.clone())
                 .or_else(|| province.biome_primary_id.clone())
+                .or_else(|| biome_palette.get(&province.biome_primary).cloned())
                 .unwrap_or_else(|| "unknown".to_string());
             let ecology = ecology_map.get(&province.id).cloned();
             let adjacency = adjacency_map
@@ -950,9 +985,42 @@ pub fn write_locations(
         .collect::<Vec<_>>();
     let path = planets_dir.join(world_id).join("locations.json");
     write_json(path, &normalized)?;
+    write_road_waypoints(planets_dir, world_id, &normalized)?;
     Ok(normalized)
 }
 
+/// Hands major standing locations to the roads stage, so the next run links them up.
+fn write_road_waypoints(
+    planets_dir: &Path,
+    world_id: &str,
+    records: &[LocationRecord],
+) -> Result<(), String> {
+    let worldgen_dir = planets_dir.join(world_id).join("worldgen");
+    let Ok((width, height)) = image::image_dimensions(worldgen_dir.join("landmask.png")) else {
+        return Ok(());
+    };
+    let waypoints = records
+        .iter()
+        .filter(|record| {
+            record.importance >= ROAD_WAYPOINT_IMPORTANCE
+                && matches!(
+                    record.category,
+                    LocationCategory::Settlement | LocationCategory::Infrastructure
+                )
+                && !matches!(
+                    record.status,
+                    LocationStatus::Abandoned | LocationStatus::Ruined
+                )
+        })
+        .map(|record| RoadWaypoint {
+            id: record.id.clone(),
+            x: ((record.x.clamp(0.0, 1.0) * width as f32) as u32).min(width - 1),
+            y: ((record.y.clamp(0.0, 1.0) * height as f32) as u32).min(height - 1),
+        })
+        .collect::<Vec<_>>();
+    write_json(worldgen_dir.join(ROAD_WAYPOINTS_FILE), &waypoints)
+}
+
 pub fn normalize_locations_value(value: serde_json::Value) -> Vec<LocationRecord> {
     match value {
         serde_json::Value::Array(items) => items
@@ -1151,10 +1219,26 @@ fn analyze_rasters(
     landmask: &image::GrayImage,
     river_mask: &image::GrayImage,
     height: &image::ImageBuffer<image::Luma<u16>, Vec<u16>>,
+    road_distance: Option<&[f32]>,
     cell_features: Option<&CellFeaturesLite>,
-) -> HashMap<u32, ProvinceRasterStats> {
+) -> Result<HashMap<u32, ProvinceRasterStats>, String> {
     let width = province_image.width();
     let raster_height = province_image.height();
+    for (name, dimensions) in [
+        ("landmask.png", landmask.dimensions()),
+        ("river_mask.png", river_mask.dimensions()),
+        ("height16.png", height.dimensions()),
+    ] {
+        if dimensions != (width, raster_height) {
+            return Err(format!(
+                "{name} is {}x{} but province_id.png is {width}x{raster_height}",
+                dimensions.0, dimensions.1
+            ));
+        }
+    }
+    if road_distance.is_some_and(|distance| distance.len() != (width * raster_height) as usize) {
+        return Err("Road distance does not match province_id.png".to_string());
+    }
     let mut stats: HashMap<u32, ProvinceRasterStats> = HashMap::new();
     let cell_grid = cell_features.map(|entry| {
         let map = entry
@@ -1222,6 +1306,11 @@ fn analyze_rasters(
                         } else {
                             (0.4, 0.4, 0.0)
                         };
+                    let road_proximity = road_distance
+                        .map(|distance| {
+                            1.0 - (distance[(y * width + x) as usize] / ROAD_REACH).min(1.0)
+                        })
+                        .unwrap_or(0.0);
                     let candidate_score = ((if river_mask.get_pixel(x, y).0[0] > 0 {
                         0.45
                     } else {
@@ -1233,13 +1322,15 @@ fn analyze_rasters(
                     }) + (1.0 - slope.clamp(0.0, 1.0)) * 0.2
                         + terrain_bonus
                         + cell_vegetation * 0.08
-                        + (1.0 - cell_aridity) * 0.06)
+                        + (1.0 - cell_aridity) * 0.06
+                        + road_proximity * 0.25)
                         .clamp(0.0, 1.0);
                     entry.candidate_points.push(CandidatePoint {
                         x: x as f32 / width as f32,
                         y: y as f32 / raster_height as f32,
                         river: river_mask.get_pixel(x, y).0[0] > 0,
                         coastal: is_coastal_pixel(x, y, landmask),
+                        road: road_proximity > 0.0,
                         slope,
                         elevation,
                         score: candidate_score,
@@ -1262,7 +1353,7 @@ fn analyze_rasters(
         entry.candidate_points.truncate(64);
     }
 
-    stats
+    Ok(stats)
 }
 
 fn build_continent_index(continents: &[ContinentRecord]) -> HashMap<u32, u32> {
@@ -1520,6 +1611,7 @@ fn fallback_anchor(context: &ProvinceContext) -> CandidatePoint {
         y: context.province.seed_y as f32 / 2048.0,
         river: false,
         coastal: false,
+        road: false,
         slope: context.raster.mean_slope,
         elevation: context.raster.mean_elevation,
         score: 0.3,
@@ -1953,6 +2045,9 @@ fn describe_placement_drivers(
     if anchor.coastal {
         drivers.push("coastal approach".to_string());
     }
+    if anchor.road {
+        drivers.push("road access".to_string());
+    }
     if context.scores.strategic >= 65 {
         drivers.push("defensible terrain".to_string());
     }
@@ -2478,7 +2573,7 @@ fn load_world_context(
     })
 }
 
-fn read_json<T>(path: PathBuf) -> Result<T, String>
+pub(crate) fn read_json<T>(path: PathBuf) -> Result<T, String>
 where
     T: for<'de> Deserialize<'de>,
 {
@@ -2707,7 +2802,7 @@ fn biome_resource_bias(biome_id: &str) -> f32 {
     }
 }
 
-fn biome_hazard_bias(biome_id: &str) -> f32 {
+pub(crate) fn biome_hazard_bias(biome_id: &str) -> f32 {
     if biome_id.contains("abyssal") || biome_id.contains("deep_ocean") {
         0.95
     } else if biome_id.contains("ash") || biome_id.contains("volcan") {
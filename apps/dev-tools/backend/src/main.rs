@@ -16,6 +16,7 @@ mod jobs;
 mod locations;
 mod progression;
 mod quest_ai;
+mod travel;
 mod worldgen_pipeline;
 
 use axum::{
@@ -48,8 +49,10 @@ use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tracing::{error, info, warn};
 use uuid::Uuid;
-use worldgen_core::cluster::{DuchyRecord, KingdomRecord, ProvinceRecord};
-use worldgen_core::graph::ProvinceAdjacency;
+use worldgen_core::cluster::{
+    DuchyRecord, HierarchyLocks, KingdomRecord, ProvinceRecord, HIERARCHY_LOCKS_FILE,
+};
+use worldgen_core::graph::{EdgeKind, ProvinceAdjacency};
 
 #[derive(Clone)]
 struct AppState {
@@ -912,6 +915,7 @@ async fn main() {
             get(get_factions).post(save_factions),
         )
         .route("/api/planet/areas/{id}", get(get_areas))
+        .route("/api/planet/travel/{world_id}", post(travel::plan_travel_handler))
         .route(
             "/api/planet/locations/{world_id}/{location_id}/exploration-manifest",
             get(exploration_jobs::get_exploration_manifest),
@@ -920,6 +924,15 @@ async fn main() {
             "/api/planet/locations/{world_id}/{location_id}/exploration-chunks/{chunk_row}/{chunk_col}",
             get(exploration_jobs::get_exploration_chunk),
         )
+        .route(
+            "/api/planet/locations/{world_id}/{location_id}/exploration-edits",
+            get(exploration_engine::editing::get_exploration_edits)
+                .post(exploration_engine::editing::apply_exploration_edit),
+        )
+        .route(
+            "/api/planet/locations/{world_id}/{location_id}/exploration-edits/undo",
+            post(exploration_engine::editing::undo_exploration_edit),
+        )
         .route(
             "/api/planet/locations/{world_id}/exploration-manifests",
             get(exploration_jobs::list_exploration_manifests),
@@ -4148,6 +4161,9 @@ async fn reassign_worldgen_hierarchy(
         let mut existing_continents = read_continents_file(&continents_path).unwrap_or_default();
 
         let entity_type = request.entity_type.trim().to_ascii_lowercase();
+        // Titles touched by the edit are locked so re-clustering keeps it.
+        let mut edited_duchies: Vec<u32> = Vec::new();
+        let mut edited_kingdoms: Vec<u32> = Vec::new();
         match entity_type.as_str() {
             "province" => {
                 let Some(new_duchy_index) = duchies.iter().position(|d| d.id == request.target_id)
@@ -4183,6 +4199,7 @@ async fn reassign_worldgen_hierarchy(
 
                 provinces[province_index].duchy_id = new_duchy_id;
                 provinces[province_index].kingdom_id = new_kingdom_id;
+                edited_duchies = vec![old_duchy_id, new_duchy_id];
             }
             "duchy" => {
                 let Some(new_kingdom_index) =
@@ -4219,6 +4236,7 @@ async fn reassign_worldgen_hierarchy(
                         province.kingdom_id = new_kingdom_id;
                     }
                 }
+                edited_kingdoms = vec![old_kingdom_id, new_kingdom_id];
             }
             "kingdom" => {
                 let Some(kingdom_index) = kingdoms.iter().position(|k| k.id == request.entity_id)
@@ -4268,6 +4286,18 @@ async fn reassign_worldgen_hierarchy(
             kingdom.duchy_ids.dedup();
         }
 
+        let locks_path = worldgen_dir.join(HIERARCHY_LOCKS_FILE);
+        let mut locks: HierarchyLocks = std::fs::read_to_string(&locks_path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        for duchy in duchies.iter().filter(|d| edited_duchies.contains(&d.id)) {
+            locks.lock_duchy(duchy);
+        }
+        for kingdom in kingdoms.iter().filter(|k| edited_kingdoms.contains(&k.id)) {
+            locks.lock_kingdom(kingdom, &duchies);
+        }
+
         let provinces_json = serde_json::to_string_pretty(&provinces)
             .map_err(|e| format!("Failed to serialize provinces: {e}"))?;
         let duchies_json = serde_json::to_string_pretty(&duchies)
@@ -4281,6 +4311,10 @@ async fn reassign_worldgen_hierarchy(
             .map_err(|e| format!("Failed to write duchies.json: {e}"))?;
         std::fs::write(&kingdoms_path, kingdoms_json)
             .map_err(|e| format!("Failed to write kingdoms.json: {e}"))?;
+        let locks_json = serde_json::to_string_pretty(&locks)
+            .map_err(|e| format!("Failed to serialize hierarchy locks: {e}"))?;
+        std::fs::write(&locks_path, locks_json)
+            .map_err(|e| format!("Failed to write {HIERARCHY_LOCKS_FILE}: {e}"))?;
 
         // Rebuild duchy/kingdom ID textures from province labels + updated mappings.
         let province_img = image::open(&province_id_path)
@@ -4616,7 +4650,7 @@ fn build_continents(
         let Some(&k1) = province_to_kingdom.get(&adj.province_id) else {
             continue;
         };
-        for edge in &adj.neighbors {
+        for edge in adj.neighbors.iter().filter(|e| e.kind == EdgeKind::Land) {
             let Some(&k2) = province_to_kingdom.get(&edge.neighbor_id) else {
                 continue;
             };
@@ -0,0 +1,771 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+    path::Path as FsPath,
+};
+use worldgen_core::{
+    cluster::ProvinceRecord,
+    graph::{EdgeInfo, EdgeKind, ProvinceAdjacency},
+    roads::{RoadClass, RoadNetwork},
+};
+
+use crate::{
+    ecology::{fauna_for_province, load_ecology_bundle, EcologyBundle, FaunaEntry},
+    exploration_engine::{manifest::manifest_path, wildlife::fauna_spawnable},
+    jobs::seed_from_key,
+    locations::{self, LocationCategory, LocationRecord, LocationStatus},
+    AppState,
+};
+
+/// Map pixels a party covers in a day over open, level ground.
+const PIXELS_PER_TRAVEL_DAY: f32 = 40.0;
+/// Shortest leg, so neighbouring locations still take part of a day.
+const MIN_SEGMENT_DAYS: f32 = 0.1;
+const ROAD_SPEED_FACTOR: f32 = 0.6;
+const HIGHWAY_SPEED_FACTOR: f32 = 0.45;
+/// Days lost fording a river where no bridge carries the road.
+const RIVER_FORD_DAYS: f32 = 0.5;
+const ENCOUNTER_CHANCE_PER_DAY: f32 = 0.18;
+const MAX_ENCOUNTER_CHANCE: f32 = 0.9;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TravelRequest {
+    pub from_location_id: String,
+    pub to_location_id: String,
+    /// Seeds the encounter rolls; defaults to a hash of the world and both locations.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TravelPlan {
+    pub world_id: String,
+    pub from_location_id: String,
+    pub to_location_id: String,
+    pub seed: u64,
+    pub total_days: f32,
+    pub segments: Vec<TravelSegment>,
+    pub arrival: TravelArrival,
+}
+
+/// One leg of the route, ending in `to_province_id`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TravelSegment {
+    pub from_province_id: u32,
+    pub to_province_id: u32,
+    pub province_name: String,
+    pub biome_id: String,
+    pub start_day: f32,
+    pub days: f32,
+    pub road: bool,
+    pub crosses_river: bool,
+    pub bridged: bool,
+    pub encounter_chance: f32,
+    pub encounter_roll: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<TravelEncounter>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TravelEncounter {
+    pub fauna_id: String,
+    pub name: String,
+    pub danger_level: i32,
+    /// Day of the journey the encounter happens on.
+    pub day: f32,
+}
+
+/// Where the party arrives. With `explorationReady` the client can start an exploration session
+/// for the location right away; otherwise it has to generate the location first.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TravelArrival {
+    pub world_id: String,
+    pub location_id: String,
+    pub location_name: String,
+    pub province_id: u32,
+    pub exploration_ready: bool,
+}
+
+#[derive(Debug, Clone)]
+struct TravelProvince {
+    name: String,
+    x: f32,
+    y: f32,
+    biome_id: String,
+    hazard: f32,
+    /// Hosts a living settlement or infrastructure, so roads reach it.
+    road: bool,
+    bridge: bool,
+}
+
+/// A road between two neighbouring provinces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RoadLink {
+    highway: bool,
+    bridged: bool,
+}
+
+struct TravelGraph {
+    provinces: HashMap<u32, TravelProvince>,
+    neighbors: HashMap<u32, Vec<EdgeInfo>>,
+    /// Province pairs joined by the generated road network, lower id first. `None` until the
+    /// roads stage has run.
+    road_links: Option<HashMap<(u32, u32), RoadLink>>,
+}
+
+impl TravelGraph {
+    /// The generated road between two provinces, or without a road network, a road between
+    /// settlements standing on both ends.
+    fn road(&self, from_id: u32, to_id: u32) -> Option<RoadLink> {
+        match &self.road_links {
+            Some(links) => links
+                .get(&(from_id.min(to_id), from_id.max(to_id)))
+                .copied(),
+            None => {
+                let (from, to) = (&self.provinces[&from_id], &self.provinces[&to_id]);
+                (from.road && to.road).then_some(RoadLink {
+                    highway: false,
+                    bridged: from.bridge || to.bridge,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OpenEntry {
+    days: f32,
+    province_id: u32,
+}
+
+impl Eq for OpenEntry {}
+
+impl PartialOrd for OpenEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenEntry {
+    // Reversed so the std max-heap pops the quickest entry.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .days
+            .total_cmp(&self.days)
+            .then_with(|| other.province_id.cmp(&self.province_id))
+    }
+}
+
+pub async fn plan_travel_handler(
+    State(state): State<AppState>,
+    Path(world_id): Path<String>,
+    Json(request): Json<TravelRequest>,
+) -> impl IntoResponse {
+    match plan_travel(&state.planets_dir, &world_id, &request) {
+        Ok(plan) => (StatusCode::OK, Json(plan)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+fn plan_travel(
+    planets_dir: &FsPath,
+    world_id: &str,
+    request: &TravelRequest,
+) -> Result<TravelPlan, (StatusCode, String)> {
+    let all_locations = locations::read_locations(planets_dir, world_id);
+    let find_location = |location_id: &str| {
+        all_locations
+            .iter()
+            .find(|location| location.id == location_id)
+            .ok_or_else(|| {
+                (
+                    StatusCode::NOT_FOUND,
+                    format!("Location {location_id} not found"),
+                )
+            })
+    };
+    let from = find_location(&request.from_location_id)?;
+    let to = find_location(&request.to_location_id)?;
+
+    let worldgen_dir = planets_dir.join(world_id).join("worldgen");
+    let provinces: Vec<ProvinceRecord> = locations::read_json(worldgen_dir.join("provinces.json"))
+        .map_err(|error| (StatusCode::NOT_FOUND, error))?;
+    let adjacency: Vec<ProvinceAdjacency> =
+        locations::read_json(worldgen_dir.join("adjacency.json"))
+            .map_err(|error| (StatusCode::NOT_FOUND, error))?;
+    let roads: Option<RoadNetwork> = locations::read_json(worldgen_dir.join("roads.json")).ok();
+    let bundle = load_ecology_bundle(planets_dir, world_id)
+        .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error))?;
+    let graph = build_travel_graph(&provinces, adjacency, &all_locations, roads.as_ref());
+
+    let seed = request
+        .seed
+        .unwrap_or_else(|| travel_seed(world_id, &from.id, &to.id));
+    let segments = plan_segments(&graph, &bundle, from, to, seed).ok_or_else(|| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("No overland route from {} to {}", from.name, to.name),
+        )
+    })?;
+    let exploration_ready = manifest_path(planets_dir, world_id, &to.id).exists();
+
+    Ok(TravelPlan {
+        world_id: world_id.to_string(),
+        from_location_id: from.id.clone(),
+        to_location_id: to.id.clone(),
+        seed,
+        total_days: segments.iter().map(|segment| segment.days).sum(),
+        segments,
+        arrival: TravelArrival {
+            world_id: world_id.to_string(),
+            location_id: to.id.clone(),
+            location_name: to.name.clone(),
+            province_id: to.province_id,
+            exploration_ready,
+        },
+    })
+}
+
+fn build_travel_graph(
+    provinces: &[ProvinceRecord],
+    adjacency: Vec<ProvinceAdjacency>,
+    all_locations: &[LocationRecord],
+    roads: Option<&RoadNetwork>,
+) -> TravelGraph {
+    let mut road_provinces = HashSet::new();
+    let mut bridge_provinces = HashSet::new();
+    for location in all_locations {
+        let standing = !matches!(
+            location.status,
+            LocationStatus::Abandoned | LocationStatus::Ruined
+        );
+        if standing
+            && matches!(
+                location.category,
+                LocationCategory::Settlement | LocationCategory::Infrastructure
+            )
+        {
+            road_provinces.insert(location.province_id);
+            if location.subtype == "bridge_crossing" {
+                bridge_provinces.insert(location.province_id);
+            }
+        }
+    }
+
+    TravelGraph {
+        provinces: provinces
+            .iter()
+            .map(|province| {
+                let biome_id = province
+                    .biome_primary_id
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string());
+                (
+                    province.id,
+                    TravelProvince {
+                        name: province.name.clone(),
+                        x: province.seed_x as f32,
+                        y: province.seed_y as f32,
+                        hazard: locations::biome_hazard_bias(&biome_id),
+                        biome_id,
+                        road: road_provinces.contains(&province.id),
+                        bridge: bridge_provinces.contains(&province.id),
+                    },
+                )
+            })
+            .collect(),
+        // Parties travel overland; coast, sea and strait edges lead onto water.
+        neighbors: adjacency
+            .into_iter()
+            .map(|entry| {
+                let land = entry
+                    .neighbors
+                    .into_iter()
+                    .filter(|edge| edge.kind == EdgeKind::Land)
+                    .collect();
+                (entry.province_id, land)
+            })
+            .collect(),
+        road_links: roads.map(road_links),
+    }
+}
+
+/// Road links between provinces from the network's routes. Parallel routes keep the best class,
+/// and a crossing counts as bridged only when some route bridges every river it crosses.
+fn road_links(network: &RoadNetwork) -> HashMap<(u32, u32), RoadLink> {
+    let mut links = HashMap::<(u32, u32), RoadLink>::new();
+    for route in network
+        .routes
+        .iter()
+        .filter(|route| route.from_province != route.to_province)
+    {
+        let key = (
+            route.from_province.min(route.to_province),
+            route.from_province.max(route.to_province),
+        );
+        let link = links.entry(key).or_insert(RoadLink {
+            highway: false,
+            bridged: false,
+        });
+        link.highway |= route.class == RoadClass::Highway;
+        link.bridged |= route.fords == 0;
+    }
+    links
+}
+
+/// Quickest province path between the two locations, as legs with their encounter rolls.
+fn plan_segments(
+    graph: &TravelGraph,
+    bundle: &EcologyBundle,
+    from: &LocationRecord,
+    to: &LocationRecord,
+    seed: u64,
+) -> Option<Vec<TravelSegment>> {
+    let path = find_province_path(graph, from.province_id, to.province_id)?;
+    let mut waypoints = path
+        .iter()
+        .map(|province_id| {
+            let province = &graph.provinces[province_id];
+            (province.x, province.y)
+        })
+        .collect::<Vec<_>>();
+    waypoints[0] = (from.x, from.y);
+    let last = waypoints.len() - 1;
+    if last == 0 {
+        waypoints.push((to.x, to.y));
+    } else {
+        waypoints[last] = (to.x, to.y);
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut segments = Vec::with_capacity(waypoints.len() - 1);
+    let mut elapsed = 0.0;
+    for (index, pair) in waypoints.windows(2).enumerate() {
+        let from_id = path[index.min(path.len() - 1)];
+        let to_id = path[(index + 1).min(path.len() - 1)];
+        let edge = graph
+            .neighbors
+            .get(&from_id)
+            .and_then(|edges| edges.iter().find(|edge| edge.neighbor_id == to_id));
+        let to_province = &graph.provinces[&to_id];
+        let leg = leg_cost(
+            to_province,
+            edge,
+            graph.road(from_id, to_id),
+            pair[0],
+            pair[1],
+        );
+        let mut segment = TravelSegment {
+            from_province_id: from_id,
+            to_province_id: to_id,
+            province_name: to_province.name.clone(),
+            biome_id: to_province.biome_id.clone(),
+            start_day: elapsed,
+            days: leg.days,
+            road: leg.road,
+            crosses_river: leg.crosses_river,
+            bridged: leg.bridged,
+            encounter_chance: 0.0,
+            encounter_roll: 0.0,
+            encounter: None,
+        };
+        roll_encounter(
+            &mut segment,
+            to_province.hazard,
+            &fauna_for_province(bundle, to_id),
+            &mut rng,
+        );
+        elapsed += leg.days;
+        segments.push(segment);
+    }
+    Some(segments)
+}
+
+fn find_province_path(graph: &TravelGraph, start: u32, goal: u32) -> Option<Vec<u32>> {
+    if !graph.provinces.contains_key(&start) || !graph.provinces.contains_key(&goal) {
+        return None;
+    }
+    let mut best = HashMap::from([(start, 0.0_f32)]);
+    let mut parent = HashMap::<u32, u32>::new();
+    let mut open = BinaryHeap::from([OpenEntry {
+        days: 0.0,
+        province_id: start,
+    }]);
+    while let Some(OpenEntry { days, province_id }) = open.pop() {
+        if province_id == goal {
+            let mut path = vec![goal];
+            let mut cursor = goal;
+            while let Some(previous) = parent.get(&cursor) {
+                path.push(*previous);
+                cursor = *previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if days > best.get(&province_id).copied().unwrap_or(f32::INFINITY) {
+            continue;
+        }
+        let current = &graph.provinces[&province_id];
+        for edge in graph.neighbors.get(&province_id).into_iter().flatten() {
+            let Some(next) = graph.provinces.get(&edge.neighbor_id) else {
+                continue;
+            };
+            let next_days = days
+                + leg_cost(
+                    next,
+                    Some(edge),
+                    graph.road(province_id, edge.neighbor_id),
+                    (current.x, current.y),
+                    (next.x, next.y),
+                )
+                .days;
+            if next_days
+                < best
+                    .get(&edge.neighbor_id)
+                    .copied()
+                    .unwrap_or(f32::INFINITY)
+            {
+                best.insert(edge.neighbor_id, next_days);
+                parent.insert(edge.neighbor_id, province_id);
+                open.push(OpenEntry {
+                    days: next_days,
+                    province_id: edge.neighbor_id,
+                });
+            }
+        }
+    }
+    None
+}
+
+struct LegCost {
+    days: f32,
+    road: bool,
+    crosses_river: bool,
+    bridged: bool,
+}
+
+/// Days to cover a leg into `to`. Distance is slowed by the border's height and the
+/// destination's biome hazard and sped up on roads, highways most; unbridged rivers add a ford.
+fn leg_cost(
+    to: &TravelProvince,
+    edge: Option<&EdgeInfo>,
+    road_link: Option<RoadLink>,
+    start: (f32, f32),
+    end: (f32, f32),
+) -> LegCost {
+    let distance = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt();
+    let border_height = edge
+        .map(|edge| (edge.mean_border_height / u16::MAX as f32).clamp(0.0, 1.0))
+        .unwrap_or(0.0);
+    let road = road_link.is_some();
+    let crosses_river = edge.is_some_and(|edge| edge.crosses_river);
+    let bridged = crosses_river && road_link.is_some_and(|link| link.bridged);
+
+    let mut days = distance / PIXELS_PER_TRAVEL_DAY
+        * (1.0 + 2.0 * border_height * border_height)
+        * (1.0 + 0.5 * to.hazard);
+    match road_link {
+        Some(link) if link.highway => days *= HIGHWAY_SPEED_FACTOR,
+        Some(_) => days *= ROAD_SPEED_FACTOR,
+        None => {}
+    }
+    if crosses_river && !bridged {
+        days += RIVER_FORD_DAYS;
+    }
+    LegCost {
+        days: days.max(MIN_SEGMENT_DAYS),
+        road,
+        crosses_river,
+        bridged,
+    }
+}
+
+/// Rolls for an encounter on the segment. Longer, more hazardous legs and more dangerous local
+/// fauna raise the chance, roads lower it, and the encounter favours the most dangerous fauna.
+fn roll_encounter(
+    segment: &mut TravelSegment,
+    hazard: f32,
+    fauna: &[FaunaEntry],
+    rng: &mut StdRng,
+) {
+    let candidates = fauna
+        .iter()
+        .filter(|entry| fauna_spawnable(entry))
+        .collect::<Vec<_>>();
+    let mean_danger = if candidates.is_empty() {
+        0.0
+    } else {
+        candidates
+            .iter()
+            .map(|entry| entry.danger_level.clamp(0, 100) as f32 / 100.0)
+            .sum::<f32>()
+            / candidates.len() as f32
+    };
+    let mut chance = ENCOUNTER_CHANCE_PER_DAY * segment.days * (0.5 + mean_danger + 0.5 * hazard);
+    if segment.road {
+        chance *= 0.5;
+    }
+    segment.encounter_chance = chance.clamp(0.0, MAX_ENCOUNTER_CHANCE);
+    segment.encounter_roll = rng.random::<f32>();
+    if segment.encounter_roll >= segment.encounter_chance {
+        return;
+    }
+
+    let total = candidates
+        .iter()
+        .map(|entry| encounter_weight(entry))
+        .sum::<u32>();
+    if total == 0 {
+        return;
+    }
+    let mut pick = rng.random_range(0..total);
+    let Some(entry) = candidates.iter().find(|entry| {
+        let weight = encounter_weight(entry);
+        if pick < weight {
+            return true;
+        }
+        pick -= weight;
+        false
+    }) else {
+        return;
+    };
+    segment.encounter = Some(TravelEncounter {
+        fauna_id: entry.id.clone(),
+        name: entry.name.clone(),
+        danger_level: entry.danger_level,
+        day: segment.start_day + rng.random::<f32>() * segment.days,
+    });
+}
+
+fn encounter_weight(entry: &FaunaEntry) -> u32 {
+    (entry.danger_level.clamp(0, 100) + 10) as u32
+}
+
+fn travel_seed(world_id: &str, from_location_id: &str, to_location_id: &str) -> u64 {
+    seed_from_key(&format!("{world_id}:{from_location_id}:{to_location_id}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn province(id: u32, x: u32, y: u32) -> ProvinceRecord {
+        serde_json::from_value(json!({
+            "id": id,
+            "seedX": x,
+            "seedY": y,
+            "area": 400,
+            "duchyId": 1,
+            "kingdomId": 1,
+            "biomePrimary": 0,
+            "biomePrimaryId": "temperate_forest",
+            "name": format!("Province {id}"),
+        }))
+        .unwrap()
+    }
+
+    fn edge(neighbor_id: u32, mean_border_height: f32, crosses_river: bool) -> EdgeInfo {
+        EdgeInfo {
+            neighbor_id,
+            shared_border_length: 20,
+            crosses_river,
+            mean_border_height,
+            kind: EdgeKind::Land,
+            through_sea_zone: None,
+        }
+    }
+
+    #[test]
+    fn route_prefers_roads_around_mountains_and_is_deterministic() {
+        let provinces = vec![
+            province(1, 0, 0),
+            province(2, 100, 0),
+            province(3, 200, 0),
+            province(4, 100, 60),
+        ];
+        let adjacency = vec![
+            ProvinceAdjacency {
+                province_id: 1,
+                neighbors: vec![edge(2, 60_000.0, false), edge(4, 8_000.0, true)],
+            },
+            ProvinceAdjacency {
+                province_id: 2,
+                neighbors: vec![edge(1, 60_000.0, false), edge(3, 60_000.0, false)],
+            },
+            ProvinceAdjacency {
+                province_id: 3,
+                neighbors: vec![edge(2, 60_000.0, false), edge(4, 8_000.0, false)],
+            },
+            ProvinceAdjacency {
+                province_id: 4,
+                neighbors: vec![edge(1, 8_000.0, true), edge(3, 8_000.0, false)],
+            },
+        ];
+        let all_locations = locations::normalize_locations_value(json!([
+            { "id": "home", "name": "Home", "category": "settlement", "provinceId": 1, "x": 4.0, "y": 2.0 },
+            { "id": "away", "name": "Away", "category": "settlement", "provinceId": 3, "x": 196.0, "y": 4.0 },
+            { "id": "ford", "name": "Ford", "category": "infrastructure", "subtype": "bridge_crossing", "provinceId": 4, "x": 100.0, "y": 60.0 }
+        ]));
+        assert_eq!(all_locations.len(), 3);
+        let graph = build_travel_graph(&provinces, adjacency, &all_locations, None);
+        let bundle: EcologyBundle =
+            serde_json::from_value(json!({ "worldId": "world", "updatedAt": "" })).unwrap();
+
+        let segments =
+            plan_segments(&graph, &bundle, &all_locations[0], &all_locations[1], 9).unwrap();
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| segment.to_province_id)
+                .collect::<Vec<_>>(),
+            vec![4, 3]
+        );
+        assert!(segments.iter().all(|segment| segment.road));
+        assert!(segments[0].crosses_river && segments[0].bridged);
+        assert!((segments[1].start_day - segments[0].days).abs() < 1e-6);
+
+        let again =
+            plan_segments(&graph, &bundle, &all_locations[0], &all_locations[1], 9).unwrap();
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| segment.encounter_roll)
+                .collect::<Vec<_>>(),
+            again
+                .iter()
+                .map(|segment| segment.encounter_roll)
+                .collect::<Vec<_>>()
+        );
+
+        let local =
+            plan_segments(&graph, &bundle, &all_locations[0], &all_locations[0], 9).unwrap();
+        assert_eq!(local.len(), 1);
+        assert_eq!(local[0].days, MIN_SEGMENT_DAYS);
+    }
+
+    #[test]
+    fn generated_roads_replace_settlement_roads() {
+        let provinces = vec![
+            province(1, 0, 0),
+            province(2, 100, 0),
+            province(3, 200, 0),
+            province(4, 100, 60),
+        ];
+        let adjacency = vec![
+            ProvinceAdjacency {
+                province_id: 1,
+                neighbors: vec![edge(2, 8_000.0, false), edge(4, 8_000.0, true)],
+            },
+            ProvinceAdjacency {
+                province_id: 2,
+                neighbors: vec![edge(1, 8_000.0, false), edge(3, 8_000.0, false)],
+            },
+            ProvinceAdjacency {
+                province_id: 3,
+                neighbors: vec![edge(2, 8_000.0, false), edge(4, 8_000.0, false)],
+            },
+            ProvinceAdjacency {
+                province_id: 4,
+                neighbors: vec![edge(1, 8_000.0, true), edge(3, 8_000.0, false)],
+            },
+        ];
+        let all_locations = locations::normalize_locations_value(json!([
+            { "id": "home", "name": "Home", "category": "settlement", "provinceId": 1, "x": 4.0, "y": 2.0 },
+            { "id": "away", "name": "Away", "category": "settlement", "provinceId": 3, "x": 196.0, "y": 4.0 },
+            { "id": "midway", "name": "Midway", "category": "settlement", "provinceId": 2, "x": 100.0, "y": 0.0 }
+        ]));
+        let roads: RoadNetwork = serde_json::from_value(json!({
+            "width": 256,
+            "height": 128,
+            "nodes": [],
+            "segments": [],
+            "routes": [
+                { "fromNode": 0, "toNode": 3, "fromProvince": 1, "toProvince": 4, "class": "highway", "length": 110.0, "bridges": 1, "fords": 0 },
+                { "fromNode": 3, "toNode": 2, "fromProvince": 4, "toProvince": 3, "class": "highway", "length": 110.0, "bridges": 0, "fords": 0 }
+            ]
+        }))
+        .unwrap();
+        let graph = build_travel_graph(&provinces, adjacency, &all_locations, Some(&roads));
+        assert_eq!(graph.road(1, 2), None);
+        assert_eq!(
+            graph.road(4, 1),
+            Some(RoadLink {
+                highway: true,
+                bridged: true
+            })
+        );
+
+        let bundle: EcologyBundle =
+            serde_json::from_value(json!({ "worldId": "world", "updatedAt": "" })).unwrap();
+        let segments =
+            plan_segments(&graph, &bundle, &all_locations[0], &all_locations[1], 3).unwrap();
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| segment.to_province_id)
+                .collect::<Vec<_>>(),
+            vec![4, 3]
+        );
+        assert!(segments.iter().all(|segment| segment.road));
+        assert!(segments[0].crosses_river && segments[0].bridged);
+    }
+
+    #[test]
+    fn route_goes_around_straits_overland() {
+        let provinces = vec![province(1, 0, 0), province(2, 40, 0), province(3, 20, 150)];
+        let strait = |neighbor_id: u32| EdgeInfo {
+            neighbor_id,
+            shared_border_length: 0,
+            crosses_river: false,
+            mean_border_height: 0.0,
+            kind: EdgeKind::Strait,
+            through_sea_zone: Some(1 << 20),
+        };
+        let adjacency = vec![
+            ProvinceAdjacency {
+                province_id: 1,
+                neighbors: vec![strait(2), edge(3, 8_000.0, false)],
+            },
+            ProvinceAdjacency {
+                province_id: 2,
+                neighbors: vec![strait(1), edge(3, 8_000.0, false)],
+            },
+            ProvinceAdjacency {
+                province_id: 3,
+                neighbors: vec![edge(1, 8_000.0, false), edge(2, 8_000.0, false)],
+            },
+        ];
+        let all_locations = locations::normalize_locations_value(json!([
+            { "id": "west", "name": "West", "category": "settlement", "provinceId": 1, "x": 0.0, "y": 0.0 },
+            { "id": "east", "name": "East", "category": "settlement", "provinceId": 2, "x": 40.0, "y": 0.0 }
+        ]));
+        let graph = build_travel_graph(&provinces, adjacency, &all_locations, None);
+        let bundle: EcologyBundle =
+            serde_json::from_value(json!({ "worldId": "world", "updatedAt": "" })).unwrap();
+
+        let segments =
+            plan_segments(&graph, &bundle, &all_locations[0], &all_locations[1], 5).unwrap();
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| segment.to_province_id)
+                .collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+        assert!(segments.iter().all(|segment| !segment.crosses_river));
+    }
+}
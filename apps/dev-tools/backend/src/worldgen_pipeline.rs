@@ -21,6 +21,7 @@ use uuid::Uuid;
 
 use worldgen_core::cluster::{DuchyRecord, KingdomRecord, ProvinceRecord};
 use worldgen_core::export::PipelineStatus;
+use worldgen_core::pipeline::{self, DefaultStageRunner, Pipeline, StageContext, StageRunner};
 use worldgen_core::*;
 
 use crate::{gemini, AppState, JobRecord, JobStatus};
@@ -396,7 +397,7 @@ pub async fn get_pipeline_status(
     Path(planet_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let out_dir = worldgen_dir(&state.planets_dir, &planet_id);
-    let status_path = out_dir.join("pipeline_status.json");
+    let status_path = out_dir.join(pipeline::STATUS_FILE);
     let pipeline_status = PipelineStatus::load(&status_path);
 
     let stages: HashMap<String, StageInfo> = pipeline_status
@@ -988,21 +989,7 @@ pub async fn run_pipeline_stage(
     let job_id = Uuid::new_v4().to_string();
 
     // Validate stage name
-    let valid_stages = [
-        "normalize",
-        "landmask",
-        "height",
-        "rivers",
-        "biome",
-        "suitability",
-        "seeds",
-        "partition",
-        "postprocess",
-        "adjacency",
-        "clustering",
-        "naming",
-    ];
-    if !valid_stages.contains(&stage_name.as_str()) {
+    if pipeline::Stage::from_id(&stage_name).is_none() {
         return Err((
             StatusCode::BAD_REQUEST,
             format!("Unknown stage: {}", stage_name),
@@ -2084,14 +2071,19 @@ fn run_stage_blocking(
         }
     };
 
-    let result = run_single_stage(
-        stage_name,
-        config,
-        planets_dir,
-        planet_id,
-        &out_dir,
-        &mut |pct, msg| update_progress(pct, msg),
-    );
+    let base_path = base_image_path(planets_dir, planet_id);
+    let result = match pipeline::Stage::from_id(stage_name) {
+        Some(stage) => {
+            let mut runner = PlanetStageRunner::new(planets_dir, planet_id);
+            let mut pipeline = Pipeline::open(&base_path, &out_dir, config);
+            pipeline.migrate_artifacts(&runner).and_then(|_| {
+                pipeline.run_stage(stage, &mut runner, &mut |pct, msg| {
+                    update_progress(pct, msg)
+                })
+            })
+        }
+        None => Err(format!("Unknown stage: {}", stage_name)),
+    };
 
     let mut jobs = jobs.lock().unwrap();
     if let Some(job) = jobs.get_mut(job_id) {
@@ -2103,11 +2095,6 @@ fn run_stage_blocking(
                     "Worldgen stage '{}' completed for planet {}",
                     stage_name, planet_id
                 );
-
-                // Update pipeline status
-                let mut pipeline = PipelineStatus::load(&out_dir.join("pipeline_status.json"));
-                pipeline.mark_completed(stage_name);
-                pipeline.save(&out_dir.join("pipeline_status.json")).ok();
             }
             Err(e) => {
                 job.status = JobStatus::Failed;
@@ -2118,249 +2105,109 @@ fn run_stage_blocking(
     }
 }
 
-fn run_single_stage(
-    stage_name: &str,
-    config: &WorldgenConfig,
-    planets_dir: &std::path::Path,
-    planet_id: &str,
-    out_dir: &std::path::Path,
-    progress: &mut dyn FnMut(f32, &str),
-) -> Result<(), String> {
-    let base_path = base_image_path(planets_dir, planet_id);
-
-    match stage_name {
-        "landmask" => {
-            let base_img = load_rgb_png(&base_path, "Failed to load base image")?;
-            let mask = landmask::extract_landmask(&base_img, config, 500, 200, progress);
-            let (w, h) = base_img.dimensions();
-            export::write_landmask(&mask, w, h, &out_dir.join("landmask.png"))
-        }
-
-        "normalize" => {
-            let img = load_base_image(&base_path)?;
-            let (w, h) = img.dimensions();
-            let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
-            let flat = normalize::normalize_albedo(&img, &mask, 60.0, progress);
-            export::write_rgb_image(&flat, &out_dir.join("albedo_flat.png"))
-        }
-
-        "height" => {
-            let flat = load_rgb_png(&out_dir.join("albedo_flat.png"), "Run 'normalize' first")?;
-            let (w, h) = flat.dimensions();
-            let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
-            let hf = height::reconstruct_height(&flat, &mask, 42, progress);
-            export::write_height_texture(&hf, w, h, &out_dir.join("height16.png"))
-        }
-
-        "rivers" => {
-            let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
-            let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
-            let hf = load_height16(&out_dir.join("height16.png"), w, h)?;
-            let (river_mask, _) = hydrology::compute_rivers(&hf, &mask, w, h, 200, progress);
-            export::write_mask_texture(&river_mask, w, h, &out_dir.join("river_mask.png"))
-        }
-
-        "biome" => {
-            let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
-            let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
-            let hf = load_height16(&out_dir.join("height16.png"), w, h)?;
-            let base_img = load_rgb_png(&base_path, "Failed to load base image")?;
-            let river = load_optional_mask_u8(&out_dir.join("river_mask.png"), w, h)?;
-
-            let bundle = crate::ecology::load_ecology_bundle(&planets_dir, planet_id)
-                .unwrap_or_else(|_| crate::ecology::empty_bundle(planet_id));
-            let registry = bundle.archetypes.clone();
-            let vision_analysis = load_or_initialize_biome_vision_priors(
-                &base_path,
-                &bundle.biome_model_settings,
-                &out_dir,
-            )?;
-            let analysis = biome::classify_biomes(
-                &hf,
-                &mask,
-                river.as_deref(),
-                &base_img,
-                config,
-                &registry,
-                &bundle.biome_model_settings,
-                Some(&vision_analysis),
-                w,
-                h,
-                progress,
-            );
-
-            export::write_mask_texture(&analysis.biome_indices, w, h, &out_dir.join("biome.png"))?;
-            export::write_mask_texture(
-                &analysis.confidence_map,
-                w,
-                h,
-                &out_dir.join("biome_confidence.png"),
-            )?;
-            write_json_pretty(&out_dir.join("biome_report.json"), &analysis.report)?;
-            write_json_pretty(
-                &out_dir.join("biome_palette.json"),
-                &biome::biome_palette(&registry),
-            )?;
-            write_json_pretty(&out_dir.join("biome_vision_priors.json"), &vision_analysis)
-        }
-
-        "suitability" => {
-            let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
-            let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
-            let hf = load_height16(&out_dir.join("height16.png"), w, h)?;
-            let river = load_mask_u8(&out_dir.join("river_mask.png"), w, h)?;
-            let biomes = load_mask_u8(&out_dir.join("biome.png"), w, h)?;
-
-            // Load registry from ecology
-            let bundle = crate::ecology::load_ecology_bundle(&planets_dir, planet_id)
-                .unwrap_or_else(|_| crate::ecology::empty_bundle(planet_id));
-            let registry = bundle.archetypes;
-
-            let suit = suitability::compute_suitability(
-                &hf, &mask, &river, &biomes, &registry, w, h, progress,
-            );
-            export::write_f32_binary(&suit, &out_dir.join("suitability.bin"))
-        }
-
-        "seeds" => {
-            let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
-            let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
-            let suit = load_f32_binary(&out_dir.join("suitability.bin"), w, h)?;
-            let seeds = sampling::place_seeds(
-                &suit,
-                &mask,
-                w,
-                h,
-                config.counties,
-                config.seed_radius_min,
-                config.seed_radius_max,
-                42,
-                progress,
-            );
-            export::write_seeds_json(&seeds, &out_dir.join("seeds.json"))
-        }
-
-        "partition" => {
-            let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
-            let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
-            let hf = load_height16(&out_dir.join("height16.png"), w, h)?;
-            let river = load_mask_u8(&out_dir.join("river_mask.png"), w, h)?;
-            let seeds = load_seeds_json(&out_dir.join("seeds.json"))?;
-            let labels = partition::grow_provinces(
-                &seeds,
-                &hf,
-                &mask,
-                &river,
-                w,
-                h,
-                config.cost_slope,
-                config.cost_river_crossing,
-                config.cost_ridge_crossing,
-                progress,
-            );
-            export::write_id_texture(&labels, w, h, &out_dir.join("province_id.png"))
-        }
-
-        "postprocess" => {
-            let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
-            let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
-            let mut labels = load_id_texture(&out_dir.join("province_id.png"), w, h)?;
-            postprocess::postprocess_provinces(
-                &mut labels,
-                &mask,
-                w,
-                h,
-                config.min_county_area,
-                config.smooth_iterations,
-                progress,
-            );
-            export::write_id_texture(&labels, w, h, &out_dir.join("province_id.png"))
-        }
-
-        "adjacency" => {
-            let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
-            let hf = load_height16(&out_dir.join("height16.png"), w, h)?;
-            let river = load_mask_u8(&out_dir.join("river_mask.png"), w, h)?;
-            let labels = load_id_texture(&out_dir.join("province_id.png"), w, h)?;
-            let adj = graph::build_adjacency(&labels, &hf, &river, w, h, progress);
-            export::write_adjacency_json(&adj, &out_dir.join("adjacency.json"))
-        }
-
-        "clustering" => {
-            let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
-            let labels = load_id_texture(&out_dir.join("province_id.png"), w, h)?;
-            let biomes = load_mask_u8(&out_dir.join("biome.png"), w, h)?;
-            let biome_confidence =
-                load_optional_mask_u8(&out_dir.join("biome_confidence.png"), w, h)?
-                    .unwrap_or_else(|| vec![255; (w * h) as usize]);
-            let seeds = load_seeds_json(&out_dir.join("seeds.json"))?;
-            let adj_json = std::fs::read_to_string(out_dir.join("adjacency.json"))
-                .map_err(|e| format!("Failed to read adjacency.json: {}", e))?;
-            let adj: Vec<graph::ProvinceAdjacency> = serde_json::from_str(&adj_json)
-                .map_err(|e| format!("Failed to parse adjacency.json: {}", e))?;
-            let bundle = crate::ecology::load_ecology_bundle(&planets_dir, planet_id)
-                .unwrap_or_else(|_| crate::ecology::empty_bundle(planet_id));
-            let registry = bundle.archetypes.clone();
-
-            let seed_tuples: Vec<(u32, u32, u32)> =
-                seeds.iter().map(|s| (s.id, s.x, s.y)).collect();
-            let (mut provinces, duchies, kingdoms, duchy_labels, kingdom_labels) =
-                cluster::cluster_hierarchy(
-                    &labels,
-                    &biomes,
-                    &seed_tuples,
-                    &adj,
-                    w,
-                    h,
-                    config.duchy_size_min,
-                    config.duchy_size_max,
-                    config.kingdom_size_min,
-                    config.kingdom_size_max,
-                    progress,
-                );
-            let province_summaries = enrich_province_biome_records(
-                &mut provinces,
-                &labels,
-                &biomes,
-                &biome_confidence,
-                &registry,
-            );
+/// Runs stages from the planet's files, with the planet's ecology bundle as the biome registry
+/// and its vision priors. Clustering also writes the continents and per-province biome summaries
+/// the editor reads.
+struct PlanetStageRunner {
+    inner: DefaultStageRunner,
+}
 
-            export::write_id_texture(&duchy_labels, w, h, &out_dir.join("duchy_id.png"))?;
-            export::write_id_texture(&kingdom_labels, w, h, &out_dir.join("kingdom_id.png"))?;
-            let continents = build_continents(&provinces, &duchies, &kingdoms, &adj, None);
-            let kingdom_to_continent: HashMap<u32, u32> = continents
-                .iter()
-                .flat_map(|c| c.kingdom_ids.iter().map(move |&kid| (kid, c.id)))
-                .collect();
-            let continent_labels: Vec<u32> = kingdom_labels
-                .iter()
-                .map(|&kid| kingdom_to_continent.get(&kid).copied().unwrap_or(0))
-                .collect();
-            export::write_id_texture(&continent_labels, w, h, &out_dir.join("continent_id.png"))?;
-            let continents_json = serde_json::to_string_pretty(&continents)
-                .map_err(|e| format!("Failed to serialize continents.json: {e}"))?;
-            std::fs::write(out_dir.join("continents.json"), continents_json)
-                .map_err(|e| format!("Failed to write continents.json: {e}"))?;
-            enrich_biome_report_with_provinces(
-                &out_dir.join("biome_report.json"),
-                &province_summaries,
-            )?;
-            export::write_provinces_json(&provinces, &out_dir.join("provinces.json"))?;
-            export::write_duchies_json(&duchies, &out_dir.join("duchies.json"))?;
-            export::write_kingdoms_json(&kingdoms, &out_dir.join("kingdoms.json"))
+impl PlanetStageRunner {
+    fn new(planets_dir: &std::path::Path, planet_id: &str) -> Self {
+        let bundle = crate::ecology::load_ecology_bundle(planets_dir, planet_id)
+            .unwrap_or_else(|_| crate::ecology::empty_bundle(planet_id));
+        Self {
+            inner: DefaultStageRunner {
+                registry: bundle.archetypes,
+                model_settings: bundle.biome_model_settings,
+                vision_priors: Some(|ctx, settings| {
+                    load_or_initialize_biome_vision_priors(ctx.base_image, settings, ctx.out_dir)
+                }),
+            },
         }
+    }
+}
 
-        "naming" => {
-            progress(0.0, "Placeholder — naming not yet implemented");
-            progress(100.0, "Naming stage skipped");
-            Ok(())
+impl StageRunner for PlanetStageRunner {
+    fn run_stage(
+        &mut self,
+        stage: pipeline::Stage,
+        ctx: &StageContext,
+        on_progress: &mut dyn FnMut(f32, &str),
+    ) -> Result<(), String> {
+        self.inner.run_stage(stage, ctx, on_progress)?;
+        if stage == pipeline::Stage::Clustering {
+            write_continents_and_biome_summaries(ctx.out_dir, &self.inner.registry)?;
         }
+        Ok(())
+    }
 
-        _ => Err(format!("Unknown stage: {}", stage_name)),
+    fn extra_fingerprint(&self, stage: pipeline::Stage) -> String {
+        use pipeline::Stage;
+        if !matches!(
+            stage,
+            Stage::Biome
+                | Stage::Suitability
+                | Stage::SeaZones
+                | Stage::Clustering
+                | Stage::Naming
+                | Stage::Roads
+        ) {
+            return String::new();
+        }
+        serde_json::to_string(&(&self.inner.registry, &self.inner.model_settings))
+            .unwrap_or_default()
     }
 }
 
+/// Adds biome summaries to the clustered provinces and derives continents from the kingdoms.
+fn write_continents_and_biome_summaries(
+    out_dir: &std::path::Path,
+    registry: &BiomeRegistry,
+) -> Result<(), String> {
+    let (w, h) = get_dimensions(&out_dir.join("landmask.png"))?;
+    let mask = load_landmask(&out_dir.join("landmask.png"), w, h)?;
+    let labels = load_labels(&out_dir.join("province_id.png"), &mask)?;
+    let kingdom_labels = load_labels(&out_dir.join("kingdom_id.png"), &mask)?;
+    let biomes = load_biome_indices(&out_dir.join("biome.png"))?;
+    let biome_confidence = load_optional_mask_u8(&out_dir.join("biome_confidence.png"), w, h)?
+        .unwrap_or_else(|| vec![255; (w * h) as usize]);
+    let adj = load_json_file::<Vec<graph::ProvinceAdjacency>>(
+        &out_dir.join("adjacency.json"),
+        "adjacency.json",
+    )?;
+    let HierarchyRecords {
+        mut provinces,
+        duchies,
+        kingdoms,
+        ..
+    } = load_hierarchy_records(out_dir)?;
+
+    let province_summaries = enrich_province_biome_records(
+        &mut provinces,
+        &labels,
+        &biomes,
+        &biome_confidence,
+        registry,
+    );
+    let continents = build_continents(&provinces, &duchies, &kingdoms, &adj, None);
+    let kingdom_to_continent: HashMap<u32, u32> = continents
+        .iter()
+        .flat_map(|c| c.kingdom_ids.iter().map(move |&kid| (kid, c.id)))
+        .collect();
+    let continent_labels: Vec<u32> = kingdom_labels
+        .iter()
+        .map(|&kid| kingdom_to_continent.get(&kid).copied().unwrap_or(0))
+        .collect();
+    export::write_id_texture(&continent_labels, w, h, &out_dir.join("continent_id.png"))?;
+    write_json_pretty(&out_dir.join("continents.json"), &continents)?;
+    enrich_biome_report_with_provinces(
+        &out_dir.join(biome::BIOME_REPORT_FILE),
+        &province_summaries,
+    )?;
+    export::write_provinces_json(&provinces, &out_dir.join("provinces.json"))
+}
+
 fn run_isolate_all_entities_job(
     job_id: String,
     jobs: Arc<Mutex<HashMap<String, JobRecord>>>,
@@ -2556,11 +2403,11 @@ fn load_or_initialize_biome_vision_priors(
 fn enrich_province_biome_records(
     provinces: &mut [ProvinceRecord],
     labels: &[u32],
-    biome_indices: &[u8],
+    biome_indices: &[u16],
     biome_confidence: &[u8],
     registry: &BiomeRegistry,
 ) -> Vec<BiomeProvinceSummary> {
-    let mut biome_counts: HashMap<u32, HashMap<u8, u32>> = HashMap::new();
+    let mut biome_counts: HashMap<u32, HashMap<u16, u32>> = HashMap::new();
     let mut confidence_sums: HashMap<u32, u64> = HashMap::new();
     let mut pixel_counts: HashMap<u32, u32> = HashMap::new();
     let no_label = u32::MAX;
@@ -3031,38 +2878,15 @@ fn load_landmask(path: &std::path::Path, _w: u32, _h: u32) -> Result<Vec<bool>,
     Ok(gray.pixels().map(|p| p[0] > 128).collect())
 }
 
-fn load_height16(path: &std::path::Path, _w: u32, _h: u32) -> Result<Vec<u16>, String> {
-    let img = image::open(path).map_err(|e| format!("Failed to open height16: {}", e))?;
-    let gray16 = img.to_luma16();
-    Ok(gray16.pixels().map(|p| p[0]).collect())
-}
-
 fn load_mask_u8(path: &std::path::Path, _w: u32, _h: u32) -> Result<Vec<u8>, String> {
     let img = image::open(path).map_err(|e| format!("Failed to open mask: {}", e))?;
     let gray = img.to_luma8();
     Ok(gray.pixels().map(|p| p[0]).collect())
 }
 
-fn load_f32_binary(path: &std::path::Path, w: u32, h: u32) -> Result<Vec<f32>, String> {
-    let bytes =
-        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
-    let n = (w * h) as usize;
-    if bytes.len() != n * 4 {
-        return Err(format!(
-            "Binary file size mismatch: expected {} bytes, got {}",
-            n * 4,
-            bytes.len()
-        ));
-    }
-    Ok(bytes
-        .chunks_exact(4)
-        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-        .collect())
-}
-
-fn load_seeds_json(path: &std::path::Path) -> Result<Vec<sampling::Seed>, String> {
-    let json = std::fs::read_to_string(path).map_err(|e| format!("Failed to read seeds: {}", e))?;
-    serde_json::from_str(&json).map_err(|e| format!("Failed to parse seeds: {}", e))
+fn load_biome_indices(path: &std::path::Path) -> Result<Vec<u16>, String> {
+    let img = image::open(path).map_err(|e| format!("Failed to open biome map: {}", e))?;
+    Ok(biome::decode_biome_texture(&img))
 }
 
 fn load_id_texture(path: &std::path::Path, _w: u32, _h: u32) -> Result<Vec<u32>, String> {
@@ -3074,6 +2898,24 @@ fn load_id_texture(path: &std::path::Path, _w: u32, _h: u32) -> Result<Vec<u32>,
         .collect())
 }
 
+/// Like [`load_id_texture`], but pixels outside `labeled` read as `u32::MAX`, since unlabeled
+/// pixels are stored black like id 0.
+fn load_labels(path: &std::path::Path, labeled: &[bool]) -> Result<Vec<u32>, String> {
+    let img = image::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    Ok(img
+        .to_rgb8()
+        .pixels()
+        .zip(labeled)
+        .map(|(p, &has_id)| {
+            if has_id {
+                p[0] as u32 | ((p[1] as u32) << 8) | ((p[2] as u32) << 16)
+            } else {
+                u32::MAX
+            }
+        })
+        .collect())
+}
+
 fn build_continents(
     provinces: &[cluster::ProvinceRecord],
     duchies: &[cluster::DuchyRecord],
@@ -3090,7 +2932,7 @@ fn build_continents(
         let Some(&k1) = province_to_kingdom.get(&adj.province_id) else {
             continue;
         };
-        for edge in &adj.neighbors {
+        for edge in adj.neighbors.iter().filter(|e| e.kind == graph::EdgeKind::Land) {
             let Some(&k2) = province_to_kingdom.get(&edge.neighbor_id) else {
                 continue;
             };
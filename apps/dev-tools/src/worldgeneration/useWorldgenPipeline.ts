@@ -45,7 +45,7 @@ export const PIPELINE_STAGES: PipelineStage[] = [
         id: "biome",
         name: "Biome Classification",
         description: "Classify biomes from latitude, elevation, slope, and coast distance",
-        outputs: ["biome.png"],
+        outputs: ["biome.png", "biome_palette.json"],
         requires: ["height", "landmask"],
     },
     {
@@ -66,7 +66,7 @@ export const PIPELINE_STAGES: PipelineStage[] = [
         id: "partition",
         name: "Province Growth",
         description: "Multi-source Dijkstra partitioning with cost-aware borders",
-        outputs: ["province_id.png"],
+        outputs: ["province_id_raw.png"],
         requires: ["seeds", "height", "rivers"],
     },
     {
@@ -76,12 +76,19 @@ export const PIPELINE_STAGES: PipelineStage[] = [
         outputs: ["province_id.png"],
         requires: ["partition"],
     },
+    {
+        id: "sea_zones",
+        name: "Sea Zones",
+        description: "Partition water into sea zones by depth band and detect straits",
+        outputs: ["sea_zone_id.png", "sea_zones.json", "straits.json"],
+        requires: ["postprocess", "biome"],
+    },
     {
         id: "adjacency",
         name: "Adjacency Graph",
-        description: "Build province neighbor graph with border/river crossing info",
+        description: "Build province neighbor graph with border/river crossing, coast and strait info",
         outputs: ["adjacency.json"],
-        requires: ["postprocess", "rivers"],
+        requires: ["sea_zones", "rivers"],
     },
     {
         id: "clustering",
@@ -93,8 +100,15 @@ export const PIPELINE_STAGES: PipelineStage[] = [
     {
         id: "naming",
         name: "Naming & Flavor",
-        description: "Generate names, culture tags, and lore (placeholder)",
-        outputs: ["provinces.json"],
+        description: "Generate culture-aware names for provinces, duchies and kingdoms",
+        outputs: ["names.json"],
+        requires: ["clustering"],
+    },
+    {
+        id: "roads",
+        name: "Road Network",
+        description: "Link province seats and major locations with highways, trails, bridges and fords",
+        outputs: ["roads.json", "roads.geojson", "road_mask.png"],
         requires: ["clustering"],
     },
 ];
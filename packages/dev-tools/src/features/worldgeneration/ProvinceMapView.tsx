@@ -1,6 +1,6 @@
 import { useEffect, useRef, useState, useCallback, type PointerEvent } from "react";
 
-const MAX_BIOME_PALETTE = 32;
+const BIOME_PALETTE_WIDTH = 256;
 
 // ── Shader Sources ──
 
@@ -39,8 +39,9 @@ uniform float u_opacity;
 uniform vec2 u_texSize;
 uniform vec3 u_highlightId;   // RGB of hovered province
 uniform int u_hasHighlight;
-uniform vec3 u_biomePalette[32];
-uniform int u_biomePaletteSize;
+uniform sampler2D u_biomePaletteTexture; // one texel per biome index, 256 per row
+uniform float u_biomePaletteCount;
+uniform float u_biomePaletteRows;
 
 // Stable hash for ID → color
 vec3 idToColor(vec3 id) {
@@ -61,14 +62,16 @@ vec3 idToColor(vec3 id) {
     return rgb + m;
 }
 
-vec3 biomeColor(float biomeVal) {
-    int b = int(biomeVal * 255.0 + 0.5);
-    for (int i = 0; i < 32; i++) {
-        if (i == b && i < u_biomePaletteSize) {
-            return u_biomePalette[i];
-        }
+// biome.png packs the index as r = low byte, g = high byte, b = 0. Maps from before the
+// encoding change are grayscale, which shows up as a non-zero blue channel.
+vec3 biomeColor(vec3 biomeVal) {
+    vec3 bytes = floor(biomeVal * 255.0 + 0.5);
+    float index = bytes.b > 0.0 ? bytes.r : bytes.r + bytes.g * 256.0;
+    if (index >= u_biomePaletteCount) {
+        return vec3(0.4, 0.2, 0.1);
     }
-    return vec3(0.4, 0.2, 0.1);
+    vec2 uv = vec2((mod(index, 256.0) + 0.5) / 256.0, (floor(index / 256.0) + 0.5) / u_biomePaletteRows);
+    return texture2D(u_biomePaletteTexture, uv).rgb;
 }
 
 // Border detection: check if any neighbor has a different ID
@@ -108,7 +111,7 @@ void main() {
         if (land < 0.5) color = vec3(0.05, 0.12, 0.25);
     } else if (u_layer == 4) {
         // Biome visualization
-        float b = texture2D(u_biomeTexture, v_texCoord).r;
+        vec3 b = texture2D(u_biomeTexture, v_texCoord).rgb;
         color = biomeColor(b);
     } else {
         // Province / Duchy / Kingdom ID visualization
@@ -221,7 +224,7 @@ export function ProvinceMapView({
     const programRef = useRef<WebGLProgram | null>(null);
     const texturesRef = useRef<Record<string, WebGLTexture>>({});
     const pickingDataRef = useRef<Record<string, Uint8ClampedArray>>({});
-    const biomePaletteRef = useRef<number[]>([]);
+    const biomePaletteRef = useRef({ count: 0, rows: 1 });
     const rafRef = useRef<number>(0);
     const sizeRef = useRef({ width: 0, height: 0 });
     const texSizeRef = useRef({ width: 0, height: 0 });
@@ -371,21 +374,35 @@ export function ProvinceMapView({
                     const paletteRes = await fetch(`${worldgenBase}/biome_palette.json?${cacheBuster}`);
                     if (paletteRes.ok) {
                         const payload = await paletteRes.json();
-                        const palette = Array.from({ length: MAX_BIOME_PALETTE }, (_, index) => {
-                            const entry = Array.isArray(payload) ? payload.find((candidate: any) => candidate.index === index) : null;
-                            if (!entry?.hexColor) return [0.25, 0.12, 0.1];
-                            const hex = String(entry.hexColor).replace("#", "");
+                        const entries: any[] = Array.isArray(payload) ? payload : [];
+                        const count = entries.reduce((max, entry) => Math.max(max, Number(entry.index) + 1), 0);
+                        const rows = Math.max(1, Math.ceil(count / BIOME_PALETTE_WIDTH));
+                        const texels = new Uint8Array(BIOME_PALETTE_WIDTH * rows * 4);
+                        for (let index = 0; index < count; index++) {
+                            texels.set([64, 31, 26, 255], index * 4);
+                        }
+                        for (const entry of entries) {
+                            const hex = String(entry.hexColor ?? "").replace("#", "");
                             const normalized = hex.length === 6 ? hex : "7d6b5b";
-                            return [
-                                parseInt(normalized.slice(0, 2), 16) / 255,
-                                parseInt(normalized.slice(2, 4), 16) / 255,
-                                parseInt(normalized.slice(4, 6), 16) / 255,
-                            ];
-                        }).flat();
-                        biomePaletteRef.current = palette;
+                            texels.set([
+                                parseInt(normalized.slice(0, 2), 16),
+                                parseInt(normalized.slice(2, 4), 16),
+                                parseInt(normalized.slice(4, 6), 16),
+                                255,
+                            ], Number(entry.index) * 4);
+                        }
+                        const tex = gl.createTexture()!;
+                        gl.bindTexture(gl.TEXTURE_2D, tex);
+                        gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
+                        gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
+                        gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.NEAREST);
+                        gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.NEAREST);
+                        gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, BIOME_PALETTE_WIDTH, rows, 0, gl.RGBA, gl.UNSIGNED_BYTE, texels);
+                        texturesRef.current["biome_palette"] = tex;
+                        biomePaletteRef.current = { count, rows };
                     }
                 } catch {
-                    biomePaletteRef.current = [];
+                    biomePaletteRef.current = { count: 0, rows: 1 };
                 }
                 setLoaded(true);
                 setLoadError(null);
@@ -472,14 +489,8 @@ export function ProvinceMapView({
             gl.uniform1f(gl.getUniformLocation(program, "u_borderWidth"), borderWidth);
             gl.uniform1f(gl.getUniformLocation(program, "u_opacity"), opacity);
             gl.uniform2f(gl.getUniformLocation(program, "u_texSize"), imgW, imgH);
-            const paletteValues = biomePaletteRef.current.length === MAX_BIOME_PALETTE * 3
-                ? biomePaletteRef.current
-                : Array.from({ length: MAX_BIOME_PALETTE * 3 }, () => 0.2);
-            gl.uniform3fv(gl.getUniformLocation(program, "u_biomePalette[0]"), new Float32Array(paletteValues));
-            gl.uniform1i(
-                gl.getUniformLocation(program, "u_biomePaletteSize"),
-                Math.min(MAX_BIOME_PALETTE, Math.floor(paletteValues.length / 3)),
-            );
+            gl.uniform1f(gl.getUniformLocation(program, "u_biomePaletteCount"), biomePaletteRef.current.count);
+            gl.uniform1f(gl.getUniformLocation(program, "u_biomePaletteRows"), biomePaletteRef.current.rows);
 
             // Apply specific highlight ID from parent props
             const targetHighlightId = selectedId !== null ? selectedId : hoveredId;
@@ -506,6 +517,7 @@ export function ProvinceMapView({
                 ["biome", "u_biomeTexture", 6],
                 ["biome_confidence", "u_biomeConfidenceTexture", 7],
                 ["landmask", "u_landmaskTexture", 8],
+                ["biome_palette", "u_biomePaletteTexture", 9],
             ];
 
             for (const [key, uniform, unit] of texBindings) {
@@ -2,10 +2,16 @@ use crate::biome_archetype::BiomeRegistry;
 use crate::landmask::rgb_to_hsv;
 use crate::raster::distance_transform;
 use crate::WorldgenConfig;
-use image::RgbImage;
+use image::{DynamicImage, RgbImage};
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::collections::HashMap;
 
+/// Sidecar to `biome.png` mapping each texture index to the archetype it was classified as.
+pub const BIOME_PALETTE_FILE: &str = "biome_palette.json";
+/// Per-biome coverage and confidence written by the biome stage.
+pub const BIOME_REPORT_FILE: &str = "biome_report.json";
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BiomeModelSettings {
@@ -88,7 +94,7 @@ pub struct BiomeVisionAnalysis {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BiomePaletteEntry {
-    pub index: u8,
+    pub index: u16,
     pub biome_id: String,
     pub name: String,
     pub hex_color: String,
@@ -150,7 +156,7 @@ pub struct BiomeReport {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BiomeAnalysisResult {
-    pub biome_indices: Vec<u8>,
+    pub biome_indices: Vec<u16>,
     pub confidence_map: Vec<u8>,
     pub report: BiomeReport,
 }
@@ -162,45 +168,53 @@ struct EnvironmentalRasters {
     elevation: Vec<f32>,
     slope: Vec<f32>,
     coast_distance: Vec<f32>,
-    river_distance: Vec<f32>,
     hue: Vec<f32>,
     saturation: Vec<f32>,
     value: Vec<f32>,
 }
 
+/// Rasters biomes are classified from.
+pub struct BiomeInput<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub height_field: &'a [u16],
+    pub landmask: &'a [bool],
+    pub river_mask: Option<&'a [u8]>,
+    /// The planet's base texture, scored against archetype colors when enabled.
+    pub image: &'a RgbImage,
+}
+
+/// Per-archetype tallies taken while scoring, before low-confidence edges are smoothed.
+struct ScoringTally {
+    pixel_counts: Vec<u64>,
+    confidence_sums: Vec<f32>,
+    /// How often each archetype won with each other archetype as a runner-up.
+    confusion_counts: Vec<Vec<u64>>,
+}
+
 /// Stage 5: biome classification with calibrated archetypes, confidence, and optional vision priors.
 pub fn classify_biomes(
-    height: &[u16],
-    landmask: &[bool],
-    river_mask: Option<&[u8]>,
-    img: &RgbImage,
+    input: &BiomeInput,
     config: &WorldgenConfig,
     registry: &BiomeRegistry,
     model_settings: &BiomeModelSettings,
     vision_analysis: Option<&BiomeVisionAnalysis>,
-    width: u32,
-    height_dim: u32,
     on_progress: &mut dyn FnMut(f32, &str),
 ) -> BiomeAnalysisResult {
+    let (width, height_dim, landmask) = (input.width, input.height, input.landmask);
     let n = (width * height_dim) as usize;
-    let rasters = derive_environmental_rasters(
-        height,
-        landmask,
-        river_mask,
-        img,
-        config,
-        width,
-        height_dim,
-        on_progress,
-    );
+    let rasters = derive_environmental_rasters(input, config, on_progress);
 
     on_progress(55.0, "Scoring biome archetypes");
 
-    let mut biome_indices = vec![0u8; n];
+    let mut biome_indices = vec![0u16; n];
     let mut confidence = vec![0u8; n];
-    let mut pixel_counts = vec![0u64; registry.archetypes.len()];
-    let mut confidence_sums = vec![0f32; registry.archetypes.len()];
-    let mut confusion_counts = vec![vec![0u64; registry.archetypes.len()]; registry.archetypes.len()];
+    let archetype_count = registry.archetypes.len();
+    let mut tally = ScoringTally {
+        pixel_counts: vec![0; archetype_count],
+        confidence_sums: vec![0.0; archetype_count],
+        confusion_counts: vec![vec![0; archetype_count]; archetype_count],
+    };
     let use_color = config.color_based_biomes;
     let vision_available = vision_analysis
         .map(|analysis| !analysis.cells.is_empty())
@@ -222,10 +236,10 @@ pub fn classify_biomes(
                 } else {
                     ocean_idx
                 };
-                biome_indices[i] = water_idx as u8;
+                biome_indices[i] = water_idx as u16;
                 confidence[i] = 255;
-                pixel_counts[water_idx] += 1;
-                confidence_sums[water_idx] += 1.0;
+                tally.pixel_counts[water_idx] += 1;
+                tally.confidence_sums[water_idx] += 1.0;
                 continue;
             }
 
@@ -271,12 +285,12 @@ pub fn classify_biomes(
             let denom = second.1.abs() + best.1.abs() + 0.0001;
             let conf = (margin / denom).clamp(0.0, 1.0);
 
-            biome_indices[i] = best.0 as u8;
+            biome_indices[i] = best.0 as u16;
             confidence[i] = (conf * 255.0).round() as u8;
-            pixel_counts[best.0] += 1;
-            confidence_sums[best.0] += conf;
-            confusion_counts[best.0][second.0] += 1;
-            confusion_counts[best.0][third.0] += 1;
+            tally.pixel_counts[best.0] += 1;
+            tally.confidence_sums[best.0] += conf;
+            tally.confusion_counts[best.0][second.0] += 1;
+            tally.confusion_counts[best.0][third.0] += 1;
         }
     }
 
@@ -295,16 +309,13 @@ pub fn classify_biomes(
     on_progress(92.0, "Building biome diagnostics");
 
     let report = build_biome_report(
+        input,
         registry,
         vision_analysis,
         model_settings,
-        width,
-        height_dim,
         &biome_indices,
         &confidence,
-        &pixel_counts,
-        &confidence_sums,
-        &confusion_counts,
+        &tally,
     );
 
     on_progress(100.0, "Biome classification complete");
@@ -322,7 +333,7 @@ pub fn biome_palette(registry: &BiomeRegistry) -> Vec<BiomePaletteEntry> {
         .iter()
         .enumerate()
         .map(|(index, archetype)| BiomePaletteEntry {
-            index: index as u8,
+            index: index as u16,
             biome_id: archetype.id.clone(),
             name: archetype.name.clone(),
             hex_color: archetype.hex_color.clone(),
@@ -330,16 +341,37 @@ pub fn biome_palette(registry: &BiomeRegistry) -> Vec<BiomePaletteEntry> {
         .collect()
 }
 
+/// Textures written before indices were widened are 8-bit grayscale holding the index directly.
+pub fn is_legacy_biome_texture(img: &DynamicImage) -> bool {
+    matches!(img, DynamicImage::ImageLuma8(_))
+}
+
+/// Decode biome indices from a texture written by `export::write_biome_texture`, or from a
+/// legacy grayscale one.
+pub fn decode_biome_texture(img: &DynamicImage) -> Vec<u16> {
+    match img {
+        DynamicImage::ImageLuma8(gray) => gray.pixels().map(|p| p[0] as u16).collect(),
+        _ => img
+            .to_rgb8()
+            .pixels()
+            .map(|p| p[0] as u16 | ((p[1] as u16) << 8))
+            .collect(),
+    }
+}
+
 fn derive_environmental_rasters(
-    height: &[u16],
-    landmask: &[bool],
-    river_mask: Option<&[u8]>,
-    img: &RgbImage,
+    input: &BiomeInput,
     config: &WorldgenConfig,
-    width: u32,
-    height_dim: u32,
     on_progress: &mut dyn FnMut(f32, &str),
 ) -> EnvironmentalRasters {
+    let BiomeInput {
+        width,
+        height: height_dim,
+        height_field: height,
+        landmask,
+        river_mask,
+        image: img,
+    } = *input;
     let n = (width * height_dim) as usize;
 
     on_progress(0.0, "Computing slope");
@@ -364,7 +396,10 @@ fn derive_environmental_rasters(
 
     on_progress(25.0, "Computing river distance");
     let river_distance = if let Some(river_mask) = river_mask {
-        let river_bool = river_mask.iter().map(|&value| value > 0).collect::<Vec<_>>();
+        let river_bool = river_mask
+            .iter()
+            .map(|&value| value > 0)
+            .collect::<Vec<_>>();
         distance_transform(&river_bool, width, height_dim)
     } else {
         vec![width.max(height_dim) as f32; n]
@@ -388,8 +423,8 @@ fn derive_environmental_rasters(
             let river_prox = (1.0 - (river_distance[i] / 120.0).min(1.0)).max(0.0);
 
             temperature[i] = (equator_warmth - elev * 0.35).clamp(0.0, 1.0);
-            precipitation[i] = (0.25 + coast_prox * 0.45 + river_prox * 0.2 - elev * 0.25)
-                .clamp(0.0, 1.0);
+            precipitation[i] =
+                (0.25 + coast_prox * 0.45 + river_prox * 0.2 - elev * 0.25).clamp(0.0, 1.0);
             elevation[i] = elev;
 
             if config.color_based_biomes {
@@ -408,7 +443,6 @@ fn derive_environmental_rasters(
         elevation,
         slope,
         coast_distance,
-        river_distance,
         hue,
         saturation,
         value,
@@ -416,16 +450,13 @@ fn derive_environmental_rasters(
 }
 
 fn build_biome_report(
+    input: &BiomeInput,
     registry: &BiomeRegistry,
     vision_analysis: Option<&BiomeVisionAnalysis>,
     model_settings: &BiomeModelSettings,
-    width: u32,
-    height: u32,
-    biome_indices: &[u8],
+    biome_indices: &[u16],
     confidence_map: &[u8],
-    pre_smooth_pixel_counts: &[u64],
-    pre_smooth_confidence_sums: &[f32],
-    confusion_counts: &[Vec<u64>],
+    pre_smooth: &ScoringTally,
 ) -> BiomeReport {
     let total_pixels = biome_indices.len() as f32;
     let mut counts = vec![0u64; registry.archetypes.len()];
@@ -451,7 +482,7 @@ fn build_biome_report(
             continue;
         }
 
-        let mut top_candidates = confusion_counts[idx]
+        let mut top_candidates = pre_smooth.confusion_counts[idx]
             .iter()
             .enumerate()
             .filter(|(candidate_idx, count)| *candidate_idx != idx && **count > 0)
@@ -466,8 +497,8 @@ fn build_biome_report(
             pixel_share: pixel_count as f32 / total_pixels,
             avg_confidence: if pixel_count > 0 {
                 confidence_sums[idx] / pixel_count as f32
-            } else if pre_smooth_pixel_counts[idx] > 0 {
-                pre_smooth_confidence_sums[idx] / pre_smooth_pixel_counts[idx] as f32
+            } else if pre_smooth.pixel_counts[idx] > 0 {
+                pre_smooth.confidence_sums[idx] / pre_smooth.pixel_counts[idx] as f32
             } else {
                 0.0
             },
@@ -480,11 +511,11 @@ fn build_biome_report(
         });
     }
 
-    active_biomes.sort_by(|left, right| right.pixel_count.cmp(&left.pixel_count));
+    active_biomes.sort_by_key(|summary| Reverse(summary.pixel_count));
 
     BiomeReport {
-        width,
-        height,
+        width: input.width,
+        height: input.height,
         analysis_version: model_settings.analysis_version.clone(),
         source_image_hash: vision_analysis.and_then(|analysis| {
             if analysis.source_image_hash.is_empty() {
@@ -516,7 +547,7 @@ fn build_biome_report(
 }
 
 fn smooth_low_confidence_edges(
-    biome_indices: &mut Vec<u8>,
+    biome_indices: &mut Vec<u16>,
     confidence: &mut Vec<u8>,
     landmask: &[bool],
     rasters: &EnvironmentalRasters,
@@ -541,8 +572,8 @@ fn smooth_low_confidence_edges(
                     continue;
                 }
 
-                let mut counts = HashMap::<u8, u32>::new();
-                let mut conf_sum = HashMap::<u8, u32>::new();
+                let mut counts = HashMap::<u16, u32>::new();
+                let mut conf_sum = HashMap::<u16, u32>::new();
                 for ny in (y - 1)..=(y + 1) {
                     for nx in (x - 1)..=(x + 1) {
                         if nx == x && ny == y {
@@ -577,9 +608,12 @@ fn smooth_low_confidence_edges(
 }
 
 fn water_biome_index(registry: &BiomeRegistry, preferred_id: &str) -> Option<usize> {
-    registry
-        .index_of_id(preferred_id)
-        .or_else(|| registry.archetypes.iter().position(|entry| entry.id.contains("ocean")))
+    registry.index_of_id(preferred_id).or_else(|| {
+        registry
+            .archetypes
+            .iter()
+            .position(|entry| entry.id.contains("ocean"))
+    })
 }
 
 fn vision_penalty_for_pixel(
@@ -619,3 +653,58 @@ fn vision_penalty_for_pixel(
         1.0 - prior_strength
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export, pipeline};
+    use image::GrayImage;
+    use std::path::PathBuf;
+
+    /// A texture path unique to this test process, removed when dropped.
+    struct TempTexture(PathBuf);
+
+    impl TempTexture {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("worldgen-{}-{}.png", name, std::process::id())))
+        }
+    }
+
+    impl Drop for TempTexture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn biome_texture_round_trips_indices_above_255() {
+        let (width, height) = (4, 3);
+        let indices: Vec<u16> = vec![0, 1, 255, 256, 300, 511, 1024, 4097, 65534, 65535, 7, 42];
+        let texture = TempTexture::new("biome-u16");
+
+        export::write_biome_texture(&indices, width, height, &texture.0).unwrap();
+        let img = image::open(&texture.0).unwrap();
+
+        assert!(!is_legacy_biome_texture(&img));
+        assert_eq!(pipeline::read_biome(&texture.0).unwrap(), indices);
+    }
+
+    #[test]
+    fn legacy_grayscale_biome_texture_still_decodes() {
+        let (width, height) = (3, 2);
+        let indices: Vec<u8> = vec![0, 3, 17, 128, 200, 255];
+        let texture = TempTexture::new("biome-luma8");
+
+        GrayImage::from_raw(width, height, indices.clone())
+            .unwrap()
+            .save(&texture.0)
+            .unwrap();
+        let img = image::open(&texture.0).unwrap();
+
+        assert!(is_legacy_biome_texture(&img));
+        assert_eq!(
+            pipeline::read_biome(&texture.0).unwrap(),
+            indices.into_iter().map(u16::from).collect::<Vec<_>>()
+        );
+    }
+}
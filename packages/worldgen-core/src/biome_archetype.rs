@@ -75,16 +75,14 @@ impl BiomeRegistry {
         Self::default()
     }
 
+    /// Closest archetype to the environment, and to the (hue, saturation, value) color if given.
     pub fn find_best_match(
         &self,
         temp: f32,
         rain: f32,
         elev: f32,
         slope: f32,
-        h: f32,
-        s: f32,
-        v: f32,
-        use_color: bool,
+        hsv: Option<(f32, f32, f32)>,
     ) -> Option<&BiomeArchetype> {
         if self.archetypes.is_empty() {
             return None;
@@ -95,17 +93,10 @@ impl BiomeRegistry {
 
         for archetype in &self.archetypes {
             let env_score = archetype.environmental_score(temp, rain, elev, slope);
-            let color_score = if use_color {
-                archetype.color_score(h, s, v)
-            } else {
-                0.0
-            };
-
             // Heuristic penalty to prevent "jittery" selection if values are on the edge
-            let total_score = if use_color {
-                env_score * 0.2 + color_score
-            } else {
-                env_score
+            let total_score = match hsv {
+                Some((h, s, v)) => env_score * 0.2 + archetype.color_score(h, s, v),
+                None => env_score,
             };
 
             if total_score < min_score {
@@ -126,10 +117,8 @@ impl BiomeRegistry {
     }
 
     pub fn default_registry() -> Self {
-        let mut archetypes = Vec::new();
-
         // --- OCEANIC ---
-        archetypes.push(BiomeArchetype {
+        let mut archetypes = vec![BiomeArchetype {
             id: "abyssal_ocean".to_string(),
             name: "Abyssal Ocean".to_string(),
             hex_color: "#0a1628".to_string(),
@@ -150,7 +139,7 @@ impl BiomeRegistry {
             },
             suitability_weight: 0.0,
             calibration: BiomeArchetypeCalibration::default(),
-        });
+        }];
 
         archetypes.push(BiomeArchetype {
             id: "deep_ocean".to_string(),
@@ -1,6 +1,22 @@
-use crate::graph::ProvinceAdjacency;
+use crate::config::{ClusterBalance, MapProjection, WorldgenConfig};
+use crate::graph::{EdgeInfo, EdgeKind, ProvinceAdjacency};
+use crate::height;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Locked assignments are kept next to the stage artifacts under this name.
+pub const HIERARCHY_LOCKS_FILE: &str = "hierarchy_locks.json";
+
+/// Share of a border's affinity kept when a river runs along it.
+const RIVER_BORDER_FACTOR: f64 = 0.25;
+/// Share of a border's affinity lost at the highest elevation.
+const RIDGE_BORDER_FACTOR: f64 = 0.9;
+/// Share of a border's affinity kept between provinces of different biomes.
+const BIOME_BORDER_FACTOR: f64 = 0.5;
+/// Affinity of a strait, which has no shared border to measure.
+const STRAIT_AFFINITY: f64 = 1.0;
+
+const NO_GROUP: usize = usize::MAX;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -8,10 +24,11 @@ pub struct ProvinceRecord {
     pub id: u32,
     pub seed_x: u32,
     pub seed_y: u32,
+    /// Pixel count, in equator pixels under an equirectangular projection.
     pub area: u32,
     pub duchy_id: u32,
     pub kingdom_id: u32,
-    pub biome_primary: u8,
+    pub biome_primary: u16,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub biome_primary_id: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -44,38 +61,128 @@ pub struct KingdomRecord {
     pub name: String,
 }
 
+#[derive(Debug, Clone)]
+pub struct ClusterSettings {
+    /// Provinces per duchy.
+    pub duchy_size_min: u32,
+    pub duchy_size_max: u32,
+    /// Duchies per kingdom.
+    pub kingdom_size_min: u32,
+    pub kingdom_size_max: u32,
+    pub balance: ClusterBalance,
+    pub projection: MapProjection,
+}
+
+impl ClusterSettings {
+    pub fn for_config(config: &WorldgenConfig) -> Self {
+        Self {
+            duchy_size_min: config.duchy_size_min,
+            duchy_size_max: config.duchy_size_max,
+            kingdom_size_min: config.kingdom_size_min,
+            kingdom_size_max: config.kingdom_size_max,
+            balance: config.cluster_balance,
+            projection: config.projection,
+        }
+    }
+}
+
+/// Hand-made assignments that survive re-clustering. A locked title keeps its id and exactly
+/// its locked members; everything else is clustered around it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HierarchyLocks {
+    /// Province id -> duchy id.
+    #[serde(default)]
+    pub province_duchy: BTreeMap<u32, u32>,
+    /// Duchy id -> kingdom id. Only duchies with locked provinces keep their id, so entries for
+    /// other duchies are ignored.
+    #[serde(default)]
+    pub duchy_kingdom: BTreeMap<u32, u32>,
+}
+
+impl HierarchyLocks {
+    /// Locks a duchy to its current provinces.
+    pub fn lock_duchy(&mut self, duchy: &DuchyRecord) {
+        self.province_duchy
+            .retain(|_, duchy_id| *duchy_id != duchy.id);
+        for &province_id in &duchy.province_ids {
+            self.province_duchy.insert(province_id, duchy.id);
+        }
+    }
+
+    /// Locks a kingdom to its current duchies, and each of those to its provinces.
+    pub fn lock_kingdom(&mut self, kingdom: &KingdomRecord, duchies: &[DuchyRecord]) {
+        self.duchy_kingdom
+            .retain(|_, kingdom_id| *kingdom_id != kingdom.id);
+        for duchy in duchies.iter().filter(|d| kingdom.duchy_ids.contains(&d.id)) {
+            self.lock_duchy(duchy);
+            self.duchy_kingdom.insert(duchy.id, kingdom.id);
+        }
+    }
+}
+
+/// Rasters and graph provinces are clustered over. `labels` uses `u32::MAX` for water.
+pub struct ClusterInput<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub labels: &'a [u32],
+    pub biome_indices: &'a [u16],
+    /// Balance weight per pixel when balancing on suitability.
+    pub suitability: Option<&'a [f32]>,
+    /// (province id, x, y) of each province seed.
+    pub seeds: &'a [(u32, u32, u32)],
+    pub adjacency: &'a [ProvinceAdjacency],
+}
+
+pub struct ClusterResult {
+    pub provinces: Vec<ProvinceRecord>,
+    pub duchies: Vec<DuchyRecord>,
+    pub kingdoms: Vec<KingdomRecord>,
+    /// Duchy id per pixel, `u32::MAX` on water.
+    pub duchy_labels: Vec<u32>,
+    /// Kingdom id per pixel, `u32::MAX` on water.
+    pub kingdom_labels: Vec<u32>,
+}
+
 /// Stage 11: Cluster provinces into duchies and kingdoms.
+///
+/// Both ranks use the same constrained region growing: provinces into duchies across land
+/// borders, then duchies into kingdoms, which may also span straits. Borders prefer rivers,
+/// ridges and biome changes, titles of a rank are balanced on area or suitability, and every
+/// generated title is contiguous.
 pub fn cluster_hierarchy(
-    labels: &[u32],
-    biome: &[u8],
-    seeds: &[(u32, u32, u32)], // (id, x, y)
-    adjacency: &[ProvinceAdjacency],
-    width: u32,
-    height: u32,
-    duchy_size_min: u32,
-    duchy_size_max: u32,
-    _kingdom_size_min: u32,
-    kingdom_size_max: u32,
+    input: &ClusterInput,
+    settings: &ClusterSettings,
+    locks: &HierarchyLocks,
     on_progress: &mut dyn FnMut(f32, &str),
-) -> (
-    Vec<ProvinceRecord>,
-    Vec<DuchyRecord>,
-    Vec<KingdomRecord>,
-    Vec<u32>,
-    Vec<u32>,
-) {
+) -> ClusterResult {
+    let ClusterInput {
+        width,
+        height,
+        labels,
+        biome_indices: biome,
+        suitability,
+        seeds,
+        adjacency,
+    } = *input;
     let n = (width * height) as usize;
     let no_label = u32::MAX;
 
     on_progress(0.0, "Computing province stats");
 
-    // Compute per-province area and dominant biome
-    let mut area_map: HashMap<u32, u32> = HashMap::new();
-    let mut biome_counts: HashMap<u32, HashMap<u8, u32>> = HashMap::new();
+    // Compute per-province area, balance weight and dominant biome
+    let mut area_map: HashMap<u32, f64> = HashMap::new();
+    let mut weight_map: HashMap<u32, f64> = HashMap::new();
+    let mut biome_counts: HashMap<u32, HashMap<u16, u32>> = HashMap::new();
 
     for i in 0..n {
         if labels[i] != no_label {
-            *area_map.entry(labels[i]).or_insert(0) += 1;
+            let scale = settings.projection.row_scale(i as u32 / width, height);
+            *area_map.entry(labels[i]).or_insert(0.0) += scale;
+            *weight_map.entry(labels[i]).or_insert(0.0) += match (settings.balance, suitability) {
+                (ClusterBalance::Suitability, Some(suit)) => suit[i] as f64 * scale,
+                _ => scale,
+            };
             *biome_counts
                 .entry(labels[i])
                 .or_default()
@@ -84,7 +191,7 @@ pub fn cluster_hierarchy(
         }
     }
 
-    let dominant_biome: HashMap<u32, u8> = biome_counts
+    let dominant_biome: HashMap<u32, u16> = biome_counts
         .iter()
         .map(|(&pid, counts)| {
             let &b = counts.iter().max_by_key(|(_, &c)| c).unwrap().0;
@@ -92,186 +199,153 @@ pub fn cluster_hierarchy(
         })
         .collect();
 
-    // Build adjacency map for quick lookup
-    let adj_map: HashMap<u32, &ProvinceAdjacency> =
-        adjacency.iter().map(|a| (a.province_id, a)).collect();
-
-    on_progress(20.0, "Clustering duchies");
-
-    // ── Duchy Clustering: Greedy Region Growing ──
-    let mut duchy_assignment: HashMap<u32, u32> = HashMap::new();
-    let mut duchies: Vec<DuchyRecord> = Vec::new();
-    let _target_duchy_size = (duchy_size_min + duchy_size_max) / 2;
-
-    // Sort provinces by area descending (start from largest)
     let mut province_ids: Vec<u32> = area_map.keys().cloned().collect();
-    province_ids.sort_by(|a, b| area_map.get(b).cmp(&area_map.get(a)));
+    province_ids.sort_unstable();
+    let province_index: HashMap<u32, usize> = province_ids
+        .iter()
+        .enumerate()
+        .map(|(index, &pid)| (pid, index))
+        .collect();
 
-    let mut duchy_id_counter = 0u32;
+    on_progress(20.0, "Clustering duchies");
 
-    for &start_pid in &province_ids {
-        if duchy_assignment.contains_key(&start_pid) {
+    // ── Duchy Clustering: provinces joined across land borders ──
+    let mut province_links: Vec<HashMap<usize, f64>> = vec![HashMap::new(); province_ids.len()];
+    for prov_adj in adjacency {
+        let Some(&a) = province_index.get(&prov_adj.province_id) else {
             continue;
+        };
+        for edge in prov_adj
+            .neighbors
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::Land)
+        {
+            let Some(&b) = province_index.get(&edge.neighbor_id) else {
+                continue;
+            };
+            let same_biome =
+                dominant_biome.get(&prov_adj.province_id) == dominant_biome.get(&edge.neighbor_id);
+            province_links[a].insert(b, border_affinity(edge, same_biome));
         }
-
-        let duchy_id = duchy_id_counter;
-        duchy_id_counter += 1;
-
-        let mut duchy_members = vec![start_pid];
-        duchy_assignment.insert(start_pid, duchy_id);
-
-        // Grow by adding best neighbor
-        while (duchy_members.len() as u32) < duchy_size_max {
-            let mut best_candidate = None;
-            let mut best_score = f64::MIN;
-
-            for &member in &duchy_members {
-                if let Some(adj) = adj_map.get(&member) {
-                    for edge in &adj.neighbors {
-                        if duchy_assignment.contains_key(&edge.neighbor_id) {
-                            continue;
-                        }
-                        // Score: shared border + biome similarity
-                        let border_score = edge.shared_border_length as f64;
-                        let biome_bonus = if dominant_biome.get(&edge.neighbor_id)
-                            == dominant_biome.get(&member)
-                        {
-                            50.0
-                        } else {
-                            0.0
-                        };
-                        let score = border_score + biome_bonus;
-                        if score > best_score {
-                            best_score = score;
-                            best_candidate = Some(edge.neighbor_id);
-                        }
-                    }
-                }
-            }
-
-            if let Some(candidate) = best_candidate {
-                duchy_assignment.insert(candidate, duchy_id);
-                duchy_members.push(candidate);
-            } else {
-                break; // No unassigned neighbors
-            }
+    }
+    let province_weights: Vec<f64> = province_ids.iter().map(|pid| weight_map[pid]).collect();
+    let province_locks: Vec<Option<u32>> = province_ids
+        .iter()
+        .map(|pid| locks.province_duchy.get(pid).copied())
+        .collect();
+    let duchy_groups = grow_groups(
+        &province_weights,
+        &province_locks,
+        &province_links,
+        settings.duchy_size_min,
+        settings.duchy_size_max,
+    );
+    let duchy_ids = assign_ids(&duchy_groups);
+    let mut duchy_of_province = vec![0usize; province_ids.len()];
+    for (d, group) in duchy_groups.iter().enumerate() {
+        for &member in &group.members {
+            duchy_of_province[member] = d;
         }
-
-        duchies.push(DuchyRecord {
-            id: duchy_id,
-            province_ids: duchy_members,
-            kingdom_id: 0, // Will be set later
-            name: format!("Duchy {}", duchy_id + 1),
-        });
     }
 
     on_progress(50.0, "Clustering kingdoms");
 
-    // ── Kingdom Clustering: Same approach on duchy graph ──
-    // Build duchy adjacency from province adjacency
-    let mut duchy_adj: HashMap<u32, HashSet<u32>> = HashMap::new();
+    // ── Kingdom Clustering: same approach on the duchy graph ──
+    // Kingdoms may span a strait, duchies may not.
+    let mut duchy_links: Vec<HashMap<usize, f64>> = vec![HashMap::new(); duchy_groups.len()];
     for prov_adj in adjacency {
-        let d1 = duchy_assignment
-            .get(&prov_adj.province_id)
-            .cloned()
-            .unwrap_or(no_label);
-        for edge in &prov_adj.neighbors {
-            let d2 = duchy_assignment
-                .get(&edge.neighbor_id)
-                .cloned()
-                .unwrap_or(no_label);
-            if d1 != no_label && d2 != no_label && d1 != d2 {
-                duchy_adj.entry(d1).or_default().insert(d2);
-                duchy_adj.entry(d2).or_default().insert(d1);
+        let Some(&a) = province_index.get(&prov_adj.province_id) else {
+            continue;
+        };
+        for edge in prov_adj
+            .neighbors
+            .iter()
+            .filter(|edge| matches!(edge.kind, EdgeKind::Land | EdgeKind::Strait))
+        {
+            let Some(&b) = province_index.get(&edge.neighbor_id) else {
+                continue;
+            };
+            let (d1, d2) = (duchy_of_province[a], duchy_of_province[b]);
+            if d1 == d2 {
+                continue;
             }
+            let affinity = if edge.kind == EdgeKind::Strait {
+                STRAIT_AFFINITY
+            } else {
+                let same_biome = dominant_biome.get(&prov_adj.province_id)
+                    == dominant_biome.get(&edge.neighbor_id);
+                border_affinity(edge, same_biome)
+            };
+            *duchy_links[d1].entry(d2).or_insert(0.0) += affinity;
         }
     }
+    let duchy_weights: Vec<f64> = duchy_groups.iter().map(|g| g.weight).collect();
+    let duchy_locks: Vec<Option<u32>> = duchy_groups
+        .iter()
+        .zip(&duchy_ids)
+        .map(|(group, id)| {
+            group
+                .locked
+                .and_then(|_| locks.duchy_kingdom.get(id).copied())
+        })
+        .collect();
+    let kingdom_groups = grow_groups(
+        &duchy_weights,
+        &duchy_locks,
+        &duchy_links,
+        settings.kingdom_size_min,
+        settings.kingdom_size_max,
+    );
+    let kingdom_ids = assign_ids(&kingdom_groups);
 
+    let mut duchy_assignment: HashMap<u32, u32> = HashMap::new();
     let mut kingdom_assignment: HashMap<u32, u32> = HashMap::new();
+    let mut duchies: Vec<DuchyRecord> = Vec::new();
     let mut kingdoms: Vec<KingdomRecord> = Vec::new();
-    let mut kingdom_id_counter = 0u32;
-
-    // Sort duchies by size descending
-    let mut duchy_ids: Vec<u32> = duchies.iter().map(|d| d.id).collect();
-    duchy_ids.sort_by(|a, b| {
-        let sa = duchies
-            .iter()
-            .find(|d| d.id == *a)
-            .map(|d| d.province_ids.len())
-            .unwrap_or(0);
-        let sb = duchies
-            .iter()
-            .find(|d| d.id == *b)
-            .map(|d| d.province_ids.len())
-            .unwrap_or(0);
-        sb.cmp(&sa)
-    });
-
-    for &start_did in &duchy_ids {
-        if kingdom_assignment.contains_key(&start_did) {
-            continue;
-        }
-
-        let kingdom_id = kingdom_id_counter;
-        kingdom_id_counter += 1;
-
-        let mut kingdom_members = vec![start_did];
-        kingdom_assignment.insert(start_did, kingdom_id);
-
-        while (kingdom_members.len() as u32) < kingdom_size_max {
-            let mut best_candidate = None;
-            let mut best_score = 0usize;
-
-            for &member in &kingdom_members {
-                if let Some(neighbors) = duchy_adj.get(&member) {
-                    for &neighbor_did in neighbors {
-                        if kingdom_assignment.contains_key(&neighbor_did) {
-                            continue;
-                        }
-                        let score = 1; // Simple: just pick any unassigned neighbor
-                        if score > best_score || best_candidate.is_none() {
-                            best_score = score;
-                            best_candidate = Some(neighbor_did);
-                        }
-                    }
-                }
-            }
-
-            if let Some(candidate) = best_candidate {
-                kingdom_assignment.insert(candidate, kingdom_id);
-                kingdom_members.push(candidate);
-            } else {
-                break;
-            }
-        }
-
-        // Update duchy records with kingdom assignment
-        for &did in &kingdom_members {
-            if let Some(duchy) = duchies.iter_mut().find(|d| d.id == did) {
-                duchy.kingdom_id = kingdom_id;
+    for (kingdom_group, &kingdom_id) in kingdom_groups.iter().zip(&kingdom_ids) {
+        let mut member_duchies = Vec::new();
+        for &d in &kingdom_group.members {
+            let duchy_id = duchy_ids[d];
+            let mut members: Vec<u32> = duchy_groups[d]
+                .members
+                .iter()
+                .map(|&p| province_ids[p])
+                .collect();
+            members.sort_unstable();
+            for &pid in &members {
+                duchy_assignment.insert(pid, duchy_id);
             }
+            kingdom_assignment.insert(duchy_id, kingdom_id);
+            member_duchies.push(duchy_id);
+            duchies.push(DuchyRecord {
+                id: duchy_id,
+                province_ids: members,
+                kingdom_id,
+                name: format!("Duchy {}", duchy_id + 1),
+            });
         }
-
+        member_duchies.sort_unstable();
         kingdoms.push(KingdomRecord {
             id: kingdom_id,
-            duchy_ids: kingdom_members,
+            duchy_ids: member_duchies,
             name: format!("Kingdom {}", kingdom_id + 1),
         });
     }
+    duchies.sort_by_key(|d| d.id);
+    kingdoms.sort_by_key(|k| k.id);
 
     on_progress(75.0, "Building province records");
-
     // Build province records
     let seed_map: HashMap<u32, (u32, u32)> = seeds.iter().map(|&(id, x, y)| (id, (x, y))).collect();
     let provinces: Vec<ProvinceRecord> = province_ids
         .iter()
-        .filter_map(|&pid| {
+        .map(|&pid| {
             let (sx, sy) = seed_map.get(&pid).cloned().unwrap_or((0, 0));
-            let area = area_map.get(&pid).cloned().unwrap_or(0);
+            let area = area_map.get(&pid).cloned().unwrap_or(0.0).round() as u32;
             let duchy = duchy_assignment.get(&pid).cloned().unwrap_or(0);
             let kingdom = kingdom_assignment.get(&duchy).cloned().unwrap_or(0);
             let biome_p = dominant_biome.get(&pid).cloned().unwrap_or(0);
-            Some(ProvinceRecord {
+            ProvinceRecord {
                 id: pid,
                 seed_x: sx,
                 seed_y: sy,
@@ -286,7 +360,7 @@ pub fn cluster_hierarchy(
                 wealth: None,
                 development: None,
                 population: None,
-            })
+            }
         })
         .collect();
 
@@ -310,5 +384,572 @@ pub fn cluster_hierarchy(
     }
 
     on_progress(100.0, "Hierarchy clustering complete");
-    (provinces, duchies, kingdoms, duchy_labels, kingdom_labels)
+    ClusterResult {
+        provinces,
+        duchies,
+        kingdoms,
+        duchy_labels,
+        kingdom_labels,
+    }
+}
+
+/// How strongly two provinces want to share a title. Rivers, ridges and biome changes make
+/// weak links, so title borders settle on them.
+fn border_affinity(edge: &EdgeInfo, same_biome: bool) -> f64 {
+    let ridge = ((edge.mean_border_height - height::SEA_LEVEL as f32)
+        / (u16::MAX - height::SEA_LEVEL) as f32)
+        .clamp(0.0, 1.0) as f64;
+    let mut affinity = edge.shared_border_length as f64 * (1.0 - RIDGE_BORDER_FACTOR * ridge);
+    if edge.crosses_river {
+        affinity *= RIVER_BORDER_FACTOR;
+    }
+    if !same_biome {
+        affinity *= BIOME_BORDER_FACTOR;
+    }
+    affinity
+}
+
+/// A title being grown: a duchy over provinces or a kingdom over duchies.
+struct Group {
+    locked: Option<u32>,
+    members: Vec<usize>,
+    weight: f64,
+}
+
+/// Locked groups keep their id; the rest are numbered after the highest locked id.
+fn assign_ids(groups: &[Group]) -> Vec<u32> {
+    let mut next = groups
+        .iter()
+        .filter_map(|g| g.locked)
+        .max()
+        .map_or(0, |id| id + 1);
+    groups
+        .iter()
+        .map(|g| {
+            g.locked.unwrap_or_else(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect()
+}
+
+/// Partitions units into contiguous groups of `size_min..=size_max` units with similar total
+/// weight. `links[u]` maps each neighbouring unit to its affinity; groups grow along strong
+/// links so their borders fall on weak ones. Units with a locked id form fixed groups that are
+/// neither grown nor merged. A connected piece smaller than `size_min` stays one group.
+fn grow_groups(
+    weights: &[f64],
+    locked: &[Option<u32>],
+    links: &[HashMap<usize, f64>],
+    size_min: u32,
+    size_max: u32,
+) -> Vec<Group> {
+    let n = weights.len();
+    let size_max = size_max.max(1) as usize;
+    let size_min = (size_min.max(1) as usize).min(size_max);
+    let mut group_of = vec![NO_GROUP; n];
+    let mut groups: Vec<Group> = Vec::new();
+
+    let mut locked_groups: BTreeMap<u32, usize> = BTreeMap::new();
+    for unit in 0..n {
+        let Some(id) = locked[unit] else {
+            continue;
+        };
+        let g = *locked_groups.entry(id).or_insert_with(|| {
+            groups.push(Group {
+                locked: Some(id),
+                members: Vec::new(),
+                weight: 0.0,
+            });
+            groups.len() - 1
+        });
+        group_of[unit] = g;
+        groups[g].members.push(unit);
+        groups[g].weight += weights[unit];
+    }
+
+    for start in 0..n {
+        if group_of[start] != NO_GROUP {
+            continue;
+        }
+        let component = hop_distances(start, links, &group_of);
+        let mut remaining: Vec<usize> = component.keys().copied().collect();
+        remaining.sort_unstable();
+        let mean_weight =
+            remaining.iter().map(|&unit| weights[unit]).sum::<f64>() / remaining.len() as f64;
+        let first_group = groups.len();
+        let count = group_count(remaining.len(), size_min, size_max);
+        for seed in farthest_seeds(&remaining, count, links, &group_of) {
+            group_of[seed] = groups.len();
+            groups.push(Group {
+                locked: None,
+                members: vec![seed],
+                weight: weights[seed],
+            });
+        }
+        remaining.retain(|&unit| group_of[unit] == NO_GROUP);
+
+        // Best unassigned neighbour per group, refreshed when the group or its frontier changes.
+        let mut frontier: Vec<Option<(usize, f64)>> = vec![None; groups.len()];
+        let mut dirty: Vec<usize> = (first_group..groups.len()).collect();
+        while !remaining.is_empty() {
+            for g in dirty.drain(..) {
+                frontier[g] = if groups[g].members.len() < size_max {
+                    best_frontier(&groups[g], links, &group_of)
+                } else {
+                    None
+                };
+            }
+            // Strong links win and light groups break near-ties, so groups stay balanced
+            // without reaching across rivers and ridges while they have other options.
+            let step = (first_group..groups.len())
+                .filter_map(|g| {
+                    frontier[g].map(|(unit, affinity)| {
+                        let load = (groups[g].weight + mean_weight).max(f64::MIN_POSITIVE);
+                        (g, unit, affinity / load)
+                    })
+                })
+                .max_by(|a, b| a.2.total_cmp(&b.2).then(b.0.cmp(&a.0)));
+            let (g, unit) = match step {
+                Some((g, unit, _)) => (g, unit),
+                None => {
+                    // Every group is full or walled in, so open another.
+                    groups.push(Group {
+                        locked: None,
+                        members: Vec::new(),
+                        weight: 0.0,
+                    });
+                    frontier.push(None);
+                    (groups.len() - 1, remaining[0])
+                }
+            };
+            group_of[unit] = g;
+            groups[g].members.push(unit);
+            groups[g].weight += weights[unit];
+            remaining.retain(|&u| u != unit);
+            dirty.push(g);
+            for &next in links[unit].keys() {
+                let h = group_of[next];
+                if h != NO_GROUP && h >= first_group && !dirty.contains(&h) {
+                    dirty.push(h);
+                }
+            }
+        }
+    }
+
+    absorb_undersized(
+        &mut groups,
+        &mut group_of,
+        weights,
+        links,
+        size_min,
+        size_max,
+    );
+    groups.retain(|g| !g.members.is_empty());
+    groups
+}
+
+/// Number of groups that keeps a piece of `len` units closest to the middle of the size range.
+fn group_count(len: usize, size_min: usize, size_max: usize) -> usize {
+    let target = (size_min + size_max) as f64 / 2.0;
+    let fewest = len.div_ceil(size_max).max(1);
+    let most = (len / size_min).max(fewest);
+    ((len as f64 / target).round() as usize).clamp(fewest, most)
+}
+
+/// Hop counts from `start` to every unassigned unit connected to it.
+fn hop_distances(
+    start: usize,
+    links: &[HashMap<usize, f64>],
+    group_of: &[usize],
+) -> HashMap<usize, u32> {
+    let mut dist = HashMap::from([(start, 0)]);
+    let mut queue = std::collections::VecDeque::from([start]);
+    while let Some(unit) = queue.pop_front() {
+        let d = dist[&unit];
+        for &next in links[unit].keys() {
+            if group_of[next] == NO_GROUP && !dist.contains_key(&next) {
+                dist.insert(next, d + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    dist
+}
+
+/// Farthest-point seeds by hop count, starting on the rim of the piece.
+fn farthest_seeds(
+    piece: &[usize],
+    count: usize,
+    links: &[HashMap<usize, f64>],
+    group_of: &[usize],
+) -> Vec<usize> {
+    let farthest = |dist: &HashMap<usize, u32>, seeds: &[usize]| {
+        piece
+            .iter()
+            .copied()
+            .filter(|unit| !seeds.contains(unit))
+            .max_by_key(|&unit| (dist[&unit], std::cmp::Reverse(unit)))
+    };
+    let mut seeds = Vec::new();
+    let mut nearest: HashMap<usize, u32> = piece.iter().map(|&unit| (unit, u32::MAX)).collect();
+    let mut next = farthest(&hop_distances(piece[0], links, group_of), &seeds);
+    while let Some(seed) = next {
+        if seeds.len() == count {
+            break;
+        }
+        seeds.push(seed);
+        for (unit, d) in hop_distances(seed, links, group_of) {
+            let entry = nearest.get_mut(&unit).expect("unit outside piece");
+            *entry = (*entry).min(d);
+        }
+        next = farthest(&nearest, &seeds);
+    }
+    seeds
+}
+
+/// Unassigned neighbour with the strongest total link into the group, and that link.
+fn best_frontier(
+    group: &Group,
+    links: &[HashMap<usize, f64>],
+    group_of: &[usize],
+) -> Option<(usize, f64)> {
+    let mut affinity: BTreeMap<usize, f64> = BTreeMap::new();
+    for &member in &group.members {
+        for (&next, &a) in &links[member] {
+            if group_of[next] == NO_GROUP {
+                *affinity.entry(next).or_insert(0.0) += a;
+            }
+        }
+    }
+    affinity
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+}
+
+fn strongest(affinity: BTreeMap<usize, f64>) -> Option<usize> {
+    affinity
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map(|(key, _)| key)
+}
+
+/// Brings groups below `size_min` up to size by merging them into a neighbour, or by taking
+/// border units from neighbours that can spare them. Groups stay within `size_max` and
+/// contiguous; locked groups are left alone.
+fn absorb_undersized(
+    groups: &mut [Group],
+    group_of: &mut [usize],
+    weights: &[f64],
+    links: &[HashMap<usize, f64>],
+    size_min: usize,
+    size_max: usize,
+) {
+    let mut stuck: HashSet<usize> = HashSet::new();
+    while let Some(g) = (0..groups.len()).find(|&g| {
+        groups[g].locked.is_none()
+            && !groups[g].members.is_empty()
+            && groups[g].members.len() < size_min
+            && !stuck.contains(&g)
+    }) {
+        let mut neighbours: BTreeMap<usize, f64> = BTreeMap::new();
+        let mut candidates: BTreeMap<usize, f64> = BTreeMap::new();
+        for &member in &groups[g].members {
+            for (&next, &a) in &links[member] {
+                let h = group_of[next];
+                if h != g && groups[h].locked.is_none() {
+                    *neighbours.entry(h).or_insert(0.0) += a;
+                    *candidates.entry(next).or_insert(0.0) += a;
+                }
+            }
+        }
+
+        let size = groups[g].members.len();
+        neighbours.retain(|&h, _| groups[h].members.len() + size <= size_max);
+        if let Some(h) = strongest(neighbours) {
+            let members = std::mem::take(&mut groups[g].members);
+            for &member in &members {
+                group_of[member] = h;
+            }
+            groups[h].members.extend(members);
+            groups[h].weight += groups[g].weight;
+            groups[g].weight = 0.0;
+            continue;
+        }
+
+        candidates.retain(|&unit, _| {
+            let donor = &groups[group_of[unit]];
+            donor.members.len() > size_min && stays_connected(&donor.members, unit, links)
+        });
+        match strongest(candidates) {
+            Some(unit) => {
+                let donor = group_of[unit];
+                groups[donor].members.retain(|&m| m != unit);
+                groups[donor].weight -= weights[unit];
+                group_of[unit] = g;
+                groups[g].members.push(unit);
+                groups[g].weight += weights[unit];
+            }
+            None => {
+                stuck.insert(g);
+            }
+        }
+    }
+}
+
+/// Whether `members` stays connected once `removed` leaves.
+fn stays_connected(members: &[usize], removed: usize, links: &[HashMap<usize, f64>]) -> bool {
+    let rest: HashSet<usize> = members.iter().copied().filter(|&m| m != removed).collect();
+    let Some(&start) = rest.iter().min() else {
+        return true;
+    };
+    let mut seen = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some(unit) = stack.pop() {
+        for next in links[unit].keys() {
+            if rest.contains(next) && seen.insert(*next) {
+                stack.push(*next);
+            }
+        }
+    }
+    seen.len() == rest.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::build_adjacency;
+
+    /// Provinces per side of the grid, and pixels per side of each province.
+    const COLUMNS: u32 = 8;
+    const ROWS: u32 = 6;
+    const BLOCK: u32 = 4;
+    const WIDTH: u32 = COLUMNS * BLOCK;
+    const HEIGHT: u32 = ROWS * BLOCK;
+
+    struct Grid {
+        labels: Vec<u32>,
+        biome: Vec<u16>,
+        seeds: Vec<(u32, u32, u32)>,
+        adjacency: Vec<ProvinceAdjacency>,
+    }
+
+    /// All-land map of square provinces, numbered row by row.
+    fn province_grid() -> Grid {
+        let labels: Vec<u32> = (0..WIDTH * HEIGHT)
+            .map(|i| (i / WIDTH / BLOCK) * COLUMNS + (i % WIDTH) / BLOCK)
+            .collect();
+        let n = labels.len();
+        let seeds = (0..COLUMNS * ROWS)
+            .map(|id| {
+                let (column, row) = (id % COLUMNS, id / COLUMNS);
+                (id, column * BLOCK + BLOCK / 2, row * BLOCK + BLOCK / 2)
+            })
+            .collect();
+        let adjacency = build_adjacency(
+            &labels,
+            &vec![height::SEA_LEVEL + 1000; n],
+            &vec![0; n],
+            WIDTH,
+            HEIGHT,
+            MapProjection::Flat,
+            &mut |_, _| {},
+        );
+        Grid {
+            labels,
+            biome: vec![0; n],
+            seeds,
+            adjacency,
+        }
+    }
+
+    fn settings(duchy: (u32, u32), kingdom: (u32, u32)) -> ClusterSettings {
+        ClusterSettings {
+            duchy_size_min: duchy.0,
+            duchy_size_max: duchy.1,
+            kingdom_size_min: kingdom.0,
+            kingdom_size_max: kingdom.1,
+            balance: ClusterBalance::Area,
+            projection: MapProjection::Flat,
+        }
+    }
+
+    fn cluster(grid: &Grid, settings: &ClusterSettings, locks: &HierarchyLocks) -> ClusterResult {
+        let input = ClusterInput {
+            width: WIDTH,
+            height: HEIGHT,
+            labels: &grid.labels,
+            biome_indices: &grid.biome,
+            suitability: None,
+            seeds: &grid.seeds,
+            adjacency: &grid.adjacency,
+        };
+        cluster_hierarchy(&input, settings, locks, &mut |_, _| {})
+    }
+
+    /// Whether `members` form one piece over edges of the given kinds, after mapping each
+    /// province through `unit`.
+    fn is_contiguous(
+        grid: &Grid,
+        members: &[u32],
+        unit: impl Fn(u32) -> u32,
+        kinds: &[EdgeKind],
+    ) -> bool {
+        let mut reached = HashSet::from([members[0]]);
+        let mut stack = vec![members[0]];
+        while let Some(current) = stack.pop() {
+            for province in grid
+                .adjacency
+                .iter()
+                .filter(|p| unit(p.province_id) == current)
+            {
+                for edge in province
+                    .neighbors
+                    .iter()
+                    .filter(|e| kinds.contains(&e.kind))
+                {
+                    let next = unit(edge.neighbor_id);
+                    if members.contains(&next) && reached.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        reached.len() == members.len()
+    }
+
+    #[test]
+    fn titles_respect_size_limits_and_stay_contiguous() {
+        let grid = province_grid();
+        let result = cluster(&grid, &settings((3, 5), (2, 4)), &HierarchyLocks::default());
+
+        assert_eq!(result.provinces.len(), (COLUMNS * ROWS) as usize);
+        let province_duchy: HashMap<u32, u32> = result
+            .provinces
+            .iter()
+            .map(|p| (p.id, p.duchy_id))
+            .collect();
+        let mut clustered: Vec<u32> = Vec::new();
+        for duchy in &result.duchies {
+            assert!(
+                (3..=5).contains(&duchy.province_ids.len()),
+                "duchy {} has {} provinces",
+                duchy.id,
+                duchy.province_ids.len()
+            );
+            assert!(is_contiguous(
+                &grid,
+                &duchy.province_ids,
+                |p| p,
+                &[EdgeKind::Land]
+            ));
+            clustered.extend(&duchy.province_ids);
+        }
+        clustered.sort_unstable();
+        assert_eq!(clustered, (0..COLUMNS * ROWS).collect::<Vec<_>>());
+
+        for kingdom in &result.kingdoms {
+            assert!(
+                (2..=4).contains(&kingdom.duchy_ids.len()),
+                "kingdom {} has {} duchies",
+                kingdom.id,
+                kingdom.duchy_ids.len()
+            );
+            assert!(is_contiguous(
+                &grid,
+                &kingdom.duchy_ids,
+                |p| province_duchy[&p],
+                &[EdgeKind::Land, EdgeKind::Strait],
+            ));
+        }
+    }
+
+    #[test]
+    fn locked_titles_survive_reclustering() {
+        let grid = province_grid();
+        let first = cluster(&grid, &settings((3, 5), (2, 4)), &HierarchyLocks::default());
+
+        let mut locks = HierarchyLocks::default();
+        let kingdom = &first.kingdoms[0];
+        locks.lock_kingdom(kingdom, &first.duchies);
+        let duchy = first
+            .duchies
+            .iter()
+            .find(|d| d.kingdom_id != kingdom.id)
+            .expect("duchy outside the locked kingdom");
+        locks.lock_duchy(duchy);
+
+        let second = cluster(&grid, &settings((2, 3), (3, 6)), &locks);
+
+        let relocked = second
+            .kingdoms
+            .iter()
+            .find(|k| k.id == kingdom.id)
+            .expect("locked kingdom kept its id");
+        assert_eq!(relocked.duchy_ids, kingdom.duchy_ids);
+        for original in first.duchies.iter().filter(|d| d.kingdom_id == kingdom.id) {
+            let kept = second
+                .duchies
+                .iter()
+                .find(|d| d.id == original.id)
+                .expect("duchy of locked kingdom kept its id");
+            assert_eq!(kept.province_ids, original.province_ids);
+            assert_eq!(kept.kingdom_id, kingdom.id);
+        }
+        let kept = second
+            .duchies
+            .iter()
+            .find(|d| d.id == duchy.id)
+            .expect("locked duchy kept its id");
+        assert_eq!(kept.province_ids, duchy.province_ids);
+
+        let locked_ids: HashSet<u32> = locks.province_duchy.values().copied().collect();
+        for other in second
+            .duchies
+            .iter()
+            .filter(|d| !locked_ids.contains(&d.id))
+        {
+            assert!(other.province_ids.len() <= 3);
+            assert!(other
+                .province_ids
+                .iter()
+                .all(|p| !locks.province_duchy.contains_key(p)));
+        }
+    }
+
+    #[test]
+    fn province_areas_are_weighted_by_latitude() {
+        // Two provinces of 40 pixels each: one on the top rows, one straddling the equator.
+        let (width, height) = (10, 20);
+        let labels: Vec<u32> = (0..width * height)
+            .map(|i| match i / width {
+                0..=3 => 0,
+                8..=11 => 1,
+                _ => u32::MAX,
+            })
+            .collect();
+        let n = labels.len();
+        let input = ClusterInput {
+            width,
+            height,
+            labels: &labels,
+            biome_indices: &vec![0; n],
+            suitability: None,
+            seeds: &[(0, 5, 2), (1, 5, 10)],
+            adjacency: &[],
+        };
+        let mut settings = settings((1, 2), (1, 2));
+        settings.projection = MapProjection::Equirectangular;
+        let result = cluster_hierarchy(
+            &input,
+            &settings,
+            &HierarchyLocks::default(),
+            &mut |_, _| {},
+        );
+
+        let area = |id: u32| result.provinces.iter().find(|p| p.id == id).unwrap().area;
+        assert_eq!(area(1), 39);
+        assert_eq!(area(0), 12);
+    }
 }
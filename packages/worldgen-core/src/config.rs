@@ -1,12 +1,97 @@
 use serde::{Deserialize, Serialize};
 
+/// How the base texture maps onto the planet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MapProjection {
+    /// A bounded rectangle with uniform pixel area.
+    Flat,
+    /// Pixel x is longitude and y is latitude: x wraps at the antimeridian and pixel area
+    /// shrinks with the cosine of latitude. The default, since planet textures are generated
+    /// this way and worlds from before the setting always wrapped in x.
+    #[default]
+    Equirectangular,
+}
+
+impl MapProjection {
+    pub fn wraps_x(self) -> bool {
+        self == MapProjection::Equirectangular
+    }
+
+    /// Ground scale of a pixel row relative to the equator, which is also its horizontal
+    /// extent and area in equator pixels.
+    pub fn row_scale(self, y: u32, height: u32) -> f64 {
+        match self {
+            MapProjection::Flat => 1.0,
+            MapProjection::Equirectangular => {
+                let latitude = ((y as f64 + 0.5) / height as f64 - 0.5) * std::f64::consts::PI;
+                latitude.cos()
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for MapProjection {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "flat" => Ok(MapProjection::Flat),
+            "equirectangular" => Ok(MapProjection::Equirectangular),
+            other => Err(format!("Unknown projection: {}", other)),
+        }
+    }
+}
+
+/// What duchies and kingdoms are balanced on while clustering.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterBalance {
+    /// Province land area.
+    #[default]
+    Area,
+    /// Settlement suitability summed over each province.
+    Suitability,
+}
+
+fn default_seed() -> u64 {
+    42
+}
+
+fn default_river_threshold() -> u32 {
+    200
+}
+
+fn default_sea_zones() -> u32 {
+    64
+}
+
+fn default_strait_max_width() -> u32 {
+    8
+}
+
 /// All tuneable knobs for the worldgen pipeline.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WorldgenConfig {
     /// Number of county seeds to place
     pub counties: u32,
-    /// Minimum county area in pixels
+    /// Texture projection; equirectangular wraps in x and corrects areas for latitude
+    #[serde(default)]
+    pub projection: MapProjection,
+    /// RNG seed shared by height reconstruction and seed placement
+    #[serde(default = "default_seed")]
+    pub seed: u64,
+    /// Upstream pixels needed before a cell is drawn as a river
+    #[serde(default = "default_river_threshold")]
+    pub river_threshold: u32,
+    /// Target number of sea zones
+    #[serde(default = "default_sea_zones")]
+    pub sea_zones: u32,
+    /// Widest water gap in pixels that still counts as a strait
+    #[serde(default = "default_strait_max_width")]
+    pub strait_max_width: u32,
+    /// Minimum county area in pixels (equator pixels under equirectangular)
     pub min_county_area: u32,
     /// Minimum Poisson disk radius (dense areas)
     pub seed_radius_min: f64,
@@ -26,6 +111,9 @@ pub struct WorldgenConfig {
     pub kingdom_size_min: u32,
     /// Maximum kingdom size
     pub kingdom_size_max: u32,
+    /// What titles of the same rank are balanced on
+    #[serde(default)]
+    pub cluster_balance: ClusterBalance,
     /// Number of border smoothing iterations
     pub smooth_iterations: u32,
 
@@ -46,6 +134,11 @@ impl Default for WorldgenConfig {
     fn default() -> Self {
         Self {
             counties: 500,
+            projection: MapProjection::Equirectangular,
+            seed: default_seed(),
+            river_threshold: default_river_threshold(),
+            sea_zones: default_sea_zones(),
+            strait_max_width: default_strait_max_width(),
             min_county_area: 100,
             seed_radius_min: 8.0,
             seed_radius_max: 40.0,
@@ -56,6 +149,7 @@ impl Default for WorldgenConfig {
             duchy_size_max: 8,
             kingdom_size_min: 6,
             kingdom_size_max: 12,
+            cluster_balance: ClusterBalance::Area,
             smooth_iterations: 2,
 
             water_hue: 210.0,
@@ -66,3 +160,35 @@ impl Default for WorldgenConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worlds_default_to_wrapping_equirectangular_maps() {
+        assert_eq!(MapProjection::default(), MapProjection::Equirectangular);
+        assert_eq!(
+            WorldgenConfig::default().projection,
+            MapProjection::Equirectangular
+        );
+        assert!(MapProjection::default().wraps_x());
+        assert!(!MapProjection::Flat.wraps_x());
+    }
+
+    #[test]
+    fn equirectangular_rows_shrink_with_latitude() {
+        let height = 100;
+        let scale = |y| MapProjection::Equirectangular.row_scale(y, height);
+
+        assert!((scale(49) - scale(50)).abs() < 1e-9);
+        assert!(scale(49) > 0.999);
+        assert!((scale(0) - scale(99)).abs() < 1e-9);
+        assert!(scale(0) < 0.02);
+        assert!((scale(16) - (0.335 * std::f64::consts::PI).cos()).abs() < 1e-9);
+        // Summed over the rows, the area matches the sphere's: 2 / pi of the flat rectangle.
+        let total: f64 = (0..height).map(scale).sum();
+        assert!((total / height as f64 - 2.0 / std::f64::consts::PI).abs() < 1e-3);
+        assert_eq!(MapProjection::Flat.row_scale(0, height), 1.0);
+    }
+}
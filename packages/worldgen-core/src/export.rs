@@ -1,6 +1,12 @@
+use crate::biome::{BiomePaletteEntry, BiomeReport};
 use crate::cluster::{DuchyRecord, KingdomRecord, ProvinceRecord};
 use crate::graph::ProvinceAdjacency;
+use crate::hydrology::LakeRecord;
+use crate::naming::NameSet;
+use crate::river_network::RiverNetwork;
+use crate::roads::RoadNetwork;
 use crate::sampling::Seed;
+use crate::sea::{SeaZoneRecord, StraitRecord};
 use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
 use std::path::Path;
 
@@ -36,6 +42,22 @@ pub fn write_id_texture(
         .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
 }
 
+/// Write biome indices as an RGB24 texture: r = low byte, g = high byte, b = 0. Browsers decode
+/// 16-bit PNGs to 8 bits per channel, so indices are split across channels like id textures.
+pub fn write_biome_texture(
+    indices: &[u16],
+    width: u32,
+    height: u32,
+    path: &Path,
+) -> Result<(), String> {
+    let mut img = ImageBuffer::new(width, height);
+    for (pixel, &index) in img.pixels_mut().zip(indices) {
+        *pixel = pack_id_rgb(index as u32);
+    }
+    img.save(path)
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
 /// Write a grayscale 8-bit texture.
 pub fn write_mask_texture(data: &[u8], width: u32, height: u32, path: &Path) -> Result<(), String> {
     let img: GrayImage = ImageBuffer::from_raw(width, height, data.to_vec())
@@ -75,6 +97,20 @@ pub fn write_f32_binary(data: &[f32], path: &Path) -> Result<(), String> {
     std::fs::write(path, bytes).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
 }
 
+/// Write the biome palette sidecar mapping texture indices to archetype ids.
+pub fn write_biome_palette_json(palette: &[BiomePaletteEntry], path: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(palette)
+        .map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Write the biome stage's classification report as JSON.
+pub fn write_biome_report_json(report: &BiomeReport, path: &Path) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(report).map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
 /// Write seeds as JSON (array of {id, x, y}).
 pub fn write_seeds_json(seeds: &[Seed], path: &Path) -> Result<(), String> {
     let json =
@@ -89,6 +125,20 @@ pub fn write_adjacency_json(adjacency: &[ProvinceAdjacency], path: &Path) -> Res
     std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
 }
 
+/// Write sea zone records.
+pub fn write_sea_zones_json(zones: &[SeaZoneRecord], path: &Path) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(zones).map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Write strait crossings between land provinces.
+pub fn write_straits_json(straits: &[StraitRecord], path: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(straits)
+        .map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
 /// Write province records.
 pub fn write_provinces_json(provinces: &[ProvinceRecord], path: &Path) -> Result<(), String> {
     let json = serde_json::to_string_pretty(provinces)
@@ -110,8 +160,116 @@ pub fn write_kingdoms_json(kingdoms: &[KingdomRecord], path: &Path) -> Result<()
     std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
 }
 
+/// Write generated names.
+pub fn write_names_json(names: &NameSet, path: &Path) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(names).map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Write lake records (area, spill point, outflow, endorheic flags).
+pub fn write_lakes_json(lakes: &[LakeRecord], path: &Path) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(lakes).map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Write the vector river graph.
+pub fn write_rivers_json(network: &RiverNetwork, path: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(network)
+        .map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Write river segments as a GeoJSON FeatureCollection of LineStrings in lon/lat degrees,
+/// assuming an equirectangular texture.
+pub fn write_rivers_geojson(network: &RiverNetwork, path: &Path) -> Result<(), String> {
+    let to_lon_lat = |p: &[f32; 2]| {
+        [
+            p[0] / network.width as f32 * 360.0 - 180.0,
+            90.0 - p[1] / network.height as f32 * 180.0,
+        ]
+    };
+    let features: Vec<serde_json::Value> = network
+        .segments
+        .iter()
+        .map(|segment| {
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "LineString",
+                    "coordinates": segment.points.iter().map(to_lon_lat).collect::<Vec<_>>(),
+                },
+                "properties": {
+                    "id": segment.id,
+                    "strahlerOrder": segment.strahler_order,
+                    "discharge": segment.discharge,
+                    "fromNode": segment.from_node,
+                    "toNode": segment.to_node,
+                },
+            })
+        })
+        .collect();
+    let collection = serde_json::json!({ "type": "FeatureCollection", "features": features });
+    let json = serde_json::to_string_pretty(&collection)
+        .map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Write the vector road graph.
+pub fn write_roads_json(network: &RoadNetwork, path: &Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(network)
+        .map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Write road segments as LineStrings and road nodes as Points, in lon/lat degrees like
+/// `write_rivers_geojson`.
+pub fn write_roads_geojson(network: &RoadNetwork, path: &Path) -> Result<(), String> {
+    let to_lon_lat = |p: &[f32; 2]| {
+        [
+            p[0] / network.width as f32 * 360.0 - 180.0,
+            90.0 - p[1] / network.height as f32 * 180.0,
+        ]
+    };
+    let segments = network.segments.iter().map(|segment| {
+        serde_json::json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "LineString",
+                "coordinates": segment.points.iter().map(to_lon_lat).collect::<Vec<_>>(),
+            },
+            "properties": {
+                "id": segment.id,
+                "class": segment.class,
+                "fromNode": segment.from_node,
+                "toNode": segment.to_node,
+            },
+        })
+    });
+    let nodes = network.nodes.iter().map(|node| {
+        serde_json::json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "Point",
+                "coordinates": to_lon_lat(&[node.x as f32 + 0.5, node.y as f32 + 0.5]),
+            },
+            "properties": {
+                "id": node.id,
+                "kind": node.kind,
+                "provinceId": node.province_id,
+            },
+        })
+    });
+    let features: Vec<serde_json::Value> = segments.chain(nodes).collect();
+    let collection = serde_json::json!({ "type": "FeatureCollection", "features": features });
+    let json = serde_json::to_string_pretty(&collection)
+        .map_err(|e| format!("JSON serialize error: {}", e))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
 /// Pipeline status tracking — which stages are completed.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PipelineStatus {
     pub stages: std::collections::HashMap<String, StageRecord>,
@@ -122,6 +280,12 @@ pub struct PipelineStatus {
 pub struct StageRecord {
     pub completed: bool,
     pub completed_at: Option<u64>,
+    /// Hash of the stage's inputs and config when it last ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<String>,
+    /// Hash of the artifacts the stage wrote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_hash: Option<String>,
 }
 
 impl PipelineStatus {
@@ -132,6 +296,16 @@ impl PipelineStatus {
     }
 
     pub fn mark_completed(&mut self, stage_id: &str) {
+        self.record_run(stage_id, None, None);
+    }
+
+    /// Mark a stage completed along with the hashes it ran against.
+    pub fn record_run(
+        &mut self,
+        stage_id: &str,
+        input_hash: Option<String>,
+        output_hash: Option<String>,
+    ) {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
@@ -141,15 +315,25 @@ impl PipelineStatus {
             StageRecord {
                 completed: true,
                 completed_at: Some(now),
+                input_hash,
+                output_hash,
             },
         );
     }
 
+    /// Mark a stage stale so it re-runs next time, keeping its last completion time.
+    pub fn invalidate(&mut self, stage_id: &str) {
+        if let Some(record) = self.stages.get_mut(stage_id) {
+            record.completed = false;
+            record.input_hash = None;
+        }
+    }
+
     pub fn load(path: &Path) -> Self {
         std::fs::read_to_string(path)
             .ok()
             .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_else(Self::new)
+            .unwrap_or_default()
     }
 
     pub fn save(&self, path: &Path) -> Result<(), String> {
@@ -1,5 +1,21 @@
-use crate::raster::neighbors8;
+use crate::config::MapProjection;
+use crate::raster::neighbors8_in;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// Land provinces sharing a border.
+    #[default]
+    Land,
+    /// Land province and sea zone sharing a coastline.
+    Coast,
+    /// Two sea zones.
+    Sea,
+    /// Land provinces across a narrow water gap.
+    Strait,
+}
 
 /// Information about an edge between two provinces.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,6 +25,11 @@ pub struct EdgeInfo {
     pub shared_border_length: u32,
     pub crosses_river: bool,
     pub mean_border_height: f32,
+    #[serde(default)]
+    pub kind: EdgeKind,
+    /// Sea zone a strait crosses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub through_sea_zone: Option<u32>,
 }
 
 /// Province adjacency info.
@@ -26,6 +47,7 @@ pub fn build_adjacency(
     river_mask: &[u8],
     width: u32,
     height_dim: u32,
+    projection: MapProjection,
     on_progress: &mut dyn FnMut(f32, &str),
 ) -> Vec<ProvinceAdjacency> {
     let _n = (width * height_dim) as usize;
@@ -45,7 +67,7 @@ pub fn build_adjacency(
                 continue;
             }
 
-            for (_, _, ni) in neighbors8(x, y, width, height_dim) {
+            for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
                 let label_b = labels[ni];
                 if label_b == no_label || label_b == label_a {
                     continue;
@@ -98,11 +120,13 @@ pub fn build_adjacency(
                     } else {
                         0.0
                     },
+                    kind: EdgeKind::Land,
+                    through_sea_zone: None,
                 });
             }
         }
 
-        neighbors.sort_by(|a, b| b.shared_border_length.cmp(&a.shared_border_length));
+        neighbors.sort_by_key(|edge| Reverse(edge.shared_border_length));
         adjacency.push(ProvinceAdjacency {
             province_id: pid,
             neighbors,
@@ -112,3 +136,44 @@ pub fn build_adjacency(
     on_progress(100.0, "Adjacency graph complete");
     adjacency
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Province 0 on the western edge and 1 on the eastern edge, water between them.
+    fn edge_provinces(projection: MapProjection) -> Vec<ProvinceAdjacency> {
+        let (width, height) = (6, 3);
+        let labels: Vec<u32> = (0..width * height)
+            .map(|i| match i % width {
+                0 => 0,
+                5 => 1,
+                _ => u32::MAX,
+            })
+            .collect();
+        let n = labels.len();
+        build_adjacency(
+            &labels,
+            &vec![40_000; n],
+            &vec![0; n],
+            width,
+            height,
+            projection,
+            &mut |_, _| {},
+        )
+    }
+
+    #[test]
+    fn provinces_border_across_the_antimeridian_only_when_wrapping() {
+        let wrapped = edge_provinces(MapProjection::Equirectangular);
+        assert_eq!(wrapped.len(), 2);
+        assert_eq!(wrapped[0].neighbors.len(), 1);
+        let edge = &wrapped[0].neighbors[0];
+        assert_eq!(edge.neighbor_id, 1);
+        assert_eq!(edge.kind, EdgeKind::Land);
+        assert_eq!(edge.shared_border_length, 7);
+
+        let bounded = edge_provinces(MapProjection::Flat);
+        assert!(bounded.iter().all(|entry| entry.neighbors.is_empty()));
+    }
+}
@@ -4,6 +4,9 @@ use rand::Rng;
 use rand::SeedableRng;
 use rand_pcg::Pcg64;
 
+/// Height16 value of sea level.
+pub const SEA_LEVEL: u16 = 32768;
+
 /// Stage 3: Height reconstruction from albedo features.
 /// Uses mountain_score = grayness * local_variance, plus multi-octave noise.
 /// Enforces sea level at landmask=0 and smooths coast transition.
@@ -1,7 +1,88 @@
-use crate::raster::neighbors8;
+use crate::config::{MapProjection, WorldgenConfig};
+use crate::raster::neighbors8_in;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
 
-/// Stage 4: Rivers and flow accumulation using D8 algorithm.
-/// From height field, compute flow direction and accumulation, extract river mask.
+/// Tunables for depression filling and lake extraction.
+#[derive(Debug, Clone)]
+pub struct HydrologySettings {
+    /// Upstream cells needed before a cell is drawn as a river.
+    pub river_threshold: u32,
+    /// Filled depressions smaller than this are treated as noise and drained through.
+    pub min_lake_area: u32,
+    /// Catchment cells needed per lake cell to keep a lake brimming up to its spill point.
+    /// Lakes below this ratio lose their outlet and become endorheic.
+    pub evaporation_ratio: f32,
+    /// Whether flow may cross the left and right texture edges.
+    pub projection: MapProjection,
+}
+
+impl Default for HydrologySettings {
+    fn default() -> Self {
+        Self {
+            river_threshold: 200,
+            min_lake_area: 12,
+            evaporation_ratio: 6.0,
+            projection: MapProjection::Equirectangular,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridPoint {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A filled depression large enough to hold standing water.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LakeRecord {
+    pub id: u32,
+    pub area: u32,
+    pub surface_height: u16,
+    pub max_depth: u16,
+    pub mean_depth: f32,
+    /// Rim cell the lake would overflow through.
+    pub spill_point: GridPoint,
+    /// Lake cell feeding the outlet river; `None` for endorheic basins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outlet: Option<GridPoint>,
+    /// Cells draining into the lake, including the lake itself.
+    pub inflow: u32,
+    /// Flow carried out through the spill point; zero for endorheic basins.
+    pub outflow: u32,
+    pub endorheic: bool,
+    /// Endorheic basins too dry to hold a permanent lake.
+    pub salt_flat_candidate: bool,
+}
+
+impl HydrologySettings {
+    /// Defaults with the river threshold and projection taken from the pipeline config.
+    pub fn for_config(config: &WorldgenConfig) -> Self {
+        Self {
+            river_threshold: config.river_threshold,
+            projection: config.projection,
+            ..Self::default()
+        }
+    }
+}
+
+/// Everything the hydrology stage produces.
+#[derive(Debug, Clone)]
+pub struct HydrologyResult {
+    pub river_mask: Vec<u8>,
+    pub accumulation: Vec<u32>,
+    /// Receiver of each land cell after lakes are routed to their outlets.
+    pub flow_dir: Vec<Option<usize>>,
+    /// 255 for lake water, 128 for salt-flat candidates, 0 elsewhere.
+    pub lake_mask: Vec<u8>,
+    pub lakes: Vec<LakeRecord>,
+}
+
+/// Stage 4: Rivers and flow accumulation.
+/// Returns only the river mask and accumulation of [`compute_hydrology`].
 pub fn compute_rivers(
     height: &[u16],
     landmask: &[bool],
@@ -10,70 +91,190 @@ pub fn compute_rivers(
     river_threshold: u32,
     on_progress: &mut dyn FnMut(f32, &str),
 ) -> (Vec<u8>, Vec<u32>) {
+    let settings = HydrologySettings {
+        river_threshold,
+        ..HydrologySettings::default()
+    };
+    let result = compute_hydrology(height, landmask, width, height_dim, &settings, on_progress);
+    (result.river_mask, result.accumulation)
+}
+
+/// Priority-flood depression filling followed by lake extraction.
+///
+/// Flow is routed over the filled surface, so water crosses pits and flats toward their spill
+/// points instead of stopping dead. Filled depressions become lakes; lakes whose catchment cannot
+/// balance evaporation keep their water and are cut off from the downstream network.
+pub fn compute_hydrology(
+    height: &[u16],
+    landmask: &[bool],
+    width: u32,
+    height_dim: u32,
+    settings: &HydrologySettings,
+    on_progress: &mut dyn FnMut(f32, &str),
+) -> HydrologyResult {
     let n = (width * height_dim) as usize;
 
-    on_progress(0.0, "Computing flow direction (D8)");
+    on_progress(0.0, "Filling depressions (priority flood)");
+    let (filled, mut flow_dir) =
+        priority_flood(height, landmask, width, height_dim, settings.projection);
+
+    on_progress(30.0, "Extracting lakes");
+    let (lake_labels, mut lakes, deepest) = extract_lakes(
+        height,
+        &filled,
+        landmask,
+        width,
+        height_dim,
+        settings.min_lake_area,
+        settings.projection,
+    );
 
-    // D8 flow direction: each cell flows to its lowest neighbor
+    on_progress(45.0, "Computing flow accumulation");
+    let accumulation = accumulate(&flow_dir, landmask);
+
+    on_progress(60.0, "Routing lakes to their outlets");
+    let exits = lake_exits(&lake_labels, &flow_dir, lakes.len());
+    for ((lake, exits), deepest) in lakes.iter_mut().zip(exits).zip(deepest) {
+        lake.inflow = exits.iter().map(|&i| accumulation[i]).sum();
+        let Some(&outlet) = exits.iter().max_by_key(|&&i| accumulation[i]) else {
+            continue;
+        };
+        let spill = flow_dir[outlet];
+        lake.spill_point = point(spill.unwrap_or(outlet), width);
+        let balance = lake.inflow as f32 / lake.area.max(1) as f32;
+        // Open lakes drain through a single outlet; endorheic ones collect at their deepest cell.
+        let (root, receiver) = if balance < settings.evaporation_ratio {
+            lake.endorheic = true;
+            lake.salt_flat_candidate = balance < settings.evaporation_ratio * 0.5;
+            (deepest, None)
+        } else {
+            lake.outlet = Some(point(outlet, width));
+            (outlet, spill)
+        };
+        route_lake(
+            root,
+            receiver,
+            &lake_labels,
+            &mut flow_dir,
+            width,
+            height_dim,
+            settings.projection,
+        );
+    }
+
+    on_progress(75.0, "Propagating flow");
+    let accumulation = accumulate(&flow_dir, landmask);
+    for lake in &mut lakes {
+        if let Some(outlet) = lake.outlet {
+            lake.outflow = accumulation[(outlet.y * width + outlet.x) as usize];
+        }
+    }
+
+    on_progress(85.0, "Extracting river and lake masks");
+    let lake_mask: Vec<u8> = lake_labels
+        .iter()
+        .map(|&label| match label {
+            u32::MAX => 0,
+            id if lakes[id as usize].salt_flat_candidate => 128,
+            _ => 255,
+        })
+        .collect();
+
+    // River mask: accumulation > threshold on land, outside standing water
+    let river_mask: Vec<u8> = (0..n)
+        .map(|i| {
+            if landmask[i]
+                && lake_labels[i] == u32::MAX
+                && accumulation[i] > settings.river_threshold
+            {
+                255u8
+            } else {
+                0u8
+            }
+        })
+        .collect();
+
+    on_progress(100.0, "Rivers complete");
+    HydrologyResult {
+        river_mask,
+        accumulation,
+        flow_dir,
+        lake_mask,
+        lakes,
+    }
+}
+
+/// Fills every depression up to its spill level. Returns the filled surface and a receiver for
+/// each land cell; coastal and edge cells have none.
+fn priority_flood(
+    height: &[u16],
+    landmask: &[bool],
+    width: u32,
+    height_dim: u32,
+    projection: MapProjection,
+) -> (Vec<u16>, Vec<Option<usize>>) {
+    let n = (width * height_dim) as usize;
+    let mut filled = height.to_vec();
     let mut flow_dir: Vec<Option<usize>> = vec![None; n];
+    let mut settled = vec![false; n];
+    // Ties pop in insertion order so flats drain outward from their spill point.
+    let mut heap = BinaryHeap::new();
+    let mut sequence = 0u64;
 
     for y in 0..height_dim {
         for x in 0..width {
             let i = (y * width + x) as usize;
             if !landmask[i] {
+                settled[i] = true;
                 continue;
             }
-
-            let neighbors = neighbors8(x, y, width, height_dim);
-            let mut lowest_h = height[i];
-            let mut lowest_idx = None;
-
-            for (_, _, ni) in &neighbors {
-                if height[*ni] < lowest_h {
-                    lowest_h = height[*ni];
-                    lowest_idx = Some(*ni);
-                }
+            let at_edge = y == 0
+                || y + 1 == height_dim
+                || (!projection.wraps_x() && (x == 0 || x + 1 == width));
+            let at_coast = neighbors8_in(x, y, width, height_dim, projection)
+                .iter()
+                .any(|&(_, _, ni)| !landmask[ni]);
+            if at_edge || at_coast {
+                settled[i] = true;
+                heap.push(Reverse((height[i], sequence, i)));
+                sequence += 1;
             }
-
-            flow_dir[i] = lowest_idx;
         }
     }
 
-    on_progress(40.0, "Computing flow accumulation");
-
-    // Compute flow accumulation via topological sort
-    // Count in-degrees
-    let mut in_degree = vec![0u32; n];
-    for i in 0..n {
-        if let Some(target) = flow_dir[i] {
-            in_degree[target] += 1;
+    while let Some(Reverse((level, _, i))) = heap.pop() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
+            if settled[ni] {
+                continue;
+            }
+            settled[ni] = true;
+            filled[ni] = height[ni].max(level);
+            flow_dir[ni] = Some(i);
+            heap.push(Reverse((filled[ni], sequence, ni)));
+            sequence += 1;
         }
     }
 
-    // Topological sort starting from sources (in_degree = 0 on land)
-    let mut queue: Vec<usize> = Vec::new();
-    let mut accumulation = vec![1u32; n]; // Each cell starts with 1 (itself)
-
-    for i in 0..n {
-        if landmask[i] && in_degree[i] == 0 {
-            queue.push(i);
-        }
-    }
+    (filled, flow_dir)
+}
 
-    // Set sea cells to 0
-    for i in 0..n {
-        if !landmask[i] {
-            accumulation[i] = 0;
-        }
+/// Flow accumulation via topological sort over the receiver graph.
+fn accumulate(flow_dir: &[Option<usize>], landmask: &[bool]) -> Vec<u32> {
+    let n = flow_dir.len();
+    let mut in_degree = vec![0u32; n];
+    for target in flow_dir.iter().flatten() {
+        in_degree[*target] += 1;
     }
 
-    on_progress(60.0, "Propagating flow");
-
+    let mut accumulation: Vec<u32> = landmask.iter().map(|&land| land as u32).collect();
+    let mut queue: Vec<usize> = (0..n)
+        .filter(|&i| landmask[i] && in_degree[i] == 0)
+        .collect();
     let mut head = 0;
     while head < queue.len() {
         let i = queue[head];
         head += 1;
-
         if let Some(target) = flow_dir[i] {
             accumulation[target] += accumulation[i];
             in_degree[target] -= 1;
@@ -82,20 +283,212 @@ pub fn compute_rivers(
             }
         }
     }
+    accumulation
+}
+
+/// Points every cell of a lake along a breadth-first tree toward `root`, which drains into
+/// `receiver`.
+fn route_lake(
+    root: usize,
+    receiver: Option<usize>,
+    labels: &[u32],
+    flow_dir: &mut [Option<usize>],
+    width: u32,
+    height_dim: u32,
+    projection: MapProjection,
+) {
+    let id = labels[root];
+    flow_dir[root] = receiver;
+    let mut visited = HashSet::from([root]);
+    let mut queue = vec![root];
+    let mut head = 0;
+    while head < queue.len() {
+        let i = queue[head];
+        head += 1;
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
+            if labels[ni] == id && visited.insert(ni) {
+                flow_dir[ni] = Some(i);
+                queue.push(ni);
+            }
+        }
+    }
+}
 
-    on_progress(80.0, "Extracting river mask");
+/// Labels connected filled cells (8-connected) as lakes, dropping ones below `min_area`.
+/// Also returns the deepest cell of each lake.
+fn extract_lakes(
+    height: &[u16],
+    filled: &[u16],
+    landmask: &[bool],
+    width: u32,
+    height_dim: u32,
+    min_area: u32,
+    projection: MapProjection,
+) -> (Vec<u32>, Vec<LakeRecord>, Vec<usize>) {
+    let n = (width * height_dim) as usize;
+    let mut labels = vec![u32::MAX; n];
+    let mut lakes = Vec::new();
+    let mut deepest = Vec::new();
+    let mut visited = vec![false; n];
 
-    // River mask: accumulation > threshold AND landmask
-    let river_mask: Vec<u8> = (0..n)
-        .map(|i| {
-            if landmask[i] && accumulation[i] > river_threshold {
-                255u8
-            } else {
-                0u8
+    for start in 0..n {
+        if visited[start] || !landmask[start] || filled[start] <= height[start] {
+            continue;
+        }
+        let surface = filled[start];
+        let mut cells = vec![start];
+        visited[start] = true;
+        let mut head = 0;
+        while head < cells.len() {
+            let i = cells[head];
+            head += 1;
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
+                if !visited[ni] && landmask[ni] && filled[ni] == surface && filled[ni] > height[ni]
+                {
+                    visited[ni] = true;
+                    cells.push(ni);
+                }
             }
-        })
-        .collect();
+        }
+        if (cells.len() as u32) < min_area {
+            continue;
+        }
 
-    on_progress(100.0, "Rivers complete");
-    (river_mask, accumulation)
+        let id = lakes.len() as u32;
+        let depths = cells.iter().map(|&i| surface - height[i]);
+        let max_depth = depths.clone().max().unwrap_or(0);
+        let deepest_cell = cells
+            .iter()
+            .copied()
+            .min_by_key(|&i| height[i])
+            .unwrap_or(start);
+        let mean_depth = depths.map(f32::from).sum::<f32>() / cells.len() as f32;
+        for &i in &cells {
+            labels[i] = id;
+        }
+        lakes.push(LakeRecord {
+            id,
+            area: cells.len() as u32,
+            surface_height: surface,
+            max_depth,
+            mean_depth,
+            spill_point: point(cells[0], width),
+            outlet: None,
+            inflow: 0,
+            outflow: 0,
+            endorheic: false,
+            salt_flat_candidate: false,
+        });
+        deepest.push(deepest_cell);
+    }
+
+    (labels, lakes, deepest)
+}
+
+/// Lake cells whose receiver lies outside the lake, grouped by lake id.
+fn lake_exits(labels: &[u32], flow_dir: &[Option<usize>], lake_count: usize) -> Vec<Vec<usize>> {
+    let mut exits = vec![Vec::new(); lake_count];
+    for (i, &label) in labels.iter().enumerate() {
+        if label == u32::MAX {
+            continue;
+        }
+        if flow_dir[i].is_some_and(|target| labels[target] != label) {
+            exits[label as usize].push(i);
+        }
+    }
+    exits
+}
+
+fn point(i: usize, width: u32) -> GridPoint {
+    GridPoint {
+        x: i as u32 % width,
+        y: i as u32 / width,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: u32 = 24;
+
+    /// All-land slope falling toward the bottom edge, with a 4x4 pit cut into it. The pit spills
+    /// over its lower rim at 1110.
+    fn pitted_slope() -> (Vec<u16>, Vec<bool>, Vec<usize>) {
+        let mut height: Vec<u16> = (0..SIZE * SIZE)
+            .map(|i| 1000 + (SIZE - 1 - i / SIZE) as u16 * 10)
+            .collect();
+        let mut pit = Vec::new();
+        for y in 8..12 {
+            for x in 10..14 {
+                let i = (y * SIZE + x) as usize;
+                height[i] = 500;
+                pit.push(i);
+            }
+        }
+        (height, vec![true; (SIZE * SIZE) as usize], pit)
+    }
+
+    fn run(evaporation_ratio: f32) -> (HydrologyResult, Vec<usize>) {
+        let (height, landmask, pit) = pitted_slope();
+        let settings = HydrologySettings {
+            river_threshold: 20,
+            min_lake_area: 4,
+            evaporation_ratio,
+            projection: MapProjection::Flat,
+        };
+        let result = compute_hydrology(&height, &landmask, SIZE, SIZE, &settings, &mut |_, _| {});
+        (result, pit)
+    }
+
+    fn leaving_cells(result: &HydrologyResult, lake: &[usize]) -> Vec<usize> {
+        lake.iter()
+            .copied()
+            .filter(|&i| result.flow_dir[i].is_some_and(|target| !lake.contains(&target)))
+            .collect()
+    }
+
+    #[test]
+    fn pit_fills_into_a_lake_with_one_outlet() {
+        let (result, pit) = run(0.5);
+
+        assert_eq!(result.lakes.len(), 1);
+        let lake = &result.lakes[0];
+        assert_eq!(lake.area, pit.len() as u32);
+        assert_eq!(lake.surface_height, 1110);
+        assert_eq!(lake.max_depth, 610);
+        assert!(!lake.endorheic);
+        let outlet = lake.outlet.expect("open lake has an outlet");
+        assert_eq!(
+            leaving_cells(&result, &pit),
+            vec![(outlet.y * SIZE + outlet.x) as usize]
+        );
+        assert!(lake.outflow >= lake.inflow);
+        assert!(pit.iter().all(|&i| result.lake_mask[i] == 255));
+        assert!(pit.iter().all(|&i| result.river_mask[i] == 0));
+        let spill = (lake.spill_point.y * SIZE + lake.spill_point.x) as usize;
+        assert!(result.accumulation[spill] > lake.outflow);
+    }
+
+    #[test]
+    fn dry_pit_becomes_an_endorheic_basin() {
+        let (result, pit) = run(1000.0);
+
+        assert_eq!(result.lakes.len(), 1);
+        let lake = &result.lakes[0];
+        assert!(lake.endorheic);
+        assert!(lake.salt_flat_candidate);
+        assert_eq!(lake.outlet, None);
+        assert_eq!(lake.outflow, 0);
+        assert!(leaving_cells(&result, &pit).is_empty());
+        assert_eq!(
+            pit.iter()
+                .filter(|&&i| result.flow_dir[i].is_none())
+                .count(),
+            1
+        );
+        assert!(pit.iter().all(|&i| result.lake_mask[i] == 128));
+    }
 }
@@ -7,20 +7,25 @@ pub mod graph;
 pub mod height;
 pub mod hydrology;
 pub mod landmask;
+pub mod naming;
 pub mod normalize;
+pub mod paradox;
 pub mod partition;
+pub mod pipeline;
 pub mod postprocess;
 pub mod raster;
+pub mod river_network;
+pub mod roads;
 pub mod sampling;
+pub mod sea;
 pub mod suitability;
 
 pub use biome::{
-    BiomeAnalysisResult, BiomeCoverageSummary, BiomeMixEntry, BiomeModelSettings, BiomePaletteEntry,
-    BiomeProvinceSummary, BiomeReport, BiomeVisionAnalysis, BiomeVisionCandidate,
-    BiomeVisionCellPrior, BiomeVisionTilePrior,
+    BiomeAnalysisResult, BiomeCoverageSummary, BiomeMixEntry, BiomeModelSettings,
+    BiomePaletteEntry, BiomeProvinceSummary, BiomeReport, BiomeVisionAnalysis,
+    BiomeVisionCandidate, BiomeVisionCellPrior, BiomeVisionTilePrior,
 };
 pub use biome_archetype::{
-    BiomeArchetype, BiomeArchetypeCalibration, BiomeRegistry, ColorProfile,
-    EnvironmentalEnvelope,
+    BiomeArchetype, BiomeArchetypeCalibration, BiomeRegistry, ColorProfile, EnvironmentalEnvelope,
 };
-pub use config::WorldgenConfig;
+pub use config::{MapProjection, WorldgenConfig};
@@ -0,0 +1,639 @@
+use crate::biome_archetype::BiomeRegistry;
+use crate::cluster::{DuchyRecord, KingdomRecord, ProvinceRecord};
+use crate::config::MapProjection;
+use crate::export;
+use crate::height;
+use crate::pipeline;
+use crate::raster::neighbors4_in;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// Culture word lists shipped with the crate, so naming never needs a network service.
+const DEFAULT_CULTURES: &str = include_str!("../data/name_cultures.json");
+
+/// Chance that a province with a terrain feature carries its suffix.
+const SUFFIX_CHANCE: f64 = 0.6;
+/// Model draws per name before falling back to blending two draws.
+const MAX_ATTEMPTS: usize = 64;
+/// Mean land height, as a share of the range above sea level, that counts as high ground.
+const HIGHLAND_HEIGHT: f32 = 0.45;
+/// Share of a province's pixels on a river before it counts as a river province.
+const RIVER_SHARE: f32 = 0.01;
+
+const START: char = '^';
+const END: char = '$';
+
+/// Name endings per terrain feature.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerrainSuffixes {
+    #[serde(default)]
+    pub river: Vec<String>,
+    #[serde(default)]
+    pub highland: Vec<String>,
+    #[serde(default)]
+    pub coast: Vec<String>,
+    #[serde(default)]
+    pub forest: Vec<String>,
+    #[serde(default)]
+    pub desert: Vec<String>,
+    #[serde(default)]
+    pub wetland: Vec<String>,
+    #[serde(default)]
+    pub grassland: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameCulture {
+    pub id: String,
+    /// Biome id fragments the culture settles in, e.g. "taiga".
+    #[serde(default)]
+    pub biomes: Vec<String>,
+    /// Training words for the name model.
+    pub words: Vec<String>,
+    #[serde(default)]
+    pub suffixes: TerrainSuffixes,
+    /// Endings that turn a capital's name into a kingdom name.
+    #[serde(default)]
+    pub realm: Vec<String>,
+}
+
+impl NameCulture {
+    /// Cultures bundled in `data/name_cultures.json`.
+    pub fn default_cultures() -> Vec<NameCulture> {
+        serde_json::from_str(DEFAULT_CULTURES).expect("bundled name cultures are valid JSON")
+    }
+}
+
+/// Terrain features a province name can reflect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProvinceTerrain {
+    pub river: bool,
+    pub highland: bool,
+    pub coast: bool,
+}
+
+/// Generated names, written to `names.json` and applied to the hierarchy records.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameSet {
+    /// Kingdom id -> culture id.
+    pub kingdom_cultures: BTreeMap<u32, String>,
+    pub provinces: BTreeMap<u32, String>,
+    pub duchies: BTreeMap<u32, String>,
+    pub kingdoms: BTreeMap<u32, String>,
+}
+
+impl NameSet {
+    /// Overwrites record names; records without a generated name keep theirs.
+    pub fn apply(
+        &self,
+        provinces: &mut [ProvinceRecord],
+        duchies: &mut [DuchyRecord],
+        kingdoms: &mut [KingdomRecord],
+    ) {
+        for province in provinces {
+            if let Some(name) = self.provinces.get(&province.id) {
+                province.name = name.clone();
+            }
+        }
+        for duchy in duchies {
+            if let Some(name) = self.duchies.get(&duchy.id) {
+                duchy.name = name.clone();
+            }
+        }
+        for kingdom in kingdoms {
+            if let Some(name) = self.kingdoms.get(&kingdom.id) {
+                kingdom.name = name.clone();
+            }
+        }
+    }
+}
+
+/// Flags each province for rivers, high ground and coastline.
+pub fn province_terrain(
+    labels: &[u32],
+    landmask: &[bool],
+    height_field: &[u16],
+    river_mask: &[u8],
+    width: u32,
+    height_dim: u32,
+    projection: MapProjection,
+) -> HashMap<u32, ProvinceTerrain> {
+    // (pixels, river pixels, summed land height, touches water)
+    let mut stats: HashMap<u32, (u32, u32, f64, bool)> = HashMap::new();
+    let land_range = (u16::MAX - height::SEA_LEVEL) as f64;
+    for y in 0..height_dim {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            if labels[i] == u32::MAX {
+                continue;
+            }
+            let entry = stats.entry(labels[i]).or_insert((0, 0, 0.0, false));
+            entry.0 += 1;
+            if river_mask[i] > 0 {
+                entry.1 += 1;
+            }
+            entry.2 += height_field[i].saturating_sub(height::SEA_LEVEL) as f64 / land_range;
+            if !entry.3 {
+                entry.3 = neighbors4_in(x, y, width, height_dim, projection)
+                    .iter()
+                    .any(|&(_, _, ni)| !landmask[ni]);
+            }
+        }
+    }
+    stats
+        .into_iter()
+        .map(|(id, (pixels, river, height_sum, coast))| {
+            let terrain = ProvinceTerrain {
+                river: river > 0 && river as f32 >= pixels as f32 * RIVER_SHARE,
+                highland: (height_sum / pixels as f64) as f32 >= HIGHLAND_HEIGHT,
+                coast,
+            };
+            (id, terrain)
+        })
+        .collect()
+}
+
+/// Order-2 character Markov chain over a culture's words.
+struct NameModel {
+    transitions: HashMap<(char, char), Vec<char>>,
+    words: HashSet<String>,
+    min_len: usize,
+    max_len: usize,
+}
+
+impl NameModel {
+    fn train(words: &[String]) -> Self {
+        let mut transitions: HashMap<(char, char), Vec<char>> = HashMap::new();
+        let mut lengths = Vec::new();
+        for word in words {
+            let word = word.to_lowercase();
+            lengths.push(word.chars().count());
+            let mut state = (START, START);
+            for c in word.chars().chain([END]) {
+                transitions.entry(state).or_default().push(c);
+                state = (state.1, c);
+            }
+        }
+        Self {
+            transitions,
+            words: words.iter().map(|w| w.to_lowercase()).collect(),
+            min_len: lengths.iter().copied().min().unwrap_or(3).max(3),
+            max_len: lengths.iter().copied().max().unwrap_or(8),
+        }
+    }
+
+    /// A new word within the training lengths, or `None` if the draw was rejected.
+    fn sample(&self, rng: &mut Pcg64) -> Option<String> {
+        let mut word = String::new();
+        let mut state = (START, START);
+        loop {
+            let next = *self.transitions.get(&state)?.choose(rng)?;
+            if next == END {
+                break;
+            }
+            word.push(next);
+            if word.chars().count() > self.max_len {
+                return None;
+            }
+            state = (state.1, next);
+        }
+        (word.chars().count() >= self.min_len && !self.words.contains(&word)).then_some(word)
+    }
+
+    /// A root no other province uses. Once the model stops producing fresh words, two draws
+    /// are blended, and as a last resort a numeral is appended.
+    fn unique_root(&self, rng: &mut Pcg64, used: &HashSet<String>) -> String {
+        let mut draws = Vec::new();
+        for _ in 0..MAX_ATTEMPTS {
+            if let Some(word) = self.sample(rng) {
+                if !used.contains(&word) {
+                    return word;
+                }
+                draws.push(word);
+            }
+        }
+        if draws.is_empty() {
+            draws = self.words.iter().cloned().collect();
+            draws.sort_unstable();
+        }
+        let Some(base) = draws.first().cloned() else {
+            return format!("{}", used.len());
+        };
+        for _ in 0..MAX_ATTEMPTS {
+            let (Some(a), Some(b)) = (draws.choose(rng), draws.choose(rng)) else {
+                break;
+            };
+            let head: String = a.chars().take(a.chars().count().div_ceil(2)).collect();
+            let tail: String = b.chars().skip(b.chars().count() / 2).collect();
+            let blend = format!("{}{}", head, tail);
+            if !used.contains(&blend) {
+                return blend;
+            }
+        }
+        (2..)
+            .map(|n| format!("{} {}", base, roman(n)))
+            .find(|name| !used.contains(name))
+            .unwrap_or(base)
+    }
+}
+
+/// The clustered hierarchy and what each province looks like, as read by [`generate_names`].
+pub struct NamingInput<'a> {
+    pub provinces: &'a [ProvinceRecord],
+    pub duchies: &'a [DuchyRecord],
+    pub kingdoms: &'a [KingdomRecord],
+    pub terrain: &'a HashMap<u32, ProvinceTerrain>,
+    pub registry: &'a BiomeRegistry,
+}
+
+/// Stage 13: Name provinces, duchies and kingdoms.
+///
+/// Each kingdom takes the culture whose biomes best match its provinces. Provinces draw fresh
+/// words from that culture's model and may take a suffix for rivers, high ground, coast or
+/// biome. Duchies are named after their largest province and kingdoms after theirs, with a
+/// realm ending. Names are unique within each rank and depend only on the inputs and `seed`.
+pub fn generate_names(
+    input: &NamingInput,
+    cultures: &[NameCulture],
+    seed: u64,
+    on_progress: &mut dyn FnMut(f32, &str),
+) -> NameSet {
+    let NamingInput {
+        provinces,
+        duchies,
+        kingdoms,
+        terrain,
+        registry,
+    } = *input;
+    let mut names = NameSet::default();
+    if cultures.is_empty() {
+        return names;
+    }
+    let mut rng = Pcg64::seed_from_u64(seed);
+    let models: Vec<NameModel> = cultures
+        .iter()
+        .map(|c| NameModel::train(&c.words))
+        .collect();
+
+    let biome_ids: HashMap<u32, String> = provinces
+        .iter()
+        .map(|p| {
+            let id = p.biome_primary_id.clone().unwrap_or_else(|| {
+                registry
+                    .archetypes
+                    .get(p.biome_primary as usize)
+                    .map(|a| a.id.clone())
+                    .unwrap_or_default()
+            });
+            (p.id, id)
+        })
+        .collect();
+
+    on_progress(0.0, "Assigning cultures");
+    let mut kingdom_culture: HashMap<u32, usize> = HashMap::new();
+    let mut sorted_kingdoms: Vec<&KingdomRecord> = kingdoms.iter().collect();
+    sorted_kingdoms.sort_by_key(|k| k.id);
+    for kingdom in &sorted_kingdoms {
+        let scores: Vec<usize> = cultures
+            .iter()
+            .map(|culture| {
+                provinces
+                    .iter()
+                    .filter(|p| p.kingdom_id == kingdom.id)
+                    .filter(|p| culture.biomes.iter().any(|b| biome_ids[&p.id].contains(b)))
+                    .count()
+            })
+            .collect();
+        let best = scores.iter().copied().max().unwrap_or(0);
+        let tied: Vec<usize> = (0..cultures.len())
+            .filter(|&c| best == 0 || scores[c] == best)
+            .collect();
+        let culture = tied[rng.gen_range(0..tied.len())];
+        kingdom_culture.insert(kingdom.id, culture);
+        names
+            .kingdom_cultures
+            .insert(kingdom.id, cultures[culture].id.clone());
+    }
+
+    on_progress(20.0, "Naming provinces");
+    let mut sorted_provinces: Vec<&ProvinceRecord> = provinces.iter().collect();
+    sorted_provinces.sort_by_key(|p| p.id);
+    let mut roots: HashMap<u32, String> = HashMap::new();
+    let mut used: HashSet<String> = HashSet::new();
+    for province in sorted_provinces {
+        let culture = kingdom_culture
+            .get(&province.kingdom_id)
+            .copied()
+            .unwrap_or(0);
+        let suffixes = terrain_suffixes(
+            &cultures[culture].suffixes,
+            terrain.get(&province.id).copied().unwrap_or_default(),
+            &biome_ids[&province.id],
+        );
+        let root = models[culture].unique_root(&mut rng, &used);
+        let mut name = root.clone();
+        if !suffixes.is_empty() && rng.gen_bool(SUFFIX_CHANCE) {
+            let suffixed = join(&root, &suffixes[rng.gen_range(0..suffixes.len())]);
+            if !used.contains(&suffixed) {
+                name = suffixed;
+            }
+        }
+        used.insert(root.clone());
+        used.insert(name.clone());
+        roots.insert(province.id, root);
+        names.provinces.insert(province.id, capitalize(&name));
+    }
+
+    on_progress(70.0, "Naming duchies and kingdoms");
+    let area: HashMap<u32, u32> = provinces.iter().map(|p| (p.id, p.area)).collect();
+    let capital = |ids: &mut dyn Iterator<Item = u32>| {
+        ids.filter(|id| area.contains_key(id))
+            .max_by_key(|&id| (area[&id], std::cmp::Reverse(id)))
+    };
+    let mut duchy_capital: HashMap<u32, u32> = HashMap::new();
+    for duchy in duchies {
+        if let Some(seat) = capital(&mut duchy.province_ids.iter().copied()) {
+            duchy_capital.insert(duchy.id, seat);
+            names
+                .duchies
+                .insert(duchy.id, names.provinces[&seat].clone());
+        }
+    }
+
+    let mut kingdom_names: HashSet<String> = HashSet::new();
+    for kingdom in sorted_kingdoms {
+        let Some(seat) = capital(
+            &mut kingdom
+                .duchy_ids
+                .iter()
+                .filter_map(|d| duchies.iter().find(|duchy| duchy.id == *d))
+                .flat_map(|duchy| duchy.province_ids.iter().copied()),
+        ) else {
+            continue;
+        };
+        let culture = &cultures[kingdom_culture[&kingdom.id]];
+        let root = &roots[&seat];
+        let mut endings: Vec<&String> = culture.realm.iter().collect();
+        endings.shuffle(&mut rng);
+        let name = endings
+            .into_iter()
+            .map(|ending| join(root, ending))
+            .chain([root.clone()])
+            .find(|name| !kingdom_names.contains(name))
+            .unwrap_or_else(|| root.clone());
+        kingdom_names.insert(name.clone());
+        names.kingdoms.insert(kingdom.id, capitalize(&name));
+    }
+
+    on_progress(100.0, "Naming complete");
+    names
+}
+
+/// Names the hierarchy in a planet folder: writes `names.json` and updates the names in
+/// `provinces.json`, `duchies.json` and `kingdoms.json`.
+pub fn name_planet_dir(
+    planet_dir: &Path,
+    registry: &BiomeRegistry,
+    seed: u64,
+    projection: MapProjection,
+    on_progress: &mut dyn FnMut(f32, &str),
+) -> Result<NameSet, String> {
+    let (width, height) = pipeline::read_dimensions(&planet_dir.join("province_id.png"))?;
+    let landmask = pipeline::read_landmask(&planet_dir.join("landmask.png"))?;
+    let labels = pipeline::read_labels(&planet_dir.join("province_id.png"), &landmask)?;
+    let height_field = pipeline::read_height16(&planet_dir.join("height16.png"))?;
+    let river_mask = pipeline::read_mask_u8(&planet_dir.join("river_mask.png"))?;
+    let mut provinces: Vec<ProvinceRecord> =
+        pipeline::read_json(&planet_dir.join("provinces.json"))?;
+    let mut duchies: Vec<DuchyRecord> = pipeline::read_json(&planet_dir.join("duchies.json"))?;
+    let mut kingdoms: Vec<KingdomRecord> = pipeline::read_json(&planet_dir.join("kingdoms.json"))?;
+
+    let terrain = province_terrain(
+        &labels,
+        &landmask,
+        &height_field,
+        &river_mask,
+        width,
+        height,
+        projection,
+    );
+    let input = NamingInput {
+        provinces: &provinces,
+        duchies: &duchies,
+        kingdoms: &kingdoms,
+        terrain: &terrain,
+        registry,
+    };
+    let names = generate_names(&input, &NameCulture::default_cultures(), seed, on_progress);
+    names.apply(&mut provinces, &mut duchies, &mut kingdoms);
+
+    export::write_provinces_json(&provinces, &planet_dir.join("provinces.json"))?;
+    export::write_duchies_json(&duchies, &planet_dir.join("duchies.json"))?;
+    export::write_kingdoms_json(&kingdoms, &planet_dir.join("kingdoms.json"))?;
+    export::write_names_json(&names, &planet_dir.join("names.json"))?;
+    Ok(names)
+}
+
+/// Suffixes for the province's most distinctive feature: river, then high ground, then coast,
+/// then biome.
+fn terrain_suffixes<'a>(
+    suffixes: &'a TerrainSuffixes,
+    terrain: ProvinceTerrain,
+    biome_id: &str,
+) -> &'a [String] {
+    let biome_list: &[String] = if ["forest", "taiga"].iter().any(|k| biome_id.contains(k)) {
+        &suffixes.forest
+    } else if ["desert", "scrub", "ash"]
+        .iter()
+        .any(|k| biome_id.contains(k))
+    {
+        &suffixes.desert
+    } else if ["marsh", "mangrove"].iter().any(|k| biome_id.contains(k)) {
+        &suffixes.wetland
+    } else if ["savanna", "steppe", "grassland", "meadow", "matorral"]
+        .iter()
+        .any(|k| biome_id.contains(k))
+    {
+        &suffixes.grassland
+    } else {
+        &[]
+    };
+    [
+        (terrain.river, suffixes.river.as_slice()),
+        (terrain.highland, suffixes.highland.as_slice()),
+        (terrain.coast, suffixes.coast.as_slice()),
+        (true, biome_list),
+    ]
+    .into_iter()
+    .find(|(present, list)| *present && !list.is_empty())
+    .map_or(&[], |(_, list)| list)
+}
+
+/// Joins a root and an ending, dropping a doubled letter at the seam.
+fn join(root: &str, ending: &str) -> String {
+    let ending = ending.to_lowercase();
+    match (root.chars().last(), ending.chars().next()) {
+        (Some(a), Some(b)) if a == b => format!("{}{}", root, &ending[b.len_utf8()..]),
+        _ => format!("{}{}", root, ending),
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn roman(n: usize) -> String {
+    const NUMERALS: [(usize, &str); 7] = [
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut rest = n;
+    let mut out = String::new();
+    for &(value, numeral) in &NUMERALS {
+        while rest >= value {
+            out.push_str(numeral);
+            rest -= value;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROVINCES_PER_DUCHY: u32 = 5;
+    const DUCHIES_PER_KINGDOM: u32 = 4;
+    const KINGDOMS: u32 = 3;
+
+    struct Hierarchy {
+        provinces: Vec<ProvinceRecord>,
+        duchies: Vec<DuchyRecord>,
+        kingdoms: Vec<KingdomRecord>,
+    }
+
+    fn hierarchy() -> Hierarchy {
+        let duchy_count = DUCHIES_PER_KINGDOM * KINGDOMS;
+        let provinces = (0..PROVINCES_PER_DUCHY * duchy_count)
+            .map(|id| {
+                let duchy_id = id / PROVINCES_PER_DUCHY;
+                ProvinceRecord {
+                    id,
+                    seed_x: id,
+                    seed_y: 0,
+                    area: 100 + id * 7 % 13,
+                    duchy_id,
+                    kingdom_id: duchy_id / DUCHIES_PER_KINGDOM,
+                    biome_primary: (id % 4) as u16,
+                    biome_primary_id: None,
+                    biome_confidence: None,
+                    biome_candidate_ids: Vec::new(),
+                    name: String::new(),
+                    wealth: None,
+                    development: None,
+                    population: None,
+                }
+            })
+            .collect();
+        let duchies = (0..duchy_count)
+            .map(|id| DuchyRecord {
+                id,
+                province_ids: (id * PROVINCES_PER_DUCHY..(id + 1) * PROVINCES_PER_DUCHY).collect(),
+                kingdom_id: id / DUCHIES_PER_KINGDOM,
+                name: String::new(),
+            })
+            .collect();
+        let kingdoms = (0..KINGDOMS)
+            .map(|id| KingdomRecord {
+                id,
+                duchy_ids: (id * DUCHIES_PER_KINGDOM..(id + 1) * DUCHIES_PER_KINGDOM).collect(),
+                name: String::new(),
+            })
+            .collect();
+        Hierarchy {
+            provinces,
+            duchies,
+            kingdoms,
+        }
+    }
+
+    fn names(hierarchy: &Hierarchy, seed: u64) -> NameSet {
+        let terrain: HashMap<u32, ProvinceTerrain> = hierarchy
+            .provinces
+            .iter()
+            .map(|p| {
+                let terrain = ProvinceTerrain {
+                    river: p.id % 3 == 0,
+                    highland: p.id % 5 == 0,
+                    coast: p.id % 7 == 0,
+                };
+                (p.id, terrain)
+            })
+            .collect();
+        let registry = BiomeRegistry::default_registry();
+        let input = NamingInput {
+            provinces: &hierarchy.provinces,
+            duchies: &hierarchy.duchies,
+            kingdoms: &hierarchy.kingdoms,
+            terrain: &terrain,
+            registry: &registry,
+        };
+        generate_names(
+            &input,
+            &NameCulture::default_cultures(),
+            seed,
+            &mut |_, _| {},
+        )
+    }
+
+    fn assert_unique(names: &BTreeMap<u32, String>) {
+        let distinct: HashSet<&String> = names.values().collect();
+        assert_eq!(distinct.len(), names.len(), "duplicate names in {names:?}");
+    }
+
+    #[test]
+    fn names_are_deterministic_for_a_seed() {
+        let hierarchy = hierarchy();
+        let first = names(&hierarchy, 42);
+        let second = names(&hierarchy, 42);
+
+        assert_eq!(first.kingdom_cultures, second.kingdom_cultures);
+        assert_eq!(first.provinces, second.provinces);
+        assert_eq!(first.duchies, second.duchies);
+        assert_eq!(first.kingdoms, second.kingdoms);
+        assert_ne!(first.provinces, names(&hierarchy, 43).provinces);
+    }
+
+    #[test]
+    fn names_are_unique_within_each_rank() {
+        let hierarchy = hierarchy();
+        let names = names(&hierarchy, 7);
+
+        assert_eq!(names.provinces.len(), hierarchy.provinces.len());
+        assert_eq!(names.duchies.len(), hierarchy.duchies.len());
+        assert_eq!(names.kingdoms.len(), hierarchy.kingdoms.len());
+        assert_unique(&names.provinces);
+        assert_unique(&names.duchies);
+        assert_unique(&names.kingdoms);
+        assert!(names
+            .provinces
+            .values()
+            .all(|name| name.chars().next().is_some_and(char::is_uppercase)));
+    }
+}
@@ -0,0 +1,1211 @@
+use crate::biome_archetype::BiomeRegistry;
+use crate::cluster::{DuchyRecord, KingdomRecord, ProvinceRecord};
+use crate::graph::{EdgeKind, ProvinceAdjacency};
+use crate::height;
+use crate::pipeline;
+use crate::river_network::{RiverNetwork, RiverNodeKind, RiverSegment};
+use crate::sea::SEA_ZONE_ID_BASE;
+use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Heightmap value of the waterline; Paradox maps keep water below roughly 19 of 255.
+const SEA_LEVEL: u8 = 19;
+
+// rivers.png palette indices.
+const RIVER_SOURCE: u8 = 0;
+const RIVER_MERGE: u8 = 1;
+const RIVER_SPLIT: u8 = 2;
+const RIVER_NARROWEST: u8 = 3;
+const RIVER_WIDEST: u8 = 11;
+const RIVER_WATER: u8 = 254;
+const RIVER_LAND: u8 = 255;
+
+const NO_PROVINCE: u32 = u32::MAX;
+
+#[derive(Debug, Clone)]
+pub struct ParadoxExportSettings {
+    /// Widest water gap, in pixels, still crossable as a strait.
+    pub strait_max_width: u32,
+    /// Without sea zones, ocean is cut into square tiles of this size, one sea province per
+    /// connected piece.
+    pub sea_tile_size: u32,
+}
+
+impl Default for ParadoxExportSettings {
+    fn default() -> Self {
+        Self {
+            strait_max_width: 8,
+            sea_tile_size: 64,
+        }
+    }
+}
+
+/// Pipeline outputs needed for a Paradox map. `province_labels` uses `u32::MAX` for water.
+pub struct ParadoxMapInput<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub landmask: &'a [bool],
+    pub province_labels: &'a [u32],
+    pub height_field: &'a [u16],
+    pub river_mask: &'a [u8],
+    pub lake_mask: &'a [u8],
+    /// Sea zone id per pixel; each zone becomes one sea province.
+    pub sea_zone_labels: Option<&'a [u32]>,
+    pub rivers: &'a RiverNetwork,
+    pub registry: &'a BiomeRegistry,
+    pub provinces: &'a [ProvinceRecord],
+    pub duchies: &'a [DuchyRecord],
+    pub kingdoms: &'a [KingdomRecord],
+    pub adjacency: &'a [ProvinceAdjacency],
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParadoxExportSummary {
+    pub land_provinces: usize,
+    pub lake_provinces: usize,
+    pub sea_provinces: usize,
+    pub wasteland_provinces: usize,
+    pub river_crossings: usize,
+    pub straits: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProvinceClass {
+    Land,
+    Lake,
+    Sea,
+    /// Unlabeled land, e.g. islands too small to receive a seed.
+    Wasteland,
+}
+
+struct ParadoxProvince {
+    class: ProvinceClass,
+    /// Worldgen province id for land provinces.
+    source_id: Option<u32>,
+    name: String,
+    color: [u8; 3],
+}
+
+struct Adjacency {
+    from: u32,
+    to: u32,
+    kind: &'static str,
+    through: Option<u32>,
+    start: Option<(u32, u32)>,
+    stop: Option<(u32, u32)>,
+    comment: String,
+}
+
+/// Reads a finished pipeline output directory and writes a Paradox map mod into `out_dir`.
+pub fn export_from_dir(
+    planet_dir: &Path,
+    out_dir: &Path,
+    registry: &BiomeRegistry,
+    settings: &ParadoxExportSettings,
+) -> Result<ParadoxExportSummary, String> {
+    let (width, height) = pipeline::read_dimensions(&planet_dir.join("province_id.png"))?;
+    let landmask = pipeline::read_landmask(&planet_dir.join("landmask.png"))?;
+    let province_labels = pipeline::read_labels(&planet_dir.join("province_id.png"), &landmask)?;
+    let height_field = pipeline::read_height16(&planet_dir.join("height16.png"))?;
+    let river_mask = pipeline::read_mask_u8(&planet_dir.join("river_mask.png"))?;
+    let lake_path = planet_dir.join("lake_mask.png");
+    let lake_mask = if lake_path.exists() {
+        pipeline::read_mask_u8(&lake_path)?
+    } else {
+        vec![0; (width * height) as usize]
+    };
+    let sea_path = planet_dir.join("sea_zone_id.png");
+    let sea_zone_labels = if sea_path.exists() {
+        let water: Vec<bool> = landmask.iter().map(|&land| !land).collect();
+        Some(pipeline::read_labels(&sea_path, &water)?)
+    } else {
+        None
+    };
+    let rivers_path = planet_dir.join("rivers.json");
+    let rivers = if rivers_path.exists() {
+        pipeline::read_json::<RiverNetwork>(&rivers_path)?
+    } else {
+        RiverNetwork::default()
+    };
+    let provinces = pipeline::read_json::<Vec<ProvinceRecord>>(&planet_dir.join("provinces.json"))?;
+    let duchies = pipeline::read_json::<Vec<DuchyRecord>>(&planet_dir.join("duchies.json"))?;
+    let kingdoms = pipeline::read_json::<Vec<KingdomRecord>>(&planet_dir.join("kingdoms.json"))?;
+    let adjacency =
+        pipeline::read_json::<Vec<ProvinceAdjacency>>(&planet_dir.join("adjacency.json"))?;
+
+    let input = ParadoxMapInput {
+        width,
+        height,
+        landmask: &landmask,
+        province_labels: &province_labels,
+        height_field: &height_field,
+        river_mask: &river_mask,
+        lake_mask: &lake_mask,
+        sea_zone_labels: sea_zone_labels.as_deref(),
+        rivers: &rivers,
+        registry,
+        provinces: &provinces,
+        duchies: &duchies,
+        kingdoms: &kingdoms,
+        adjacency: &adjacency,
+    };
+    write_paradox_map(&input, settings, out_dir)
+}
+
+/// Writes `map_data/` (provinces.png, definition.csv, default.map, adjacencies.csv,
+/// heightmap.png, rivers.png) and `common/` (landed titles, province terrain).
+///
+/// Paradox maps need every pixel in a province, so lakes and ocean become provinces of their
+/// own. Lakes that would swallow a whole county are left as land.
+pub fn write_paradox_map(
+    input: &ParadoxMapInput,
+    settings: &ParadoxExportSettings,
+    out_dir: &Path,
+) -> Result<ParadoxExportSummary, String> {
+    let (width, height) = (input.width, input.height);
+    let n = (width * height) as usize;
+    let labels = input.province_labels;
+    let is_land = |i: usize| input.landmask[i] && labels[i] != NO_PROVINCE;
+
+    // Lakes, unless carving one would leave a county without pixels.
+    let (lake_components, lake_count) = components(width, height, u32::MAX, |i| {
+        is_land(i) && input.lake_mask[i] == 255
+    });
+    let mut dry_pixels: HashMap<u32, u32> = HashMap::new();
+    for i in (0..n).filter(|&i| is_land(i)) {
+        let count = dry_pixels.entry(labels[i]).or_insert(0);
+        if lake_components[i] == NO_PROVINCE {
+            *count += 1;
+        }
+    }
+    let mut kept_lakes = vec![true; lake_count];
+    for i in (0..n).filter(|&i| lake_components[i] != NO_PROVINCE) {
+        if dry_pixels.get(&labels[i]) == Some(&0) {
+            kept_lakes[lake_components[i] as usize] = false;
+        }
+    }
+    let in_lake =
+        |i: usize| lake_components[i] != NO_PROVINCE && kept_lakes[lake_components[i] as usize];
+
+    let sea_components = match input.sea_zone_labels {
+        Some(zones) => zones
+            .iter()
+            .map(|&z| z.checked_sub(SEA_ZONE_ID_BASE).unwrap_or(NO_PROVINCE))
+            .collect(),
+        None => {
+            components(width, height, settings.sea_tile_size, |i| {
+                !input.landmask[i]
+            })
+            .0
+        }
+    };
+    let (waste_components, _) = components(width, height, settings.sea_tile_size, |i| {
+        input.landmask[i] && labels[i] == NO_PROVINCE
+    });
+
+    // Paradox ids are contiguous from 1: land, then lakes, then wasteland, then sea.
+    let mut defs: Vec<ParadoxProvince> = Vec::new();
+    let mut used_colors: HashSet<[u8; 3]> = HashSet::new();
+    let mut push_province = |defs: &mut Vec<ParadoxProvince>,
+                             class: ProvinceClass,
+                             source_id: Option<u32>,
+                             name: String| {
+        let id = defs.len() as u32 + 1;
+        defs.push(ParadoxProvince {
+            class,
+            source_id,
+            name,
+            color: unique_color(id, &mut used_colors),
+        });
+        id
+    };
+
+    let mut land_ids: HashMap<u32, u32> = HashMap::new();
+    let mut sorted_provinces: Vec<&ProvinceRecord> = input.provinces.iter().collect();
+    sorted_provinces.sort_by_key(|p| p.id);
+    for province in sorted_provinces {
+        if !dry_pixels.contains_key(&province.id) {
+            continue;
+        }
+        let id = push_province(
+            &mut defs,
+            ProvinceClass::Land,
+            Some(province.id),
+            province.name.clone(),
+        );
+        land_ids.insert(province.id, id);
+    }
+    let mut component_ids = |defs: &mut Vec<ParadoxProvince>,
+                             component: &[u32],
+                             keep: &dyn Fn(u32) -> bool,
+                             class: ProvinceClass,
+                             prefix: &str| {
+        let mut ids: HashMap<u32, u32> = HashMap::new();
+        for &c in component.iter().filter(|&&c| c != NO_PROVINCE && keep(c)) {
+            if !ids.contains_key(&c) {
+                let name = format!("{}_{}", prefix, ids.len() + 1);
+                ids.insert(c, push_province(defs, class, None, name));
+            }
+        }
+        ids
+    };
+    let lake_ids = component_ids(
+        &mut defs,
+        &lake_components,
+        &|c| kept_lakes[c as usize],
+        ProvinceClass::Lake,
+        "lake",
+    );
+    let waste_ids = component_ids(
+        &mut defs,
+        &waste_components,
+        &|_| true,
+        ProvinceClass::Wasteland,
+        "wasteland",
+    );
+    let sea_ids = component_ids(
+        &mut defs,
+        &sea_components,
+        &|_| true,
+        ProvinceClass::Sea,
+        "sea",
+    );
+
+    // Id 0 is not a valid Paradox province, so labeled land must have a record to export.
+    if let Some(i) =
+        (0..n).find(|&i| is_land(i) && !in_lake(i) && !land_ids.contains_key(&labels[i]))
+    {
+        return Err(format!(
+            "Province {} has land pixels but no entry in provinces.json",
+            labels[i]
+        ));
+    }
+    let pdx: Vec<u32> = (0..n)
+        .map(|i| {
+            if in_lake(i) {
+                lake_ids[&lake_components[i]]
+            } else if is_land(i) {
+                land_ids[&labels[i]]
+            } else if input.landmask[i] {
+                waste_ids[&waste_components[i]]
+            } else {
+                sea_ids.get(&sea_components[i]).copied().unwrap_or(0)
+            }
+        })
+        .collect();
+
+    let map_dir = out_dir.join("map_data");
+    let titles_dir = out_dir.join("common").join("landed_titles");
+    let terrain_dir = out_dir.join("common").join("province_terrain");
+    for dir in [&map_dir, &titles_dir, &terrain_dir] {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let provinces_img: RgbImage = ImageBuffer::from_fn(width, height, |x, y| {
+        let id = pdx[(y * width + x) as usize];
+        let def = (id as usize).checked_sub(1).and_then(|k| defs.get(k));
+        Rgb(def.map_or([0, 0, 0], |d| d.color))
+    });
+    provinces_img
+        .save(map_dir.join("provinces.png"))
+        .map_err(|e| format!("Failed to save provinces.png: {}", e))?;
+
+    let mut definition = String::from("0;0;0;0;x;x;\n");
+    for (index, def) in defs.iter().enumerate() {
+        definition.push_str(&format!(
+            "{};{};{};{};{};x;\n",
+            index + 1,
+            def.color[0],
+            def.color[1],
+            def.color[2],
+            def.name.replace(';', "")
+        ));
+    }
+    write_text(&map_dir.join("definition.csv"), &definition)?;
+
+    write_text(&map_dir.join("default.map"), &default_map(&defs))?;
+
+    let heightmap: GrayImage = ImageBuffer::from_fn(width, height, |x, y| {
+        Luma([heightmap_value(
+            input.height_field[(y * width + x) as usize],
+        )])
+    });
+    heightmap
+        .save(map_dir.join("heightmap.png"))
+        .map_err(|e| format!("Failed to save heightmap.png: {}", e))?;
+
+    let water = |i: usize| !input.landmask[i] || in_lake(i);
+    write_rivers_png(input, &water, &map_dir.join("rivers.png"))?;
+
+    let mut adjacencies = river_crossings(input, &land_ids);
+    let river_crossing_count = adjacencies.len();
+    adjacencies.extend(straits(input, settings, &pdx, &defs));
+    let strait_count = adjacencies.len() - river_crossing_count;
+    write_text(
+        &map_dir.join("adjacencies.csv"),
+        &adjacencies_csv(&adjacencies, height),
+    )?;
+
+    write_text(
+        &titles_dir.join("00_landed_titles.txt"),
+        &landed_titles(input, &land_ids, &defs),
+    )?;
+    write_text(
+        &terrain_dir.join("00_province_terrain.txt"),
+        &province_terrain(input, &land_ids, &pdx),
+    )?;
+
+    let count = |class: ProvinceClass| defs.iter().filter(|d| d.class == class).count();
+    Ok(ParadoxExportSummary {
+        land_provinces: count(ProvinceClass::Land),
+        lake_provinces: count(ProvinceClass::Lake),
+        sea_provinces: count(ProvinceClass::Sea),
+        wasteland_provinces: count(ProvinceClass::Wasteland),
+        river_crossings: river_crossing_count,
+        straits: strait_count,
+    })
+}
+
+/// 4-connected components of `member`, split along a grid of `tile`-sized squares.
+fn components(
+    width: u32,
+    height: u32,
+    tile: u32,
+    member: impl Fn(usize) -> bool,
+) -> (Vec<u32>, usize) {
+    let n = (width * height) as usize;
+    let tile = tile.max(1);
+    let tile_of = |i: usize| ((i as u32 % width) / tile, (i as u32 / width) / tile);
+    let mut component = vec![NO_PROVINCE; n];
+    let mut count = 0usize;
+    for start in 0..n {
+        if component[start] != NO_PROVINCE || !member(start) {
+            continue;
+        }
+        let start_tile = tile_of(start);
+        component[start] = count as u32;
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let neighbors = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width as usize),
+                (y + 1 < height).then(|| i + width as usize),
+            ];
+            for ni in neighbors.into_iter().flatten() {
+                if component[ni] == NO_PROVINCE && member(ni) && tile_of(ni) == start_tile {
+                    component[ni] = count as u32;
+                    stack.push(ni);
+                }
+            }
+        }
+        count += 1;
+    }
+    (component, count)
+}
+
+/// Spreads ids over the color cube so neighboring ids do not get near-identical colors.
+fn unique_color(id: u32, used: &mut HashSet<[u8; 3]>) -> [u8; 3] {
+    let mut seed = id;
+    loop {
+        let mut hash = seed;
+        hash ^= hash >> 16;
+        hash = hash.wrapping_mul(0x85EB_CA6B);
+        hash ^= hash >> 13;
+        hash = hash.wrapping_mul(0xC2B2_AE35);
+        hash ^= hash >> 16;
+        let color = [(hash >> 16) as u8, (hash >> 8) as u8, hash as u8];
+        if color != [0, 0, 0] && used.insert(color) {
+            return color;
+        }
+        seed = seed.wrapping_add(0x9E37_79B9);
+    }
+}
+
+fn heightmap_value(h: u16) -> u8 {
+    if h < height::SEA_LEVEL {
+        (h as f32 / height::SEA_LEVEL as f32 * (SEA_LEVEL - 1) as f32) as u8
+    } else {
+        let land = (h - height::SEA_LEVEL) as f32 / (u16::MAX - height::SEA_LEVEL) as f32;
+        SEA_LEVEL + 1 + (land * (254 - SEA_LEVEL) as f32) as u8
+    }
+}
+
+fn default_map(defs: &[ParadoxProvince]) -> String {
+    let list = |class: ProvinceClass| {
+        let ids: Vec<String> = defs
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.class == class)
+            .map(|(index, _)| (index + 1).to_string())
+            .collect();
+        if ids.is_empty() {
+            "{ }".to_string()
+        } else {
+            format!("{{ {} }}", ids.join(" "))
+        }
+    };
+    format!(
+        "definitions = \"definition.csv\"\n\
+         provinces = \"provinces.png\"\n\
+         rivers = \"rivers.png\"\n\
+         topology = \"heightmap.png\"\n\
+         adjacencies = \"adjacencies.csv\"\n\
+         \n\
+         sea_zones = LIST {}\n\
+         lakes = LIST {}\n\
+         impassable_mountains = LIST {}\n",
+        list(ProvinceClass::Sea),
+        list(ProvinceClass::Lake),
+        list(ProvinceClass::Wasteland),
+    )
+}
+
+/// Draws the river network 4-connected and one pixel wide, with width indices from Strahler
+/// order, source markers at river heads and merge markers where tributaries join.
+fn write_rivers_png(
+    input: &ParadoxMapInput,
+    water: &dyn Fn(usize) -> bool,
+    path: &Path,
+) -> Result<(), String> {
+    let (width, height) = (input.width, input.height);
+    let n = (width * height) as usize;
+    let mut pixels: Vec<u8> = (0..n)
+        .map(|i| if water(i) { RIVER_WATER } else { RIVER_LAND })
+        .collect();
+
+    let network = input.rivers;
+    let mut segments: Vec<_> = network.segments.iter().collect();
+    segments.sort_by_key(|s| (s.strahler_order, s.discharge));
+    let mut cells_by_segment: HashMap<u32, Vec<usize>> = HashMap::new();
+    for segment in &segments {
+        let cells = rasterize_4(&segment.points, width, height);
+        let value = (RIVER_NARROWEST + segment.strahler_order.saturating_sub(1)).min(RIVER_WIDEST);
+        for &i in cells.iter().filter(|&&i| !water(i)) {
+            pixels[i] = value;
+        }
+        cells_by_segment.insert(segment.id, cells);
+    }
+
+    let node_kind: HashMap<u32, RiverNodeKind> = network
+        .nodes
+        .iter()
+        .map(|node| (node.id, node.kind))
+        .collect();
+    let mut tributaries: HashMap<u32, Vec<&RiverSegment>> = HashMap::new();
+    for segment in &segments {
+        match node_kind.get(&segment.from_node) {
+            Some(RiverNodeKind::Source) | Some(RiverNodeKind::LakeOutlet) => {
+                let cells = &cells_by_segment[&segment.id];
+                if let Some(&first) = cells.iter().find(|&&i| !water(i)) {
+                    pixels[first] = RIVER_SOURCE;
+                }
+            }
+            _ => {}
+        }
+        if node_kind.get(&segment.to_node) == Some(&RiverNodeKind::Confluence) {
+            tributaries
+                .entry(segment.to_node)
+                .or_default()
+                .push(segment);
+        }
+    }
+    // The strongest inflow continues as the main stem; the others merge into it.
+    for incoming in tributaries.values() {
+        for segment in incoming.iter().rev().skip(1) {
+            let cells = &cells_by_segment[&segment.id];
+            if cells.len() >= 2 {
+                let last = cells[cells.len() - 2];
+                if !water(last) {
+                    pixels[last] = RIVER_MERGE;
+                }
+            }
+        }
+    }
+
+    let mut palette = vec![0u8; 256 * 3];
+    let mut set = |index: u8, rgb: [u8; 3]| {
+        palette[index as usize * 3..index as usize * 3 + 3].copy_from_slice(&rgb)
+    };
+    set(RIVER_SOURCE, [0, 255, 0]);
+    set(RIVER_MERGE, [255, 0, 0]);
+    set(RIVER_SPLIT, [255, 252, 0]);
+    let blues = [
+        [0, 225, 255],
+        [0, 200, 255],
+        [0, 150, 255],
+        [0, 100, 255],
+        [0, 0, 255],
+        [0, 0, 225],
+        [0, 0, 200],
+        [0, 0, 150],
+        [0, 0, 100],
+    ];
+    for (offset, rgb) in blues.into_iter().enumerate() {
+        set(RIVER_NARROWEST + offset as u8, rgb);
+    }
+    set(RIVER_WATER, [255, 0, 128]);
+    set(RIVER_LAND, [255, 255, 255]);
+
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+/// Cells along a polyline, stepping one axis at a time so consecutive cells share an edge.
+/// Unwrapped x coordinates are folded back into the texture.
+fn rasterize_4(points: &[[f32; 2]], width: u32, height: u32) -> Vec<usize> {
+    let cell = |p: [f32; 2]| {
+        (
+            p[0].floor() as i64,
+            (p[1].floor() as i64).clamp(0, height as i64 - 1),
+        )
+    };
+    let mut cells: Vec<usize> = Vec::new();
+    let mut push = |x: i64, y: i64| {
+        let i = (y * width as i64 + x.rem_euclid(width as i64)) as usize;
+        if cells.last() != Some(&i) {
+            cells.push(i);
+        }
+    };
+    let Some(&first) = points.first() else {
+        return cells;
+    };
+    let (mut x, mut y) = cell(first);
+    push(x, y);
+    for &point in &points[1..] {
+        let (tx, ty) = cell(point);
+        while (x, y) != (tx, ty) {
+            if (tx - x).abs() >= (ty - y).abs() {
+                x += (tx - x).signum();
+            } else {
+                y += (ty - y).signum();
+            }
+            push(x, y);
+        }
+    }
+    cells
+}
+
+/// Land borders crossed by a river, located at the first river pixel on the border.
+fn river_crossings(input: &ParadoxMapInput, land_ids: &HashMap<u32, u32>) -> Vec<Adjacency> {
+    let (width, height) = (input.width, input.height);
+    let labels = input.province_labels;
+    let mut points: HashMap<(u32, u32), (u32, u32)> = HashMap::new();
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            let right = (x + 1 < width).then(|| i + 1);
+            let down = (y + 1 < height).then(|| i + width as usize);
+            let down_left = (x > 0 && y + 1 < height).then(|| i + width as usize - 1);
+            let down_right = (x + 1 < width && y + 1 < height).then(|| i + width as usize + 1);
+            for j in [right, down, down_left, down_right].into_iter().flatten() {
+                let (a, b) = (labels[i], labels[j]);
+                if a == b || a == NO_PROVINCE || b == NO_PROVINCE {
+                    continue;
+                }
+                if input.river_mask[i] > 0 || input.river_mask[j] > 0 {
+                    points.entry((a.min(b), a.max(b))).or_insert((x, y));
+                }
+            }
+        }
+    }
+
+    let mut crossings = Vec::new();
+    for entry in input.adjacency {
+        for edge in entry.neighbors.iter().filter(|e| e.crosses_river) {
+            if entry.province_id >= edge.neighbor_id {
+                continue;
+            }
+            let (Some(&from), Some(&to)) = (
+                land_ids.get(&entry.province_id),
+                land_ids.get(&edge.neighbor_id),
+            ) else {
+                continue;
+            };
+            let point = points.get(&(entry.province_id, edge.neighbor_id)).copied();
+            crossings.push(Adjacency {
+                from,
+                to,
+                kind: "river_large",
+                through: None,
+                start: point,
+                stop: point,
+                comment: "river crossing".to_string(),
+            });
+        }
+    }
+    crossings.sort_by_key(|a| (a.from, a.to));
+    crossings
+}
+
+/// Shortest open-water gap between land provinces that do not already share a border.
+fn straits(
+    input: &ParadoxMapInput,
+    settings: &ParadoxExportSettings,
+    pdx: &[u32],
+    defs: &[ParadoxProvince],
+) -> Vec<Adjacency> {
+    let (width, height) = (input.width as i64, input.height as i64);
+    let bordering: HashSet<(u32, u32)> = input
+        .adjacency
+        .iter()
+        .flat_map(|entry| {
+            entry
+                .neighbors
+                .iter()
+                .filter(|edge| edge.kind == EdgeKind::Land)
+                .map(move |edge| (entry.province_id, edge.neighbor_id))
+        })
+        .collect();
+    let is_sea = |i: usize| pdx[i] > 0 && defs[pdx[i] as usize - 1].class == ProvinceClass::Sea;
+    let land_of = |i: usize| {
+        let id = pdx[i];
+        (id > 0 && defs[id as usize - 1].class == ProvinceClass::Land).then_some(id)
+    };
+
+    // (from, to) -> (gap, start, stop, through)
+    let mut best: HashMap<(u32, u32), (i64, usize, usize, u32)> = HashMap::new();
+    let max_gap = settings.strait_max_width as i64;
+    for y in 0..height {
+        for x in 0..width {
+            let start = (y * width + x) as usize;
+            let Some(from) = land_of(start) else {
+                continue;
+            };
+            for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                let mut gap = 0;
+                let (mut cx, mut cy) = (x + dx, y + dy);
+                while cx >= 0 && cx < width && cy >= 0 && cy < height && gap <= max_gap {
+                    let i = (cy * width + cx) as usize;
+                    if is_sea(i) {
+                        gap += 1;
+                        cx += dx;
+                        cy += dy;
+                        continue;
+                    }
+                    if let Some(to) = land_of(i).filter(|&to| to != from && gap > 0) {
+                        let key = (from.min(to), from.max(to));
+                        let mid =
+                            ((y + dy * (gap + 1) / 2) * width + x + dx * (gap + 1) / 2) as usize;
+                        if best.get(&key).is_none_or(|b| gap < b.0) {
+                            best.insert(key, (gap, start, i, pdx[mid]));
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    let source_of = |id: u32| defs[id as usize - 1].source_id;
+    let mut straits: Vec<Adjacency> = best
+        .into_iter()
+        .filter(|((a, b), _)| match (source_of(*a), source_of(*b)) {
+            (Some(sa), Some(sb)) => !bordering.contains(&(sa, sb)),
+            _ => false,
+        })
+        .map(|((from, to), (gap, start, stop, through))| {
+            let point = |i: usize| (i as u32 % input.width, i as u32 / input.width);
+            let (start, stop) = if pdx[start] == from {
+                (start, stop)
+            } else {
+                (stop, start)
+            };
+            Adjacency {
+                from,
+                to,
+                kind: "sea",
+                through: Some(through),
+                start: Some(point(start)),
+                stop: Some(point(stop)),
+                comment: format!("strait ({} px)", gap),
+            }
+        })
+        .collect();
+    straits.sort_by_key(|a| (a.from, a.to));
+    straits
+}
+
+/// Paradox map coordinates put the origin in the bottom-left corner.
+fn adjacencies_csv(adjacencies: &[Adjacency], height: u32) -> String {
+    let mut csv = String::from("From;To;Type;Through;start_x;start_y;stop_x;stop_y;Comment\n");
+    let coords = |point: Option<(u32, u32)>| match point {
+        Some((x, y)) => format!("{};{}", x, height - 1 - y),
+        None => "-1;-1".to_string(),
+    };
+    for adjacency in adjacencies {
+        csv.push_str(&format!(
+            "{};{};{};{};{};{};{}\n",
+            adjacency.from,
+            adjacency.to,
+            adjacency.kind,
+            adjacency.through.map_or(-1, |t| t as i64),
+            coords(adjacency.start),
+            coords(adjacency.stop),
+            adjacency.comment
+        ));
+    }
+    csv.push_str("-1;-1;;-1;-1;-1;-1;-1;\n");
+    csv
+}
+
+/// Kingdom → duchy → county → barony, with one county and one barony per land province.
+fn landed_titles(
+    input: &ParadoxMapInput,
+    land_ids: &HashMap<u32, u32>,
+    defs: &[ParadoxProvince],
+) -> String {
+    let provinces: HashMap<u32, &ProvinceRecord> =
+        input.provinces.iter().map(|p| (p.id, p)).collect();
+    let duchies: HashMap<u32, &DuchyRecord> = input.duchies.iter().map(|d| (d.id, d)).collect();
+    let mut keys = TitleKeys::default();
+    let color = |c: [u8; 3]| format!("{{ {} {} {} }}", c[0], c[1], c[2]);
+    let mut used_colors: HashSet<[u8; 3]> = defs.iter().map(|d| d.color).collect();
+
+    let mut out = String::from("\u{feff}");
+    for kingdom in input.kingdoms {
+        let mut kingdom_body = String::new();
+        let mut kingdom_capital: Option<String> = None;
+        for duchy in kingdom.duchy_ids.iter().filter_map(|id| duchies.get(id)) {
+            let mut duchy_body = String::new();
+            let mut duchy_capital: Option<String> = None;
+            for province in duchy.province_ids.iter().filter_map(|id| provinces.get(id)) {
+                let Some(&pdx_id) = land_ids.get(&province.id) else {
+                    continue;
+                };
+                let county = keys.key("c", &province.name, province.id);
+                let barony = keys.key("b", &province.name, province.id);
+                let county_color = color(defs[pdx_id as usize - 1].color);
+                duchy_body.push_str(&format!(
+                    "\t\t{} = {{\n\t\t\tcolor = {}\n\t\t\t{} = {{\n\t\t\t\tprovince = {}\n\t\t\t\tcolor = {}\n\t\t\t}}\n\t\t}}\n",
+                    county, county_color, barony, pdx_id, county_color
+                ));
+                duchy_capital.get_or_insert(county);
+            }
+            let Some(capital) = duchy_capital else {
+                continue;
+            };
+            let duchy_key = keys.key("d", &duchy.name, duchy.id);
+            kingdom_body.push_str(&format!(
+                "\t{} = {{\n\t\tcolor = {}\n\t\tcapital = {}\n{}\t}}\n",
+                duchy_key,
+                color(unique_color(duchy.id | 1 << 24, &mut used_colors)),
+                capital,
+                duchy_body
+            ));
+            kingdom_capital.get_or_insert(capital);
+        }
+        let Some(capital) = kingdom_capital else {
+            continue;
+        };
+        out.push_str(&format!(
+            "{} = {{\n\tcolor = {}\n\tcapital = {}\n{}}}\n\n",
+            keys.key("k", &kingdom.name, kingdom.id),
+            color(unique_color(kingdom.id | 2 << 24, &mut used_colors)),
+            capital,
+            kingdom_body
+        ));
+    }
+    out
+}
+
+/// Title keys from names, made unique with the record id when two names collide.
+#[derive(Default)]
+struct TitleKeys {
+    used: HashSet<String>,
+}
+
+impl TitleKeys {
+    fn key(&mut self, tier: &str, name: &str, id: u32) -> String {
+        let mut slug = String::new();
+        for c in name.chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.ends_with('_') {
+                slug.push('_');
+            }
+        }
+        let slug = slug.trim_matches('_');
+        let mut key = if slug.is_empty() {
+            format!("{}_{}", tier, id)
+        } else {
+            format!("{}_{}", tier, slug)
+        };
+        if !self.used.insert(key.clone()) {
+            key = format!("{}_{}", key, id);
+            self.used.insert(key.clone());
+        }
+        key
+    }
+}
+
+/// Terrain from each province's primary biome, raised to hills or mountains for the highest
+/// provinces and to floodplains for river valleys through dry land.
+fn province_terrain(input: &ParadoxMapInput, land_ids: &HashMap<u32, u32>, pdx: &[u32]) -> String {
+    let mut height_sum: HashMap<u32, (f64, u32, bool)> = HashMap::new();
+    for (i, &id) in pdx.iter().enumerate() {
+        if id == 0 || !input.landmask[i] {
+            continue;
+        }
+        let entry = height_sum.entry(id).or_insert((0.0, 0, false));
+        entry.0 += input.height_field[i] as f64;
+        entry.1 += 1;
+        entry.2 |= input.river_mask[i] > 0;
+    }
+    let mut mean_heights: Vec<f64> = height_sum
+        .values()
+        .map(|&(sum, count, _)| sum / count.max(1) as f64)
+        .collect();
+    mean_heights.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |p: f64| {
+        mean_heights
+            .get(
+                ((mean_heights.len() as f64 * p) as usize)
+                    .min(mean_heights.len().saturating_sub(1)),
+            )
+            .copied()
+            .unwrap_or(f64::MAX)
+    };
+    let (hills, mountains) = (percentile(0.75), percentile(0.9));
+
+    let mut rows: Vec<(u32, &str)> = Vec::new();
+    for province in input.provinces {
+        let Some(&id) = land_ids.get(&province.id) else {
+            continue;
+        };
+        let base = input
+            .registry
+            .archetypes
+            .get(province.biome_primary as usize)
+            .map(|a| terrain_for_biome(&a.id))
+            .unwrap_or("plains");
+        let (sum, count, has_river) = height_sum.get(&id).copied().unwrap_or((0.0, 1, false));
+        let mean = sum / count.max(1) as f64;
+        let dry = matches!(base, "desert" | "drylands");
+        let terrain = if mean >= mountains {
+            if dry {
+                "desert_mountains"
+            } else {
+                "mountains"
+            }
+        } else if mean >= hills && !matches!(base, "wetlands" | "jungle") {
+            "hills"
+        } else if dry && has_river {
+            "floodplains"
+        } else {
+            base
+        };
+        rows.push((id, terrain));
+    }
+    rows.sort_by_key(|r| r.0);
+
+    let mut out = String::from("\u{feff}default = plains\n");
+    for (id, terrain) in rows {
+        out.push_str(&format!("{} = {}\n", id, terrain));
+    }
+    out
+}
+
+/// Maps archetype ids onto Paradox terrain types by keyword, so custom registries still land
+/// on a sensible terrain.
+fn terrain_for_biome(archetype_id: &str) -> &'static str {
+    const KEYWORDS: [(&str, &str); 16] = [
+        ("rainforest", "jungle"),
+        ("jungle", "jungle"),
+        ("monsoon", "jungle"),
+        ("mangrove", "wetlands"),
+        ("marsh", "wetlands"),
+        ("swamp", "wetlands"),
+        ("taiga", "taiga"),
+        ("boreal", "taiga"),
+        ("tundra", "taiga"),
+        ("forest", "forest"),
+        ("desert", "desert"),
+        ("savanna", "drylands"),
+        ("scrub", "drylands"),
+        ("matorral", "drylands"),
+        ("steppe", "steppe"),
+        ("grassland", "steppe"),
+    ];
+    let id = archetype_id.to_ascii_lowercase();
+    if ["alpine", "volcanic", "ash", "ice", "rock"]
+        .iter()
+        .any(|k| id.contains(k))
+    {
+        return "mountains";
+    }
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| id.contains(keyword))
+        .map(|&(_, terrain)| terrain)
+        .unwrap_or("plains")
+}
+
+fn write_text(path: &Path, text: &str) -> Result<(), String> {
+    std::fs::write(path, text).map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MapProjection;
+    use crate::graph::build_adjacency;
+    use crate::sea::{self, SeaZoneInput, SeaZoneSettings};
+    use std::path::PathBuf;
+
+    /// An output directory unique to this test process, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("worldgen-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            Self(path)
+        }
+
+        fn read(&self, file: &str) -> String {
+            std::fs::read_to_string(self.0.join("map_data").join(file)).unwrap()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Rasters of a map where every row reads `row`: digits are land provinces, `~` is sea and
+    /// `r` marks a river on the land province to its left.
+    struct Map {
+        width: u32,
+        height: u32,
+        landmask: Vec<bool>,
+        labels: Vec<u32>,
+        height_field: Vec<u16>,
+        river_mask: Vec<u8>,
+        lake_mask: Vec<u8>,
+    }
+
+    impl Map {
+        fn new(row: &str, height: u32) -> Self {
+            let mut current = NO_PROVINCE;
+            let columns: Vec<(u32, bool)> = row
+                .chars()
+                .map(|c| {
+                    match c {
+                        '~' => current = NO_PROVINCE,
+                        'r' => return (current, true),
+                        digit => current = digit.to_digit(10).unwrap(),
+                    }
+                    (current, false)
+                })
+                .collect();
+            let cells = || (0..height).flat_map(|_| columns.iter().copied());
+            let labels: Vec<u32> = cells().map(|(label, _)| label).collect();
+            Self {
+                width: columns.len() as u32,
+                height,
+                landmask: labels.iter().map(|&l| l != NO_PROVINCE).collect(),
+                height_field: labels
+                    .iter()
+                    .map(|&l| {
+                        if l == NO_PROVINCE {
+                            height::SEA_LEVEL / 2
+                        } else {
+                            height::SEA_LEVEL + 5000
+                        }
+                    })
+                    .collect(),
+                river_mask: cells().map(|(_, river)| u8::from(river) * 255).collect(),
+                lake_mask: vec![0; labels.len()],
+                labels,
+            }
+        }
+
+        fn adjacency(&self) -> Vec<ProvinceAdjacency> {
+            build_adjacency(
+                &self.labels,
+                &self.height_field,
+                &self.river_mask,
+                self.width,
+                self.height,
+                MapProjection::Flat,
+                &mut |_, _| {},
+            )
+        }
+    }
+
+    fn province(id: u32) -> ProvinceRecord {
+        ProvinceRecord {
+            id,
+            seed_x: 0,
+            seed_y: 0,
+            area: 1,
+            duchy_id: 0,
+            kingdom_id: 0,
+            biome_primary: 0,
+            biome_primary_id: None,
+            biome_confidence: None,
+            biome_candidate_ids: Vec::new(),
+            name: format!("Province {}", id + 1),
+            wealth: None,
+            development: None,
+            population: None,
+        }
+    }
+
+    fn export(
+        map: &Map,
+        provinces: &[ProvinceRecord],
+        adjacency: &[ProvinceAdjacency],
+        sea_zone_labels: Option<&[u32]>,
+        out: &TempDir,
+    ) -> Result<ParadoxExportSummary, String> {
+        let province_ids: Vec<u32> = provinces.iter().map(|p| p.id).collect();
+        let duchies = [DuchyRecord {
+            id: 0,
+            province_ids,
+            kingdom_id: 0,
+            name: "Duchy 1".to_string(),
+        }];
+        let kingdoms = [KingdomRecord {
+            id: 0,
+            duchy_ids: vec![0],
+            name: "Kingdom 1".to_string(),
+        }];
+        let input = ParadoxMapInput {
+            width: map.width,
+            height: map.height,
+            landmask: &map.landmask,
+            province_labels: &map.labels,
+            height_field: &map.height_field,
+            river_mask: &map.river_mask,
+            lake_mask: &map.lake_mask,
+            sea_zone_labels,
+            rivers: &RiverNetwork::default(),
+            registry: &BiomeRegistry::default_registry(),
+            provinces,
+            duchies: &duchies,
+            kingdoms: &kingdoms,
+            adjacency,
+        };
+        write_paradox_map(&input, &ParadoxExportSettings::default(), &out.0)
+    }
+
+    #[test]
+    fn definitions_list_land_then_sea_and_rivers_become_crossings() {
+        let map = Map::new("000r11~~~~", 6);
+        let out = TempDir::new("paradox-definitions");
+        let summary = export(
+            &map,
+            &[province(0), province(1)],
+            &map.adjacency(),
+            None,
+            &out,
+        )
+        .unwrap();
+
+        assert_eq!(summary.land_provinces, 2);
+        assert_eq!(summary.sea_provinces, 1);
+        assert_eq!(summary.river_crossings, 1);
+        assert_eq!(summary.straits, 0);
+
+        let definition = out.read("definition.csv");
+        let rows: Vec<Vec<&str>> = definition
+            .lines()
+            .map(|line| line.split(';').collect())
+            .collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], ["0", "0", "0", "0", "x", "x", ""]);
+        assert_eq!((rows[1][0], rows[1][4]), ("1", "Province 1"));
+        assert_eq!((rows[2][0], rows[2][4]), ("2", "Province 2"));
+        assert_eq!((rows[3][0], rows[3][4]), ("3", "sea_1"));
+        let colors: HashSet<&[&str]> = rows[1..].iter().map(|row| &row[1..4]).collect();
+        assert_eq!(colors.len(), 3);
+
+        let adjacencies = out.read("adjacencies.csv");
+        let lines: Vec<&str> = adjacencies.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("From;To;Type;Through;"));
+        assert!(lines[1].starts_with("1;2;river_large;-1;"));
+        assert_eq!(lines[2], "-1;-1;;-1;-1;-1;-1;-1;");
+    }
+
+    #[test]
+    fn land_without_a_province_record_is_an_error() {
+        let map = Map::new("000111~~~~", 4);
+        let out = TempDir::new("paradox-unknown-province");
+        let error = export(&map, &[province(0)], &map.adjacency(), None, &out).unwrap_err();
+
+        assert!(error.contains("Province 1"), "{error}");
+    }
+
+    #[test]
+    fn straits_tagged_by_sea_zones_reach_adjacencies_csv() {
+        let map = Map::new("000~~111~~~~", 6);
+        let mut adjacency = map.adjacency();
+        let registry = BiomeRegistry::default_registry();
+        let sea_input = SeaZoneInput {
+            width: map.width,
+            height: map.height,
+            landmask: &map.landmask,
+            province_labels: &map.labels,
+            biome_indices: &vec![0; map.labels.len()],
+            registry: &registry,
+        };
+        let settings = SeaZoneSettings {
+            target_count: 2,
+            strait_max_width: 4,
+            projection: MapProjection::Flat,
+        };
+        let zones = sea::partition_sea_zones(&sea_input, &settings, &mut |_, _| {});
+        sea::add_naval_edges(
+            &mut adjacency,
+            &map.labels,
+            &zones.labels,
+            &zones.straits,
+            map.width,
+            map.height,
+            MapProjection::Flat,
+        );
+        assert!(adjacency
+            .iter()
+            .flat_map(|entry| &entry.neighbors)
+            .any(|edge| edge.kind == EdgeKind::Strait));
+
+        let out = TempDir::new("paradox-straits");
+        let summary = export(
+            &map,
+            &[province(0), province(1)],
+            &adjacency,
+            Some(&zones.labels),
+            &out,
+        )
+        .unwrap();
+
+        assert_eq!(summary.straits, 1);
+        let adjacencies = out.read("adjacencies.csv");
+        let strait = adjacencies
+            .lines()
+            .find(|line| line.split(';').nth(2) == Some("sea"))
+            .expect("strait row");
+        assert!(strait.starts_with("1;2;sea;"), "{strait}");
+        assert!(strait.ends_with("strait (2 px)"), "{strait}");
+    }
+}
@@ -1,4 +1,5 @@
-use crate::raster::neighbors8;
+use crate::config::{MapProjection, WorldgenConfig};
+use crate::raster::{neighbors8_in, step_length};
 use crate::sampling::Seed;
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
@@ -35,21 +36,59 @@ impl PartialOrd for DijkstraEntry {
     }
 }
 
+/// Rasters provinces are grown over, from the seeds.
+pub struct PartitionInput<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub seeds: &'a [Seed],
+    pub height_field: &'a [u16],
+    pub landmask: &'a [bool],
+    pub river_mask: &'a [u8],
+}
+
+/// Crossing costs for province growth.
+#[derive(Debug, Clone, Copy)]
+pub struct PartitionSettings {
+    pub cost_slope: f64,
+    pub cost_river: f64,
+    pub cost_ridge: f64,
+    pub projection: MapProjection,
+}
+
+impl PartitionSettings {
+    pub fn for_config(config: &WorldgenConfig) -> Self {
+        Self {
+            cost_slope: config.cost_slope,
+            cost_river: config.cost_river_crossing,
+            cost_ridge: config.cost_ridge_crossing,
+            projection: config.projection,
+        }
+    }
+}
+
 /// Stage 8: Province growth via multi-source Dijkstra.
 /// Labels every land pixel with a province ID.
 /// Cost function considers slope, river crossings, and ridge crossings.
+/// Step lengths follow the projection, so polar steps across longitude are cheap.
 pub fn grow_provinces(
-    seeds: &[Seed],
-    height: &[u16],
-    landmask: &[bool],
-    river_mask: &[u8],
-    width: u32,
-    height_dim: u32,
-    cost_slope: f64,
-    cost_river: f64,
-    cost_ridge: f64,
+    input: &PartitionInput,
+    settings: &PartitionSettings,
     on_progress: &mut dyn FnMut(f32, &str),
 ) -> Vec<u32> {
+    let PartitionInput {
+        width,
+        height: height_dim,
+        seeds,
+        height_field: height,
+        landmask,
+        river_mask,
+    } = *input;
+    let PartitionSettings {
+        cost_slope,
+        cost_river,
+        cost_ridge,
+        projection,
+    } = *settings;
     let n = (width * height_dim) as usize;
     let no_label = u32::MAX;
     let mut labels = vec![no_label; n];
@@ -88,7 +127,7 @@ pub fn grow_provinces(
         let x = (entry.index % width as usize) as u32;
         let y = (entry.index / width as usize) as u32;
 
-        let neighbors = neighbors8(x, y, width, height_dim);
+        let neighbors = neighbors8_in(x, y, width, height_dim, projection);
 
         for (nx, ny, ni) in neighbors {
             if !landmask[ni] {
@@ -116,13 +155,12 @@ pub fn grow_provinces(
                 0.0
             };
 
-            // Diagonal movement costs sqrt(2)
-            let dx = (nx as i32 - x as i32).abs();
-            let dy = (ny as i32 - y as i32).abs();
-            let diag_mul = if dx > 0 && dy > 0 { 1.414 } else { 1.0 };
+            // Diagonal movement costs sqrt(2), horizontal steps shrink toward the poles
+            let dx = nx as i32 - x as i32;
+            let dx = if dx.abs() > 1 { -dx.signum() } else { dx };
+            let step = step_length(projection, y, dx, ny as i32 - y as i32, height_dim);
 
-            let total_cost =
-                entry.cost + (base_cost + slope_cost + river_cost + ridge_cost) * diag_mul;
+            let total_cost = entry.cost + (base_cost + slope_cost + river_cost + ridge_cost) * step;
 
             if total_cost < costs[ni] {
                 costs[ni] = total_cost;
@@ -136,7 +174,7 @@ pub fn grow_provinces(
                 });
 
                 // Progress update
-                if labeled_count % 10000 == 0 {
+                if labeled_count.is_multiple_of(10000) {
                     let progress = 5.0 + (labeled_count as f32 / total_land as f32) * 90.0;
                     if progress - last_progress > 2.0 {
                         on_progress(progress, "Growing provinces");
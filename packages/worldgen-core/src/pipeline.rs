@@ -0,0 +1,1086 @@
+use crate::biome::{self, BiomeModelSettings, BiomeVisionAnalysis};
+use crate::biome_archetype::BiomeRegistry;
+use crate::config::WorldgenConfig;
+use crate::export::{self, PipelineStatus};
+use crate::graph::ProvinceAdjacency;
+use crate::river_network::RiverNetwork;
+use crate::sampling::Seed;
+use crate::sea::{self, StraitRecord};
+use crate::{
+    cluster, graph, height, hydrology, landmask, naming, normalize, partition, postprocess,
+    river_network, roads, sampling, suitability,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Status file kept next to the stage artifacts.
+pub const STATUS_FILE: &str = "pipeline_status.json";
+
+/// A pipeline stage. Stages are wired into a DAG through the artifacts they read and write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Landmask,
+    Normalize,
+    Height,
+    Rivers,
+    Biome,
+    Suitability,
+    Seeds,
+    Partition,
+    Postprocess,
+    SeaZones,
+    Adjacency,
+    Clustering,
+    Naming,
+    Roads,
+}
+
+impl Stage {
+    /// Every stage in dependency order.
+    pub const ALL: [Stage; 14] = [
+        Stage::Landmask,
+        Stage::Normalize,
+        Stage::Height,
+        Stage::Rivers,
+        Stage::Biome,
+        Stage::Suitability,
+        Stage::Seeds,
+        Stage::Partition,
+        Stage::Postprocess,
+        Stage::SeaZones,
+        Stage::Adjacency,
+        Stage::Clustering,
+        Stage::Naming,
+        Stage::Roads,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Stage::Landmask => "landmask",
+            Stage::Normalize => "normalize",
+            Stage::Height => "height",
+            Stage::Rivers => "rivers",
+            Stage::Biome => "biome",
+            Stage::Suitability => "suitability",
+            Stage::Seeds => "seeds",
+            Stage::Partition => "partition",
+            Stage::Postprocess => "postprocess",
+            Stage::SeaZones => "sea_zones",
+            Stage::Adjacency => "adjacency",
+            Stage::Clustering => "clustering",
+            Stage::Naming => "naming",
+            Stage::Roads => "roads",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Stage> {
+        Stage::ALL.into_iter().find(|stage| stage.id() == id)
+    }
+
+    /// Whether the stage reads the planet's base texture.
+    pub fn reads_base_image(self) -> bool {
+        matches!(self, Stage::Landmask | Stage::Normalize | Stage::Biome)
+    }
+
+    /// Artifacts read from the output directory.
+    pub fn inputs(self) -> &'static [&'static str] {
+        match self {
+            Stage::Landmask => &[],
+            Stage::Normalize => &["landmask.png"],
+            Stage::Height => &["albedo_flat.png", "landmask.png"],
+            Stage::Rivers => &["landmask.png", "height16.png"],
+            Stage::Biome => &["landmask.png", "height16.png", "river_mask.png"],
+            Stage::Suitability => &[
+                "landmask.png",
+                "height16.png",
+                "river_mask.png",
+                "biome.png",
+            ],
+            Stage::Seeds => &["landmask.png", "suitability.bin"],
+            Stage::Partition => &[
+                "landmask.png",
+                "height16.png",
+                "river_mask.png",
+                "seeds.json",
+            ],
+            Stage::Postprocess => &["landmask.png", "province_id_raw.png"],
+            Stage::SeaZones => &["landmask.png", "biome.png", "province_id.png"],
+            Stage::Adjacency => &[
+                "landmask.png",
+                "province_id.png",
+                "height16.png",
+                "river_mask.png",
+                "sea_zone_id.png",
+                "straits.json",
+            ],
+            Stage::Clustering => &[
+                "landmask.png",
+                "province_id.png",
+                "biome.png",
+                "suitability.bin",
+                "seeds.json",
+                "adjacency.json",
+                cluster::HIERARCHY_LOCKS_FILE,
+            ],
+            Stage::Naming => &[
+                "landmask.png",
+                "province_id.png",
+                "height16.png",
+                "river_mask.png",
+                "duchy_id.png",
+                "kingdom_id.png",
+            ],
+            Stage::Roads => &[
+                "landmask.png",
+                "height16.png",
+                "river_mask.png",
+                "rivers.json",
+                "biome.png",
+                "province_id.png",
+                "duchy_id.png",
+                "seeds.json",
+                "adjacency.json",
+                roads::ROAD_WAYPOINTS_FILE,
+            ],
+        }
+    }
+
+    /// Artifacts written to the output directory. Runners may write more, but these are the
+    /// ones downstream stages depend on.
+    pub fn outputs(self) -> &'static [&'static str] {
+        match self {
+            Stage::Landmask => &["landmask.png"],
+            Stage::Normalize => &["albedo_flat.png"],
+            Stage::Height => &["height16.png"],
+            Stage::Rivers => &[
+                "river_mask.png",
+                "lake_mask.png",
+                "lakes.json",
+                "rivers.json",
+                "rivers.geojson",
+            ],
+            Stage::Biome => &["biome.png", "biome_confidence.png"],
+            Stage::Suitability => &["suitability.bin"],
+            Stage::Seeds => &["seeds.json"],
+            Stage::Partition => &["province_id_raw.png"],
+            Stage::Postprocess => &["province_id.png"],
+            Stage::SeaZones => &["sea_zone_id.png", "sea_zones.json", "straits.json"],
+            Stage::Adjacency => &["adjacency.json"],
+            Stage::Clustering => &[
+                "duchy_id.png",
+                "kingdom_id.png",
+                "provinces.json",
+                "duchies.json",
+                "kingdoms.json",
+            ],
+            Stage::Naming => &["names.json"],
+            Stage::Roads => &["roads.json", "roads.geojson", "road_mask.png"],
+        }
+    }
+
+    /// `WorldgenConfig` fields (camelCase) the stage reads.
+    pub fn config_keys(self) -> &'static [&'static str] {
+        match self {
+            Stage::Landmask => &[
+                "waterHue",
+                "waterHueTolerance",
+                "waterSatMin",
+                "waterValMin",
+            ],
+            Stage::Normalize | Stage::Suitability => &[],
+            Stage::Height | Stage::Naming => &["seed"],
+            Stage::Rivers => &["riverThreshold", "projection"],
+            Stage::Biome => &["colorBasedBiomes"],
+            Stage::Seeds => &[
+                "counties",
+                "seedRadiusMin",
+                "seedRadiusMax",
+                "seed",
+                "projection",
+            ],
+            Stage::Partition => &[
+                "costSlope",
+                "costRiverCrossing",
+                "costRidgeCrossing",
+                "projection",
+            ],
+            Stage::Postprocess => &["minCountyArea", "smoothIterations", "projection"],
+            Stage::SeaZones => &["seaZones", "straitMaxWidth", "projection"],
+            Stage::Adjacency => &["projection"],
+            Stage::Clustering => &[
+                "duchySizeMin",
+                "duchySizeMax",
+                "kingdomSizeMin",
+                "kingdomSizeMax",
+                "clusterBalance",
+                "projection",
+            ],
+            Stage::Roads => &["costSlope", "costRiverCrossing", "projection"],
+        }
+    }
+
+    /// Upstream artifacts the stage edits in place. They are left out of `inputs` so the edit
+    /// does not make the stage stale, and re-running their producer always marks it stale.
+    pub fn rewrites(self) -> &'static [&'static str] {
+        match self {
+            Stage::Naming => &["provinces.json", "duchies.json", "kingdoms.json"],
+            _ => &[],
+        }
+    }
+
+    /// Stages producing this stage's inputs.
+    pub fn depends_on(self) -> Vec<Stage> {
+        Stage::ALL
+            .into_iter()
+            .filter(|producer| {
+                producer
+                    .outputs()
+                    .iter()
+                    .any(|artifact| self.inputs().contains(artifact))
+            })
+            .collect()
+    }
+
+    /// Every stage that transitively consumes this stage's outputs, in dependency order.
+    pub fn downstream(self) -> Vec<Stage> {
+        let mut affected = vec![self];
+        for stage in Stage::ALL {
+            if stage
+                .depends_on()
+                .iter()
+                .any(|dependency| affected.contains(dependency))
+            {
+                affected.push(stage);
+            }
+        }
+        affected.remove(0);
+        affected
+    }
+}
+
+/// Paths and config a stage runs against.
+pub struct StageContext<'a> {
+    pub base_image: &'a Path,
+    pub out_dir: &'a Path,
+    pub config: &'a WorldgenConfig,
+}
+
+/// Executes individual stages. The pipeline decides which stages run; runners only do the work.
+pub trait StageRunner {
+    fn run_stage(
+        &mut self,
+        stage: Stage,
+        ctx: &StageContext,
+        on_progress: &mut dyn FnMut(f32, &str),
+    ) -> Result<(), String>;
+
+    /// Inputs outside the artifact graph that affect a stage, such as a custom biome registry.
+    fn extra_fingerprint(&self, _stage: Stage) -> String {
+        String::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageOutcome {
+    Ran,
+    UpToDate,
+}
+
+/// Incremental pipeline over one output directory.
+pub struct Pipeline<'a> {
+    ctx: StageContext<'a>,
+    status: PipelineStatus,
+    file_hashes: HashMap<PathBuf, String>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn open(base_image: &'a Path, out_dir: &'a Path, config: &'a WorldgenConfig) -> Self {
+        Self {
+            ctx: StageContext {
+                base_image,
+                out_dir,
+                config,
+            },
+            status: PipelineStatus::load(&out_dir.join(STATUS_FILE)),
+            file_hashes: HashMap::new(),
+        }
+    }
+
+    pub fn status(&self) -> &PipelineStatus {
+        &self.status
+    }
+
+    /// Hash of everything a stage reads: its input artifacts, the base image, its config fields
+    /// and the runner's extra fingerprint.
+    pub fn input_hash(&mut self, stage: Stage, runner: &dyn StageRunner) -> String {
+        let config = serde_json::to_value(self.ctx.config).unwrap_or_default();
+        let mut hasher = Fnv64::new();
+        hasher.write(stage.id().as_bytes());
+        for key in stage.config_keys() {
+            hasher.write(key.as_bytes());
+            hasher.write(
+                config
+                    .get(*key)
+                    .map(|v| v.to_string())
+                    .unwrap_or_default()
+                    .as_bytes(),
+            );
+        }
+        if stage.reads_base_image() {
+            let hash = self.file_hash(&self.ctx.base_image.to_path_buf());
+            hasher.write(hash.as_bytes());
+        }
+        for artifact in stage.inputs() {
+            let hash = self.file_hash(&self.ctx.out_dir.join(artifact));
+            hasher.write(artifact.as_bytes());
+            hasher.write(hash.as_bytes());
+        }
+        hasher.write(runner.extra_fingerprint(stage).as_bytes());
+        hasher.finish_hex()
+    }
+
+    /// A stage is up to date when it completed against the current inputs and its outputs still
+    /// exist. Outputs edited after the fact are kept rather than regenerated.
+    pub fn is_up_to_date(&mut self, stage: Stage, runner: &dyn StageRunner) -> bool {
+        let recorded = match self.status.stages.get(stage.id()) {
+            Some(record) if record.completed => record.input_hash.clone(),
+            _ => return false,
+        };
+        let outputs_exist = stage
+            .outputs()
+            .iter()
+            .all(|artifact| self.ctx.out_dir.join(artifact).exists());
+        outputs_exist && recorded == Some(self.input_hash(stage, runner))
+    }
+
+    /// Stages that would re-run if the whole pipeline were requested.
+    pub fn stale_stages(&mut self, runner: &dyn StageRunner) -> Vec<Stage> {
+        Stage::ALL
+            .into_iter()
+            .filter(|&stage| !self.is_up_to_date(stage, runner))
+            .collect()
+    }
+
+    /// Brings `targets` up to date, running stale dependencies first. With `force`, the targets
+    /// themselves always run. When a stage's outputs change, every downstream stage is marked
+    /// stale in the status file.
+    pub fn run(
+        &mut self,
+        targets: &[Stage],
+        force: bool,
+        runner: &mut dyn StageRunner,
+        on_progress: &mut dyn FnMut(Stage, f32, &str),
+    ) -> Result<Vec<(Stage, StageOutcome)>, String> {
+        let mut wanted: Vec<Stage> = targets.to_vec();
+        let mut index = 0;
+        while index < wanted.len() {
+            for dependency in wanted[index].depends_on() {
+                if !wanted.contains(&dependency) {
+                    wanted.push(dependency);
+                }
+            }
+            index += 1;
+        }
+
+        let mut outcomes = Vec::new();
+        for stage in Stage::ALL
+            .into_iter()
+            .filter(|stage| wanted.contains(stage))
+        {
+            let forced = force && targets.contains(&stage);
+            if !forced && self.is_up_to_date(stage, runner) {
+                outcomes.push((stage, StageOutcome::UpToDate));
+                continue;
+            }
+
+            self.run_stage(stage, runner, &mut |pct, msg| on_progress(stage, pct, msg))?;
+            outcomes.push((stage, StageOutcome::Ran));
+        }
+        Ok(outcomes)
+    }
+
+    /// Runs one stage unconditionally and records its hashes. If its outputs differ from the
+    /// last recorded run, downstream stages are marked stale.
+    pub fn run_stage(
+        &mut self,
+        stage: Stage,
+        runner: &mut dyn StageRunner,
+        on_progress: &mut dyn FnMut(f32, &str),
+    ) -> Result<(), String> {
+        let input_hash = self.input_hash(stage, runner);
+        runner.run_stage(stage, &self.ctx, on_progress)?;
+
+        for artifact in stage.outputs() {
+            self.file_hashes.remove(&self.ctx.out_dir.join(artifact));
+        }
+        let output_hash = self.output_hash(stage);
+        let previous = self
+            .status
+            .stages
+            .get(stage.id())
+            .and_then(|record| record.output_hash.clone());
+        let changed = previous.as_deref() != Some(output_hash.as_str());
+        for downstream in stage.downstream() {
+            let overwritten = downstream
+                .rewrites()
+                .iter()
+                .any(|artifact| stage.outputs().contains(artifact));
+            if changed || overwritten {
+                self.status.invalidate(downstream.id());
+            }
+        }
+        self.status
+            .record_run(stage.id(), Some(input_hash), Some(output_hash));
+        self.status.save(&self.ctx.out_dir.join(STATUS_FILE))
+    }
+
+    /// Rewrites artifacts left in an older encoding by earlier builds. The data is unchanged, so
+    /// stages that were up to date are re-recorded against the new files instead of re-running.
+    pub fn migrate_artifacts(&mut self, runner: &dyn StageRunner) -> Result<(), String> {
+        let biome_path = self.ctx.out_dir.join("biome.png");
+        if !biome_path.exists() {
+            return Ok(());
+        }
+        let img = open_image(&biome_path)?;
+        if !biome::is_legacy_biome_texture(&img) {
+            return Ok(());
+        }
+
+        let fresh: Vec<Stage> = Stage::ALL
+            .into_iter()
+            .filter(|&stage| self.is_up_to_date(stage, runner))
+            .collect();
+        export::write_biome_texture(
+            &biome::decode_biome_texture(&img),
+            img.width(),
+            img.height(),
+            &biome_path,
+        )?;
+        self.file_hashes.clear();
+
+        for stage in fresh {
+            let input_hash = self.input_hash(stage, runner);
+            let output_hash = self.output_hash(stage);
+            if let Some(record) = self.status.stages.get_mut(stage.id()) {
+                record.input_hash = Some(input_hash);
+                record.output_hash = Some(output_hash);
+            }
+        }
+        self.status.save(&self.ctx.out_dir.join(STATUS_FILE))
+    }
+
+    fn output_hash(&mut self, stage: Stage) -> String {
+        let mut hasher = Fnv64::new();
+        for artifact in stage.outputs() {
+            let hash = self.file_hash(&self.ctx.out_dir.join(artifact));
+            hasher.write(artifact.as_bytes());
+            hasher.write(hash.as_bytes());
+        }
+        hasher.finish_hex()
+    }
+
+    fn file_hash(&mut self, path: &PathBuf) -> String {
+        if let Some(hash) = self.file_hashes.get(path) {
+            return hash.clone();
+        }
+        let hash = match std::fs::read(path) {
+            Ok(bytes) => {
+                let mut hasher = Fnv64::new();
+                hasher.write(&bytes);
+                hasher.finish_hex()
+            }
+            Err(_) => "missing".to_string(),
+        };
+        self.file_hashes.insert(path.clone(), hash.clone());
+        hash
+    }
+}
+
+/// FNV-1a, 64-bit. Stable across builds, which `DefaultHasher` is not.
+struct Fnv64(u64);
+
+impl Fnv64 {
+    fn new() -> Self {
+        Fnv64(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+        // Separator so ("ab", "c") and ("a", "bc") hash differently.
+        self.0 ^= 0xff;
+        self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    fn finish_hex(&self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
+
+/// Loads the vision priors the biome stage blends into its classification.
+pub type VisionPriorsHook =
+    fn(&StageContext, &BiomeModelSettings) -> Result<BiomeVisionAnalysis, String>;
+
+/// Runs every stage from files in the output directory. Defaults to the built-in biome registry
+/// and no vision priors.
+pub struct DefaultStageRunner {
+    pub registry: BiomeRegistry,
+    pub model_settings: BiomeModelSettings,
+    pub vision_priors: Option<VisionPriorsHook>,
+}
+
+impl Default for DefaultStageRunner {
+    fn default() -> Self {
+        Self {
+            registry: BiomeRegistry::default_registry(),
+            model_settings: BiomeModelSettings::default(),
+            vision_priors: None,
+        }
+    }
+}
+
+impl StageRunner for DefaultStageRunner {
+    fn run_stage(
+        &mut self,
+        stage: Stage,
+        ctx: &StageContext,
+        progress: &mut dyn FnMut(f32, &str),
+    ) -> Result<(), String> {
+        let out_dir = ctx.out_dir;
+        let config = ctx.config;
+        match stage {
+            Stage::Landmask => {
+                let base_img = read_rgb(ctx.base_image)?;
+                let mask = landmask::extract_landmask(&base_img, config, 500, 200, progress);
+                let (w, h) = base_img.dimensions();
+                export::write_landmask(&mask, w, h, &out_dir.join("landmask.png"))
+            }
+            Stage::Normalize => {
+                let img = read_rgb(ctx.base_image)?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let flat = normalize::normalize_albedo(&img, &mask, 60.0, progress);
+                export::write_rgb_image(&flat, &out_dir.join("albedo_flat.png"))
+            }
+            Stage::Height => {
+                let flat = read_rgb(&out_dir.join("albedo_flat.png"))?;
+                let (w, h) = flat.dimensions();
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let hf = height::reconstruct_height(&flat, &mask, config.seed, progress);
+                export::write_height_texture(&hf, w, h, &out_dir.join("height16.png"))
+            }
+            Stage::Rivers => {
+                let (w, h) = read_dimensions(&out_dir.join("landmask.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let hf = read_height16(&out_dir.join("height16.png"))?;
+                let settings = hydrology::HydrologySettings::for_config(config);
+                let result = hydrology::compute_hydrology(&hf, &mask, w, h, &settings, progress);
+                let network = river_network::build_river_network(&result, &mask, w, h, &settings);
+                export::write_rivers_json(&network, &out_dir.join("rivers.json"))?;
+                export::write_rivers_geojson(&network, &out_dir.join("rivers.geojson"))?;
+                export::write_mask_texture(
+                    &result.lake_mask,
+                    w,
+                    h,
+                    &out_dir.join("lake_mask.png"),
+                )?;
+                export::write_lakes_json(&result.lakes, &out_dir.join("lakes.json"))?;
+                export::write_mask_texture(
+                    &result.river_mask,
+                    w,
+                    h,
+                    &out_dir.join("river_mask.png"),
+                )
+            }
+            Stage::Biome => {
+                let base_img = read_rgb(ctx.base_image)?;
+                let (w, h) = base_img.dimensions();
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let hf = read_height16(&out_dir.join("height16.png"))?;
+                let river = read_mask_u8(&out_dir.join("river_mask.png"))?;
+                let vision_analysis = self
+                    .vision_priors
+                    .map(|load| load(ctx, &self.model_settings))
+                    .transpose()?;
+                let input = biome::BiomeInput {
+                    width: w,
+                    height: h,
+                    height_field: &hf,
+                    landmask: &mask,
+                    river_mask: Some(&river),
+                    image: &base_img,
+                };
+                let analysis = biome::classify_biomes(
+                    &input,
+                    config,
+                    &self.registry,
+                    &self.model_settings,
+                    vision_analysis.as_ref(),
+                    progress,
+                );
+                export::write_biome_texture(
+                    &analysis.biome_indices,
+                    w,
+                    h,
+                    &out_dir.join("biome.png"),
+                )?;
+                export::write_mask_texture(
+                    &analysis.confidence_map,
+                    w,
+                    h,
+                    &out_dir.join("biome_confidence.png"),
+                )?;
+                export::write_biome_report_json(
+                    &analysis.report,
+                    &out_dir.join(biome::BIOME_REPORT_FILE),
+                )?;
+                export::write_biome_palette_json(
+                    &biome::biome_palette(&self.registry),
+                    &out_dir.join(biome::BIOME_PALETTE_FILE),
+                )
+            }
+            Stage::Suitability => {
+                let (w, h) = read_dimensions(&out_dir.join("landmask.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let hf = read_height16(&out_dir.join("height16.png"))?;
+                let river = read_mask_u8(&out_dir.join("river_mask.png"))?;
+                let biomes = read_biome(&out_dir.join("biome.png"))?;
+                let input = suitability::SuitabilityInput {
+                    width: w,
+                    height: h,
+                    height_field: &hf,
+                    landmask: &mask,
+                    river_mask: &river,
+                    biome_indices: &biomes,
+                };
+                let suit = suitability::compute_suitability(&input, &self.registry, progress);
+                export::write_f32_binary(&suit, &out_dir.join("suitability.bin"))
+            }
+            Stage::Seeds => {
+                let (w, h) = read_dimensions(&out_dir.join("landmask.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let suit = read_f32_binary(&out_dir.join("suitability.bin"))?;
+                let seeds = sampling::place_seeds(
+                    &suit,
+                    &mask,
+                    w,
+                    h,
+                    &sampling::SeedSettings::for_config(config),
+                    progress,
+                );
+                export::write_seeds_json(&seeds, &out_dir.join("seeds.json"))
+            }
+            Stage::Partition => {
+                let (w, h) = read_dimensions(&out_dir.join("landmask.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let hf = read_height16(&out_dir.join("height16.png"))?;
+                let river = read_mask_u8(&out_dir.join("river_mask.png"))?;
+                let seeds = read_json::<Vec<Seed>>(&out_dir.join("seeds.json"))?;
+                let input = partition::PartitionInput {
+                    width: w,
+                    height: h,
+                    seeds: &seeds,
+                    height_field: &hf,
+                    landmask: &mask,
+                    river_mask: &river,
+                };
+                let labels = partition::grow_provinces(
+                    &input,
+                    &partition::PartitionSettings::for_config(config),
+                    progress,
+                );
+                export::write_id_texture(&labels, w, h, &out_dir.join("province_id_raw.png"))
+            }
+            Stage::Postprocess => {
+                let (w, h) = read_dimensions(&out_dir.join("landmask.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let mut labels = read_labels(&out_dir.join("province_id_raw.png"), &mask)?;
+                postprocess::postprocess_provinces(
+                    &mut labels,
+                    &mask,
+                    w,
+                    h,
+                    &postprocess::PostprocessSettings::for_config(config),
+                    progress,
+                );
+                export::write_id_texture(&labels, w, h, &out_dir.join("province_id.png"))
+            }
+            Stage::SeaZones => {
+                let (w, h) = read_dimensions(&out_dir.join("province_id.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let labels = read_labels(&out_dir.join("province_id.png"), &mask)?;
+                let biomes = read_biome(&out_dir.join("biome.png"))?;
+                let settings = sea::SeaZoneSettings::for_config(config);
+                let input = sea::SeaZoneInput {
+                    width: w,
+                    height: h,
+                    landmask: &mask,
+                    province_labels: &labels,
+                    biome_indices: &biomes,
+                    registry: &self.registry,
+                };
+                let result = sea::partition_sea_zones(&input, &settings, progress);
+                export::write_id_texture(&result.labels, w, h, &out_dir.join("sea_zone_id.png"))?;
+                export::write_sea_zones_json(&result.zones, &out_dir.join("sea_zones.json"))?;
+                export::write_straits_json(&result.straits, &out_dir.join("straits.json"))
+            }
+            Stage::Adjacency => {
+                let (w, h) = read_dimensions(&out_dir.join("province_id.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let labels = read_labels(&out_dir.join("province_id.png"), &mask)?;
+                let water: Vec<bool> = mask.iter().map(|&land| !land).collect();
+                let sea_labels = read_labels(&out_dir.join("sea_zone_id.png"), &water)?;
+                let straits = read_json::<Vec<StraitRecord>>(&out_dir.join("straits.json"))?;
+                let hf = read_height16(&out_dir.join("height16.png"))?;
+                let river = read_mask_u8(&out_dir.join("river_mask.png"))?;
+                let mut adjacency =
+                    graph::build_adjacency(&labels, &hf, &river, w, h, config.projection, progress);
+                sea::add_naval_edges(
+                    &mut adjacency,
+                    &labels,
+                    &sea_labels,
+                    &straits,
+                    w,
+                    h,
+                    config.projection,
+                );
+                export::write_adjacency_json(&adjacency, &out_dir.join("adjacency.json"))
+            }
+            Stage::Clustering => {
+                let (w, h) = read_dimensions(&out_dir.join("province_id.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let labels = read_labels(&out_dir.join("province_id.png"), &mask)?;
+                let biomes = read_biome(&out_dir.join("biome.png"))?;
+                let seeds = read_json::<Vec<Seed>>(&out_dir.join("seeds.json"))?;
+                let adjacency =
+                    read_json::<Vec<ProvinceAdjacency>>(&out_dir.join("adjacency.json"))?;
+                let suit = read_f32_binary(&out_dir.join("suitability.bin"))?;
+                let locks_path = out_dir.join(cluster::HIERARCHY_LOCKS_FILE);
+                let locks = if locks_path.exists() {
+                    read_json::<cluster::HierarchyLocks>(&locks_path)?
+                } else {
+                    cluster::HierarchyLocks::default()
+                };
+                let seed_tuples: Vec<(u32, u32, u32)> =
+                    seeds.iter().map(|s| (s.id, s.x, s.y)).collect();
+                let input = cluster::ClusterInput {
+                    width: w,
+                    height: h,
+                    labels: &labels,
+                    biome_indices: &biomes,
+                    suitability: Some(&suit),
+                    seeds: &seed_tuples,
+                    adjacency: &adjacency,
+                };
+                let result = cluster::cluster_hierarchy(
+                    &input,
+                    &cluster::ClusterSettings::for_config(config),
+                    &locks,
+                    progress,
+                );
+                export::write_id_texture(
+                    &result.duchy_labels,
+                    w,
+                    h,
+                    &out_dir.join("duchy_id.png"),
+                )?;
+                export::write_id_texture(
+                    &result.kingdom_labels,
+                    w,
+                    h,
+                    &out_dir.join("kingdom_id.png"),
+                )?;
+                export::write_provinces_json(&result.provinces, &out_dir.join("provinces.json"))?;
+                export::write_duchies_json(&result.duchies, &out_dir.join("duchies.json"))?;
+                export::write_kingdoms_json(&result.kingdoms, &out_dir.join("kingdoms.json"))
+            }
+            Stage::Naming => naming::name_planet_dir(
+                out_dir,
+                &self.registry,
+                config.seed,
+                config.projection,
+                progress,
+            )
+            .map(|_| ()),
+            Stage::Roads => {
+                let (w, h) = read_dimensions(&out_dir.join("province_id.png"))?;
+                let mask = read_landmask(&out_dir.join("landmask.png"))?;
+                let labels = read_labels(&out_dir.join("province_id.png"), &mask)?;
+                let duchy_labels = read_labels(&out_dir.join("duchy_id.png"), &mask)?;
+                let hf = read_height16(&out_dir.join("height16.png"))?;
+                let river = read_mask_u8(&out_dir.join("river_mask.png"))?;
+                let rivers = read_json::<RiverNetwork>(&out_dir.join("rivers.json"))?;
+                let biomes = read_biome(&out_dir.join("biome.png"))?;
+                let seeds = read_json::<Vec<Seed>>(&out_dir.join("seeds.json"))?;
+                let adjacency =
+                    read_json::<Vec<ProvinceAdjacency>>(&out_dir.join("adjacency.json"))?;
+                let waypoints_path = out_dir.join(roads::ROAD_WAYPOINTS_FILE);
+                let waypoints = if waypoints_path.exists() {
+                    read_json::<Vec<roads::RoadWaypoint>>(&waypoints_path)?
+                } else {
+                    Vec::new()
+                };
+                let input = roads::RoadNetworkInput {
+                    width: w,
+                    height: h,
+                    landmask: &mask,
+                    height_field: &hf,
+                    river_mask: &river,
+                    rivers: &rivers,
+                    biome_indices: &biomes,
+                    registry: &self.registry,
+                    province_labels: &labels,
+                    duchy_labels: &duchy_labels,
+                    seeds: &seeds,
+                    adjacency: &adjacency,
+                    waypoints: &waypoints,
+                };
+                let result = roads::build_road_network(
+                    &input,
+                    &roads::RoadSettings::for_config(config),
+                    progress,
+                );
+                export::write_roads_json(&result.network, &out_dir.join("roads.json"))?;
+                export::write_roads_geojson(&result.network, &out_dir.join("roads.geojson"))?;
+                export::write_mask_texture(&result.mask, w, h, &out_dir.join("road_mask.png"))
+            }
+        }
+    }
+}
+
+// ── Artifact readers ──
+
+fn open_image(path: &Path) -> Result<image::DynamicImage, String> {
+    image::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+pub(crate) fn read_rgb(path: &Path) -> Result<image::RgbImage, String> {
+    open_image(path).map(|img| img.to_rgb8())
+}
+
+pub(crate) fn read_dimensions(path: &Path) -> Result<(u32, u32), String> {
+    image::image_dimensions(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+pub(crate) fn read_landmask(path: &Path) -> Result<Vec<bool>, String> {
+    let gray = open_image(path)?.to_luma8();
+    Ok(gray.pixels().map(|p| p[0] > 128).collect())
+}
+
+pub(crate) fn read_height16(path: &Path) -> Result<Vec<u16>, String> {
+    let gray16 = open_image(path)?.to_luma16();
+    Ok(gray16.pixels().map(|p| p[0]).collect())
+}
+
+pub(crate) fn read_mask_u8(path: &Path) -> Result<Vec<u8>, String> {
+    let gray = open_image(path)?.to_luma8();
+    Ok(gray.pixels().map(|p| p[0]).collect())
+}
+
+pub(crate) fn read_biome(path: &Path) -> Result<Vec<u16>, String> {
+    open_image(path).map(|img| biome::decode_biome_texture(&img))
+}
+
+/// Unlabeled pixels are written black, the same color as id 0, so `labeled` says which pixels
+/// carry an id; the rest read as `u32::MAX`.
+pub(crate) fn read_labels(path: &Path, labeled: &[bool]) -> Result<Vec<u32>, String> {
+    let rgb = open_image(path)?.to_rgb8();
+    Ok(rgb
+        .pixels()
+        .zip(labeled)
+        .map(|(p, &has_id)| {
+            if has_id {
+                p[0] as u32 | ((p[1] as u32) << 8) | ((p[2] as u32) << 16)
+            } else {
+                u32::MAX
+            }
+        })
+        .collect())
+}
+
+pub(crate) fn read_f32_binary(path: &Path) -> Result<Vec<f32>, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect())
+}
+
+pub(crate) fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GrayImage;
+
+    /// A base image and output directory unique to this test process, removed when dropped.
+    struct Workspace {
+        root: PathBuf,
+        base_image: PathBuf,
+        out_dir: PathBuf,
+    }
+
+    impl Workspace {
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("worldgen-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            let out_dir = root.join("out");
+            std::fs::create_dir_all(&out_dir).unwrap();
+            let base_image = root.join("base.png");
+            std::fs::write(&base_image, b"base").unwrap();
+            Self {
+                root,
+                base_image,
+                out_dir,
+            }
+        }
+    }
+
+    impl Drop for Workspace {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    /// Writes each output as a hash of what the stage reads, and records which stages ran.
+    /// `biome.png` is written as a legacy 8-bit grayscale texture.
+    #[derive(Default)]
+    struct RecordingRunner {
+        ran: Vec<Stage>,
+    }
+
+    impl StageRunner for RecordingRunner {
+        fn run_stage(
+            &mut self,
+            stage: Stage,
+            ctx: &StageContext,
+            _on_progress: &mut dyn FnMut(f32, &str),
+        ) -> Result<(), String> {
+            self.ran.push(stage);
+            let config = serde_json::to_value(ctx.config).unwrap();
+            let mut hasher = Fnv64::new();
+            for key in stage.config_keys() {
+                hasher.write(config[*key].to_string().as_bytes());
+            }
+            if stage.reads_base_image() {
+                hasher.write(&std::fs::read(ctx.base_image).unwrap());
+            }
+            for artifact in stage.inputs() {
+                hasher.write(&std::fs::read(ctx.out_dir.join(artifact)).unwrap_or_default());
+            }
+            let content = format!("{}:{}", stage.id(), hasher.finish_hex());
+            for artifact in stage.outputs() {
+                let path = ctx.out_dir.join(artifact);
+                if *artifact == "biome.png" {
+                    GrayImage::from_raw(2, 2, vec![0, 3, 17, 255])
+                        .unwrap()
+                        .save(&path)
+                        .map_err(|e| e.to_string())?;
+                } else {
+                    std::fs::write(&path, &content).map_err(|e| e.to_string())?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn run_all(workspace: &Workspace, config: &WorldgenConfig) -> Vec<Stage> {
+        let mut runner = RecordingRunner::default();
+        let mut pipeline = Pipeline::open(&workspace.base_image, &workspace.out_dir, config);
+        let outcomes = pipeline
+            .run(&Stage::ALL, false, &mut runner, &mut |_, _, _| {})
+            .unwrap();
+        for (stage, outcome) in outcomes {
+            let expected = if runner.ran.contains(&stage) {
+                StageOutcome::Ran
+            } else {
+                StageOutcome::UpToDate
+            };
+            assert_eq!(outcome, expected, "{}", stage.id());
+        }
+        runner.ran
+    }
+
+    #[test]
+    fn unchanged_inputs_skip_every_stage() {
+        let workspace = Workspace::new("pipeline-skip");
+        let config = WorldgenConfig::default();
+
+        assert_eq!(run_all(&workspace, &config), Stage::ALL.to_vec());
+        assert!(run_all(&workspace, &config).is_empty());
+    }
+
+    #[test]
+    fn changed_config_or_artifact_reruns_only_downstream_stages() {
+        let workspace = Workspace::new("pipeline-invalidate");
+        let mut config = WorldgenConfig::default();
+        run_all(&workspace, &config);
+
+        config.river_threshold += 1;
+        let mut expected = vec![Stage::Rivers];
+        expected.extend(Stage::Rivers.downstream());
+        assert_eq!(run_all(&workspace, &config), expected);
+        assert!(!expected.contains(&Stage::Height));
+
+        // An edited output is kept, but everything reading it runs again.
+        std::fs::write(workspace.out_dir.join("seeds.json"), "edited").unwrap();
+        let mut pipeline = Pipeline::open(&workspace.base_image, &workspace.out_dir, &config);
+        let runner = RecordingRunner::default();
+        assert!(pipeline.is_up_to_date(Stage::Seeds, &runner));
+        assert_eq!(
+            pipeline.stale_stages(&runner),
+            vec![Stage::Partition, Stage::Clustering, Stage::Roads]
+        );
+        assert_eq!(run_all(&workspace, &config), Stage::Seeds.downstream());
+    }
+
+    #[test]
+    fn rerun_with_new_outputs_marks_downstream_stale() {
+        let workspace = Workspace::new("pipeline-rerun");
+        let mut config = WorldgenConfig::default();
+        run_all(&workspace, &config);
+
+        config.seed += 1;
+        let mut pipeline = Pipeline::open(&workspace.base_image, &workspace.out_dir, &config);
+        let mut runner = RecordingRunner::default();
+        pipeline
+            .run_stage(Stage::Height, &mut runner, &mut |_, _| {})
+            .unwrap();
+        for stage in Stage::Height.downstream() {
+            assert!(
+                !pipeline.status().stages[stage.id()].completed,
+                "{} still completed",
+                stage.id()
+            );
+        }
+        assert!(pipeline.status().stages[Stage::Landmask.id()].completed);
+    }
+
+    #[test]
+    fn legacy_biome_texture_migrates_without_rerunning_stages() {
+        let workspace = Workspace::new("pipeline-migrate");
+        let config = WorldgenConfig::default();
+        run_all(&workspace, &config);
+        let biome_path = workspace.out_dir.join("biome.png");
+        assert!(biome::is_legacy_biome_texture(
+            &image::open(&biome_path).unwrap()
+        ));
+
+        let mut pipeline = Pipeline::open(&workspace.base_image, &workspace.out_dir, &config);
+        pipeline
+            .migrate_artifacts(&RecordingRunner::default())
+            .unwrap();
+
+        let migrated = image::open(&biome_path).unwrap();
+        assert!(!biome::is_legacy_biome_texture(&migrated));
+        assert_eq!(read_biome(&biome_path).unwrap(), vec![0, 3, 17, 255]);
+        assert!(run_all(&workspace, &config).is_empty());
+    }
+}
@@ -1,27 +1,50 @@
-use crate::raster::neighbors8;
+use crate::config::{MapProjection, WorldgenConfig};
+use crate::raster::neighbors8_in;
+
+/// Province cleanup thresholds.
+#[derive(Debug, Clone, Copy)]
+pub struct PostprocessSettings {
+    /// Provinces smaller than this many pixels are merged into a neighbour.
+    pub min_area: u32,
+    pub smooth_iterations: u32,
+    pub projection: MapProjection,
+}
+
+impl PostprocessSettings {
+    pub fn for_config(config: &WorldgenConfig) -> Self {
+        Self {
+            min_area: config.min_county_area,
+            smooth_iterations: config.smooth_iterations,
+            projection: config.projection,
+        }
+    }
+}
 
 /// Stage 9: Postprocessing for CK3-style province cleanup.
 /// - Enforce contiguity (one connected component per province)
 /// - Merge tiny provinces into neighbors
 /// - Border smoothing via majority filter
-
 pub fn postprocess_provinces(
-    labels: &mut Vec<u32>,
+    labels: &mut [u32],
     landmask: &[bool],
     width: u32,
     height: u32,
-    min_area: u32,
-    smooth_iterations: u32,
+    settings: &PostprocessSettings,
     on_progress: &mut dyn FnMut(f32, &str),
 ) {
+    let PostprocessSettings {
+        min_area,
+        smooth_iterations,
+        projection,
+    } = *settings;
     let _n = (width * height) as usize;
     let _no_label = u32::MAX;
 
     on_progress(0.0, "Enforcing contiguity");
-    enforce_contiguity(labels, landmask, width, height);
+    enforce_contiguity(labels, landmask, width, height, projection);
 
     on_progress(30.0, "Merging tiny provinces");
-    merge_tiny_provinces(labels, landmask, width, height, min_area);
+    merge_tiny_provinces(labels, landmask, width, height, min_area, projection);
 
     on_progress(60.0, "Smoothing borders");
     for iter in 0..smooth_iterations {
@@ -30,7 +53,7 @@ pub fn postprocess_provinces(
             progress,
             &format!("Smoothing pass {}/{}", iter + 1, smooth_iterations),
         );
-        majority_filter(labels, landmask, width, height);
+        majority_filter(labels, landmask, width, height, projection);
     }
 
     on_progress(100.0, "Postprocessing complete");
@@ -38,7 +61,13 @@ pub fn postprocess_provinces(
 
 /// For each province, keep only the largest connected component.
 /// Reassign orphaned pixels to the nearest province by shared border.
-fn enforce_contiguity(labels: &mut [u32], _landmask: &[bool], width: u32, height: u32) {
+fn enforce_contiguity(
+    labels: &mut [u32],
+    _landmask: &[bool],
+    width: u32,
+    height: u32,
+    projection: MapProjection,
+) {
     let n = (width * height) as usize;
     let no_label = u32::MAX;
 
@@ -64,7 +93,7 @@ fn enforce_contiguity(labels: &mut [u32], _landmask: &[bool], width: u32, height
                     component.push(ci);
                     let x = (ci % width as usize) as u32;
                     let y = (ci / width as usize) as u32;
-                    for (_, _, ni) in neighbors8(x, y, width, height) {
+                    for (_, _, ni) in neighbors8_in(x, y, width, height, projection) {
                         if !visited[ni] && labels[ni] == pid {
                             visited[ni] = true;
                             stack.push(ni);
@@ -99,7 +128,7 @@ fn enforce_contiguity(labels: &mut [u32], _landmask: &[bool], width: u32, height
                 let mut best_count = 0u32;
                 let mut neighbor_counts = std::collections::HashMap::new();
 
-                for (_, _, ni) in neighbors8(x, y, width, height) {
+                for (_, _, ni) in neighbors8_in(x, y, width, height, projection) {
                     let nl = labels[ni];
                     if nl != pid && nl != no_label {
                         *neighbor_counts.entry(nl).or_insert(0u32) += 1;
@@ -122,12 +151,14 @@ fn enforce_contiguity(labels: &mut [u32], _landmask: &[bool], width: u32, height
 }
 
 /// Merge provinces smaller than min_area into their best neighbor.
+/// Areas are measured in equator pixels so polar provinces are not kept artificially large.
 fn merge_tiny_provinces(
     labels: &mut [u32],
     _landmask: &[bool],
     width: u32,
     height: u32,
     min_area: u32,
+    projection: MapProjection,
 ) {
     let n = (width * height) as usize;
     let no_label = u32::MAX;
@@ -135,16 +166,17 @@ fn merge_tiny_provinces(
     loop {
         // Count areas
         let mut area_map = std::collections::HashMap::new();
-        for i in 0..n {
-            if labels[i] != no_label {
-                *area_map.entry(labels[i]).or_insert(0u32) += 1;
+        for (i, &label) in labels.iter().enumerate() {
+            if label != no_label {
+                *area_map.entry(label).or_insert(0.0f64) +=
+                    projection.row_scale(i as u32 / width, height);
             }
         }
 
         // Find provinces below min_area
         let tiny: Vec<u32> = area_map
             .iter()
-            .filter(|(_, &area)| area < min_area)
+            .filter(|(_, &area)| area < min_area as f64)
             .map(|(&id, _)| id)
             .collect();
 
@@ -152,6 +184,8 @@ fn merge_tiny_provinces(
             break;
         }
 
+        // Tiny islands have no neighbor to merge into; stop once a pass changes nothing.
+        let mut merged = false;
         for &pid in &tiny {
             // Find the neighbor with the longest shared border
             let mut border_counts: std::collections::HashMap<u32, u32> =
@@ -163,7 +197,7 @@ fn merge_tiny_provinces(
                 }
                 let x = (i % width as usize) as u32;
                 let y = (i / width as usize) as u32;
-                for (_, _, ni) in neighbors8(x, y, width, height) {
+                for (_, _, ni) in neighbors8_in(x, y, width, height, projection) {
                     let nl = labels[ni];
                     if nl != pid && nl != no_label {
                         *border_counts.entry(nl).or_insert(0) += 1;
@@ -173,18 +207,26 @@ fn merge_tiny_provinces(
 
             if let Some((&best_neighbor, _)) = border_counts.iter().max_by_key(|(_, &c)| c) {
                 // Merge: relabel all pixels
-                for i in 0..n {
-                    if labels[i] == pid {
-                        labels[i] = best_neighbor;
-                    }
+                for label in labels.iter_mut().filter(|label| **label == pid) {
+                    *label = best_neighbor;
                 }
+                merged = true;
             }
         }
+        if !merged {
+            break;
+        }
     }
 }
 
 /// Border smoothing: majority filter on boundary pixels only.
-fn majority_filter(labels: &mut [u32], _landmask: &[bool], width: u32, height: u32) {
+fn majority_filter(
+    labels: &mut [u32],
+    _landmask: &[bool],
+    width: u32,
+    height: u32,
+    projection: MapProjection,
+) {
     let n = (width * height) as usize;
     let no_label = u32::MAX;
     let original = labels.to_vec();
@@ -196,7 +238,7 @@ fn majority_filter(labels: &mut [u32], _landmask: &[bool], width: u32, height: u
 
         let x = (i % width as usize) as u32;
         let y = (i / width as usize) as u32;
-        let neighbors = neighbors8(x, y, width, height);
+        let neighbors = neighbors8_in(x, y, width, height, projection);
 
         // Check if this is a border pixel
         let is_border = neighbors
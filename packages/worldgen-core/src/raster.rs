@@ -1,19 +1,42 @@
-/// Shared raster/grid utilities for equirectangular projection.
-/// All operations assume pixel x = longitude, pixel y = latitude.
-/// x wraps around (left-right), y is clamped (top-bottom).
+//! Shared raster/grid utilities for equirectangular projection.
+//! All operations assume pixel x = longitude, pixel y = latitude.
+//! x wraps around (left-right), y is clamped (top-bottom).
+//! The `_in` variants follow a [`MapProjection`] instead and only wrap when it does.
+
+use crate::config::MapProjection;
 
 /// Get a flat index from (x, y) with x-wrapping.
 #[inline]
 pub fn idx(x: i32, y: i32, width: u32, height: u32) -> Option<usize> {
+    idx_in(x, y, width, height, MapProjection::Equirectangular)
+}
+
+/// Get a flat index from (x, y); x wraps only when the projection does.
+#[inline]
+pub fn idx_in(x: i32, y: i32, width: u32, height: u32, projection: MapProjection) -> Option<usize> {
     if y < 0 || y >= height as i32 {
         return None;
     }
+    if !projection.wraps_x() && (x < 0 || x >= width as i32) {
+        return None;
+    }
     let wx = ((x % width as i32) + width as i32) % width as i32;
     Some((y as u32 * width + wx as u32) as usize)
 }
 
 /// Get the 8 neighbors (indices) of a pixel, with x-wrapping and y-clamping.
 pub fn neighbors8(x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32, usize)> {
+    neighbors8_in(x, y, width, height, MapProjection::Equirectangular)
+}
+
+/// Get the 8 neighbors of a pixel under `projection`.
+pub fn neighbors8_in(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    projection: MapProjection,
+) -> Vec<(u32, u32, usize)> {
     let mut out = Vec::with_capacity(8);
     for dy in [-1i32, 0, 1] {
         for dx in [-1i32, 0, 1] {
@@ -22,9 +45,8 @@ pub fn neighbors8(x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32, usi
             }
             let nx = x as i32 + dx;
             let ny = y as i32 + dy;
-            if let Some(i) = idx(nx, ny, width, height) {
-                let wx = ((nx % width as i32) + width as i32) as u32 % width;
-                out.push((wx, ny as u32, i));
+            if let Some(i) = idx_in(nx, ny, width, height, projection) {
+                out.push((i as u32 % width, ny as u32, i));
             }
         }
     }
@@ -33,18 +55,36 @@ pub fn neighbors8(x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32, usi
 
 /// Get the 4 cardinal neighbors with wrapping.
 pub fn neighbors4(x: u32, y: u32, width: u32, height: u32) -> Vec<(u32, u32, usize)> {
+    neighbors4_in(x, y, width, height, MapProjection::Equirectangular)
+}
+
+/// Get the 4 cardinal neighbors of a pixel under `projection`.
+pub fn neighbors4_in(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    projection: MapProjection,
+) -> Vec<(u32, u32, usize)> {
     let mut out = Vec::with_capacity(4);
     for (dx, dy) in [(-1i32, 0), (1, 0), (0, -1i32), (0, 1)] {
         let nx = x as i32 + dx;
         let ny = y as i32 + dy;
-        if let Some(i) = idx(nx, ny, width, height) {
-            let wx = ((nx % width as i32) + width as i32) as u32 % width;
-            out.push((wx, ny as u32, i));
+        if let Some(i) = idx_in(nx, ny, width, height, projection) {
+            out.push((i as u32 % width, ny as u32, i));
         }
     }
     out
 }
 
+/// Ground distance in equator pixels of a one-pixel step, using the mean latitude of both rows.
+pub fn step_length(projection: MapProjection, y: u32, dx: i32, dy: i32, height: u32) -> f64 {
+    let scale =
+        projection.row_scale(y, height) + projection.row_scale((y as i32 + dy) as u32, height);
+    let horizontal = dx as f64 * scale / 2.0;
+    (horizontal * horizontal + (dy * dy) as f64).sqrt()
+}
+
 /// Compute Euclidean distance transform from a binary mask.
 /// Returns a buffer where each pixel has the distance to the nearest `true` pixel.
 /// Uses a two-pass approximation that's fast and good enough.
@@ -168,3 +208,55 @@ pub fn gaussian_blur_approx(data: &[f32], width: u32, height: u32, sigma: f32) -
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(neighbors: &[(u32, u32, usize)]) -> Vec<u32> {
+        let mut xs: Vec<u32> = neighbors.iter().map(|&(x, _, _)| x).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        xs
+    }
+
+    #[test]
+    fn equirectangular_neighbors_wrap_across_the_antimeridian() {
+        let wrapped = neighbors8_in(0, 2, 8, 5, MapProjection::Equirectangular);
+        assert_eq!(wrapped.len(), 8);
+        assert_eq!(columns(&wrapped), vec![0, 1, 7]);
+        assert!(wrapped.contains(&(7, 2, 2 * 8 + 7)));
+        assert_eq!(neighbors8(0, 2, 8, 5), wrapped);
+
+        let bounded = neighbors8_in(0, 2, 8, 5, MapProjection::Flat);
+        assert_eq!(bounded.len(), 5);
+        assert_eq!(columns(&bounded), vec![0, 1]);
+
+        assert_eq!(idx_in(-1, 0, 8, 5, MapProjection::Equirectangular), Some(7));
+        assert_eq!(idx_in(8, 4, 8, 5, MapProjection::Equirectangular), Some(32));
+        assert_eq!(idx_in(-1, 0, 8, 5, MapProjection::Flat), None);
+        // Latitude never wraps.
+        assert_eq!(
+            neighbors8_in(3, 0, 8, 5, MapProjection::Equirectangular).len(),
+            5
+        );
+        assert_eq!(idx_in(3, -1, 8, 5, MapProjection::Equirectangular), None);
+    }
+
+    #[test]
+    fn equirectangular_steps_shorten_toward_the_poles() {
+        let (height, equator) = (90, 45);
+        let flat = step_length(MapProjection::Flat, 2, 1, 0, height);
+        let polar = step_length(MapProjection::Equirectangular, 2, 1, 0, height);
+        let level = step_length(MapProjection::Equirectangular, equator, 1, 0, height);
+
+        assert_eq!(flat, 1.0);
+        assert!((level - 1.0).abs() < 1e-3);
+        assert!(polar < 0.1);
+        // North-south steps keep their length everywhere.
+        assert_eq!(
+            step_length(MapProjection::Equirectangular, 2, 0, 1, height),
+            1.0
+        );
+    }
+}
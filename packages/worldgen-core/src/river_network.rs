@@ -0,0 +1,375 @@
+use crate::config::MapProjection;
+use crate::hydrology::{HydrologyResult, HydrologySettings};
+use crate::raster::neighbors8_in;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Douglas-Peucker tolerance in pixels applied before smoothing.
+const SIMPLIFY_TOLERANCE: f32 = 0.75;
+/// Chaikin corner-cutting passes applied after simplification.
+const SMOOTHING_ITERATIONS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiverNodeKind {
+    Source,
+    Confluence,
+    Mouth,
+    /// River ends in a lake.
+    LakeInlet,
+    /// River leaves a lake through its outlet.
+    LakeOutlet,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiverNode {
+    pub id: u32,
+    pub kind: RiverNodeKind,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A channel stretch between two nodes with a constant stream order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiverSegment {
+    pub id: u32,
+    pub from_node: u32,
+    pub to_node: u32,
+    pub strahler_order: u8,
+    /// Upstream cells draining through the segment, not counting other inflows at its downstream
+    /// junction.
+    pub discharge: u32,
+    /// Length in pixels of the unsmoothed channel.
+    pub length: f32,
+    /// Smoothed polyline in pixel coordinates. X is unwrapped across the antimeridian so the
+    /// line stays continuous and may fall outside `0..width`.
+    pub points: Vec<[f32; 2]>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiverNetwork {
+    pub width: u32,
+    pub height: u32,
+    pub nodes: Vec<RiverNode>,
+    pub segments: Vec<RiverSegment>,
+}
+
+/// Vectorizes the channel cells of a hydrology result into a river graph.
+///
+/// Channels are land cells above the river threshold, lakes included, so stream order carries
+/// through a lake from its inlets to its outlet even though no segment is drawn across it.
+pub fn build_river_network(
+    hydrology: &HydrologyResult,
+    landmask: &[bool],
+    width: u32,
+    height_dim: u32,
+    settings: &HydrologySettings,
+) -> RiverNetwork {
+    let n = (width * height_dim) as usize;
+    let flow_dir = &hydrology.flow_dir;
+    let is_lake = |i: usize| hydrology.lake_mask[i] > 0;
+    let channel: Vec<bool> = (0..n)
+        .map(|i| landmask[i] && hydrology.accumulation[i] > settings.river_threshold)
+        .collect();
+
+    let mut donors = vec![0u32; n];
+    for i in (0..n).filter(|&i| channel[i]) {
+        if let Some(target) = flow_dir[i].filter(|&target| channel[target]) {
+            donors[target] += 1;
+        }
+    }
+
+    // Strahler order over the channel tree, upstream first.
+    let mut order = vec![0u8; n];
+    let mut max_in = vec![0u8; n];
+    let mut max_in_count = vec![0u8; n];
+    let mut pending = donors.clone();
+    let mut queue: Vec<usize> = (0..n).filter(|&i| channel[i] && donors[i] == 0).collect();
+    let mut head = 0;
+    while head < queue.len() {
+        let i = queue[head];
+        head += 1;
+        order[i] = match (max_in[i], max_in_count[i]) {
+            (0, _) => 1,
+            (max, count) if count >= 2 => max.saturating_add(1),
+            (max, _) => max,
+        };
+        let Some(target) = flow_dir[i].filter(|&target| channel[target]) else {
+            continue;
+        };
+        if order[i] > max_in[target] {
+            max_in[target] = order[i];
+            max_in_count[target] = 1;
+        } else if order[i] == max_in[target] {
+            max_in_count[target] = max_in_count[target].saturating_add(1);
+        }
+        pending[target] -= 1;
+        if pending[target] == 0 {
+            queue.push(target);
+        }
+    }
+
+    let fed_by_lake = |i: usize| {
+        donors[i] > 0
+            && neighbors8_in(
+                i as u32 % width,
+                i as u32 / width,
+                width,
+                height_dim,
+                settings.projection,
+            )
+            .iter()
+            .any(|&(_, _, ni)| is_lake(ni) && flow_dir[ni] == Some(i))
+    };
+    let river_cell = |i: usize| channel[i] && !is_lake(i);
+
+    let mut network = RiverNetwork {
+        width,
+        height: height_dim,
+        ..RiverNetwork::default()
+    };
+    let mut node_ids: HashMap<usize, u32> = HashMap::new();
+    let mut node_for = |network: &mut RiverNetwork, cell: usize, kind: RiverNodeKind| {
+        *node_ids.entry(cell).or_insert_with(|| {
+            let id = network.nodes.len() as u32;
+            network.nodes.push(RiverNode {
+                id,
+                kind,
+                x: cell as u32 % width,
+                y: cell as u32 / width,
+            });
+            id
+        })
+    };
+
+    for start in (0..n).filter(|&i| river_cell(i)) {
+        let start_kind = if fed_by_lake(start) {
+            RiverNodeKind::LakeOutlet
+        } else if donors[start] == 0 {
+            RiverNodeKind::Source
+        } else if donors[start] >= 2 {
+            RiverNodeKind::Confluence
+        } else {
+            continue;
+        };
+
+        let mut cells = vec![start];
+        let mut current = start;
+        let end_kind = loop {
+            match flow_dir[current].filter(|&next| channel[next]) {
+                None => break RiverNodeKind::Mouth,
+                Some(next) if is_lake(next) => break RiverNodeKind::LakeInlet,
+                Some(next) => {
+                    cells.push(next);
+                    current = next;
+                    if donors[next] >= 2 || fed_by_lake(next) {
+                        break RiverNodeKind::Confluence;
+                    }
+                }
+            }
+        };
+        if cells.len() < 2 {
+            continue;
+        }
+
+        let end = *cells.last().unwrap_or(&start);
+        // A segment stopping at a junction includes the junction cell, whose accumulation
+        // already counts the other inflows; its own discharge is read one cell upstream.
+        let outflow = if end_kind == RiverNodeKind::Confluence {
+            cells[cells.len() - 2]
+        } else {
+            end
+        };
+        let end_kind = if end_kind == RiverNodeKind::Confluence && fed_by_lake(end) {
+            RiverNodeKind::LakeOutlet
+        } else {
+            end_kind
+        };
+        let from_node = node_for(&mut network, start, start_kind);
+        let to_node = node_for(&mut network, end, end_kind);
+        let raw = unwrap_polyline(&cells, width, settings.projection);
+        let length = raw
+            .windows(2)
+            .map(|pair| distance(pair[0], pair[1]))
+            .sum::<f32>();
+        network.segments.push(RiverSegment {
+            id: network.segments.len() as u32,
+            from_node,
+            to_node,
+            strahler_order: order[start],
+            discharge: hydrology.accumulation[outflow],
+            length,
+            points: chaikin(
+                &douglas_peucker(&raw, SIMPLIFY_TOLERANCE),
+                SMOOTHING_ITERATIONS,
+            ),
+        });
+    }
+
+    network
+}
+
+/// Pixel centers along a channel, shifting x by whole widths so wrap-around steps stay adjacent.
+pub(crate) fn unwrap_polyline(
+    cells: &[usize],
+    width: u32,
+    projection: MapProjection,
+) -> Vec<[f32; 2]> {
+    let mut points: Vec<[f32; 2]> = Vec::with_capacity(cells.len());
+    for &cell in cells {
+        let mut x = (cell as u32 % width) as f32 + 0.5;
+        let y = (cell as u32 / width) as f32 + 0.5;
+        if let Some(previous) = points.last().filter(|_| projection.wraps_x()) {
+            let half = width as f32 / 2.0;
+            while x - previous[0] > half {
+                x -= width as f32;
+            }
+            while previous[0] - x > half {
+                x += width as f32;
+            }
+        }
+        points.push([x, y]);
+    }
+    points
+}
+
+pub(crate) fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+pub(crate) fn douglas_peucker(points: &[[f32; 2]], tolerance: f32) -> Vec<[f32; 2]> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let span = distance(first, last);
+    let offset = |p: [f32; 2]| {
+        if span < f32::EPSILON {
+            distance(p, first)
+        } else {
+            ((last[0] - first[0]) * (first[1] - p[1]) - (first[0] - p[0]) * (last[1] - first[1]))
+                .abs()
+                / span
+        }
+    };
+    let (split, max_offset) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(index, &p)| (index + 1, offset(p)))
+        .fold((0, 0.0f32), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        });
+    if max_offset <= tolerance {
+        return vec![first, last];
+    }
+    let mut left = douglas_peucker(&points[..=split], tolerance);
+    let right = douglas_peucker(&points[split..], tolerance);
+    left.pop();
+    left.extend(right);
+    left
+}
+
+/// Chaikin corner cutting that keeps both endpoints fixed.
+pub(crate) fn chaikin(points: &[[f32; 2]], iterations: usize) -> Vec<[f32; 2]> {
+    let mut current = points.to_vec();
+    for _ in 0..iterations {
+        if current.len() < 3 {
+            break;
+        }
+        let mut next = Vec::with_capacity(current.len() * 2);
+        next.push(current[0]);
+        for pair in current.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            next.push([a[0] * 0.75 + b[0] * 0.25, a[1] * 0.75 + b[1] * 0.25]);
+            next.push([a[0] * 0.25 + b[0] * 0.75, a[1] * 0.25 + b[1] * 0.75]);
+        }
+        next.push(current[current.len() - 1]);
+        current = next;
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 5;
+    const HEIGHT: u32 = 6;
+
+    fn cell(x: u32, y: u32) -> usize {
+        (y * WIDTH + x) as usize
+    }
+
+    /// Two single-cell-wide sources running diagonally into (2, 2), then a trunk straight down to
+    /// the bottom edge.
+    fn two_source_confluence() -> HydrologyResult {
+        let n = (WIDTH * HEIGHT) as usize;
+        let mut flow_dir = vec![None; n];
+        let mut accumulation = vec![0u32; n];
+        let mut route = |from: (u32, u32), to: Option<(u32, u32)>, upstream: u32| {
+            let i = cell(from.0, from.1);
+            flow_dir[i] = to.map(|(x, y)| cell(x, y));
+            accumulation[i] = upstream;
+        };
+        route((0, 0), Some((1, 1)), 1);
+        route((1, 1), Some((2, 2)), 2);
+        route((4, 0), Some((3, 1)), 1);
+        route((3, 1), Some((2, 2)), 2);
+        route((2, 2), Some((2, 3)), 5);
+        route((2, 3), Some((2, 4)), 6);
+        route((2, 4), Some((2, 5)), 7);
+        route((2, 5), None, 8);
+        HydrologyResult {
+            river_mask: vec![0; n],
+            accumulation,
+            flow_dir,
+            lake_mask: vec![0; n],
+            lakes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn strahler_order_increments_below_a_two_source_confluence() {
+        let hydrology = two_source_confluence();
+        let settings = HydrologySettings {
+            river_threshold: 0,
+            ..HydrologySettings::default()
+        };
+        let landmask = vec![true; (WIDTH * HEIGHT) as usize];
+        let network = build_river_network(&hydrology, &landmask, WIDTH, HEIGHT, &settings);
+
+        assert_eq!(network.segments.len(), 3);
+        let node = |id: u32| &network.nodes[id as usize];
+        let confluence = network
+            .nodes
+            .iter()
+            .find(|node| node.kind == RiverNodeKind::Confluence)
+            .expect("confluence node");
+        assert_eq!((confluence.x, confluence.y), (2, 2));
+
+        let (branches, trunk): (Vec<&RiverSegment>, Vec<&RiverSegment>) = network
+            .segments
+            .iter()
+            .partition(|segment| segment.to_node == confluence.id);
+        assert_eq!(branches.len(), 2);
+        for branch in &branches {
+            assert_eq!(node(branch.from_node).kind, RiverNodeKind::Source);
+            assert_eq!(branch.strahler_order, 1);
+            assert_eq!(branch.discharge, 2);
+        }
+
+        let trunk = trunk[0];
+        assert_eq!(trunk.from_node, confluence.id);
+        assert_eq!(node(trunk.to_node).kind, RiverNodeKind::Mouth);
+        assert_eq!(trunk.strahler_order, 2);
+        assert_eq!(trunk.discharge, 8);
+        assert!((trunk.length - 3.0).abs() < 1e-5);
+    }
+}
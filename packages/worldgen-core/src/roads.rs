@@ -0,0 +1,941 @@
+use crate::biome_archetype::BiomeRegistry;
+use crate::config::{MapProjection, WorldgenConfig};
+use crate::graph::{EdgeKind, ProvinceAdjacency};
+use crate::raster::{idx_in, neighbors8_in, step_length};
+use crate::river_network::{self, RiverNetwork};
+use crate::sampling::Seed;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet};
+
+/// Extra stops the network must reach, such as major locations. Optional.
+pub const ROAD_WAYPOINTS_FILE: &str = "road_waypoints.json";
+
+/// `road_mask.png` value under a highway.
+pub const HIGHWAY_MASK: u8 = 255;
+/// `road_mask.png` value under a trail.
+pub const TRAIL_MASK: u8 = 128;
+
+/// Per-pixel cost added on the least suitable biome.
+const BIOME_COST: f64 = 2.0;
+/// Per-pixel cost of following a river channel rather than crossing it.
+const CHANNEL_COST: f64 = 4.0;
+/// Share of the usual cost paid on pixels an earlier route already paved, so similar routes
+/// merge instead of running side by side.
+const REUSE_FACTOR: f64 = 0.4;
+/// A link outside the spanning tree is added when going around it costs this many times more.
+const REDUNDANCY_DETOUR: f64 = 1.6;
+/// Redundancy links allowed, as a share of the spanning tree's links.
+const REDUNDANCY_SHARE: f64 = 0.2;
+/// Stream order from which a crossing is bridged rather than forded.
+const BRIDGE_ORDER: u8 = 3;
+/// River pixels crossed in one go from which a crossing is bridged whatever the order.
+const BRIDGE_WIDTH: usize = 3;
+/// Douglas-Peucker tolerance in pixels applied before smoothing.
+const SIMPLIFY_TOLERANCE: f32 = 1.0;
+/// Chaikin corner-cutting passes applied after simplification.
+const SMOOTHING_ITERATIONS: usize = 2;
+
+const NO_LABEL: u32 = u32::MAX;
+const NO_CELL: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+pub struct RoadSettings {
+    pub cost_slope: f64,
+    pub cost_river_crossing: f64,
+    pub projection: MapProjection,
+}
+
+impl RoadSettings {
+    pub fn for_config(config: &WorldgenConfig) -> Self {
+        Self {
+            cost_slope: config.cost_slope,
+            cost_river_crossing: config.cost_river_crossing,
+            projection: config.projection,
+        }
+    }
+}
+
+/// A stop besides the province seats, in pixel coordinates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoadWaypoint {
+    pub id: String,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Ordered by precedence when two kinds land on the same pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoadNodeKind {
+    /// Where roads meet or end.
+    Junction,
+    Ford,
+    Bridge,
+    /// A waypoint.
+    Location,
+    /// A province seed.
+    Seat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoadClass {
+    Trail,
+    /// Part of the best route between neighbouring duchy seats.
+    Highway,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoadNode {
+    pub id: u32,
+    pub kind: RoadNodeKind,
+    pub x: u32,
+    pub y: u32,
+    pub province_id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waypoint_id: Option<String>,
+}
+
+/// A stretch of road between two nodes with a single class.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoadSegment {
+    pub id: u32,
+    pub from_node: u32,
+    pub to_node: u32,
+    pub class: RoadClass,
+    /// Length in pixels of the unsmoothed road.
+    pub length: f32,
+    /// Smoothed polyline in pixel coordinates, unwrapped across the antimeridian like rivers.
+    pub points: Vec<[f32; 2]>,
+}
+
+/// A link of the network between two stops, which may share segments with other routes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoadRoute {
+    pub from_node: u32,
+    pub to_node: u32,
+    pub from_province: u32,
+    pub to_province: u32,
+    pub class: RoadClass,
+    /// Length in pixels of the unsmoothed road.
+    pub length: f32,
+    pub bridges: u32,
+    pub fords: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoadNetwork {
+    pub width: u32,
+    pub height: u32,
+    pub nodes: Vec<RoadNode>,
+    pub segments: Vec<RoadSegment>,
+    pub routes: Vec<RoadRoute>,
+}
+
+pub struct RoadResult {
+    pub network: RoadNetwork,
+    /// `HIGHWAY_MASK` or `TRAIL_MASK` under roads, 0 elsewhere.
+    pub mask: Vec<u8>,
+}
+
+struct Stop {
+    cell: usize,
+    province: u32,
+    kind: RoadNodeKind,
+    waypoint_id: Option<String>,
+}
+
+struct Link {
+    from: usize,
+    to: usize,
+    cost: f64,
+    class: RoadClass,
+}
+
+#[derive(PartialEq)]
+struct Frontier(f64, usize);
+
+impl Eq for Frontier {}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reverse ordering for min-heap
+        other.0.total_cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Travel cost over land pixels, with search buffers reused between searches.
+struct CostField<'a> {
+    height: &'a [u16],
+    landmask: &'a [bool],
+    labels: &'a [u32],
+    river_order: &'a [u8],
+    terrain: Vec<f64>,
+    settings: &'a RoadSettings,
+    width: u32,
+    height_dim: u32,
+    dist: Vec<f64>,
+    prev: Vec<usize>,
+    touched: Vec<usize>,
+}
+
+impl CostField<'_> {
+    fn step_cost(&self, from: usize, to: usize, step: f64) -> f64 {
+        let h_diff = (self.height[to] as f64 - self.height[from] as f64).abs() / 65535.0;
+        let mut cost = self.terrain[to] + h_diff * self.settings.cost_slope * 200.0;
+        if self.river_order[to] > 0 && self.river_order[from] == 0 {
+            cost += self.settings.cost_river_crossing * (1.0 + 0.25 * self.river_order[to] as f64);
+        }
+        cost * step
+    }
+
+    /// Least-cost search from `start` over the land of the `allowed` provinces, stopping once
+    /// every target is settled. Paved pixels are discounted. Returns each target's cost.
+    fn search(
+        &mut self,
+        start: usize,
+        allowed: &HashSet<u32>,
+        targets: &[usize],
+        paved: &[u8],
+    ) -> Vec<Option<f64>> {
+        for &i in &self.touched {
+            self.dist[i] = f64::MAX;
+            self.prev[i] = NO_CELL;
+        }
+        self.touched.clear();
+
+        let mut remaining: HashSet<usize> = targets.iter().copied().collect();
+        remaining.remove(&start);
+        self.dist[start] = 0.0;
+        self.touched.push(start);
+        let mut heap = BinaryHeap::from([Frontier(0.0, start)]);
+        while let Some(Frontier(cost, i)) = heap.pop() {
+            if cost > self.dist[i] {
+                continue;
+            }
+            remaining.remove(&i);
+            if remaining.is_empty() {
+                break;
+            }
+            let x = i as u32 % self.width;
+            let y = i as u32 / self.width;
+            for (nx, ny, ni) in
+                neighbors8_in(x, y, self.width, self.height_dim, self.settings.projection)
+            {
+                if !self.landmask[ni] || !allowed.contains(&self.labels[ni]) {
+                    continue;
+                }
+                let dx = nx as i32 - x as i32;
+                let dx = if dx.abs() > 1 { -dx.signum() } else { dx };
+                let step = step_length(
+                    self.settings.projection,
+                    y,
+                    dx,
+                    ny as i32 - y as i32,
+                    self.height_dim,
+                );
+                let mut next = self.step_cost(i, ni, step);
+                if paved[ni] > 0 {
+                    next *= REUSE_FACTOR;
+                }
+                let total = cost + next;
+                if total < self.dist[ni] {
+                    if self.dist[ni] == f64::MAX {
+                        self.touched.push(ni);
+                    }
+                    self.dist[ni] = total;
+                    self.prev[ni] = i;
+                    heap.push(Frontier(total, ni));
+                }
+            }
+        }
+        targets
+            .iter()
+            .map(|&target| (self.dist[target] < f64::MAX).then_some(self.dist[target]))
+            .collect()
+    }
+
+    /// Cells from the last search's start to `target`.
+    fn path_to(&self, target: usize) -> Vec<usize> {
+        let mut path = vec![target];
+        let mut cursor = target;
+        while self.prev[cursor] != NO_CELL {
+            cursor = self.prev[cursor];
+            path.push(cursor);
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Pipeline outputs roads are routed over. Label rasters use `u32::MAX` for unlabeled pixels.
+pub struct RoadNetworkInput<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub landmask: &'a [bool],
+    pub height_field: &'a [u16],
+    pub river_mask: &'a [u8],
+    pub rivers: &'a RiverNetwork,
+    pub biome_indices: &'a [u16],
+    pub registry: &'a BiomeRegistry,
+    pub province_labels: &'a [u32],
+    pub duchy_labels: &'a [u32],
+    pub seeds: &'a [Seed],
+    pub adjacency: &'a [ProvinceAdjacency],
+    /// Extra stops placed by hand; see [`ROAD_WAYPOINTS_FILE`].
+    pub waypoints: &'a [RoadWaypoint],
+}
+
+/// Builds a road network linking province seats and waypoints.
+///
+/// Candidate links join stops in the same or neighbouring provinces along least-cost paths over
+/// slope, biome and river crossings. A minimum spanning tree of the candidates is kept, plus
+/// links that save a long detour. Links on the best network route between neighbouring duchy
+/// seats become highways and the rest trails. Highways are laid first and later routes are
+/// drawn to already paved pixels, so similar routes merge. River crossings become bridge or
+/// ford nodes depending on the stream order and width crossed.
+pub fn build_road_network(
+    input: &RoadNetworkInput,
+    settings: &RoadSettings,
+    on_progress: &mut dyn FnMut(f32, &str),
+) -> RoadResult {
+    let RoadNetworkInput {
+        width,
+        height: height_dim,
+        landmask,
+        height_field: height,
+        river_mask,
+        rivers,
+        biome_indices,
+        registry,
+        province_labels: labels,
+        duchy_labels,
+        seeds,
+        adjacency,
+        waypoints,
+    } = *input;
+    let n = (width * height_dim) as usize;
+    let projection = settings.projection;
+
+    on_progress(0.0, "Building travel cost field");
+    let river_order = river_orders(river_mask, rivers, width, height_dim, projection);
+    let terrain: Vec<f64> = (0..n)
+        .map(|i| {
+            let weight = registry
+                .archetypes
+                .get(biome_indices[i] as usize)
+                .map(|archetype| archetype.suitability_weight)
+                .unwrap_or(0.4);
+            let channel = if river_order[i] > 0 {
+                CHANNEL_COST
+            } else {
+                0.0
+            };
+            1.0 + BIOME_COST * (1.0 - weight.clamp(0.0, 1.0) as f64) + channel
+        })
+        .collect();
+    let mut field = CostField {
+        height,
+        landmask,
+        labels,
+        river_order: &river_order,
+        terrain,
+        settings,
+        width,
+        height_dim,
+        dist: vec![f64::MAX; n],
+        prev: vec![NO_CELL; n],
+        touched: Vec::new(),
+    };
+
+    let mut stops: Vec<Stop> = Vec::new();
+    let mut stop_at: HashMap<usize, usize> = HashMap::new();
+    for seed in seeds {
+        let i = (seed.y * width + seed.x) as usize;
+        if i < n && landmask[i] && labels[i] == seed.id && !stop_at.contains_key(&i) {
+            stop_at.insert(i, stops.len());
+            stops.push(Stop {
+                cell: i,
+                province: seed.id,
+                kind: RoadNodeKind::Seat,
+                waypoint_id: None,
+            });
+        }
+    }
+    for waypoint in waypoints {
+        if waypoint.x >= width || waypoint.y >= height_dim {
+            continue;
+        }
+        let i = (waypoint.y * width + waypoint.x) as usize;
+        if !landmask[i] || labels[i] == NO_LABEL || stop_at.contains_key(&i) {
+            continue;
+        }
+        stop_at.insert(i, stops.len());
+        stops.push(Stop {
+            cell: i,
+            province: labels[i],
+            kind: RoadNodeKind::Location,
+            waypoint_id: Some(waypoint.id.clone()),
+        });
+    }
+
+    let mut land_neighbors: HashMap<u32, HashSet<u32>> = HashMap::new();
+    for entry in adjacency {
+        for edge in entry
+            .neighbors
+            .iter()
+            .filter(|edge| edge.kind == EdgeKind::Land)
+        {
+            land_neighbors
+                .entry(entry.province_id)
+                .or_default()
+                .insert(edge.neighbor_id);
+        }
+    }
+    let region = |province: u32| {
+        let mut allowed = land_neighbors.get(&province).cloned().unwrap_or_default();
+        allowed.insert(province);
+        allowed
+    };
+    let mut stops_by_province: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, stop) in stops.iter().enumerate() {
+        stops_by_province
+            .entry(stop.province)
+            .or_default()
+            .push(index);
+    }
+
+    on_progress(10.0, "Costing candidate routes");
+    let unpaved = vec![0u8; n];
+    let mut candidates = Vec::new();
+    for (from, stop) in stops.iter().enumerate() {
+        let allowed = region(stop.province);
+        let mut targets: Vec<usize> = allowed
+            .iter()
+            .filter_map(|province| stops_by_province.get(province))
+            .flatten()
+            .copied()
+            .filter(|&to| to > from)
+            .collect();
+        targets.sort_unstable();
+        if targets.is_empty() {
+            continue;
+        }
+        let cells: Vec<usize> = targets.iter().map(|&to| stops[to].cell).collect();
+        let costs = field.search(stop.cell, &allowed, &cells, &unpaved);
+        for (&to, cost) in targets.iter().zip(costs) {
+            if let Some(cost) = cost {
+                candidates.push(Link {
+                    from,
+                    to,
+                    cost,
+                    class: RoadClass::Trail,
+                });
+            }
+        }
+        if from % 32 == 0 {
+            on_progress(
+                10.0 + 50.0 * from as f32 / stops.len() as f32,
+                "Costing candidate routes",
+            );
+        }
+    }
+    candidates.sort_by(|a, b| {
+        a.cost
+            .total_cmp(&b.cost)
+            .then((a.from, a.to).cmp(&(b.from, b.to)))
+    });
+
+    on_progress(60.0, "Selecting links");
+    let mut root: Vec<usize> = (0..stops.len()).collect();
+    let mut links = Vec::new();
+    let mut spare = Vec::new();
+    for link in candidates {
+        let (a, b) = (
+            find_root(&mut root, link.from),
+            find_root(&mut root, link.to),
+        );
+        if a == b {
+            spare.push(link);
+        } else {
+            root[a] = b;
+            links.push(link);
+        }
+    }
+    let budget = (links.len() as f64 * REDUNDANCY_SHARE).ceil() as usize;
+    let mut redundant = 0;
+    for link in spare {
+        if redundant >= budget {
+            break;
+        }
+        let bound = link.cost * REDUNDANCY_DETOUR;
+        let network = link_graph(&links, stops.len());
+        if network_path(&network, &links, link.from, link.to, bound).is_none() {
+            links.push(link);
+            redundant += 1;
+        }
+    }
+
+    // Highways follow the network between the seats of neighbouring duchies.
+    let mut province_area: HashMap<u32, f64> = HashMap::new();
+    for (i, &label) in labels.iter().enumerate() {
+        if label != NO_LABEL {
+            *province_area.entry(label).or_default() +=
+                projection.row_scale(i as u32 / width, height_dim);
+        }
+    }
+    let mut province_duchy: HashMap<u32, u32> = HashMap::new();
+    let mut duchy_seat: BTreeMap<u32, usize> = BTreeMap::new();
+    for (index, stop) in stops.iter().enumerate() {
+        if stop.kind != RoadNodeKind::Seat || duchy_labels[stop.cell] == NO_LABEL {
+            continue;
+        }
+        let duchy = duchy_labels[stop.cell];
+        province_duchy.insert(stop.province, duchy);
+        let area = |index: usize| province_area.get(&stops[index].province).copied();
+        let seat = duchy_seat.entry(duchy).or_insert(index);
+        if area(index) > area(*seat) {
+            *seat = index;
+        }
+    }
+    let mut duchy_pairs: BTreeSet<(u32, u32)> = BTreeSet::new();
+    for (province, neighbors) in &land_neighbors {
+        for neighbor in neighbors {
+            if let (Some(&a), Some(&b)) =
+                (province_duchy.get(province), province_duchy.get(neighbor))
+            {
+                if a != b {
+                    duchy_pairs.insert((a.min(b), a.max(b)));
+                }
+            }
+        }
+    }
+    let network = link_graph(&links, stops.len());
+    let mut highway = vec![false; links.len()];
+    for (a, b) in duchy_pairs {
+        if let Some(path) = network_path(
+            &network,
+            &links,
+            duchy_seat[&a],
+            duchy_seat[&b],
+            f64::INFINITY,
+        ) {
+            for link in path {
+                highway[link] = true;
+            }
+        }
+    }
+    for (link, &is_highway) in links.iter_mut().zip(&highway) {
+        if is_highway {
+            link.class = RoadClass::Highway;
+        }
+    }
+    links.sort_by(|a, b| {
+        b.class
+            .cmp(&a.class)
+            .then(a.cost.total_cmp(&b.cost))
+            .then((a.from, a.to).cmp(&(b.from, b.to)))
+    });
+
+    on_progress(70.0, "Laying roads");
+    let mut paved = vec![0u8; n];
+    let mut routes = Vec::new();
+    for (index, link) in links.iter().enumerate() {
+        let (from, to) = (&stops[link.from], &stops[link.to]);
+        let allowed = region(from.province);
+        if field.search(from.cell, &allowed, &[to.cell], &paved)[0].is_none() {
+            continue;
+        }
+        let path = field.path_to(to.cell);
+        let value = match link.class {
+            RoadClass::Highway => HIGHWAY_MASK,
+            RoadClass::Trail => TRAIL_MASK,
+        };
+        for &cell in &path {
+            paved[cell] = paved[cell].max(value);
+        }
+        routes.push((link, path));
+        if index % 32 == 0 {
+            on_progress(
+                70.0 + 20.0 * index as f32 / links.len() as f32,
+                "Laying roads",
+            );
+        }
+    }
+
+    on_progress(90.0, "Vectorizing roads");
+    let mut pixel_links: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    let mut step_class: HashMap<(usize, usize), RoadClass> = HashMap::new();
+    let mut node_kind: BTreeMap<usize, RoadNodeKind> = BTreeMap::new();
+    for stop in &stops {
+        node_kind.insert(stop.cell, stop.kind);
+    }
+    let mut crossings = Vec::with_capacity(routes.len());
+    for (link, path) in &routes {
+        for pair in path.windows(2) {
+            pixel_links.entry(pair[0]).or_default().insert(pair[1]);
+            pixel_links.entry(pair[1]).or_default().insert(pair[0]);
+            let class = step_class
+                .entry((pair[0].min(pair[1]), pair[0].max(pair[1])))
+                .or_insert(link.class);
+            *class = (*class).max(link.class);
+        }
+        let (mut bridges, mut fords) = (0, 0);
+        for run in path
+            .split(|&cell| river_order[cell] == 0)
+            .filter(|run| !run.is_empty())
+        {
+            let order = run.iter().map(|&cell| river_order[cell]).max().unwrap_or(1);
+            let kind = if order >= BRIDGE_ORDER || run.len() >= BRIDGE_WIDTH {
+                bridges += 1;
+                RoadNodeKind::Bridge
+            } else {
+                fords += 1;
+                RoadNodeKind::Ford
+            };
+            let entry = node_kind.entry(run[run.len() / 2]).or_insert(kind);
+            *entry = (*entry).max(kind);
+        }
+        crossings.push((bridges, fords));
+    }
+    for (&cell, neighbors) in &pixel_links {
+        if neighbors.len() != 2 {
+            node_kind.entry(cell).or_insert(RoadNodeKind::Junction);
+        }
+    }
+
+    let mut network = RoadNetwork {
+        width,
+        height: height_dim,
+        ..RoadNetwork::default()
+    };
+    let mut node_ids: HashMap<usize, u32> = HashMap::new();
+    let stop_cells = stops.iter().map(|stop| stop.cell);
+    let other_cells = node_kind
+        .keys()
+        .copied()
+        .filter(|cell| !stop_at.contains_key(cell));
+    for cell in stop_cells.chain(other_cells) {
+        let id = network.nodes.len() as u32;
+        node_ids.insert(cell, id);
+        network.nodes.push(RoadNode {
+            id,
+            kind: node_kind[&cell],
+            x: cell as u32 % width,
+            y: cell as u32 / width,
+            province_id: labels[cell],
+            waypoint_id: stop_at
+                .get(&cell)
+                .and_then(|&index| stops[index].waypoint_id.clone()),
+        });
+    }
+
+    let mut walked: HashSet<(usize, usize)> = HashSet::new();
+    for &start in node_kind.keys() {
+        for &first in pixel_links.get(&start).into_iter().flatten() {
+            if walked.contains(&(start, first)) {
+                continue;
+            }
+            let mut cells = vec![start];
+            let mut class = RoadClass::Trail;
+            let (mut previous, mut current) = (start, first);
+            loop {
+                walked.insert((previous, current));
+                walked.insert((current, previous));
+                class = class.max(step_class[&(previous.min(current), previous.max(current))]);
+                cells.push(current);
+                if node_kind.contains_key(&current) {
+                    break;
+                }
+                // Cells that are not nodes have exactly two links.
+                let Some(&next) = pixel_links[&current].iter().find(|&&cell| cell != previous)
+                else {
+                    break;
+                };
+                previous = current;
+                current = next;
+            }
+            let raw = river_network::unwrap_polyline(&cells, width, projection);
+            network.segments.push(RoadSegment {
+                id: network.segments.len() as u32,
+                from_node: node_ids[&start],
+                to_node: node_ids[&current],
+                class,
+                length: polyline_length(&raw),
+                points: river_network::chaikin(
+                    &river_network::douglas_peucker(&raw, SIMPLIFY_TOLERANCE),
+                    SMOOTHING_ITERATIONS,
+                ),
+            });
+        }
+    }
+
+    for ((link, path), (bridges, fords)) in routes.iter().zip(crossings) {
+        let (from, to) = (&stops[link.from], &stops[link.to]);
+        network.routes.push(RoadRoute {
+            from_node: node_ids[&from.cell],
+            to_node: node_ids[&to.cell],
+            from_province: from.province,
+            to_province: to.province,
+            class: link.class,
+            length: polyline_length(&river_network::unwrap_polyline(path, width, projection)),
+            bridges,
+            fords,
+        });
+    }
+
+    on_progress(100.0, "Road network complete");
+    RoadResult {
+        network,
+        mask: paved,
+    }
+}
+
+/// Stream order per river pixel, taken from the vector network where a segment passes and 1
+/// elsewhere on the mask.
+fn river_orders(
+    river_mask: &[u8],
+    rivers: &RiverNetwork,
+    width: u32,
+    height_dim: u32,
+    projection: MapProjection,
+) -> Vec<u8> {
+    let mut order: Vec<u8> = river_mask.iter().map(|&v| u8::from(v > 0)).collect();
+    for segment in &rivers.segments {
+        for pair in segment.points.windows(2) {
+            let steps = (river_network::distance(pair[0], pair[1]) * 2.0)
+                .ceil()
+                .max(1.0) as usize;
+            for step in 0..=steps {
+                let t = step as f32 / steps as f32;
+                let x = pair[0][0] + (pair[1][0] - pair[0][0]) * t;
+                let y = pair[0][1] + (pair[1][1] - pair[0][1]) * t;
+                let Some(i) = idx_in(
+                    x.floor() as i32,
+                    y.floor() as i32,
+                    width,
+                    height_dim,
+                    projection,
+                ) else {
+                    continue;
+                };
+                // Smoothing pulls the polyline off the mask, so stamp the surrounding pixels too.
+                let around = neighbors8_in(
+                    i as u32 % width,
+                    i as u32 / width,
+                    width,
+                    height_dim,
+                    projection,
+                );
+                for cell in around.into_iter().map(|(_, _, cell)| cell).chain([i]) {
+                    if river_mask[cell] > 0 {
+                        order[cell] = order[cell].max(segment.strahler_order);
+                    }
+                }
+            }
+        }
+    }
+    order
+}
+
+fn find_root(root: &mut [usize], mut i: usize) -> usize {
+    while root[i] != i {
+        root[i] = root[root[i]];
+        i = root[i];
+    }
+    i
+}
+
+/// Links touching each stop.
+fn link_graph(links: &[Link], stop_count: usize) -> Vec<Vec<usize>> {
+    let mut graph = vec![Vec::new(); stop_count];
+    for (index, link) in links.iter().enumerate() {
+        graph[link.from].push(index);
+        graph[link.to].push(index);
+    }
+    graph
+}
+
+/// Cheapest chain of links from `from` to `to` costing less than `bound`.
+fn network_path(
+    graph: &[Vec<usize>],
+    links: &[Link],
+    from: usize,
+    to: usize,
+    bound: f64,
+) -> Option<Vec<usize>> {
+    let mut dist = vec![f64::MAX; graph.len()];
+    let mut via: Vec<Option<usize>> = vec![None; graph.len()];
+    dist[from] = 0.0;
+    let mut heap = BinaryHeap::from([Frontier(0.0, from)]);
+    while let Some(Frontier(cost, stop)) = heap.pop() {
+        if cost > dist[stop] {
+            continue;
+        }
+        if stop == to {
+            let mut path = Vec::new();
+            let mut cursor = to;
+            while let Some(link) = via[cursor] {
+                path.push(link);
+                cursor = if links[link].from == cursor {
+                    links[link].to
+                } else {
+                    links[link].from
+                };
+            }
+            return Some(path);
+        }
+        for &link in graph.get(stop).into_iter().flatten() {
+            let other = if links[link].from == stop {
+                links[link].to
+            } else {
+                links[link].from
+            };
+            let total = cost + links[link].cost;
+            if total < bound && total < dist[other] {
+                dist[other] = total;
+                via[other] = Some(link);
+                heap.push(Frontier(total, other));
+            }
+        }
+    }
+    None
+}
+
+fn polyline_length(points: &[[f32; 2]]) -> f32 {
+    points
+        .windows(2)
+        .map(|pair| river_network::distance(pair[0], pair[1]))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::build_adjacency;
+    use crate::river_network::RiverSegment;
+
+    const WIDTH: u32 = 21;
+    const HEIGHT: u32 = 9;
+    /// First column of the river running from the top edge to the bottom edge.
+    const RIVER_X: u32 = 10;
+
+    /// Routes a road between two provinces split by a straight river `river_width` pixels wide,
+    /// with the given vector network laid over it.
+    fn cross_river(river_width: u32, rivers: &RiverNetwork) -> RoadNetwork {
+        let n = (WIDTH * HEIGHT) as usize;
+        let labels: Vec<u32> = (0..WIDTH * HEIGHT)
+            .map(|i| u32::from(i % WIDTH >= RIVER_X))
+            .collect();
+        let river_mask: Vec<u8> = (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let x = i % WIDTH;
+                if (RIVER_X..RIVER_X + river_width).contains(&x) {
+                    255
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let height_field = vec![40_000u16; n];
+        let landmask = vec![true; n];
+        let adjacency = build_adjacency(
+            &labels,
+            &height_field,
+            &river_mask,
+            WIDTH,
+            HEIGHT,
+            MapProjection::Flat,
+            &mut |_, _| {},
+        );
+        let seeds = [Seed { id: 0, x: 3, y: 4 }, Seed { id: 1, x: 17, y: 4 }];
+        let input = RoadNetworkInput {
+            width: WIDTH,
+            height: HEIGHT,
+            landmask: &landmask,
+            height_field: &height_field,
+            river_mask: &river_mask,
+            rivers,
+            biome_indices: &vec![0; n],
+            registry: &BiomeRegistry::default_registry(),
+            province_labels: &labels,
+            duchy_labels: &labels,
+            seeds: &seeds,
+            adjacency: &adjacency,
+            waypoints: &[],
+        };
+        let settings = RoadSettings {
+            cost_slope: 1.0,
+            cost_river_crossing: 5.0,
+            projection: MapProjection::Flat,
+        };
+        build_road_network(&input, &settings, &mut |_, _| {}).network
+    }
+
+    /// The single route's bridge and ford counts, and the crossing nodes on the river.
+    fn crossings(network: &RoadNetwork, river_width: u32) -> (u32, u32, Vec<RoadNodeKind>) {
+        assert_eq!(network.routes.len(), 1);
+        let route = &network.routes[0];
+        let on_river = network
+            .nodes
+            .iter()
+            .filter(|node| (RIVER_X..RIVER_X + river_width).contains(&node.x))
+            .map(|node| node.kind)
+            .collect();
+        (route.bridges, route.fords, on_river)
+    }
+
+    #[test]
+    fn narrow_minor_river_is_forded() {
+        let network = cross_river(1, &RiverNetwork::default());
+
+        assert_eq!(crossings(&network, 1), (0, 1, vec![RoadNodeKind::Ford]));
+    }
+
+    #[test]
+    fn high_order_river_is_bridged() {
+        let rivers = RiverNetwork {
+            width: WIDTH,
+            height: HEIGHT,
+            nodes: Vec::new(),
+            segments: vec![RiverSegment {
+                id: 0,
+                from_node: 0,
+                to_node: 1,
+                strahler_order: BRIDGE_ORDER,
+                discharge: 1000,
+                length: (HEIGHT - 1) as f32,
+                points: vec![
+                    [RIVER_X as f32 + 0.5, 0.5],
+                    [RIVER_X as f32 + 0.5, HEIGHT as f32 - 0.5],
+                ],
+            }],
+        };
+        let network = cross_river(1, &rivers);
+
+        assert_eq!(crossings(&network, 1), (1, 0, vec![RoadNodeKind::Bridge]));
+    }
+
+    #[test]
+    fn wide_river_is_bridged_whatever_its_order() {
+        let width = BRIDGE_WIDTH as u32;
+        let network = cross_river(width, &RiverNetwork::default());
+
+        assert_eq!(
+            crossings(&network, width),
+            (1, 0, vec![RoadNodeKind::Bridge])
+        );
+    }
+}
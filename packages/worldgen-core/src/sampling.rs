@@ -2,6 +2,9 @@ use rand::Rng;
 use rand::SeedableRng;
 use rand_pcg::Pcg64;
 
+use crate::config::{MapProjection, WorldgenConfig};
+use crate::raster::neighbors8_in;
+
 /// Seed point for province generation.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Seed {
@@ -11,18 +14,48 @@ pub struct Seed {
 }
 
 /// Stage 7: Weighted Poisson disk seed placement.
+/// How many seeds to place and how far apart.
+#[derive(Debug, Clone, Copy)]
+pub struct SeedSettings {
+    pub target_count: u32,
+    /// Seed spacing where suitability is highest.
+    pub radius_min: f64,
+    /// Seed spacing where suitability is lowest.
+    pub radius_max: f64,
+    pub seed: u64,
+    pub projection: MapProjection,
+}
+
+impl SeedSettings {
+    pub fn for_config(config: &WorldgenConfig) -> Self {
+        Self {
+            target_count: config.counties,
+            radius_min: config.seed_radius_min,
+            radius_max: config.seed_radius_max,
+            seed: config.seed,
+            projection: config.projection,
+        }
+    }
+}
+
 /// Places N seeds distributed by suitability — denser where suitability is high.
+/// Radii are ground distances, so an equirectangular map packs more seeds per pixel row near
+/// the poles.
 pub fn place_seeds(
     suitability: &[f32],
     landmask: &[bool],
     width: u32,
     height: u32,
-    target_count: u32,
-    radius_min: f64,
-    radius_max: f64,
-    rng_seed: u64,
+    settings: &SeedSettings,
     on_progress: &mut dyn FnMut(f32, &str),
 ) -> Vec<Seed> {
+    let SeedSettings {
+        target_count,
+        radius_min,
+        radius_max,
+        seed: rng_seed,
+        projection,
+    } = *settings;
     let n = (width * height) as usize;
     let mut rng = Pcg64::seed_from_u64(rng_seed);
 
@@ -71,7 +104,7 @@ pub fn place_seeds(
     while (seeds.len() as u32) < target_count && attempts < max_attempts {
         attempts += 1;
 
-        if attempts % 500 == 0 {
+        if attempts.is_multiple_of(500) {
             let progress = 20.0 + (seeds.len() as f32 / target_count as f32) * 70.0;
             on_progress(progress, "Placing seeds");
         }
@@ -92,6 +125,9 @@ pub fn place_seeds(
         let gx = (x as f64 / cell_size) as usize;
         let gy = (y as f64 / cell_size) as usize;
         let gr = (local_radius / cell_size).ceil() as usize + 1;
+        // A ground radius spans more pixels of longitude toward the poles
+        let row_scale = projection.row_scale(y, height).max(0.01);
+        let gr_x = ((local_radius / row_scale / cell_size).ceil() as usize + 1).min(grid_w);
 
         let mut too_close = false;
         'check: for dy in 0..=(2 * gr) {
@@ -103,9 +139,11 @@ pub fn place_seeds(
             if check_gy >= grid_h {
                 continue;
             }
-            for dx in 0..=(2 * gr) {
-                let check_gx = if gx + gr >= dx {
-                    gx + gr - dx
+            for dx in 0..=(2 * gr_x) {
+                let check_gx = if projection.wraps_x() {
+                    (gx + grid_w * 2 + gr_x - dx) % grid_w
+                } else if gx + gr_x >= dx {
+                    gx + gr_x - dx
                 } else {
                     continue;
                 };
@@ -117,11 +155,16 @@ pub fn place_seeds(
                     let ddx = x as f64 - existing.x as f64;
                     let ddy = y as f64 - existing.y as f64;
                     // Handle x-wrapping
-                    let ddx_wrap = ddx
-                        .abs()
-                        .min((ddx + width as f64).abs())
-                        .min((ddx - width as f64).abs());
-                    let dist = (ddx_wrap * ddx_wrap + ddy * ddy).sqrt();
+                    let ddx_wrap = if projection.wraps_x() {
+                        ddx.abs()
+                            .min((ddx + width as f64).abs())
+                            .min((ddx - width as f64).abs())
+                    } else {
+                        ddx.abs()
+                    };
+                    let mid_scale = (row_scale + projection.row_scale(existing.y, height)) / 2.0;
+                    let ddx_ground = ddx_wrap * mid_scale;
+                    let dist = (ddx_ground * ddx_ground + ddy * ddy).sqrt();
                     if dist < local_radius {
                         too_close = true;
                         break 'check;
@@ -159,7 +202,6 @@ pub fn place_seeds(
                 let mut stack = vec![i];
                 visited[i] = true;
 
-                use crate::raster::neighbors8;
                 while let Some(ci) = stack.pop() {
                     let cx = (ci % width as usize) as u32;
                     let cy = (ci / width as usize) as u32;
@@ -172,7 +214,7 @@ pub fn place_seeds(
                         has_seed = true;
                     }
 
-                    for (_, _, ni) in neighbors8(cx, cy, width, height) {
+                    for (_, _, ni) in neighbors8_in(cx, cy, width, height, projection) {
                         if landmask[ni] && !visited[ni] {
                             visited[ni] = true;
                             stack.push(ni);
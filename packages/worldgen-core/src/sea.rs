@@ -0,0 +1,668 @@
+use crate::biome_archetype::BiomeRegistry;
+use crate::config::{MapProjection, WorldgenConfig};
+use crate::graph::{EdgeInfo, EdgeKind, ProvinceAdjacency};
+use crate::height;
+use crate::raster::{distance_transform, idx_in, neighbors8_in, step_length};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Sea zone ids start here so they never collide with land province ids in the adjacency graph.
+/// Still fits the 24-bit id textures.
+pub const SEA_ZONE_ID_BASE: u32 = 1 << 20;
+
+/// Cost multiplier for growing a zone across a depth band boundary.
+const BAND_CROSSING_COST: f64 = 4.0;
+
+const NO_LABEL: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepthBand {
+    Coastal,
+    Shelf,
+    Deep,
+    Abyssal,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeaZoneSettings {
+    pub target_count: u32,
+    /// Widest water gap, in pixels, still crossable as a strait.
+    pub strait_max_width: u32,
+    pub projection: MapProjection,
+}
+
+impl SeaZoneSettings {
+    pub fn for_config(config: &WorldgenConfig) -> Self {
+        Self {
+            target_count: config.sea_zones,
+            strait_max_width: config.strait_max_width,
+            projection: config.projection,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeaZoneRecord {
+    pub id: u32,
+    pub seed_x: u32,
+    pub seed_y: u32,
+    /// Pixel count, in equator pixels under an equirectangular projection.
+    pub area: u32,
+    /// Most common depth band in the zone.
+    pub depth_band: DepthBand,
+    /// Land provinces sharing a coastline with the zone.
+    pub coastal_province_ids: Vec<u32>,
+}
+
+/// A narrow water gap between two land provinces that do not share a border.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StraitRecord {
+    pub from_province: u32,
+    pub to_province: u32,
+    /// Sea zone at the middle of the crossing.
+    pub sea_zone_id: u32,
+    /// Water pixels crossed.
+    pub width: u32,
+    pub start: [u32; 2],
+    pub stop: [u32; 2],
+}
+
+pub struct SeaZoneResult {
+    /// Sea zone id per pixel, `u32::MAX` on land.
+    pub labels: Vec<u32>,
+    pub zones: Vec<SeaZoneRecord>,
+    pub straits: Vec<StraitRecord>,
+}
+
+#[derive(PartialEq)]
+struct Frontier(f64, usize);
+
+impl Eq for Frontier {}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reverse ordering for min-heap
+        other.0.total_cmp(&self.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Rasters sea zones are partitioned from. `province_labels` uses `u32::MAX` for water.
+pub struct SeaZoneInput<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub landmask: &'a [bool],
+    pub province_labels: &'a [u32],
+    pub biome_indices: &'a [u16],
+    pub registry: &'a BiomeRegistry,
+}
+
+/// Partitions water into sea zones.
+///
+/// Water is split into depth bands from the biome water classes plus a coastal band, then
+/// seeded by farthest-point sampling and grown by geodesic distance. Crossing a band is
+/// expensive, so zone borders settle along depth changes. Every connected water body gets at
+/// least one zone; the rest of `target_count` is shared by area.
+pub fn partition_sea_zones(
+    input: &SeaZoneInput,
+    settings: &SeaZoneSettings,
+    on_progress: &mut dyn FnMut(f32, &str),
+) -> SeaZoneResult {
+    let SeaZoneInput {
+        width,
+        height: height_dim,
+        landmask,
+        province_labels,
+        biome_indices,
+        registry,
+    } = *input;
+    let n = (width * height_dim) as usize;
+    let projection = settings.projection;
+    let row_area = |i: usize| projection.row_scale(i as u32 / width, height_dim);
+
+    on_progress(0.0, "Classifying depth bands");
+    let water_area: f64 = (0..n).filter(|&i| !landmask[i]).map(row_area).sum();
+    let zone_radius =
+        (water_area / settings.target_count.max(1) as f64 / std::f64::consts::PI).sqrt();
+    let coastal_width = (zone_radius / 2.0).max(2.0) as f32;
+    let dist_from_land = distance_transform(landmask, width, height_dim);
+    let band: Vec<DepthBand> = (0..n)
+        .map(|i| {
+            let id = registry
+                .archetypes
+                .get(biome_indices[i] as usize)
+                .map(|a| a.id.as_str())
+                .unwrap_or("");
+            if dist_from_land[i] <= coastal_width {
+                DepthBand::Coastal
+            } else if id.contains("abyssal") {
+                DepthBand::Abyssal
+            } else if id.contains("deep") {
+                DepthBand::Deep
+            } else {
+                DepthBand::Shelf
+            }
+        })
+        .collect();
+
+    on_progress(10.0, "Finding water bodies");
+    let mut body = vec![NO_LABEL; n];
+    let mut bodies: Vec<(Vec<usize>, f64)> = Vec::new();
+    for start in 0..n {
+        if landmask[start] || body[start] != NO_LABEL {
+            continue;
+        }
+        let id = bodies.len() as u32;
+        body[start] = id;
+        let mut cells = vec![start];
+        let mut head = 0;
+        while head < cells.len() {
+            let i = cells[head];
+            head += 1;
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
+                if !landmask[ni] && body[ni] == NO_LABEL {
+                    body[ni] = id;
+                    cells.push(ni);
+                }
+            }
+        }
+        let area = cells.iter().map(|&i| row_area(i)).sum();
+        bodies.push((cells, area));
+    }
+
+    // Farthest-point seeding; each new seed claims the cells it reaches first, so the final
+    // owners are the geodesic Voronoi cells of all seeds.
+    let step_cost = |i: usize, ni: usize| {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let (nx, ny) = (ni as u32 % width, ni as u32 / width);
+        let mut dx = nx as i32 - x as i32;
+        if dx.unsigned_abs() > 1 {
+            dx = -dx.signum();
+        }
+        let length = step_length(projection, y, dx, ny as i32 - y as i32, height_dim);
+        if band[i] == band[ni] {
+            length
+        } else {
+            length * BAND_CROSSING_COST
+        }
+    };
+    let mut dist = vec![f64::MAX; n];
+    let mut owner = vec![NO_LABEL; n];
+    let mut seeds: Vec<usize> = Vec::new();
+    let sample_stride = (zone_radius / 4.0).max(1.0) as usize;
+    for (body_index, (cells, area)) in bodies.iter().enumerate() {
+        let share = (settings.target_count as f64 * area / water_area.max(1.0)).round();
+        let count = (share as usize).max(1);
+        let candidates: Vec<usize> = cells.iter().copied().step_by(sample_stride).collect();
+        for k in 0..count {
+            let next = if k == 0 {
+                // Start far out to sea so the first zone is not a sliver along the coast.
+                cells.iter().copied().max_by(|&a, &b| {
+                    dist_from_land[a]
+                        .total_cmp(&dist_from_land[b])
+                        .then(b.cmp(&a))
+                })
+            } else {
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|&i| dist[i] > 0.0)
+                    .max_by(|&a, &b| dist[a].total_cmp(&dist[b]).then(b.cmp(&a)))
+            };
+            let Some(seed) = next else {
+                break;
+            };
+            let zone = seeds.len() as u32;
+            seeds.push(seed);
+            dist[seed] = 0.0;
+            owner[seed] = zone;
+            let mut heap = BinaryHeap::from([Frontier(0.0, seed)]);
+            while let Some(Frontier(d, i)) = heap.pop() {
+                if d > dist[i] {
+                    continue;
+                }
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
+                    if landmask[ni] {
+                        continue;
+                    }
+                    let nd = d + step_cost(i, ni);
+                    if nd < dist[ni] {
+                        dist[ni] = nd;
+                        owner[ni] = zone;
+                        heap.push(Frontier(nd, ni));
+                    }
+                }
+            }
+        }
+        on_progress(
+            15.0 + 65.0 * (body_index + 1) as f32 / bodies.len() as f32,
+            "Growing sea zones",
+        );
+    }
+
+    on_progress(80.0, "Summarizing sea zones");
+    let labels: Vec<u32> = owner
+        .iter()
+        .map(|&zone| {
+            if zone == NO_LABEL {
+                NO_LABEL
+            } else {
+                SEA_ZONE_ID_BASE + zone
+            }
+        })
+        .collect();
+    let mut area = vec![0.0f64; seeds.len()];
+    let mut band_counts: Vec<HashMap<DepthBand, u32>> = vec![HashMap::new(); seeds.len()];
+    let mut coasts: Vec<HashSet<u32>> = vec![HashSet::new(); seeds.len()];
+    for i in (0..n).filter(|&i| owner[i] != NO_LABEL) {
+        let zone = owner[i] as usize;
+        area[zone] += row_area(i);
+        *band_counts[zone].entry(band[i]).or_insert(0) += 1;
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
+            if landmask[ni] && province_labels[ni] != NO_LABEL {
+                coasts[zone].insert(province_labels[ni]);
+            }
+        }
+    }
+    let zones: Vec<SeaZoneRecord> = seeds
+        .iter()
+        .enumerate()
+        .map(|(zone, &seed)| {
+            let mut coastal_province_ids: Vec<u32> = coasts[zone].iter().copied().collect();
+            coastal_province_ids.sort_unstable();
+            let depth_band = band_counts[zone]
+                .iter()
+                .max_by_key(|&(&band, &count)| (count, std::cmp::Reverse(band as u8)))
+                .map(|(&band, _)| band)
+                .unwrap_or(DepthBand::Shelf);
+            SeaZoneRecord {
+                id: SEA_ZONE_ID_BASE + zone as u32,
+                seed_x: seed as u32 % width,
+                seed_y: seed as u32 / width,
+                area: area[zone].round() as u32,
+                depth_band,
+                coastal_province_ids,
+            }
+        })
+        .collect();
+
+    on_progress(90.0, "Detecting straits");
+    let straits = find_straits(
+        province_labels,
+        &labels,
+        width,
+        height_dim,
+        settings.strait_max_width,
+        projection,
+    );
+
+    on_progress(100.0, "Sea zones complete");
+    SeaZoneResult {
+        labels,
+        zones,
+        straits,
+    }
+}
+
+/// Shortest straight water crossing, up to `max_width` sea pixels, between each pair of land
+/// provinces that do not already share a border. Rays are cast in four directions from every
+/// land pixel; lakes and unlabeled land stop a ray.
+pub fn find_straits(
+    province_labels: &[u32],
+    sea_labels: &[u32],
+    width: u32,
+    height_dim: u32,
+    max_width: u32,
+    projection: MapProjection,
+) -> Vec<StraitRecord> {
+    let mut bordering: HashSet<(u32, u32)> = HashSet::new();
+    for y in 0..height_dim {
+        for x in 0..width {
+            let a = province_labels[(y * width + x) as usize];
+            if a == NO_LABEL {
+                continue;
+            }
+            for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
+                let b = province_labels[ni];
+                if b != NO_LABEL && b != a {
+                    bordering.insert((a.min(b), a.max(b)));
+                }
+            }
+        }
+    }
+
+    let mut best: HashMap<(u32, u32), StraitRecord> = HashMap::new();
+    for y in 0..height_dim as i32 {
+        for x in 0..width as i32 {
+            let from = province_labels[(y as u32 * width + x as u32) as usize];
+            if from == NO_LABEL {
+                continue;
+            }
+            for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                let mut crossed: Vec<usize> = Vec::new();
+                let mut step = 1;
+                while let Some(i) =
+                    idx_in(x + dx * step, y + dy * step, width, height_dim, projection)
+                {
+                    if sea_labels[i] != NO_LABEL && crossed.len() < max_width as usize {
+                        crossed.push(i);
+                        step += 1;
+                        continue;
+                    }
+                    let to = province_labels[i];
+                    let key = (from.min(to), from.max(to));
+                    if !crossed.is_empty()
+                        && sea_labels[i] == NO_LABEL
+                        && to != NO_LABEL
+                        && to != from
+                        && !bordering.contains(&key)
+                        && best
+                            .get(&key)
+                            .is_none_or(|b| (crossed.len() as u32) < b.width)
+                    {
+                        let point = |p: i32, q: i32| [p as u32 % width, q as u32];
+                        let start = point(x, y);
+                        let stop = [i as u32 % width, i as u32 / width];
+                        let (start, stop) = if from < to {
+                            (start, stop)
+                        } else {
+                            (stop, start)
+                        };
+                        best.insert(
+                            key,
+                            StraitRecord {
+                                from_province: key.0,
+                                to_province: key.1,
+                                sea_zone_id: sea_labels[crossed[crossed.len() / 2]],
+                                width: crossed.len() as u32,
+                                start,
+                                stop,
+                            },
+                        );
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    let mut straits: Vec<StraitRecord> = best.into_values().collect();
+    straits.sort_by_key(|s| (s.from_province, s.to_province));
+    straits
+}
+
+/// Adds coast (land–sea), sea (sea–sea) and strait edges to a land adjacency graph, and an
+/// entry for every sea zone. Naval edges sit at sea level.
+pub fn add_naval_edges(
+    adjacency: &mut Vec<ProvinceAdjacency>,
+    province_labels: &[u32],
+    sea_labels: &[u32],
+    straits: &[StraitRecord],
+    width: u32,
+    height_dim: u32,
+    projection: MapProjection,
+) {
+    // (lo, hi) -> (border pixels counted from both sides, kind)
+    let mut borders: HashMap<(u32, u32), (u32, EdgeKind)> = HashMap::new();
+    for y in 0..height_dim {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            let Some(a) = label_at(province_labels, sea_labels, i) else {
+                continue;
+            };
+            for (_, _, ni) in neighbors8_in(x, y, width, height_dim, projection) {
+                let Some(b) = label_at(province_labels, sea_labels, ni) else {
+                    continue;
+                };
+                let a_sea = sea_labels[i] != NO_LABEL;
+                let b_sea = sea_labels[ni] != NO_LABEL;
+                if a == b || !(a_sea || b_sea) {
+                    continue;
+                }
+                let kind = if a_sea && b_sea {
+                    EdgeKind::Sea
+                } else {
+                    EdgeKind::Coast
+                };
+                borders.entry((a.min(b), a.max(b))).or_insert((0, kind)).0 += 1;
+            }
+        }
+    }
+
+    let sea_level = height::SEA_LEVEL as f32;
+    let mut edges: HashMap<u32, Vec<EdgeInfo>> = HashMap::new();
+    let mut push = |a: u32, b: u32, length: u32, kind: EdgeKind, through: Option<u32>| {
+        for (from, to) in [(a, b), (b, a)] {
+            edges.entry(from).or_default().push(EdgeInfo {
+                neighbor_id: to,
+                shared_border_length: length,
+                crosses_river: false,
+                mean_border_height: sea_level,
+                kind,
+                through_sea_zone: through,
+            });
+        }
+    };
+    for (&(a, b), &(count, kind)) in &borders {
+        push(a, b, count / 2, kind, None);
+    }
+    for strait in straits {
+        push(
+            strait.from_province,
+            strait.to_province,
+            0,
+            EdgeKind::Strait,
+            Some(strait.sea_zone_id),
+        );
+    }
+
+    let mut zone_ids: Vec<u32> = sea_labels
+        .iter()
+        .copied()
+        .filter(|&l| l != NO_LABEL)
+        .collect();
+    zone_ids.sort_unstable();
+    zone_ids.dedup();
+    for zone in zone_ids {
+        if !adjacency.iter().any(|a| a.province_id == zone) {
+            adjacency.push(ProvinceAdjacency {
+                province_id: zone,
+                neighbors: Vec::new(),
+            });
+        }
+    }
+    for entry in adjacency.iter_mut() {
+        if let Some(mut extra) = edges.remove(&entry.province_id) {
+            extra.sort_by_key(|edge| Reverse(edge.shared_border_length));
+            entry.neighbors.extend(extra);
+        }
+    }
+}
+
+fn label_at(province_labels: &[u32], sea_labels: &[u32], i: usize) -> Option<u32> {
+    if sea_labels[i] != NO_LABEL {
+        Some(sea_labels[i])
+    } else if province_labels[i] != NO_LABEL {
+        Some(province_labels[i])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Province labels for a map where every row reads `row`: digits are land provinces and `~`
+    /// is water.
+    fn labels(row: &str, height: u32) -> (u32, Vec<u32>) {
+        let columns: Vec<u32> = row
+            .chars()
+            .map(|c| c.to_digit(10).unwrap_or(NO_LABEL))
+            .collect();
+        let labels = (0..height).flat_map(|_| columns.iter().copied()).collect();
+        (columns.len() as u32, labels)
+    }
+
+    fn partition(row: &str, height: u32, target_count: u32) -> (u32, Vec<u32>, SeaZoneResult) {
+        let (width, province_labels) = labels(row, height);
+        let landmask: Vec<bool> = province_labels.iter().map(|&l| l != NO_LABEL).collect();
+        let input = SeaZoneInput {
+            width,
+            height,
+            landmask: &landmask,
+            province_labels: &province_labels,
+            biome_indices: &vec![0; province_labels.len()],
+            registry: &BiomeRegistry::default_registry(),
+        };
+        let settings = SeaZoneSettings {
+            target_count,
+            strait_max_width: 3,
+            projection: MapProjection::Flat,
+        };
+        let result = partition_sea_zones(&input, &settings, &mut |_, _| {});
+        (width, province_labels, result)
+    }
+
+    #[test]
+    fn every_water_body_gets_a_zone_and_every_water_pixel_a_label() {
+        let (width, province_labels, result) = partition("~~~~00000~~~~~~~~~~~", 10, 3);
+
+        assert_eq!(result.zones.len(), 3);
+        for (i, (&province, &zone)) in province_labels.iter().zip(&result.labels).enumerate() {
+            if province == NO_LABEL {
+                assert!(zone >= SEA_ZONE_ID_BASE, "water pixel {i} has no zone");
+            } else {
+                assert_eq!(zone, NO_LABEL, "land pixel {i} has a zone");
+            }
+        }
+        // The western strip is its own water body, so it holds a zone of its own.
+        let west: HashSet<u32> = (0..10)
+            .map(|y| result.labels[(y * width) as usize])
+            .collect();
+        let east: HashSet<u32> = (0..10)
+            .map(|y| result.labels[(y * width + width - 1) as usize])
+            .collect();
+        assert_eq!(west.len(), 1);
+        assert!(west.is_disjoint(&east));
+
+        let water = province_labels.iter().filter(|&&l| l == NO_LABEL).count() as u32;
+        assert_eq!(result.zones.iter().map(|z| z.area).sum::<u32>(), water);
+        for zone in &result.zones {
+            assert!(zone.coastal_province_ids.iter().all(|&id| id == 0));
+            if west.contains(&zone.id) {
+                assert_eq!(zone.coastal_province_ids, vec![0]);
+            }
+        }
+    }
+
+    #[test]
+    fn straits_cross_narrow_channels_between_unconnected_provinces() {
+        let (width, province_labels) = labels("00~~11~~~~2233", 4);
+        let sea_labels: Vec<u32> = province_labels
+            .iter()
+            .map(|&l| {
+                if l == NO_LABEL {
+                    SEA_ZONE_ID_BASE
+                } else {
+                    NO_LABEL
+                }
+            })
+            .collect();
+        let straits = find_straits(
+            &province_labels,
+            &sea_labels,
+            width,
+            4,
+            3,
+            MapProjection::Flat,
+        );
+
+        // 1 and 2 are four pixels apart, past the limit; 2 and 3 share a border.
+        assert_eq!(straits.len(), 1);
+        let strait = &straits[0];
+        assert_eq!((strait.from_province, strait.to_province), (0, 1));
+        assert_eq!(strait.width, 2);
+        assert_eq!(strait.sea_zone_id, SEA_ZONE_ID_BASE);
+        assert_eq!(strait.start[0], 1);
+        assert_eq!(strait.stop[0], 4);
+    }
+
+    #[test]
+    fn naval_edges_are_tagged_by_kind() {
+        let (width, province_labels, result) = partition("00~~11~~~~~~~~~~~~~~", 8, 2);
+        assert_eq!(result.straits.len(), 1);
+        let mut adjacency = vec![
+            ProvinceAdjacency {
+                province_id: 0,
+                neighbors: Vec::new(),
+            },
+            ProvinceAdjacency {
+                province_id: 1,
+                neighbors: Vec::new(),
+            },
+        ];
+        add_naval_edges(
+            &mut adjacency,
+            &province_labels,
+            &result.labels,
+            &result.straits,
+            width,
+            8,
+            MapProjection::Flat,
+        );
+
+        let kinds = |id: u32| -> Vec<(u32, EdgeKind)> {
+            let entry = adjacency.iter().find(|a| a.province_id == id).unwrap();
+            let mut kinds: Vec<_> = entry
+                .neighbors
+                .iter()
+                .map(|edge| (edge.neighbor_id, edge.kind))
+                .collect();
+            kinds.sort_by_key(|&(neighbor, _)| neighbor);
+            kinds
+        };
+        let zones: Vec<u32> = result.zones.iter().map(|z| z.id).collect();
+        assert_eq!(adjacency.len(), 2 + zones.len());
+
+        let province_0 = kinds(0);
+        assert!(province_0.contains(&(1, EdgeKind::Strait)));
+        assert!(province_0
+            .iter()
+            .filter(|(neighbor, _)| zones.contains(neighbor))
+            .all(|&(_, kind)| kind == EdgeKind::Coast));
+        for &zone in &zones {
+            for (neighbor, kind) in kinds(zone) {
+                let expected = if zones.contains(&neighbor) {
+                    EdgeKind::Sea
+                } else {
+                    EdgeKind::Coast
+                };
+                assert_eq!(kind, expected, "zone {zone} to {neighbor}");
+            }
+        }
+        assert!(zones
+            .iter()
+            .any(|&zone| kinds(zone).iter().any(|&(_, kind)| kind == EdgeKind::Sea)));
+
+        let strait = adjacency[0]
+            .neighbors
+            .iter()
+            .find(|edge| edge.kind == EdgeKind::Strait)
+            .unwrap();
+        assert_eq!(strait.through_sea_zone, Some(result.straits[0].sea_zone_id));
+        assert_eq!(strait.mean_border_height, height::SEA_LEVEL as f32);
+    }
+}
@@ -1,18 +1,31 @@
 use crate::biome_archetype::BiomeRegistry;
 use crate::raster::distance_transform;
 
+/// Rasters settlement suitability is scored from.
+pub struct SuitabilityInput<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub height_field: &'a [u16],
+    pub landmask: &'a [bool],
+    pub river_mask: &'a [u8],
+    pub biome_indices: &'a [u16],
+}
+
 /// Stage 6: Suitability map for settlement density.
 /// Higher suitability = more/smaller provinces. Lower = fewer/larger provinces.
 pub fn compute_suitability(
-    height: &[u16],
-    landmask: &[bool],
-    river_mask: &[u8],
-    biome_indices: &[u8],
+    input: &SuitabilityInput,
     registry: &BiomeRegistry,
-    width: u32,
-    height_dim: u32,
     on_progress: &mut dyn FnMut(f32, &str),
 ) -> Vec<f32> {
+    let SuitabilityInput {
+        width,
+        height: height_dim,
+        height_field: height,
+        landmask,
+        river_mask,
+        biome_indices,
+    } = *input;
     let n = (width * height_dim) as usize;
 
     on_progress(0.0, "Computing river proximity");